@@ -10,7 +10,8 @@ use chrono::prelude::*;
 use mongodb::bson::doc;
 use mongodb::sync::Client;
 use rand::Rng;
-use sha3::{Digest, Sha3_256};
+use argon2::password_hash::{rand_core::OsRng, PasswordHasher, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
 
 use uuid::Uuid;
 mod collector;
@@ -44,10 +45,11 @@ pub fn populate_db(
     "email": "bird@iseeyou.ch"});
     mandant_collector.flush();
     //let mut mandant_object = mandant_collector.result.get(&0).unwrap();
-    let mut mandant_uuid = mandant_collector.uuids.get(0).unwrap().clone();
+    let mut mandant_uuid = mandant_collector.uuids.first().unwrap().clone();
     gen_birds_for_mandant(&mut birds_collector, &mandant_uuid);
+    let password_hash = get_password_hash(password_secret);
     for i in 0..records_to_insert as usize {
-        let (user_password_salt, password_hash) = get_password_and_salt(password_secret);
+        let user_password_salt = Uuid::new_v4();
         users_collector.append_doc(doc! {
         "mandant_uuid": &mandant_uuid,
         "username": format!("fg_{}", i),
@@ -55,8 +57,10 @@ pub fn populate_db(
         "lastname": "Gucker",
         "firstname":"Fritz",
         "email": format!("email_{}@birdwatch.ch", i),
-        "password_hash": password_hash,
-        "salt": user_password_salt.to_string()});
+        "password_hash": &password_hash,
+        "salt": user_password_salt.to_string(),
+        // The first user of every mandant administers it.
+        "role": if i % 100 == 0 { "mandant-admin" } else { "coordinator" }});
         let nestbox_flushed = match nestboxes_collector.append_doc(
             doc! {"public": true, "uuid": Uuid::new_v4().to_string(), "mandant_uuid": &mandant_uuid, "created_at": Utc::now()},
         ) {
@@ -77,7 +81,7 @@ pub fn populate_db(
                 doc!{ "uuid": Uuid::new_v4().to_string(), 
                     "name": format!("BirdLife {}", i),  "website": "https://www.birdwatcher.ch", "email": "bird@iseeyou.ch"});
             mandant_collector.flush();
-            mandant_uuid = mandant_collector.uuids.get(0).unwrap().clone();
+            mandant_uuid = mandant_collector.uuids.first().unwrap().clone();
             //mandant_object = mandant_collector.result.get(&0).unwrap();
             gen_birds_for_mandant(&mut birds_collector, &mandant_uuid);
         }
@@ -105,14 +109,25 @@ fn gen_birds_for_mandant(birds_collector: &mut Collector, mandant_uuid: &str) {
     birds_collector.flush();
 }
 
-fn get_password_and_salt(password_secret: &str) -> (Uuid, String) {
-    let user_password_salt = Uuid::new_v4();
-    let mut hasher = Sha3_256::new();
-    let password_with_salt = format!("{}_{}_{}", "secretbird", user_password_salt.to_string(), password_secret);
-    hasher.update(password_with_salt);
-    let password_hash = hex::encode(hasher.finalize());
-
-    (user_password_salt, password_hash)
+// Argon2id is too slow to hash the password of millions of users one by one,
+// so all of them share one hash. Its random salt is part of the PHC string,
+// the salt field is only kept for the legacy SHA3 hashes of nestboxd.
+fn get_password_hash(password_secret: &str) -> String {
+    let argon2 = if password_secret.is_empty() {
+        Argon2::default()
+    } else {
+        Argon2::new_with_secret(
+            password_secret.as_bytes(),
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::default(),
+        )
+        .unwrap()
+    };
+    argon2
+        .hash_password(b"secretbird", &SaltString::generate(&mut OsRng))
+        .unwrap()
+        .to_string()
 }
 
 fn generate_nestboxes_additionals(
@@ -133,7 +148,7 @@ fn generate_nestboxes_additionals(
             "nestbox_uuid": &nestbox_uuid,
             "from_date": Utc::now(),
             "until_date": Utc::now(),
-            "position": {"type": "point", "coordinates": [ longitude, latitude ]}});
+            "position": {"type": "Point", "coordinates": [ longitude, latitude ]}});
             breeds_collector.append_doc(doc! {
             "uuid": Uuid::new_v4().to_string(),
             "nestbox_uuid": &nestbox_uuid,
@@ -216,9 +231,6 @@ mod tests {
         let mandants_res_before_test = mandants_collection.count_documents(doc! {}, None);
         let _result = populate_db(DB_URI, DATABASE, INSERTED_RECORDS as i32, PASSWORD_SECRET);
         let mandants_res = mandants_collection.count_documents(doc! {}, None);
-        assert_eq!(
-            mandants_res.unwrap() > mandants_res_before_test.unwrap(),
-            true
-        );
+        assert!(mandants_res.unwrap() > mandants_res_before_test.unwrap());
     }
 }
@@ -1,3 +1,6 @@
+-- Schema of the postgres storage backend, applied by nestboxd on startup.
+-- The column names differ from the MongoDB attributes, the repository
+-- maps them, e.g. mandants.association_name is the mandant name.
 CREATE TABLE IF NOT EXISTS mandants (
     id uuid NOT NULL, 
     association_name varchar(256) NOT NULL,
@@ -6,35 +9,35 @@ CREATE TABLE IF NOT EXISTS mandants (
     PRIMARY KEY (id)
 );
 
-DROP TABLE users;
 CREATE TABLE IF NOT EXISTS users (
     id uuid NOT NULL,
     mandants_id uuid NOT NULL,
     locked boolean NOT NULL DEFAULT false,
     username VARCHAR(64) NOT NULL,
     lastname VARCHAR(256) NOT NULL,
+    firstname VARCHAR(256) NOT NULL DEFAULT '',
     email VARCHAR(128) NOT NULL,
     password_hash CHAR(64) NOT NULL,
     salt uuid NOT NULL,
     PRIMARY KEY(id),
+    CONSTRAINT uq_username UNIQUE (username),
     CONSTRAINT fk_mandants_id
       FOREIGN KEY(mandants_id) 
 	  REFERENCES mandants(id)
 );
 
-DROP TABLE sessions;
 CREATE TABLE IF NOT EXISTS sessions (
     id uuid NOT NULL,
     users_id uuid NOT NULL,
     created_at timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
-    exprires_at timestamp NOT NULL,
+    expires_at timestamp NOT NULL,
     PRIMARY KEY (id),
     CONSTRAINT fk_users_id
       FOREIGN KEY(users_id)
       REFERENCES users(id)
+      ON DELETE CASCADE
 );
 
-DROP TABLE nestboxes;
 CREATE TABLE IF NOT EXISTS nestboxes (
     id uuid not null,
     mandants_id uuid not null,
@@ -45,8 +48,18 @@ CREATE TABLE IF NOT EXISTS nestboxes (
       FOREIGN KEY(mandants_id) 
 	  REFERENCES mandants(id)
 );
+CREATE INDEX IF NOT EXISTS idx_nestboxes_mandants_id ON nestboxes (mandants_id);
+
+CREATE TABLE IF NOT EXISTS nestboxes_images (
+    nestboxes_id uuid not null,
+    file_name VARCHAR(256) not null,
+    created_at timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (nestboxes_id, file_name),
+    CONSTRAINT fk_nestboxes_id
+      FOREIGN KEY(nestboxes_id) 
+	  REFERENCES nestboxes(id)
+);
 
-DROP TABLE nestboxes_geolocations;
 CREATE TABLE IF NOT EXISTS nestboxes_geolocations (
     id uuid not null,
     nestboxes_id uuid not null,
@@ -59,8 +72,8 @@ CREATE TABLE IF NOT EXISTS nestboxes_geolocations (
       FOREIGN KEY(nestboxes_id) 
 	  REFERENCES nestboxes(id)
 );
+CREATE INDEX IF NOT EXISTS idx_nestboxes_geolocations_nestboxes_id ON nestboxes_geolocations (nestboxes_id);
 
-DROP TABLE birds;
 CREATE TABLE IF NOT EXISTS birds (
     id uuid not null,
     mandants_id uuid not null,
@@ -71,9 +84,8 @@ CREATE TABLE IF NOT EXISTS birds (
       FOREIGN KEY(mandants_id) 
 	  REFERENCES mandants(id)
 );
+CREATE INDEX IF NOT EXISTS idx_birds_mandants_id ON birds (mandants_id);
 
-
-DROP TABLE breeds;
 CREATE TABLE IF NOT EXISTS breeds (
     id uuid not null,
     nestboxes_id uuid not null,
@@ -91,12 +103,4 @@ CREATE TABLE IF NOT EXISTS breeds (
       FOREIGN KEY(birds_id) 
 	  REFERENCES birds(id)
 );
-
-
-
-
-
-
-
-
-
+CREATE INDEX IF NOT EXISTS idx_breeds_nestboxes_id ON breeds (nestboxes_id);
@@ -0,0 +1,2 @@
+-- Argon2id PHC strings are longer than the hex encoded SHA3 hashes.
+ALTER TABLE users ALTER COLUMN password_hash TYPE VARCHAR(256);
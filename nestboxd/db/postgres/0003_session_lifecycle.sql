@@ -0,0 +1,9 @@
+-- Several sessions per user (one per device). The session key is a bearer
+-- secret, so sessions get a public id to list and revoke them by. Sessions
+-- created before have none, their users simply log in again.
+DELETE FROM sessions;
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS public_id uuid NOT NULL;
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS last_seen_at timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP;
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS user_agent VARCHAR(256) NOT NULL DEFAULT '';
+CREATE UNIQUE INDEX IF NOT EXISTS idx_sessions_public_id ON sessions (public_id);
+CREATE INDEX IF NOT EXISTS idx_sessions_users_id ON sessions (users_id);
@@ -0,0 +1,5 @@
+-- Roles within the mandant: viewer, cleaner, coordinator or mandant-admin.
+-- Existing users could do everything but user management, so they become
+-- coordinators, new users start as viewers.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS role VARCHAR(32) NOT NULL DEFAULT 'coordinator';
+ALTER TABLE users ALTER COLUMN role SET DEFAULT 'viewer';
@@ -0,0 +1,5 @@
+-- Logo and contact details of the association, maintained by its admins.
+ALTER TABLE mandants ADD COLUMN IF NOT EXISTS logo varchar(256) NOT NULL DEFAULT '';
+ALTER TABLE mandants ADD COLUMN IF NOT EXISTS contact_person varchar(256) NOT NULL DEFAULT '';
+ALTER TABLE mandants ADD COLUMN IF NOT EXISTS phone varchar(64) NOT NULL DEFAULT '';
+ALTER TABLE mandants ADD COLUMN IF NOT EXISTS address varchar(512) NOT NULL DEFAULT '';
@@ -0,0 +1,9 @@
+-- Nestboxes are installed (from_date) and retired (until_date) instead of
+-- being deleted, so their breeds and locations stay readable.
+ALTER TABLE nestboxes ADD COLUMN IF NOT EXISTS from_date timestamp;
+UPDATE nestboxes SET from_date = created_at WHERE from_date IS NULL;
+ALTER TABLE nestboxes ALTER COLUMN from_date SET NOT NULL;
+ALTER TABLE nestboxes ALTER COLUMN from_date SET DEFAULT CURRENT_TIMESTAMP;
+ALTER TABLE nestboxes ADD COLUMN IF NOT EXISTS until_date timestamp;
+ALTER TABLE nestboxes ADD COLUMN IF NOT EXISTS description varchar(1024) NOT NULL DEFAULT '';
+ALTER TABLE nestboxes ADD COLUMN IF NOT EXISTS box_type varchar(128) NOT NULL DEFAULT '';
@@ -0,0 +1,16 @@
+-- Previous versions of corrected and deleted breeds. No foreign key to
+-- breeds, the versions of a deleted breed are kept.
+CREATE TABLE IF NOT EXISTS breeds_versions (
+    id uuid not null,
+    breeds_id uuid not null,
+    nestboxes_id uuid not null,
+    users_id uuid not null,
+    birds_id uuid not null,
+    created_at TIMESTAMP not null,
+    changed_by uuid not null,
+    changed_at TIMESTAMP not null,
+    change text not null,
+    PRIMARY KEY (id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_breeds_versions_breeds_id ON breeds_versions (breeds_id);
@@ -0,0 +1,17 @@
+-- Species identity shared by the mandants and names per language. The
+-- name of the bird stays the one the mandant uses by default.
+ALTER TABLE birds ADD COLUMN IF NOT EXISTS scientific_name VARCHAR(128);
+ALTER TABLE birds ADD COLUMN IF NOT EXISTS taxon_key VARCHAR(64);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_birds_mandants_id_taxon_key ON birds (mandants_id, taxon_key)
+    WHERE taxon_key IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_birds_taxon_key ON birds (taxon_key);
+
+CREATE TABLE IF NOT EXISTS birds_names (
+    birds_id uuid not null,
+    language VARCHAR(16) not null,
+    name VARCHAR(128) not null,
+    PRIMARY KEY (birds_id, language),
+    CONSTRAINT fk_birds_id
+      FOREIGN KEY(birds_id)
+	  REFERENCES birds(id) ON DELETE CASCADE
+);
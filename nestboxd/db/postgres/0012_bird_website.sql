@@ -0,0 +1,2 @@
+-- Link to a description of the species, e.g. from an imported species list.
+ALTER TABLE birds ADD COLUMN website VARCHAR(256);
@@ -0,0 +1,14 @@
+-- What was found in the nestbox besides the bird, NULL if not observed.
+ALTER TABLE breeds ADD COLUMN clutch_size INTEGER;
+ALTER TABLE breeds ADD COLUMN hatched INTEGER;
+ALTER TABLE breeds ADD COLUMN fledged INTEGER;
+ALTER TABLE breeds ADD COLUMN nest_state VARCHAR(16);
+ALTER TABLE breeds ADD COLUMN confidence VARCHAR(16);
+ALTER TABLE breeds ADD COLUMN notes TEXT;
+
+ALTER TABLE breeds_versions ADD COLUMN clutch_size INTEGER;
+ALTER TABLE breeds_versions ADD COLUMN hatched INTEGER;
+ALTER TABLE breeds_versions ADD COLUMN fledged INTEGER;
+ALTER TABLE breeds_versions ADD COLUMN nest_state VARCHAR(16);
+ALTER TABLE breeds_versions ADD COLUMN confidence VARCHAR(16);
+ALTER TABLE breeds_versions ADD COLUMN notes TEXT;
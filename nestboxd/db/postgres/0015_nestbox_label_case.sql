@@ -0,0 +1,4 @@
+-- Labels are unique within the mandant regardless of case, like the label
+-- search finds them.
+DROP INDEX IF EXISTS nestboxes_label_idx;
+CREATE UNIQUE INDEX IF NOT EXISTS nestboxes_label_idx ON nestboxes (mandants_id, lower(label));
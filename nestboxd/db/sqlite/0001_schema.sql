@@ -0,0 +1,76 @@
+-- Schema of the sqlite storage backend, created by nestboxd on first start.
+-- Same tables as db/postgres, uuids are stored as text.
+CREATE TABLE IF NOT EXISTS mandants (
+    id TEXT NOT NULL,
+    association_name TEXT NOT NULL,
+    website TEXT NOT NULL,
+    email TEXT NOT NULL,
+    PRIMARY KEY (id)
+);
+
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT NOT NULL,
+    mandants_id TEXT NOT NULL REFERENCES mandants(id),
+    locked BOOLEAN NOT NULL DEFAULT false,
+    username TEXT NOT NULL UNIQUE,
+    lastname TEXT NOT NULL,
+    firstname TEXT NOT NULL DEFAULT '',
+    email TEXT NOT NULL,
+    password_hash TEXT NOT NULL,
+    salt TEXT NOT NULL,
+    PRIMARY KEY (id)
+);
+
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT NOT NULL,
+    users_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (id)
+);
+
+CREATE TABLE IF NOT EXISTS nestboxes (
+    id TEXT NOT NULL,
+    mandants_id TEXT NOT NULL REFERENCES mandants(id),
+    public BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (id)
+);
+CREATE INDEX IF NOT EXISTS idx_nestboxes_mandants_id ON nestboxes (mandants_id);
+
+CREATE TABLE IF NOT EXISTS nestboxes_images (
+    nestboxes_id TEXT NOT NULL REFERENCES nestboxes(id),
+    file_name TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (nestboxes_id, file_name)
+);
+
+CREATE TABLE IF NOT EXISTS nestboxes_geolocations (
+    id TEXT NOT NULL,
+    nestboxes_id TEXT NOT NULL REFERENCES nestboxes(id),
+    lat DOUBLE NOT NULL,
+    lng DOUBLE NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expired_at TIMESTAMP NULL,
+    PRIMARY KEY (id)
+);
+CREATE INDEX IF NOT EXISTS idx_nestboxes_geolocations_nestboxes_id ON nestboxes_geolocations (nestboxes_id);
+
+CREATE TABLE IF NOT EXISTS birds (
+    id TEXT NOT NULL,
+    mandants_id TEXT NOT NULL REFERENCES mandants(id),
+    name TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (id)
+);
+CREATE INDEX IF NOT EXISTS idx_birds_mandants_id ON birds (mandants_id);
+
+CREATE TABLE IF NOT EXISTS breeds (
+    id TEXT NOT NULL,
+    nestboxes_id TEXT NOT NULL REFERENCES nestboxes(id),
+    users_id TEXT NOT NULL REFERENCES users(id),
+    birds_id TEXT NOT NULL REFERENCES birds(id),
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (id)
+);
+CREATE INDEX IF NOT EXISTS idx_breeds_nestboxes_id ON breeds (nestboxes_id);
@@ -0,0 +1,5 @@
+-- Roles within the mandant: viewer, cleaner, coordinator or mandant-admin.
+-- Existing users could do everything but user management, so they become
+-- coordinators, new users start as viewers.
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'viewer';
+UPDATE users SET role = 'coordinator';
@@ -0,0 +1,5 @@
+-- Logo and contact details of the association, maintained by its admins.
+ALTER TABLE mandants ADD COLUMN logo TEXT NOT NULL DEFAULT '';
+ALTER TABLE mandants ADD COLUMN contact_person TEXT NOT NULL DEFAULT '';
+ALTER TABLE mandants ADD COLUMN phone TEXT NOT NULL DEFAULT '';
+ALTER TABLE mandants ADD COLUMN address TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,8 @@
+-- Nestboxes are installed (from_date) and retired (until_date) instead of
+-- being deleted, so their breeds and locations stay readable. SQLite cannot
+-- add a column defaulting to CURRENT_TIMESTAMP, nestboxd always sets it.
+ALTER TABLE nestboxes ADD COLUMN from_date TIMESTAMP;
+UPDATE nestboxes SET from_date = created_at;
+ALTER TABLE nestboxes ADD COLUMN until_date TIMESTAMP;
+ALTER TABLE nestboxes ADD COLUMN description TEXT NOT NULL DEFAULT '';
+ALTER TABLE nestboxes ADD COLUMN box_type TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,4 @@
+-- Short code of the nestbox for QR codes and typing, random like the uuid.
+-- nestboxd assigns one on startup to the nestboxes created before.
+ALTER TABLE nestboxes ADD COLUMN code TEXT;
+CREATE UNIQUE INDEX nestboxes_code_idx ON nestboxes (code);
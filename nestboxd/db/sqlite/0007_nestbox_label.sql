@@ -0,0 +1,4 @@
+-- Label of the nestbox as the association numbers it, e.g. "Wald Nord 17".
+-- Optional, but unique within the mandant.
+ALTER TABLE nestboxes ADD COLUMN label TEXT;
+CREATE UNIQUE INDEX nestboxes_label_idx ON nestboxes (mandants_id, label);
@@ -0,0 +1,3 @@
+-- Narrows the search for nestboxes near a point to a bounding box, the
+-- exact distance is computed by nestboxd.
+CREATE INDEX idx_nestboxes_geolocations_lat_lng ON nestboxes_geolocations (lat, lng);
@@ -0,0 +1,15 @@
+-- Previous versions of corrected and deleted breeds. No foreign key to
+-- breeds, the versions of a deleted breed are kept.
+CREATE TABLE IF NOT EXISTS breeds_versions (
+    id TEXT NOT NULL,
+    breeds_id TEXT NOT NULL,
+    nestboxes_id TEXT NOT NULL,
+    users_id TEXT NOT NULL,
+    birds_id TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    changed_by TEXT NOT NULL,
+    changed_at TIMESTAMP NOT NULL,
+    change TEXT NOT NULL,
+    PRIMARY KEY (id)
+);
+CREATE INDEX IF NOT EXISTS idx_breeds_versions_breeds_id ON breeds_versions (breeds_id);
@@ -0,0 +1,14 @@
+-- Species identity shared by the mandants and names per language. The
+-- name of the bird stays the one the mandant uses by default.
+ALTER TABLE birds ADD COLUMN scientific_name TEXT;
+ALTER TABLE birds ADD COLUMN taxon_key TEXT;
+CREATE UNIQUE INDEX idx_birds_mandants_id_taxon_key ON birds (mandants_id, taxon_key)
+    WHERE taxon_key IS NOT NULL;
+CREATE INDEX idx_birds_taxon_key ON birds (taxon_key);
+
+CREATE TABLE IF NOT EXISTS birds_names (
+    birds_id TEXT NOT NULL REFERENCES birds(id) ON DELETE CASCADE,
+    language TEXT NOT NULL,
+    name TEXT NOT NULL,
+    PRIMARY KEY (birds_id, language)
+);
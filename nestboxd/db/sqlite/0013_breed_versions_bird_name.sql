@@ -0,0 +1,3 @@
+-- Name of the bird of a version, kept when the bird is merged into another
+-- one and deleted.
+ALTER TABLE breeds_versions ADD COLUMN bird_name TEXT;
@@ -0,0 +1,116 @@
+//! Tasks run once from the command line instead of serving the API.
+use actix_web::web;
+
+use crate::controller::req_structs::{BirdImportQuery, LabelSheetQuery, NestboxesQuery};
+use crate::controller::validator::{is_uuid, Validator};
+use crate::extract_argv::{ImportBirdsArgs, LabelsArgs};
+use crate::service::label::LabelLayout;
+use crate::service::qr::QrEcLevel;
+use crate::service::species::{parse_species, SpeciesFormat, CAVITY_NESTERS};
+use crate::ServiceContainer;
+
+/// Writes the label sheets like `GET /nestboxes/labels` does, returns the
+/// exit code.
+pub async fn labels(services: &ServiceContainer, args: &LabelsArgs) -> i32 {
+    let (label_query, query) = match (
+        web::Query::<LabelSheetQuery>::from_query(&args.query),
+        web::Query::<NestboxesQuery>::from_query(&args.query),
+    ) {
+        (Ok(l), Ok(q)) if l.is_valid() && q.is_valid() => (l, q),
+        _ => {
+            eprintln!("Invalid query {}", args.query);
+            return 2;
+        }
+    };
+    let pdf = match services
+        .label
+        .label_sheets(
+            &args.mandant_uuid,
+            &query,
+            label_query.layout.unwrap_or(LabelLayout::L7160),
+            label_query.ec.unwrap_or(QrEcLevel::Q),
+            label_query.skip.unwrap_or(0),
+        )
+        .await
+    {
+        Ok(Some(pdf)) => pdf,
+        Ok(None) => {
+            eprintln!("No nestboxes of mandant {} to print", args.mandant_uuid);
+            return 1;
+        }
+        Err(e) => {
+            eprintln!("Reading the nestboxes failed: {}", e);
+            return 1;
+        }
+    };
+    match std::fs::write(&args.output_file, pdf) {
+        Ok(()) => {
+            println!("Label sheets written to {}", args.output_file);
+            0
+        }
+        Err(e) => {
+            eprintln!("Writing {} failed: {}", args.output_file, e);
+            1
+        }
+    }
+}
+
+/// Imports the species list like `POST /birds/import` does, returns the exit
+/// code. Without a format the one of the file extension is taken.
+pub async fn import_birds(services: &ServiceContainer, args: &ImportBirdsArgs) -> i32 {
+    let import_query = match web::Query::<BirdImportQuery>::from_query(&args.query) {
+        Ok(q) if q.is_valid() && is_uuid(&args.mandant_uuid) => q,
+        _ => {
+            eprintln!(
+                "Invalid mandant {} or query {}",
+                args.mandant_uuid, args.query
+            );
+            return 2;
+        }
+    };
+    let (format, list) = match &args.input_file {
+        Some(file) => match std::fs::read_to_string(file) {
+            Ok(list) if file.to_lowercase().ends_with(".csv") => {
+                (import_query.format.unwrap_or(SpeciesFormat::Csv), list)
+            }
+            Ok(list) => (import_query.format.unwrap_or(SpeciesFormat::Json), list),
+            Err(e) => {
+                eprintln!("Reading {} failed: {}", file, e);
+                return 1;
+            }
+        },
+        None => (SpeciesFormat::Csv, CAVITY_NESTERS.to_string()),
+    };
+    let species = match parse_species(format, &list) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Invalid species list: {}", e);
+            return 2;
+        }
+    };
+    let languages: Vec<String> = import_query
+        .language
+        .iter()
+        .map(|l| l.to_lowercase())
+        .collect();
+    match services
+        .bird
+        .import(&args.mandant_uuid, &species, &languages)
+        .await
+    {
+        Ok(result) => {
+            println!(
+                "Birds of mandant {}: {} created, {} updated, {} unchanged",
+                args.mandant_uuid, result.created, result.updated, result.unchanged
+            );
+            for record in &result.rejected {
+                eprintln!("Species {} rejected", record);
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("Importing the birds failed: {}", e);
+            1
+        }
+    }
+}
@@ -1,32 +1,39 @@
-use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
 
 use crate::{
-    controller::utilities::DocumentResponse, service::res_structs::BirdResponse, ServiceContainer,
+    controller::utilities::DocumentResponse,
+    service::bird::{BirdDeleteResult, BirdMergeResult, BirdSaveResult},
+    service::res_structs::BirdResponse,
+    service::species::{parse_species, SpeciesFormat, CAVITY_NESTERS},
+    ServiceContainer,
 };
 
 use super::{
-    error_message::{create_error_message, UNAUTHORIZED},
-    utilities::{parse_auth_header, PagingQuery, Sanatiz},
+    error_message::{
+        create_error_message, BAD_REQUEST, BIRD_IN_USE, BIRD_OF_OTHER_MANDANT, BIRD_TAKEN,
+        INTERNAL_SERVER_ERROR, NOT_FOUND,
+    },
+    req_structs::{BirdDataReq, BirdImportQuery, BirdMergeReq, BirdUuidReq},
+    role::Role,
+    utilities::{
+        accept_languages, mandant_req_is_authorized, role_is_authorized, PagingQuery, Sanatiz,
+        SessionObject,
+    },
+    validator::Validator,
 };
 
 #[get("/birds")]
 pub async fn birds_get(
     app_data: web::Data<ServiceContainer>,
-    req: HttpRequest,
+    session_obj: SessionObject,
     mut paging: web::Query<PagingQuery>,
+    http_req: HttpRequest,
 ) -> impl Responder {
     paging.sanatizing();
 
-    let session_obj = app_data
-        .session
-        .validate_session(&parse_auth_header(&req))
-        .await;
-    if !session_obj.is_valid_session() {
-        return HttpResponse::Unauthorized().json(create_error_message(UNAUTHORIZED));
-    }
     let (birds, counted_documents) = app_data
         .bird
-        .get_by_mandant_uuid(&session_obj, &paging)
+        .get_by_mandant_uuid(&session_obj, &paging, &accept_languages(&http_req))
         .await;
 
     HttpResponse::Ok().json(DocumentResponse::<BirdResponse>::new(
@@ -35,3 +42,216 @@ pub async fn birds_get(
         &paging,
     ))
 }
+
+fn bird_save_response(result: BirdSaveResult, created: bool) -> HttpResponse {
+    match result {
+        BirdSaveResult::Ok(b) if created => HttpResponse::Created().json(b),
+        BirdSaveResult::Ok(b) => HttpResponse::Ok().json(b),
+        BirdSaveResult::NotFound => HttpResponse::NotFound().json(create_error_message(NOT_FOUND)),
+        BirdSaveResult::NameTaken => {
+            HttpResponse::Conflict().json(create_error_message(BIRD_TAKEN))
+        }
+        BirdSaveResult::Error => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+/// Mandant of the bird of the path if the user may edit it, else the
+/// response.
+async fn bird_req_is_authorized(
+    session: &SessionObject,
+    app_data: &ServiceContainer,
+    bird_req: &BirdUuidReq,
+) -> Result<String, HttpResponse> {
+    if !bird_req.is_valid() {
+        return Err(HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST)));
+    }
+    if let Some(value) = role_is_authorized(session, Role::Coordinator) {
+        return Err(value);
+    }
+    let mandant_uuid = match app_data.bird.get_by_uuid(&bird_req.uuid).await {
+        Ok(Some((_, m))) => m,
+        Ok(None) => return Err(HttpResponse::NotFound().json(create_error_message(NOT_FOUND))),
+        Err(_e) => {
+            return Err(HttpResponse::InternalServerError()
+                .json(create_error_message(INTERNAL_SERVER_ERROR)))
+        }
+    };
+    if let Some(value) = mandant_req_is_authorized(session, &mandant_uuid, Role::Coordinator) {
+        return Err(value);
+    }
+    Ok(mandant_uuid)
+}
+
+#[post("/birds")]
+pub async fn birds_post(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    bird_data: web::Json<BirdDataReq>,
+) -> impl Responder {
+    if let Some(value) = role_is_authorized(&session, Role::Coordinator) {
+        return value;
+    }
+    if !bird_data.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    bird_save_response(app_data.bird.create(&session, &bird_data).await, true)
+}
+
+/// Imports the species list into the birds of the mandant of the user.
+async fn bird_import_response(
+    app_data: &ServiceContainer,
+    session: &SessionObject,
+    import_query: &BirdImportQuery,
+    format: SpeciesFormat,
+    list: &str,
+    http_req: &HttpRequest,
+) -> HttpResponse {
+    if let Some(value) = role_is_authorized(session, Role::Coordinator) {
+        return value;
+    }
+    if !import_query.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    let species = match parse_species(format, list) {
+        Ok(s) => s,
+        Err(_e) => return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST)),
+    };
+    // The language asked for names new birds, else the ones of the user.
+    let languages: Vec<String> = import_query
+        .language
+        .iter()
+        .map(|l| l.to_lowercase())
+        .chain(accept_languages(http_req))
+        .collect();
+    match app_data
+        .bird
+        .import(session.get_mandant_uuid(), &species, &languages)
+        .await
+    {
+        Ok(result) => HttpResponse::Ok().json(result),
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+#[post("/birds/import")]
+pub async fn birds_import_post(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    import_query: web::Query<BirdImportQuery>,
+    list: String,
+    http_req: HttpRequest,
+) -> impl Responder {
+    bird_import_response(
+        &app_data,
+        &session,
+        &import_query,
+        import_query.format.unwrap_or(SpeciesFormat::Json),
+        &list,
+        &http_req,
+    )
+    .await
+}
+
+#[post("/birds/import/default")]
+pub async fn birds_import_default_post(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    import_query: web::Query<BirdImportQuery>,
+    http_req: HttpRequest,
+) -> impl Responder {
+    bird_import_response(
+        &app_data,
+        &session,
+        &import_query,
+        SpeciesFormat::Csv,
+        CAVITY_NESTERS,
+        &http_req,
+    )
+    .await
+}
+
+#[put("/birds/{uuid}")]
+pub async fn birds_put(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    bird_req: web::Path<BirdUuidReq>,
+    bird_data: web::Json<BirdDataReq>,
+) -> impl Responder {
+    if !bird_data.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    let mandant_uuid = match bird_req_is_authorized(&session, &app_data, &bird_req).await {
+        Ok(m) => m,
+        Err(response) => return response,
+    };
+    bird_save_response(
+        app_data
+            .bird
+            .update(&mandant_uuid, &bird_req.uuid, &bird_data)
+            .await,
+        false,
+    )
+}
+
+/// Merges the bird of the path into the bird of the body, for duplicates
+/// like "Kohlmeise" and "Kohlmeise ".
+#[post("/birds/{uuid}/merge")]
+pub async fn birds_merge_post(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    bird_req: web::Path<BirdUuidReq>,
+    merge_req: web::Json<BirdMergeReq>,
+) -> impl Responder {
+    if !merge_req.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    let mandant_uuid = match bird_req_is_authorized(&session, &app_data, &bird_req).await {
+        Ok(m) => m,
+        Err(response) => return response,
+    };
+    match app_data
+        .bird
+        .merge(
+            &session,
+            &mandant_uuid,
+            &bird_req.uuid,
+            &merge_req.bird_uuid,
+        )
+        .await
+    {
+        BirdMergeResult::Ok(merged) => HttpResponse::Ok().json(merged),
+        BirdMergeResult::NotFound => HttpResponse::NotFound().json(create_error_message(NOT_FOUND)),
+        BirdMergeResult::OtherMandant => {
+            HttpResponse::BadRequest().json(create_error_message(BIRD_OF_OTHER_MANDANT))
+        }
+        BirdMergeResult::Error => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+#[delete("/birds/{uuid}")]
+pub async fn birds_delete(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    bird_req: web::Path<BirdUuidReq>,
+) -> impl Responder {
+    if let Err(response) = bird_req_is_authorized(&session, &app_data, &bird_req).await {
+        return response;
+    }
+    match app_data.bird.delete(&bird_req.uuid).await {
+        BirdDeleteResult::Ok => HttpResponse::NoContent().finish(),
+        BirdDeleteResult::NotFound => {
+            HttpResponse::NotFound().json(create_error_message(NOT_FOUND))
+        }
+        // Breeds keep their bird, merge it into another one instead.
+        BirdDeleteResult::InUse => HttpResponse::Conflict().json(create_error_message(BIRD_IN_USE)),
+        BirdDeleteResult::Error => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
@@ -1,37 +1,39 @@
 pub use crate::controller::utilities::{PagingQuery, Sanatiz};
 use crate::{
-    controller::{
-        error_message::BAD_REQUEST,
-        utilities::{nestbox_req_is_authorized, DocumentResponse},
-        validator::Validator,
+    controller::utilities::{
+        mandant_req_is_authorized, nestbox_req_is_authorized, resolve_nestbox_req,
+        role_is_authorized, DocumentResponse, SessionObject,
     },
     service::res_structs::BreedResponse,
     ServiceContainer,
 };
-use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
 
 use super::{
-    error_message::{create_error_message, INTERNAL_SERVER_ERROR},
-    req_structs::{BirdReq, NestboxReq},
-    utilities::parse_auth_header,
+    error_message::{
+        create_error_message, BAD_REQUEST, BIRD_OF_OTHER_MANDANT, FORBIDDEN, INTERNAL_SERVER_ERROR,
+        NOT_FOUND,
+    },
+    req_structs::{BreedCreateReq, BreedReq, BreedUpdateReq, NestboxReq},
+    role::Role,
+    validator::Validator,
 };
 
 #[get("/nestboxes/{uuid}/breeds")]
 pub async fn breeds_get(
     app_data: web::Data<ServiceContainer>,
-    req: HttpRequest,
+    session_obj: Option<SessionObject>,
     breed_req: web::Path<NestboxReq>,
     mut paging: web::Query<PagingQuery>,
 ) -> impl Responder {
-    if !breed_req.is_valid() {
-        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
-    }
+    let breed_req = match resolve_nestbox_req(&app_data, breed_req).await {
+        Ok(n) => n,
+        Err(response) => return response,
+    };
 
     paging.sanatizing();
-    let session_obj = app_data
-        .session
-        .validate_session(&parse_auth_header(&req))
-        .await;
+    // Anonymous users see the breeds as well, only without user_uuid.
+    let session_obj = session_obj.unwrap_or_default();
     let (breeds, counted_documents) = app_data
         .breed
         .get_by_nestbox_uuid(&session_obj, &breed_req, &paging)
@@ -47,13 +49,14 @@ pub async fn breeds_get(
 #[post("/nestboxes/{uuid}/breeds")]
 pub async fn breeds_post(
     app_data: web::Data<ServiceContainer>,
-    req: HttpRequest,
+    session: SessionObject,
     nestbox_req: web::Path<NestboxReq>,
-    bird_req: web::Json<BirdReq>,
+    breed_data: web::Json<BreedCreateReq>,
 ) -> impl Responder {
-    if !nestbox_req.is_valid() {
-        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
-    }
+    let nestbox_req = match resolve_nestbox_req(&app_data, nestbox_req).await {
+        Ok(n) => n,
+        Err(response) => return response,
+    };
 
     // To post a new breed which means the user has discovered a nest
     // in a birdhouse the user must be
@@ -61,21 +64,188 @@ pub async fn breeds_post(
     // - nestbox and transmitted bird must belong to the same mandant as the user does
     // - if the bird does not have a uuid it is considered to create a new bird for
     //   the users mandant.
-    let session = app_data
-        .session
-        .validate_session(&parse_auth_header(&req))
-        .await;
-    if let Some(value) = nestbox_req_is_authorized(&session, &app_data, &nestbox_req).await {
+    if let Some(value) =
+        nestbox_req_is_authorized(&session, &app_data, &nestbox_req, Role::Cleaner).await
+    {
         return value;
     }
+    if !breed_data.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    let bird = match app_data.bird.resolve(&session, &breed_data.bird).await {
+        Ok(Some(b)) => b,
+        Ok(None) => {
+            return HttpResponse::BadRequest().json(create_error_message(BIRD_OF_OTHER_MANDANT))
+        }
+        Err(_e) => {
+            return HttpResponse::InternalServerError()
+                .json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    };
     match app_data
         .breed
-        .post_breed(&session, &nestbox_req, &bird_req)
+        .post_breed(&session, &nestbox_req, &bird.uuid, &breed_data.observation)
         .await
     {
-        Ok(d) => HttpResponse::Created().json(d),
+        Ok(mut d) => {
+            if let Some(nestbox) = app_data.nestbox.get_by_uuid(&nestbox_req.uuid).await.pop() {
+                d.nestbox_label = nestbox.label;
+            }
+            d.bird = bird.bird;
+            HttpResponse::Created().json(d)
+        }
         Err(_e) => {
             HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
         }
     }
 }
+
+/// Mandant of the nestbox, Err with the response if it is unknown.
+async fn nestbox_mandant_uuid(
+    app_data: &ServiceContainer,
+    nestbox_uuid: &str,
+    unknown: HttpResponse,
+) -> Result<String, HttpResponse> {
+    match app_data.nestbox.get_by_uuid(nestbox_uuid).await.pop() {
+        Some(n) => Ok(n.mandant_uuid),
+        None => Err(unknown),
+    }
+}
+
+/// The breed of the path if the user may change it: a cleaner of the mandant
+/// of its nestbox who reported the breed, or a coordinator of that mandant.
+async fn breed_req_is_authorized(
+    session: &SessionObject,
+    app_data: &ServiceContainer,
+    breed_req: &BreedReq,
+) -> Result<BreedResponse, HttpResponse> {
+    if !breed_req.is_valid() {
+        return Err(HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST)));
+    }
+    if let Some(value) = role_is_authorized(session, Role::Cleaner) {
+        return Err(value);
+    }
+    let breed = match app_data.breed.get_by_uuid(&breed_req.uuid).await {
+        Ok(Some(b)) => b,
+        Ok(None) => return Err(HttpResponse::NotFound().json(create_error_message(NOT_FOUND))),
+        Err(_e) => {
+            return Err(HttpResponse::InternalServerError()
+                .json(create_error_message(INTERNAL_SERVER_ERROR)))
+        }
+    };
+    let not_found = HttpResponse::NotFound().json(create_error_message(NOT_FOUND));
+    let mandant_uuid = nestbox_mandant_uuid(app_data, &breed.nestbox_uuid, not_found).await?;
+    if let Some(value) = mandant_req_is_authorized(session, &mandant_uuid, Role::Cleaner) {
+        return Err(value);
+    }
+    if breed.user_uuid != session.get_user_uuid() && !session.has_role(Role::Coordinator) {
+        return Err(HttpResponse::Forbidden().json(create_error_message(FORBIDDEN)));
+    }
+    Ok(breed)
+}
+
+#[put("/breeds/{uuid}")]
+pub async fn breeds_put(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    breed_req: web::Path<BreedReq>,
+    breed_data: web::Json<BreedUpdateReq>,
+) -> impl Responder {
+    if !breed_data.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    if let Err(response) = breed_req_is_authorized(&session, &app_data, &breed_req).await {
+        return response;
+    }
+    // The breed may only be moved to a nestbox of the same mandant.
+    let bad_request = HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    let mandant_uuid =
+        match nestbox_mandant_uuid(&app_data, &breed_data.nestbox_uuid, bad_request).await {
+            Ok(m) => m,
+            Err(response) => return response,
+        };
+    if let Some(value) = mandant_req_is_authorized(&session, &mandant_uuid, Role::Cleaner) {
+        return value;
+    }
+    match app_data.bird.get_by_uuid(&breed_data.bird_uuid).await {
+        Ok(Some((_, m))) if m == mandant_uuid => {}
+        Ok(_) => {
+            return HttpResponse::BadRequest().json(create_error_message(BIRD_OF_OTHER_MANDANT))
+        }
+        Err(_e) => {
+            return HttpResponse::InternalServerError()
+                .json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+    match app_data
+        .breed
+        .put_breed(&session, &breed_req.uuid, &breed_data)
+        .await
+    {
+        Ok(Some(b)) => HttpResponse::Ok().json(b),
+        Ok(None) => HttpResponse::NotFound().json(create_error_message(NOT_FOUND)),
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+#[delete("/breeds/{uuid}")]
+pub async fn breeds_delete(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    breed_req: web::Path<BreedReq>,
+) -> impl Responder {
+    if let Err(response) = breed_req_is_authorized(&session, &app_data, &breed_req).await {
+        return response;
+    }
+    match app_data.breed.delete_breed(&session, &breed_req.uuid).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().json(create_error_message(NOT_FOUND)),
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+/// Previous versions of a breed, also of a deleted one.
+#[get("/breeds/{uuid}/versions")]
+pub async fn breeds_versions_get(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    breed_req: web::Path<BreedReq>,
+) -> impl Responder {
+    if !breed_req.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    if let Some(value) = role_is_authorized(&session, Role::Viewer) {
+        return value;
+    }
+    let (versions, breed) = match (
+        app_data.breed.get_versions(&breed_req.uuid).await,
+        app_data.breed.get_by_uuid(&breed_req.uuid).await,
+    ) {
+        (Ok(v), Ok(b)) => (v, b),
+        _ => {
+            return HttpResponse::InternalServerError()
+                .json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    };
+    // A correction may move the breed to another nestbox, the current one
+    // tells the mandant. A deleted breed was last in the nestbox of its
+    // newest version.
+    let nestbox_uuid = match (breed, versions.last()) {
+        (Some(b), _) => b.nestbox_uuid,
+        (None, Some(v)) => v.nestbox_uuid.clone(),
+        (None, None) => return HttpResponse::NotFound().json(create_error_message(NOT_FOUND)),
+    };
+    let not_found = HttpResponse::NotFound().json(create_error_message(NOT_FOUND));
+    let mandant_uuid = match nestbox_mandant_uuid(&app_data, &nestbox_uuid, not_found).await {
+        Ok(m) => m,
+        Err(response) => return response,
+    };
+    if let Some(value) = mandant_req_is_authorized(&session, &mandant_uuid, Role::Viewer) {
+        return value;
+    }
+    HttpResponse::Ok().json(versions)
+}
@@ -5,6 +5,12 @@ pub const NOT_FOUND: &str = "NOT_FOUND";
 pub const UNAUTHORIZED: &str = "UNAUTHORIZED";
 pub const INTERNAL_SERVER_ERROR: &str = "INTERNAL_SERVER_ERROR";
 pub const BAD_REQUEST: &str = "BAD_REQUEST";
+pub const FORBIDDEN: &str = "FORBIDDEN";
+pub const USERNAME_TAKEN: &str = "USERNAME_TAKEN";
+pub const LABEL_TAKEN: &str = "LABEL_TAKEN";
+pub const BIRD_OF_OTHER_MANDANT: &str = "BIRD_OF_OTHER_MANDANT";
+pub const BIRD_TAKEN: &str = "BIRD_TAKEN";
+pub const BIRD_IN_USE: &str = "BIRD_IN_USE";
 
 pub fn create_error_message(msg: &str) -> Document {
     match msg {
@@ -13,6 +19,12 @@ pub fn create_error_message(msg: &str) -> Document {
         UNAUTHORIZED => doc! {"error": 2, "error_message": UNAUTHORIZED},
         INTERNAL_SERVER_ERROR => doc! {"error": 2, "error_message": INTERNAL_SERVER_ERROR},
         BAD_REQUEST => doc! {"error": 2, "error_message": BAD_REQUEST},
+        FORBIDDEN => doc! {"error": 3, "error_message": FORBIDDEN},
+        USERNAME_TAKEN => doc! {"error": 4, "error_message": USERNAME_TAKEN},
+        LABEL_TAKEN => doc! {"error": 5, "error_message": LABEL_TAKEN},
+        BIRD_OF_OTHER_MANDANT => doc! {"error": 6, "error_message": BIRD_OF_OTHER_MANDANT},
+        BIRD_TAKEN => doc! {"error": 7, "error_message": BIRD_TAKEN},
+        BIRD_IN_USE => doc! {"error": 8, "error_message": BIRD_IN_USE},
         _ => doc! {"error":255, "error_message": "UNKNOWN"},
     }
 }
@@ -0,0 +1,80 @@
+use actix_web::{get, post, put, web, HttpResponse, Responder};
+
+use crate::ServiceContainer;
+
+use super::{
+    error_message::{create_error_message, BAD_REQUEST, INTERNAL_SERVER_ERROR, NOT_FOUND},
+    req_structs::{MandantDataReq, MandantReq},
+    role::Role,
+    utilities::{mandant_req_is_authorized, role_is_authorized, SessionObject},
+    validator::Validator,
+};
+
+#[get("/mandants/{uuid}")]
+pub async fn mandants_get(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    mandant_req: web::Path<MandantReq>,
+) -> impl Responder {
+    if !mandant_req.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    if let Some(value) = mandant_req_is_authorized(&session, &mandant_req.uuid, Role::Viewer) {
+        return value;
+    }
+    match app_data.mandant.get_by_uuid(&mandant_req.uuid).await {
+        Ok(Some(m)) => HttpResponse::Ok().json(m),
+        Ok(None) => HttpResponse::NotFound().json(create_error_message(NOT_FOUND)),
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+#[put("/mandants/{uuid}")]
+pub async fn mandants_put(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    mandant_req: web::Path<MandantReq>,
+    mandant_data: web::Json<MandantDataReq>,
+) -> impl Responder {
+    if !mandant_req.is_valid() || !mandant_data.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    if let Some(value) = mandant_req_is_authorized(&session, &mandant_req.uuid, Role::MandantAdmin)
+    {
+        return value;
+    }
+    match app_data
+        .mandant
+        .update(&mandant_req.uuid, &mandant_data)
+        .await
+    {
+        Ok(Some(m)) => HttpResponse::Ok().json(m),
+        Ok(None) => HttpResponse::NotFound().json(create_error_message(NOT_FOUND)),
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+#[post("/mandants")]
+pub async fn mandants_post(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    mandant_data: web::Json<MandantDataReq>,
+) -> impl Responder {
+    // Only the operator of nestboxd onboards new associations.
+    if let Some(value) = role_is_authorized(&session, Role::Superuser) {
+        return value;
+    }
+    if !mandant_data.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    match app_data.mandant.create(&mandant_data).await {
+        Ok(m) => HttpResponse::Created().json(m),
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
@@ -1,8 +1,12 @@
+pub mod authentication;
 pub mod bird;
 pub mod breed;
 pub mod error_message;
+pub mod mandant;
 pub mod nestbox;
 pub mod req_structs;
+pub mod role;
+pub mod session;
 pub mod user;
 pub mod utilities;
 pub mod validator;
@@ -1,48 +1,386 @@
 use actix_multipart::Multipart;
-use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use actix_web::http::header::{ACCEPT, CACHE_CONTROL, CONTENT_DISPOSITION, LOCATION};
+use actix_web::{get, patch, post, web, HttpRequest, HttpResponse, Responder};
 use mongodb::bson::doc;
 
 use crate::{
-    controller::error_message::{BAD_REQUEST, INTERNAL_SERVER_ERROR},
+    controller::error_message::{BAD_REQUEST, INTERNAL_SERVER_ERROR, LABEL_TAKEN, NOT_FOUND},
+    service::export::ExportFormat,
+    service::label::LabelLayout,
+    service::nestbox::{conceal_location, NestboxCreateResult, NestboxUpdateResult},
+    service::qr::{QrEcLevel, QrFormat, QR_SIZE_DEFAULT},
+    service::res_structs::{GeolocationResponse, NestboxResponse},
+    service::route::{render_gpx, RouteFormat},
     ServiceContainer,
 };
 
 use super::{
     error_message::create_error_message,
-    req_structs::{GeolocationReq, NestboxReq},
-    utilities::{nestbox_req_is_authorized, parse_auth_header},
+    req_structs::{
+        ExportQuery, GeolocationReq, GeolocationsQuery, LabelSheetQuery, NearQuery, NestboxCodeReq,
+        NestboxCreateReq, NestboxPatchReq, NestboxReq, NestboxesQuery, QrQuery, RouteQuery,
+    },
+    role::Role,
+    utilities::{
+        mandant_req_is_authorized, nestbox_req_is_authorized, resolve_nestbox_req,
+        role_is_authorized, DocumentResponse, PagingQuery, Sanatiz, SessionObject,
+    },
     validator::Validator,
 };
 
+#[get("/nestboxes")]
+pub async fn nestboxes_list_get(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    mut paging: web::Query<PagingQuery>,
+    query: web::Query<NestboxesQuery>,
+) -> impl Responder {
+    if !query.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    paging.sanatizing();
+    match app_data
+        .nestbox
+        .get_by_mandant_uuid_filtered(session.get_mandant_uuid(), &query, &paging)
+        .await
+    {
+        Ok((nestboxes, counted_documents)) => {
+            HttpResponse::Ok().json(DocumentResponse::<NestboxResponse>::new(
+                nestboxes,
+                counted_documents,
+                &paging,
+            ))
+        }
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+/// Public, only users of the mandant of the nestbox see its exact location.
 #[get("/nestboxes/{uuid}")]
 pub async fn nestboxes_get(
     app_data: web::Data<ServiceContainer>,
+    session: Option<SessionObject>,
     nestbox: web::Path<NestboxReq>,
 ) -> HttpResponse {
-    if !nestbox.is_valid() {
+    let nestbox = match resolve_nestbox_req(&app_data, nestbox).await {
+        Ok(n) => n,
+        Err(response) => return response,
+    };
+    let session = session.unwrap_or_default();
+    match app_data.nestbox.get_by_uuid(&nestbox.uuid).await.pop() {
+        Some(mut nestbox) => {
+            if mandant_req_is_authorized(&session, &nestbox.mandant_uuid, Role::Viewer).is_some() {
+                conceal_location(&mut nestbox);
+            }
+            HttpResponse::Ok().json(nestbox)
+        }
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+/// Target of the QR codes, redirects the short code to its nestbox.
+#[get("/n/{code}")]
+pub async fn nestbox_code_get(
+    app_data: web::Data<ServiceContainer>,
+    nestbox_code: web::Path<NestboxCodeReq>,
+) -> HttpResponse {
+    if !nestbox_code.is_valid() {
         return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
     }
+    match app_data.nestbox.get_by_code(&nestbox_code.code).await {
+        Ok(Some(nestbox)) => HttpResponse::Found()
+            .insert_header((LOCATION, format!("/nestboxes/{}", nestbox.uuid)))
+            .finish(),
+        Ok(None) => HttpResponse::NotFound().json(create_error_message(NOT_FOUND)),
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
 
-    let result = app_data.nestbox.get_by_uuid(&nestbox.uuid).await;
-    match result.get(0) {
-        Some(nestbox) => HttpResponse::Ok().json(nestbox),
-        None => HttpResponse::NotFound().finish(),
+/// Registered before `nestboxes_get`, which would take "near" for an uuid.
+#[get("/nestboxes/near")]
+pub async fn nestboxes_near_get(
+    app_data: web::Data<ServiceContainer>,
+    session: Option<SessionObject>,
+    query: web::Query<NearQuery>,
+) -> impl Responder {
+    if !query.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    // Anonymous users see the public nestboxes of every mandant.
+    let session = session.unwrap_or_default();
+    match app_data.nestbox.get_near(&session, &query).await {
+        Ok(nestboxes) => HttpResponse::Ok().json(nestboxes),
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+/// Registered before `nestboxes_get`, which would take "export" for an uuid.
+#[get("/nestboxes/export")]
+pub async fn nestboxes_export_get(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    export_query: web::Query<ExportQuery>,
+    at_query: web::Query<GeolocationsQuery>,
+    query: web::Query<NestboxesQuery>,
+) -> impl Responder {
+    if let Some(value) = role_is_authorized(&session, Role::Viewer) {
+        return value;
+    }
+    if !at_query.is_valid() || !query.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    let format = export_query.format.unwrap_or(ExportFormat::Geojson);
+    match app_data
+        .export
+        .export(
+            session.get_mandant_uuid(),
+            &query,
+            at_query.at_date(),
+            format,
+        )
+        .await
+    {
+        Ok(export) => HttpResponse::Ok()
+            .content_type(format.content_type())
+            .insert_header((
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"nestboxes.{}\"", format.extension()),
+            ))
+            .body(export),
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+/// Registered before `nestboxes_get`, which would take "route" for an uuid.
+#[get("/nestboxes/route")]
+pub async fn nestboxes_route_get(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    route_query: web::Query<RouteQuery>,
+    query: web::Query<NestboxesQuery>,
+) -> impl Responder {
+    if let Some(value) = role_is_authorized(&session, Role::Cleaner) {
+        return value;
+    }
+    if !route_query.is_valid() || !query.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    match app_data
+        .route
+        .plan(
+            session.get_mandant_uuid(),
+            &query,
+            route_query.lat,
+            route_query.long,
+            route_query.routes(),
+        )
+        .await
+    {
+        Ok(routes) => match route_query.format.unwrap_or(RouteFormat::Json) {
+            RouteFormat::Json => HttpResponse::Ok().json(routes),
+            RouteFormat::Gpx => HttpResponse::Ok()
+                .content_type(ExportFormat::Gpx.content_type())
+                .insert_header((CONTENT_DISPOSITION, "attachment; filename=\"routes.gpx\""))
+                .body(render_gpx(&routes)),
+        },
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+/// Registered before `nestboxes_get`, which would take "labels" for an uuid.
+#[get("/nestboxes/labels")]
+pub async fn nestboxes_labels_get(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    label_query: web::Query<LabelSheetQuery>,
+    query: web::Query<NestboxesQuery>,
+) -> impl Responder {
+    if let Some(value) = role_is_authorized(&session, Role::Coordinator) {
+        return value;
+    }
+    if !label_query.is_valid() || !query.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    match app_data
+        .label
+        .label_sheets(
+            session.get_mandant_uuid(),
+            &query,
+            label_query.layout.unwrap_or(LabelLayout::L7160),
+            label_query.ec.unwrap_or(QrEcLevel::Q),
+            label_query.skip.unwrap_or(0),
+        )
+        .await
+    {
+        Ok(Some(pdf)) => HttpResponse::Ok()
+            .content_type("application/pdf")
+            .insert_header((CONTENT_DISPOSITION, "inline; filename=\"labels.pdf\""))
+            .body(pdf),
+        Ok(None) => HttpResponse::NotFound().json(create_error_message(NOT_FOUND)),
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+#[get("/nestboxes/{uuid}/qr")]
+pub async fn nestboxes_qr_get(
+    app_data: web::Data<ServiceContainer>,
+    http_req: HttpRequest,
+    nestbox: web::Path<NestboxReq>,
+    qr_query: web::Query<QrQuery>,
+) -> HttpResponse {
+    if !qr_query.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    let nestbox = match resolve_nestbox_req(&app_data, nestbox).await {
+        Ok(n) => n,
+        Err(response) => return response,
+    };
+    let nestbox = match app_data.nestbox.get_by_uuid(&nestbox.uuid).await.pop() {
+        Some(n) => n,
+        None => return HttpResponse::NotFound().json(create_error_message(NOT_FOUND)),
+    };
+    let format = qr_query.format.unwrap_or_else(|| {
+        let accept = http_req
+            .headers()
+            .get(ACCEPT)
+            .and_then(|a| a.to_str().ok())
+            .unwrap_or("");
+        if accept.contains(QrFormat::Svg.content_type()) {
+            QrFormat::Svg
+        } else {
+            QrFormat::Png
+        }
+    });
+    match app_data.qr.render(
+        &nestbox,
+        format,
+        qr_query.size.unwrap_or(QR_SIZE_DEFAULT),
+        qr_query.ec.unwrap_or(QrEcLevel::M),
+    ) {
+        Some(qr) => HttpResponse::Ok()
+            .content_type(format.content_type())
+            .insert_header((CACHE_CONTROL, "public, max-age=86400"))
+            .body(qr),
+        None => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+#[post("/nestboxes")]
+pub async fn nestboxes_post(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    nestbox_data: web::Json<NestboxCreateReq>,
+) -> impl Responder {
+    if let Some(value) = role_is_authorized(&session, Role::Coordinator) {
+        return value;
+    }
+    if !nestbox_data.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    match app_data
+        .nestbox
+        .create(&session, nestbox_data.nestboxes())
+        .await
+    {
+        NestboxCreateResult::Ok(uuids) => HttpResponse::Created().json(doc! {"uuids": uuids}),
+        NestboxCreateResult::LabelTaken => {
+            HttpResponse::Conflict().json(create_error_message(LABEL_TAKEN))
+        }
+        NestboxCreateResult::Error => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+#[patch("/nestboxes/{uuid}")]
+pub async fn nestboxes_patch(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    nestbox_req: web::Path<NestboxReq>,
+    nestbox_data: web::Json<NestboxPatchReq>,
+) -> impl Responder {
+    if !nestbox_data.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    let nestbox_req = match resolve_nestbox_req(&app_data, nestbox_req).await {
+        Ok(n) => n,
+        Err(response) => return response,
+    };
+    if let Some(value) =
+        nestbox_req_is_authorized(&session, &app_data, &nestbox_req, Role::Coordinator).await
+    {
+        return value;
+    }
+    match app_data
+        .nestbox
+        .update(&nestbox_req.uuid, &nestbox_data)
+        .await
+    {
+        NestboxUpdateResult::Ok(n) => HttpResponse::Ok().json(n),
+        NestboxUpdateResult::NotFound => {
+            HttpResponse::NotFound().json(create_error_message(NOT_FOUND))
+        }
+        NestboxUpdateResult::LabelTaken => {
+            HttpResponse::Conflict().json(create_error_message(LABEL_TAKEN))
+        }
+        NestboxUpdateResult::Error => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+#[post("/nestboxes/{uuid}/retire")]
+pub async fn nestboxes_retire_post(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    nestbox_req: web::Path<NestboxReq>,
+) -> impl Responder {
+    let nestbox_req = match resolve_nestbox_req(&app_data, nestbox_req).await {
+        Ok(n) => n,
+        Err(response) => return response,
+    };
+    if let Some(value) =
+        nestbox_req_is_authorized(&session, &app_data, &nestbox_req, Role::Coordinator).await
+    {
+        return value;
+    }
+    match app_data.nestbox.retire(&nestbox_req.uuid).await {
+        Ok(Some(n)) => HttpResponse::Ok().json(n),
+        Ok(None) => HttpResponse::NotFound().json(create_error_message(NOT_FOUND)),
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
     }
 }
 
 #[post("/nestboxes/{uuid}/images")]
 pub async fn nestboxes_images_post(
     app_data: web::Data<ServiceContainer>,
-    req: HttpRequest,
+    session: SessionObject,
     nestbox_req: web::Path<NestboxReq>,
     payload: Multipart,
 ) -> impl Responder {
-    let session_uuid = parse_auth_header(&req);
-    let session = app_data.session.validate_session(&session_uuid).await;
-    if !nestbox_req.is_valid() {
-        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
-    }
-    if let Some(value) = nestbox_req_is_authorized(&session, &app_data, &nestbox_req).await {
+    let nestbox_req = match resolve_nestbox_req(&app_data, nestbox_req).await {
+        Ok(n) => n,
+        Err(response) => return response,
+    };
+    if let Some(value) =
+        nestbox_req_is_authorized(&session, &app_data, &nestbox_req, Role::Cleaner).await
+    {
         return value;
     }
     let upload_status = app_data.image.save_file(payload).await;
@@ -61,20 +399,82 @@ pub async fn nestboxes_images_post(
     HttpResponse::BadRequest().json(doc! {"file_name": "undefined"})
 }
 
+/// Location history of the nestbox, or with `at` the one location valid
+/// then, which needs no paging. Needs a session of the mandant of the
+/// nestbox, see `nestboxes_get` for what others see.
+#[get("/nestboxes/{uuid}/geolocations")]
+pub async fn nestboxes_locations_get(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    nestbox_req: web::Path<NestboxReq>,
+    paging: Option<web::Query<PagingQuery>>,
+    query: web::Query<GeolocationsQuery>,
+) -> HttpResponse {
+    if let Some(value) = role_is_authorized(&session, Role::Viewer) {
+        return value;
+    }
+    if !query.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    let nestbox_req = match resolve_nestbox_req(&app_data, nestbox_req).await {
+        Ok(n) => n,
+        Err(response) => return response,
+    };
+    let Some(nestbox) = app_data.nestbox.get_by_uuid(&nestbox_req.uuid).await.pop() else {
+        return HttpResponse::NotFound().json(create_error_message(NOT_FOUND));
+    };
+    if let Some(value) = mandant_req_is_authorized(&session, &nestbox.mandant_uuid, Role::Viewer) {
+        return value;
+    }
+    if let Some(at) = query.at_date() {
+        return match app_data.geolocation.get_at(&nestbox_req.uuid, &at).await {
+            Ok(Some(g)) => HttpResponse::Ok().json(g),
+            Ok(None) => HttpResponse::NotFound().json(create_error_message(NOT_FOUND)),
+            Err(_e) => HttpResponse::InternalServerError()
+                .json(create_error_message(INTERNAL_SERVER_ERROR)),
+        };
+    }
+    let mut paging = match paging {
+        Some(p) => p,
+        None => return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST)),
+    };
+    paging.sanatizing();
+    match app_data
+        .geolocation
+        .get_by_nestbox_uuid(&nestbox_req.uuid, &paging)
+        .await
+    {
+        Ok((geolocations, counted_documents)) => {
+            HttpResponse::Ok().json(DocumentResponse::<GeolocationResponse>::new(
+                geolocations,
+                counted_documents,
+                &paging,
+            ))
+        }
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
 #[post("/nestboxes/{uuid}/geolocations")]
 pub async fn nestboxes_locations_post(
     app_data: web::Data<ServiceContainer>,
-    req: HttpRequest,
+    session: SessionObject,
     nestbox_req: web::Path<NestboxReq>,
     geoloc_req: web::Json<GeolocationReq>,
 ) -> impl Responder {
-    let session_uuid = parse_auth_header(&req);
-    let session = app_data.session.validate_session(&session_uuid).await;
-    if !nestbox_req.is_valid() {
+    if !geoloc_req.is_valid() {
         return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
     }
+    let nestbox_req = match resolve_nestbox_req(&app_data, nestbox_req).await {
+        Ok(n) => n,
+        Err(response) => return response,
+    };
 
-    if let Some(value) = nestbox_req_is_authorized(&session, &app_data, &nestbox_req).await {
+    if let Some(value) =
+        nestbox_req_is_authorized(&session, &app_data, &nestbox_req, Role::Coordinator).await
+    {
         return value;
     }
 
@@ -1,30 +1,605 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use mongodb::bson::DateTime;
 use serde::{Deserialize, Serialize};
 
-use super::validator::{is_uuid, Validator};
+use super::role::Role;
+use super::validator::{is_coordinate, is_language_tag, is_uuid, Validator};
+use crate::service::breed::{Confidence, NestState};
+use crate::service::export::ExportFormat;
+use crate::service::label::LabelLayout;
+use crate::service::nestbox::normalize_nestbox_code;
+use crate::service::qr::{QrEcLevel, QrFormat, QR_SIZE_MAX, QR_SIZE_MIN};
+use crate::service::route::RouteFormat;
+use crate::service::species::SpeciesFormat;
+
+const PASSWORD_MIN_LEN: usize = 8;
+const PASSWORD_MAX_LEN: usize = 128;
+const NESTBOX_BULK_MAX: usize = 1000;
+const NESTBOX_LABEL_MAX: usize = 64;
+const NEAR_RADIUS_DEFAULT: f64 = 1000.0;
+const NEAR_RADIUS_MAX: f64 = 50_000.0;
+const ROUTES_MAX: usize = 20;
+const BIRD_NAME_MAX: usize = 128;
+const BIRD_NAMES_MAX: usize = 64;
+const TAXON_KEY_MAX: usize = 64;
+// Larger than any clutch of the birds breeding in nestboxes.
+const CLUTCH_SIZE_MAX: u32 = 30;
+const BREED_NOTES_MAX: usize = 2000;
+const DISCOVERY_DATE_AHEAD_MILLIS: i64 = 60 * 60 * 1000;
+const DISCOVERY_DATE_MIN: DateTime = DateTime::from_millis(-2_208_988_800_000); // 1900-01-01
 
+/// Nestbox in the path, addressed by its uuid or its short code. See
+/// `resolve_nestbox_req` for the handlers working on the uuid.
 #[derive(Deserialize)]
 pub struct NestboxReq {
     pub uuid: String,
 }
 
 impl Validator for NestboxReq {
+    fn is_valid(&self) -> bool {
+        is_uuid(&self.uuid) || normalize_nestbox_code(&self.uuid).is_some()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NestboxCodeReq {
+    pub code: String,
+}
+
+impl Validator for NestboxCodeReq {
+    fn is_valid(&self) -> bool {
+        normalize_nestbox_code(&self.code).is_some()
+    }
+}
+
+/// Attributes of a new nestbox.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct NestboxDataReq {
+    #[serde(default = "default_public")]
+    pub public: bool,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub box_type: String,
+    /// Number of the nestbox within the mandant, e.g. "Wald Nord 17".
+    #[serde(default)]
+    pub label: String,
+}
+
+fn default_public() -> bool {
+    true
+}
+
+impl Validator for NestboxDataReq {
+    fn is_valid(&self) -> bool {
+        self.description.len() <= 1024
+            && self.box_type.len() <= 128
+            && self.label.trim().len() <= NESTBOX_LABEL_MAX
+    }
+}
+
+/// One nestbox or a whole batch of them, e.g. the boxes of a new forest
+/// section. Bulk goes first, serde would take an array for a struct too.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum NestboxCreateReq {
+    Bulk(Vec<NestboxDataReq>),
+    Single(NestboxDataReq),
+}
+
+impl NestboxCreateReq {
+    pub fn nestboxes(&self) -> &[NestboxDataReq] {
+        match self {
+            NestboxCreateReq::Single(n) => std::slice::from_ref(n),
+            NestboxCreateReq::Bulk(v) => v,
+        }
+    }
+}
+
+impl Validator for NestboxCreateReq {
+    fn is_valid(&self) -> bool {
+        let nestboxes = self.nestboxes();
+        !nestboxes.is_empty()
+            && nestboxes.len() <= NESTBOX_BULK_MAX
+            && nestboxes.iter().all(|n| n.is_valid())
+    }
+}
+
+/// Attributes of a nestbox to change, the others keep their value.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct NestboxPatchReq {
+    pub public: Option<bool>,
+    pub description: Option<String>,
+    pub box_type: Option<String>,
+    /// An empty label removes the label.
+    pub label: Option<String>,
+}
+
+impl Validator for NestboxPatchReq {
+    fn is_valid(&self) -> bool {
+        (self.public.is_some()
+            || self.description.is_some()
+            || self.box_type.is_some()
+            || self.label.is_some())
+            && self.description.as_ref().is_none_or(|d| d.len() <= 1024)
+            && self.box_type.as_ref().is_none_or(|b| b.len() <= 128)
+            && self
+                .label
+                .as_ref()
+                .is_none_or(|l| l.trim().len() <= NESTBOX_LABEL_MAX)
+    }
+}
+
+/// Filters of `GET /nestboxes`, all optional.
+#[derive(Default, Deserialize)]
+pub struct NestboxesQuery {
+    pub public: Option<bool>,
+    pub retired: Option<bool>,
+    pub last_breed_year: Option<i32>,
+    pub bird_uuid: Option<String>,
+    /// No breed recorded since the 1st of January.
+    pub not_inspected: Option<bool>,
+    /// Part of the label, case is ignored.
+    pub label: Option<String>,
+}
+
+impl Validator for NestboxesQuery {
+    fn is_valid(&self) -> bool {
+        self.last_breed_year
+            .is_none_or(|y| (1900..=9999).contains(&y))
+            && self.bird_uuid.as_deref().is_none_or(is_uuid)
+            && self
+                .label
+                .as_ref()
+                .is_none_or(|l| !l.trim().is_empty() && l.len() <= NESTBOX_LABEL_MAX)
+    }
+}
+
+/// Options of `GET /nestboxes/{uuid}/qr`, the format defaults to the
+/// `Accept` header.
+#[derive(Default, Deserialize)]
+pub struct QrQuery {
+    pub format: Option<QrFormat>,
+    pub size: Option<u32>,
+    pub ec: Option<QrEcLevel>,
+}
+
+impl Validator for QrQuery {
+    fn is_valid(&self) -> bool {
+        self.size
+            .is_none_or(|s| (QR_SIZE_MIN..=QR_SIZE_MAX).contains(&s))
+    }
+}
+
+/// Format of `GET /nestboxes/export`, GeoJSON if missing. The filters are
+/// those of `NestboxesQuery` and the date is `at` of `GeolocationsQuery`.
+#[derive(Default, Deserialize)]
+pub struct ExportQuery {
+    pub format: Option<ExportFormat>,
+}
+
+/// Options of `GET /nestboxes/labels` besides the filters of
+/// `NestboxesQuery`.
+#[derive(Default, Deserialize)]
+pub struct LabelSheetQuery {
+    pub layout: Option<LabelLayout>,
+    pub ec: Option<QrEcLevel>,
+    /// Labels already used on the first sheet.
+    pub skip: Option<usize>,
+}
+
+impl Validator for LabelSheetQuery {
+    fn is_valid(&self) -> bool {
+        let layout = self.layout.unwrap_or(LabelLayout::L7160);
+        self.skip.is_none_or(|s| s < layout.labels_per_sheet())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MandantReq {
+    pub uuid: String,
+}
+
+impl Validator for MandantReq {
     fn is_valid(&self) -> bool {
         is_uuid(&self.uuid)
     }
 }
 
+#[derive(Default, Clone, Deserialize, Serialize)]
+pub struct MandantDataReq {
+    pub name: String,
+    #[serde(default)]
+    pub website: String,
+    #[serde(default)]
+    pub email: String,
+    #[serde(default)]
+    pub logo: String,
+    #[serde(default)]
+    pub contact_person: String,
+    #[serde(default)]
+    pub phone: String,
+    #[serde(default)]
+    pub address: String,
+}
+
+impl Validator for MandantDataReq {
+    fn is_valid(&self) -> bool {
+        !self.name.trim().is_empty()
+            && self.name.len() <= 256
+            && is_url_or_empty(&self.website)
+            && is_url_or_empty(&self.logo)
+            && (self.email.is_empty() || (self.email.contains('@') && self.email.len() <= 128))
+            && self.contact_person.len() <= 256
+            && self.phone.len() <= 64
+            && self.address.len() <= 512
+    }
+}
+
+fn is_url_or_empty(url: &str) -> bool {
+    url.is_empty()
+        || ((url.starts_with("https://") || url.starts_with("http://")) && url.len() <= 256)
+}
+
+#[derive(Deserialize)]
+pub struct UserReq {
+    pub uuid: String,
+}
+
+impl Validator for UserReq {
+    fn is_valid(&self) -> bool {
+        is_uuid(&self.uuid)
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct UserCreateReq {
+    pub username: String,
+    pub password: String,
+    pub lastname: String,
+    #[serde(default)]
+    pub firstname: String,
+    pub email: String,
+    pub role: Role,
+    /// Only a superuser creates users in another mandant than its own.
+    #[serde(default)]
+    pub mandant_uuid: String,
+}
+
+impl Validator for UserCreateReq {
+    fn is_valid(&self) -> bool {
+        !self.username.is_empty()
+            && self.username.len() <= 64
+            && !self.username.contains(char::is_whitespace)
+            && is_password(&self.password)
+            && is_name_and_email(&self.lastname, &self.firstname, &self.email)
+            && (self.mandant_uuid.is_empty() || is_uuid(&self.mandant_uuid))
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct UserUpdateReq {
+    pub lastname: String,
+    #[serde(default)]
+    pub firstname: String,
+    pub email: String,
+    pub role: Role,
+    #[serde(default)]
+    pub locked: bool,
+}
+
+impl Validator for UserUpdateReq {
+    fn is_valid(&self) -> bool {
+        is_name_and_email(&self.lastname, &self.firstname, &self.email)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct PasswordChangeReq {
+    pub password: String,
+    pub new_password: String,
+}
+
+impl Validator for PasswordChangeReq {
+    fn is_valid(&self) -> bool {
+        is_password(&self.new_password)
+    }
+}
+
+fn is_password(password: &str) -> bool {
+    let len = password.chars().count();
+    (PASSWORD_MIN_LEN..=PASSWORD_MAX_LEN).contains(&len)
+}
+
+fn is_name_and_email(lastname: &str, firstname: &str, email: &str) -> bool {
+    !lastname.trim().is_empty()
+        && lastname.len() <= 256
+        && firstname.len() <= 256
+        && email.contains('@')
+        && email.len() <= 128
+}
+
+#[derive(Deserialize)]
+pub struct SessionReq {
+    pub session_id: String,
+}
+
+impl Validator for SessionReq {
+    fn is_valid(&self) -> bool {
+        is_uuid(&self.session_id)
+    }
+}
+
+/// Bird of a new breed, one of the mandant by its uuid or else by its name.
+/// An unknown name is added to the birds of the mandant.
 #[derive(Deserialize, Serialize)]
 pub struct BirdReq {
+    #[serde(default)]
     pub bird_uuid: String,
+    #[serde(default)]
     pub bird: String,
 }
 
+impl Validator for BirdReq {
+    fn is_valid(&self) -> bool {
+        if self.bird_uuid.is_empty() {
+            is_bird_name(&self.bird)
+        } else {
+            is_uuid(&self.bird_uuid)
+        }
+    }
+}
+
+/// Species list of `post /birds/import`, the format defaults to JSON. Birds
+/// new to the mandant are named in `language` if the list has no `bird`.
+#[derive(Deserialize)]
+pub struct BirdImportQuery {
+    pub format: Option<SpeciesFormat>,
+    pub language: Option<String>,
+}
+
+impl Validator for BirdImportQuery {
+    fn is_valid(&self) -> bool {
+        self.language.as_deref().is_none_or(is_language_tag)
+    }
+}
+
+/// Bird in the path of `/birds/{uuid}`.
+#[derive(Deserialize)]
+pub struct BirdUuidReq {
+    pub uuid: String,
+}
+
+impl Validator for BirdUuidReq {
+    fn is_valid(&self) -> bool {
+        is_uuid(&self.uuid)
+    }
+}
+
+/// Bird of the catalog of a mandant. Birds with the same `taxon_key`, e.g.
+/// a GBIF or Avibase id, are the same species in all mandants.
+#[derive(Default, Clone, Deserialize, Serialize)]
+pub struct BirdDataReq {
+    #[serde(default)]
+    pub bird: String,
+    #[serde(default)]
+    pub scientific_name: String,
+    #[serde(default)]
+    pub taxon_key: String,
+    /// Names by language tag, e.g. "de" or "de-CH".
+    #[serde(default)]
+    pub names: BTreeMap<String, String>,
+    /// Species lists call it the link of the species.
+    #[serde(default, alias = "link")]
+    pub bird_website: String,
+}
+
+impl Validator for BirdDataReq {
+    fn is_valid(&self) -> bool {
+        is_bird_name(&self.bird)
+            && self.scientific_name.trim().chars().count() <= BIRD_NAME_MAX
+            && self.taxon_key.trim().chars().count() <= TAXON_KEY_MAX
+            && is_url_or_empty(self.bird_website.trim())
+            && self.names.len() <= BIRD_NAMES_MAX
+            && self
+                .names
+                .iter()
+                .all(|(language, name)| is_language_tag(language) && is_bird_name(name))
+    }
+}
+
+fn is_bird_name(name: &str) -> bool {
+    !name.trim().is_empty() && name.trim().chars().count() <= BIRD_NAME_MAX
+}
+
+/// Bird the bird of the path is merged into.
+#[derive(Deserialize, Serialize)]
+pub struct BirdMergeReq {
+    pub bird_uuid: String,
+}
+
+impl Validator for BirdMergeReq {
+    fn is_valid(&self) -> bool {
+        is_uuid(&self.bird_uuid)
+    }
+}
+
+/// Breed in the path of `/breeds/{uuid}`.
+#[derive(Deserialize)]
+pub struct BreedReq {
+    pub uuid: String,
+}
+
+impl Validator for BreedReq {
+    fn is_valid(&self) -> bool {
+        is_uuid(&self.uuid)
+    }
+}
+
+/// What was found in the nestbox besides the bird, all optional. Counts
+/// can't exceed the ones before, e.g. more fledged than hatched.
+#[derive(Default, Deserialize, Serialize)]
+pub struct BreedObservationReq {
+    /// RFC 3339 timestamp or a date, to record breeds found earlier.
+    pub discovery_date: Option<String>,
+    pub clutch_size: Option<u32>,
+    pub hatched: Option<u32>,
+    pub fledged: Option<u32>,
+    pub nest_state: Option<NestState>,
+    pub confidence: Option<Confidence>,
+    #[serde(default)]
+    pub notes: String,
+}
+
+impl BreedObservationReq {
+    pub fn discovery_date(&self) -> Option<DateTime> {
+        parse_date(self.discovery_date.as_deref()?)
+    }
+}
+
+impl Validator for BreedObservationReq {
+    fn is_valid(&self) -> bool {
+        let date_is_valid = match (&self.discovery_date, self.discovery_date()) {
+            (None, _) => true,
+            (Some(_), Some(d)) => {
+                // Clocks of phones may be a bit ahead.
+                let latest = DateTime::now().timestamp_millis() + DISCOVERY_DATE_AHEAD_MILLIS;
+                d.timestamp_millis() <= latest
+                    && d.timestamp_millis() >= DISCOVERY_DATE_MIN.timestamp_millis()
+            }
+            (Some(_), None) => false,
+        };
+        let at_most = |count: Option<u32>, before: Option<u32>| match (count, before) {
+            (Some(c), Some(b)) => c <= b,
+            _ => true,
+        };
+        let counts = [self.clutch_size, self.hatched, self.fledged];
+        let counts_are_valid = counts.iter().flatten().all(|c| *c <= CLUTCH_SIZE_MAX)
+            && at_most(self.hatched, self.clutch_size)
+            && at_most(self.fledged, self.hatched)
+            && at_most(self.fledged, self.clutch_size);
+        date_is_valid && counts_are_valid && self.notes.chars().count() <= BREED_NOTES_MAX
+    }
+}
+
+/// Bird and observations of a new breed.
+#[derive(Deserialize, Serialize)]
+pub struct BreedCreateReq {
+    #[serde(flatten)]
+    pub bird: BirdReq,
+    #[serde(flatten)]
+    pub observation: BreedObservationReq,
+}
+
+impl Validator for BreedCreateReq {
+    fn is_valid(&self) -> bool {
+        self.bird.is_valid() && self.observation.is_valid()
+    }
+}
+
+/// Corrected nestbox, bird and observations of a breed.
+#[derive(Deserialize, Serialize)]
+pub struct BreedUpdateReq {
+    pub nestbox_uuid: String,
+    pub bird_uuid: String,
+    #[serde(flatten)]
+    pub observation: BreedObservationReq,
+}
+
+impl Validator for BreedUpdateReq {
+    fn is_valid(&self) -> bool {
+        is_uuid(&self.nestbox_uuid) && is_uuid(&self.bird_uuid) && self.observation.is_valid()
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct GeolocationReq {
     pub long: f32,
     pub lat: f32,
 }
 
+impl Validator for GeolocationReq {
+    fn is_valid(&self) -> bool {
+        is_coordinate(self.lat as f64, self.long as f64)
+    }
+}
+
+/// Point and radius in meters of `GET /nestboxes/near`.
+#[derive(Deserialize)]
+pub struct NearQuery {
+    pub lat: f64,
+    pub long: f64,
+    pub radius: Option<f64>,
+}
+
+impl NearQuery {
+    pub fn radius(&self) -> f64 {
+        self.radius.unwrap_or(NEAR_RADIUS_DEFAULT)
+    }
+}
+
+impl Validator for NearQuery {
+    fn is_valid(&self) -> bool {
+        is_coordinate(self.lat, self.long)
+            && self.radius() > 0.0
+            && self.radius() <= NEAR_RADIUS_MAX
+    }
+}
+
+/// Start point, number of routes and format of `GET /nestboxes/route`. The
+/// nestboxes to visit are selected by the filters of `NestboxesQuery`.
+#[derive(Deserialize)]
+pub struct RouteQuery {
+    pub lat: f64,
+    pub long: f64,
+    pub routes: Option<usize>,
+    pub format: Option<RouteFormat>,
+}
+
+impl RouteQuery {
+    pub fn routes(&self) -> usize {
+        self.routes.unwrap_or(1)
+    }
+}
+
+impl Validator for RouteQuery {
+    fn is_valid(&self) -> bool {
+        is_coordinate(self.lat, self.long) && (1..=ROUTES_MAX).contains(&self.routes())
+    }
+}
+
+/// RFC 3339 timestamp or a date, which stands for its midnight UTC.
+fn parse_date(date: &str) -> Option<DateTime> {
+    if let Ok(d) = DateTime::parse_rfc3339_str(date) {
+        return Some(d);
+    }
+    let midnight = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)?;
+    Some(DateTime::from_millis(midnight.and_utc().timestamp_millis()))
+}
+
+/// Options of `GET /nestboxes/{uuid}/geolocations`.
+#[derive(Default, Deserialize)]
+pub struct GeolocationsQuery {
+    /// RFC 3339 timestamp or a date, which stands for its midnight UTC.
+    pub at: Option<String>,
+}
+
+impl GeolocationsQuery {
+    pub fn at_date(&self) -> Option<DateTime> {
+        parse_date(self.at.as_deref()?)
+    }
+}
+
+impl Validator for GeolocationsQuery {
+    fn is_valid(&self) -> bool {
+        self.at.is_none() || self.at_date().is_some()
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct LoginReq {
     pub username: String,
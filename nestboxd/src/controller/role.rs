@@ -0,0 +1,96 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Role of a user within its mandant. Every role includes the rights of the
+/// ones before it:
+/// - viewer: reads nestboxes, breeds and birds
+/// - cleaner: records breeds and uploads images when checking the boxes
+/// - coordinator: relocates nestboxes and edits the bird catalog
+/// - mandant-admin: manages the users and the settings of the mandant
+/// - superuser: onboards new mandants and administers all of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    Viewer,
+    Cleaner,
+    Coordinator,
+    MandantAdmin,
+    Superuser,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Cleaner => "cleaner",
+            Role::Coordinator => "coordinator",
+            Role::MandantAdmin => "mandant-admin",
+            Role::Superuser => "superuser",
+        }
+    }
+
+    /// Whether a user with this role may create and edit users with the
+    /// other role. Coordinators take care of their cleaners and viewers,
+    /// admins may also appoint further admins.
+    pub fn may_manage(&self, other: Role) -> bool {
+        other < *self || (*self >= Role::MandantAdmin && other <= *self)
+    }
+
+    /// Role stored on a user record, users without (valid) role may only
+    /// read.
+    pub fn from_user_str(role: Option<&str>) -> Role {
+        role.and_then(|r| r.parse().ok()).unwrap_or(Role::Viewer)
+    }
+}
+
+impl FromStr for Role {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(Role::Viewer),
+            "cleaner" => Ok(Role::Cleaner),
+            "coordinator" => Ok(Role::Coordinator),
+            "mandant-admin" => Ok(Role::MandantAdmin),
+            "superuser" => Ok(Role::Superuser),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role() {
+        assert!(Role::Superuser > Role::MandantAdmin);
+        assert!(Role::MandantAdmin > Role::Coordinator);
+        assert!(Role::Coordinator > Role::Cleaner);
+        assert!(Role::Cleaner > Role::Viewer);
+        assert_eq!(
+            Role::from_user_str(Some("mandant-admin")),
+            Role::MandantAdmin
+        );
+        assert_eq!(Role::from_user_str(Some("superhero")), Role::Viewer);
+        assert_eq!(Role::from_user_str(None), Role::Viewer);
+        assert_eq!(Role::Cleaner.to_string(), "cleaner");
+    }
+
+    #[test]
+    fn test_may_manage() {
+        assert!(Role::Coordinator.may_manage(Role::Cleaner));
+        assert!(!Role::Coordinator.may_manage(Role::Coordinator));
+        assert!(Role::MandantAdmin.may_manage(Role::MandantAdmin));
+        assert!(!Role::MandantAdmin.may_manage(Role::Superuser));
+        assert!(!Role::Viewer.may_manage(Role::Viewer));
+    }
+}
@@ -0,0 +1,97 @@
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder};
+
+use crate::{service::res_structs::LoginResponse, ServiceContainer};
+
+use super::{
+    authentication::{removal_cookie, session_cookie},
+    error_message::{
+        create_error_message, BAD_REQUEST, INTERNAL_SERVER_ERROR, NOT_FOUND, UNAUTHORIZED,
+    },
+    req_structs::SessionReq,
+    utilities::{parse_user_agent, SessionObject},
+    validator::Validator,
+};
+
+#[post("/logout")]
+pub async fn logout_post(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+) -> impl Responder {
+    app_data
+        .session
+        .remove_session(session.get_session_key())
+        .await;
+    let mut response = HttpResponse::NoContent();
+    if app_data.session.cookie_enabled() {
+        response.cookie(removal_cookie());
+    }
+    response.finish()
+}
+
+#[get("/sessions")]
+pub async fn sessions_get(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+) -> impl Responder {
+    match app_data.session.get_sessions(&session).await {
+        Ok(sessions) => HttpResponse::Ok().json(sessions),
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+#[post("/sessions/refresh")]
+pub async fn sessions_refresh_post(
+    app_data: web::Data<ServiceContainer>,
+    req: HttpRequest,
+    session: SessionObject,
+) -> impl Responder {
+    match app_data
+        .session
+        .refresh_session(session.get_session_key(), &parse_user_agent(&req))
+        .await
+    {
+        Some(new_session) => {
+            let mut response = HttpResponse::Ok();
+            if app_data.session.cookie_enabled() {
+                response.cookie(session_cookie(
+                    &new_session,
+                    app_data.session.absolute_timeout(),
+                ));
+            }
+            response.json(LoginResponse {
+                username: session.get_username().to_string(),
+                success: true,
+                session: new_session,
+                role: session.get_role().to_string(),
+            })
+        }
+        // Expired in between.
+        None => HttpResponse::Unauthorized().json(create_error_message(UNAUTHORIZED)),
+    }
+}
+
+#[delete("/sessions/{session_id}")]
+pub async fn sessions_delete(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    session_req: web::Path<SessionReq>,
+) -> impl Responder {
+    if !session_req.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    match app_data
+        .session
+        .remove_session_by_id(&session, &session_req.session_id)
+        .await
+    {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        // Also sessions of other users end up here, their ids are none of
+        // the caller's business.
+        Ok(false) => HttpResponse::NotFound().json(create_error_message(NOT_FOUND)),
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
@@ -1,33 +1,211 @@
-use actix_web::{post, web, HttpResponse, Responder};
+use actix_web::{get, post, put, web, HttpRequest, HttpResponse, Responder};
 
 use crate::{
-    controller::req_structs::LoginReq, service::res_structs::LoginResponse, ServiceContainer,
+    controller::req_structs::LoginReq,
+    service::res_structs::{LoginResponse, UserResponse},
+    service::user::{PasswordChangeResult, UserCreateResult},
+    ServiceContainer,
 };
 
-use super::error_message::{create_error_message, UNAUTHORIZED};
+use super::authentication::{session_cookie, session_token};
+use super::error_message::{
+    create_error_message, BAD_REQUEST, FORBIDDEN, INTERNAL_SERVER_ERROR, NOT_FOUND, UNAUTHORIZED,
+    USERNAME_TAKEN,
+};
+use super::req_structs::{PasswordChangeReq, UserCreateReq, UserReq, UserUpdateReq};
+use super::role::Role;
+use super::utilities::{
+    mandant_req_is_authorized, parse_user_agent, role_is_authorized, DocumentResponse, PagingQuery,
+    Sanatiz, SessionObject,
+};
+use super::validator::Validator;
 
 #[post("/login")]
 pub async fn login_post(
     app_data: web::Data<ServiceContainer>,
+    req: HttpRequest,
     login: web::Json<LoginReq>,
 ) -> impl Responder {
     let user_response = app_data.user.login(&login.username, &login.password).await;
 
     match user_response {
         Some(user_obj) => {
-            return HttpResponse::Ok().json(LoginResponse {
+            let role = Role::from_user_str(user_obj.get_str("role").ok());
+            let session = app_data
+                .session
+                .create_session(user_obj, &parse_user_agent(&req))
+                .await;
+            let mut response = HttpResponse::Ok();
+            if app_data.session.cookie_enabled() {
+                response.cookie(session_cookie(
+                    &session,
+                    app_data.session.absolute_timeout(),
+                ));
+            }
+            response.json(LoginResponse {
                 username: login.username.clone(),
                 success: true,
-                session: app_data.session.create_session(user_obj).await, //String::from("n.a."),
-            });
+                session,
+                role: role.to_string(),
+            })
         }
 
         None => {
-            app_data
-                .session
-                .remove_session_by_username(&login.username)
-                .await;
+            // Only the session presented by the caller is ended, anybody can
+            // fail to log in with a username of somebody else.
+            if let Some(session_key) = session_token(&req, app_data.session.cookie_enabled()) {
+                app_data.session.remove_session(&session_key).await;
+            }
             HttpResponse::Unauthorized().json(create_error_message(UNAUTHORIZED))
         }
     }
 }
+
+#[get("/users")]
+pub async fn users_get(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    mut paging: web::Query<PagingQuery>,
+) -> impl Responder {
+    if let Some(value) = role_is_authorized(&session, Role::Coordinator) {
+        return value;
+    }
+    paging.sanatizing();
+    match app_data
+        .user
+        .get_by_mandant_uuid(session.get_mandant_uuid(), &paging)
+        .await
+    {
+        Ok((users, counted_documents)) => HttpResponse::Ok().json(
+            DocumentResponse::<UserResponse>::new(users, counted_documents, &paging),
+        ),
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+#[post("/users")]
+pub async fn users_post(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    user_data: web::Json<UserCreateReq>,
+) -> impl Responder {
+    if let Some(value) = role_is_authorized(&session, Role::Coordinator) {
+        return value;
+    }
+    if !user_data.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    let mandant_uuid = if user_data.mandant_uuid.is_empty() {
+        session.get_mandant_uuid().to_string()
+    } else {
+        if let Some(value) =
+            mandant_req_is_authorized(&session, &user_data.mandant_uuid, Role::Coordinator)
+        {
+            return value;
+        }
+        match app_data.mandant.get_by_uuid(&user_data.mandant_uuid).await {
+            Ok(Some(m)) => m.uuid,
+            Ok(None) => return HttpResponse::NotFound().json(create_error_message(NOT_FOUND)),
+            Err(_e) => {
+                return HttpResponse::InternalServerError()
+                    .json(create_error_message(INTERNAL_SERVER_ERROR))
+            }
+        }
+    };
+    if !session.get_role().may_manage(user_data.role) {
+        return HttpResponse::Forbidden().json(create_error_message(FORBIDDEN));
+    }
+    match app_data.user.create(&mandant_uuid, &user_data).await {
+        UserCreateResult::Ok(u) => HttpResponse::Created().json(u),
+        UserCreateResult::UsernameTaken => {
+            HttpResponse::Conflict().json(create_error_message(USERNAME_TAKEN))
+        }
+        UserCreateResult::Error => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+#[put("/users/{uuid}")]
+pub async fn users_put(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    user_req: web::Path<UserReq>,
+    user_data: web::Json<UserUpdateReq>,
+) -> impl Responder {
+    if !user_req.is_valid() || !user_data.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    if let Some(value) = role_is_authorized(&session, Role::Coordinator) {
+        return value;
+    }
+    let user = match app_data.user.get_by_uuid(&user_req.uuid).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return HttpResponse::NotFound().json(create_error_message(NOT_FOUND)),
+        Err(_e) => {
+            return HttpResponse::InternalServerError()
+                .json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    };
+    if let Some(value) = mandant_req_is_authorized(&session, &user.mandant_uuid, Role::Coordinator)
+    {
+        return value;
+    }
+    // Neither a user nor a role above the own one may be touched.
+    let role = Role::from_user_str(Some(&user.role));
+    if !session.get_role().may_manage(role) || !session.get_role().may_manage(user_data.role) {
+        return HttpResponse::Forbidden().json(create_error_message(FORBIDDEN));
+    }
+    if user_data.locked && user.uuid == session.get_user_uuid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    match app_data.user.update(&user_req.uuid, &user_data).await {
+        Ok(Some(u)) => {
+            // Sessions carry the role they were started with, so a locked or
+            // demoted user has to log in again.
+            if u.locked || u.role != user.role {
+                let _removed = app_data.session.remove_user_sessions(&u.username).await;
+            }
+            HttpResponse::Ok().json(u)
+        }
+        Ok(None) => HttpResponse::NotFound().json(create_error_message(NOT_FOUND)),
+        Err(_e) => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+#[put("/me/password")]
+pub async fn me_password_put(
+    app_data: web::Data<ServiceContainer>,
+    session: SessionObject,
+    password_data: web::Json<PasswordChangeReq>,
+) -> impl Responder {
+    if !password_data.is_valid() {
+        return HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST));
+    }
+    match app_data
+        .user
+        .change_password(
+            session.get_user_uuid(),
+            &password_data.password,
+            &password_data.new_password,
+        )
+        .await
+    {
+        PasswordChangeResult::Ok => {
+            // Whoever knew the old password is logged out, only this
+            // session stays.
+            let _removed = app_data.session.remove_other_sessions(&session).await;
+            HttpResponse::NoContent().finish()
+        }
+        PasswordChangeResult::WrongPassword => {
+            HttpResponse::Forbidden().json(create_error_message(FORBIDDEN))
+        }
+        PasswordChangeResult::Error => {
+            HttpResponse::InternalServerError().json(create_error_message(INTERNAL_SERVER_ERROR))
+        }
+    }
+}
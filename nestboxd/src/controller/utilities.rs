@@ -1,19 +1,22 @@
+use actix_web::http::header::{ACCEPT_LANGUAGE, USER_AGENT};
 use actix_web::{web, HttpRequest, HttpResponse};
 use mongodb::bson::Document;
-use mongodb::error::Error;
 use serde::Deserialize;
 
 use serde::Serialize;
 
+use crate::repository::RepositoryError;
 use crate::ServiceContainer;
 
-use super::error_message::UNAUTHORIZED;
 use super::error_message::{create_error_message, NESTBOX_OF_OTHER_MANDANT};
+use super::error_message::{
+    BAD_REQUEST, FORBIDDEN, INTERNAL_SERVER_ERROR, NOT_FOUND, UNAUTHORIZED,
+};
 use super::req_structs::NestboxReq;
-use super::validator::is_uuid;
+use super::role::Role;
+use super::validator::{is_language_tag, is_uuid, Validator};
 
 const MAX_PAGE_LIMIT: i64 = 100;
-const HTTP_AUTHORIZATION: &str = "Authorization";
 
 #[derive(Default, Debug, Clone, Deserialize)]
 pub struct PagingQuery {
@@ -41,43 +44,69 @@ pub struct SessionObject {
     valid_session: bool,
     user_uuid: String,
     mandant_uuid: String,
+    session_id: String,
+    session_key: String,
+    username: String,
+    role: Role,
+}
+
+impl Default for SessionObject {
+    /// No session at all, e.g. an anonymous request.
+    fn default() -> Self {
+        SessionObject {
+            user_uuid: String::from("n.a."),
+            valid_session: false,
+            mandant_uuid: String::from("n.a."),
+            session_id: String::from("n.a."),
+            session_key: String::from("n.a."),
+            username: String::from("n.a."),
+            role: Role::Viewer,
+        }
+    }
 }
 
 impl SessionObject {
-    pub fn new(user_obj: Result<Option<Document>, Error>) -> Self {
+    pub fn new(user_obj: Result<Option<Document>, RepositoryError>) -> Self {
         let session_document = match user_obj {
-            Ok(od) => match od {
-                Some(d) => d,
-                None => Document::new(),
-            },
+            Ok(od) => od.unwrap_or_default(),
             Err(_e) => Document::new(),
         };
         if session_document.is_empty() {
             // invalid session - so return - does not make sense to go any further...
-            return SessionObject {
-                user_uuid: String::from("n.a."),
-                valid_session: false,
-                mandant_uuid: String::from("n.a."),
-            };
+            return SessionObject::default();
         }
 
         let (valid_mandant, mandant_uuid) = match session_document.get("mandant_uuid") {
             Some(b) => (true, b.to_string().replace('"', "")),
             None => (false, String::from("n.a.")),
         };
-        let valid_session_key = match session_document.get("session_key") {
-            Some(_b) => true,
-            None => false,
+        let (valid_session_key, session_key) = match session_document.get_str("session_key") {
+            Ok(k) => (true, k.to_string()),
+            Err(_) => (false, String::from("n.a.")),
         };
         let (valid_user_uuid, user_uuid) = match session_document.get("uuid") {
             Some(b) => (true, b.to_string().replace('"', "")),
             None => (false, String::from("n.a.")),
         };
 
+        let session_id = session_document
+            .get_str("session_id")
+            .unwrap_or("n.a.")
+            .to_string();
+        let username = session_document
+            .get_str("username")
+            .unwrap_or("n.a.")
+            .to_string();
+        let role = Role::from_user_str(session_document.get_str("role").ok());
+
         SessionObject {
             user_uuid,
             valid_session: (valid_mandant && valid_session_key && valid_user_uuid),
             mandant_uuid,
+            session_id,
+            session_key,
+            username,
+            role,
         }
     }
 
@@ -89,6 +118,27 @@ impl SessionObject {
         self.user_uuid.as_str()
     }
 
+    pub fn get_username(&self) -> &str {
+        self.username.as_str()
+    }
+
+    pub fn get_session_id(&self) -> &str {
+        self.session_id.as_str()
+    }
+
+    pub fn get_session_key(&self) -> &str {
+        self.session_key.as_str()
+    }
+
+    pub fn get_role(&self) -> Role {
+        self.role
+    }
+
+    /// Valid session of a user with at least the given role.
+    pub fn has_role(&self, role: Role) -> bool {
+        self.valid_session && self.role >= role
+    }
+
     pub fn is_valid_session(&self) -> bool {
         self.valid_session
     }
@@ -120,28 +170,106 @@ impl<P> DocumentResponse<P> {
     }
 }
 
-pub fn parse_auth_header(http_req: &HttpRequest) -> String {
-    let session_token = match http_req.headers().get(HTTP_AUTHORIZATION) {
-        Some(t) => t.to_str(),
-        None => Ok("n.a."),
+pub fn parse_user_agent(http_req: &HttpRequest) -> String {
+    match http_req.headers().get(USER_AGENT) {
+        Some(u) => u.to_str().unwrap_or("n.a.").to_string(),
+        None => String::from("n.a."),
+    }
+}
+
+/// Languages of the `Accept-Language` header in lower case, the preferred
+/// first. Wildcards and languages with a weight of 0 are left out.
+pub fn accept_languages(http_req: &HttpRequest) -> Vec<String> {
+    let header = match http_req.headers().get(ACCEPT_LANGUAGE) {
+        Some(h) => h.to_str().unwrap_or_default(),
+        None => return Vec::new(),
     };
-    let session_str = session_token.unwrap().replace("Basic ", "");
-    if is_uuid(&session_str) {
-        session_str
-    } else {
-        String::from("00000000-0000-0000-0000-00000000")
+    parse_accept_languages(header)
+}
+
+fn parse_accept_languages(header: &str) -> Vec<String> {
+    let mut languages: Vec<(String, f64)> = header
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let tag = parts.next()?;
+            let weight = parts
+                .find_map(|p| p.strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.parse::<f64>().ok())?;
+            if !is_language_tag(tag) || weight <= 0.0 {
+                return None;
+            }
+            Some((tag.to_lowercase(), weight))
+        })
+        .collect();
+    // Stable, so languages of equal weight keep their order.
+    languages.sort_by(|a, b| b.1.total_cmp(&a.1));
+    languages.into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// Forbidden response if the user lacks the role.
+pub fn role_is_authorized(session: &SessionObject, role: Role) -> Option<HttpResponse> {
+    if !session.is_valid_session() {
+        return Some(HttpResponse::Unauthorized().json(create_error_message(UNAUTHORIZED)));
+    }
+    if !session.has_role(role) {
+        return Some(HttpResponse::Forbidden().json(create_error_message(FORBIDDEN)));
+    }
+    None
+}
+
+/// Forbidden response unless the user has the role within the given mandant
+/// or is a superuser.
+pub fn mandant_req_is_authorized(
+    session: &SessionObject,
+    mandant_uuid: &str,
+    role: Role,
+) -> Option<HttpResponse> {
+    if session.has_role(Role::Superuser) {
+        return None;
+    }
+    if let Some(value) = role_is_authorized(session, role) {
+        return Some(value);
+    }
+    if session.get_mandant_uuid() != mandant_uuid {
+        return Some(HttpResponse::Forbidden().json(create_error_message(FORBIDDEN)));
+    }
+    None
+}
+
+/// Nestbox of the path addressed by its uuid, a short code is looked up.
+/// Bad request for anything else and not found for an unknown code.
+pub async fn resolve_nestbox_req(
+    app_data: &ServiceContainer,
+    nestbox_req: web::Path<NestboxReq>,
+) -> Result<NestboxReq, HttpResponse> {
+    let nestbox_req = nestbox_req.into_inner();
+    if !nestbox_req.is_valid() {
+        return Err(HttpResponse::BadRequest().json(create_error_message(BAD_REQUEST)));
+    }
+    if is_uuid(&nestbox_req.uuid) {
+        return Ok(nestbox_req);
+    }
+    match app_data.nestbox.get_by_code(&nestbox_req.uuid).await {
+        Ok(Some(n)) => Ok(NestboxReq { uuid: n.uuid }),
+        Ok(None) => Err(HttpResponse::NotFound().json(create_error_message(NOT_FOUND))),
+        Err(_e) => {
+            Err(HttpResponse::InternalServerError()
+                .json(create_error_message(INTERNAL_SERVER_ERROR)))
+        }
     }
 }
 
 pub async fn nestbox_req_is_authorized(
     session: &super::utilities::SessionObject,
     app_data: &ServiceContainer,
-    nestbox_req: &web::Path<NestboxReq>,
+    nestbox_req: &NestboxReq,
+    role: Role,
 ) -> Option<HttpResponse> {
-    if !session.is_valid_session() {
-        //User must have a valid session here, if not it does not make sense
-        //to proceed.
-        return Some(HttpResponse::Unauthorized().json(create_error_message(UNAUTHORIZED)));
+    //User must have a valid session with the role needed here, if not it
+    //does not make sense to proceed.
+    if let Some(value) = role_is_authorized(session, role) {
+        return Some(value);
     }
     match app_data
         .nestbox
@@ -161,3 +289,22 @@ pub async fn nestbox_req_is_authorized(
         Err(_) => Some(HttpResponse::InternalServerError().json(())),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_accept_languages() {
+        assert_eq!(
+            parse_accept_languages("en-GB,de;q=0.8, fr;q=0.9,*;q=0.5"),
+            vec!["en-gb", "fr", "de"]
+        );
+        assert_eq!(
+            parse_accept_languages("de-CH, de;q=0, it"),
+            vec!["de-ch", "it"]
+        );
+        assert!(parse_accept_languages("").is_empty());
+        assert!(parse_accept_languages("en;q=x").is_empty());
+    }
+}
@@ -17,6 +17,21 @@ pub fn is_uuid(uuid: &str) -> bool {
     UUID_PATTERN.is_match(uuid)
 }
 
+/// Position a GeoJSON point may have, in degrees.
+pub fn is_coordinate(lat: f64, long: f64) -> bool {
+    (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&long)
+}
+
+/// Language tag of `Accept-Language` and bird names, a primary language
+/// with an optional region, e.g. "de" or "de-CH".
+pub fn is_language_tag(tag: &str) -> bool {
+    lazy_static! {
+        static ref LANGUAGE_TAG_PATTERN: Regex =
+            Regex::new("^[A-Za-z]{2,3}(-[A-Za-z0-9]{2,8})?$").unwrap();
+    }
+    LANGUAGE_TAG_PATTERN.is_match(tag)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -30,4 +45,23 @@ mod tests {
         assert!(!is_uuid("invalid"));
         assert!(!is_uuid("9496be03-8e94-48c9-ad08-0e6fa8b3720"))
     }
+
+    #[actix_rt::test]
+    async fn test_coordinate_validator() {
+        assert!(is_coordinate(47.5, 8.5));
+        assert!(is_coordinate(-90.0, 180.0));
+        assert!(!is_coordinate(90.5, 8.5));
+        assert!(!is_coordinate(47.5, -180.5));
+        assert!(!is_coordinate(f64::NAN, 8.5));
+    }
+
+    #[actix_rt::test]
+    async fn test_language_tag_validator() {
+        assert!(is_language_tag("de"));
+        assert!(is_language_tag("de-CH"));
+        assert!(is_language_tag("gsw"));
+        assert!(!is_language_tag("d"));
+        assert!(!is_language_tag("de_CH"));
+        assert!(!is_language_tag("*"));
+    }
 }
@@ -1,18 +1,78 @@
 use getopts::Options;
 use std::env;
+use std::path::Path;
 use std::process;
 use yaml_rust::YamlLoader;
 
 fn print_usage(program: &str, opts: &Options) {
-    let brief = format!("Usage: {} -c CONFIG_FILE", program);
+    let brief = format!(
+        "Usage: {} -c CONFIG_FILE [--labels MANDANT_UUID -o OUTPUT_FILE [-q QUERY]] \
+        [--import-birds MANDANT_UUID [-i INPUT_FILE] [-q QUERY]]",
+        program
+    );
     print!("{}", opts.usage(&brief));
 }
 
-pub fn extract_argv() -> String {
+/// Command line of nestboxd, it serves the API unless a task is given.
+pub struct Args {
+    pub config_file: String,
+    pub labels: Option<LabelsArgs>,
+    pub import_birds: Option<ImportBirdsArgs>,
+}
+
+/// Renders the label sheets of a mandant into a PDF file and exits.
+pub struct LabelsArgs {
+    pub mandant_uuid: String,
+    pub output_file: String,
+    /// Query string as taken by `GET /nestboxes/labels`.
+    pub query: String,
+}
+
+/// Imports a species list into the birds of a mandant and exits.
+pub struct ImportBirdsArgs {
+    pub mandant_uuid: String,
+    /// CSV or JSON species list, the bundled list of cavity nesters if none.
+    pub input_file: Option<String>,
+    /// Query string as taken by `POST /birds/import`.
+    pub query: String,
+}
+
+pub fn extract_argv() -> Args {
     let args: Vec<String> = env::args().collect();
     let program = args[0].clone();
     let mut opts = Options::new();
     opts.optopt("c", "config", "Path to configuration file", "CONFIG_FILE");
+    opts.optopt(
+        "",
+        "labels",
+        "Write the label sheets of the mandant and exit",
+        "MANDANT_UUID",
+    );
+    opts.optopt(
+        "o",
+        "output",
+        "PDF file the labels are written to",
+        "OUTPUT_FILE",
+    );
+    opts.optopt(
+        "q",
+        "query",
+        "Layout and selection of the labels, e.g. \"layout=l7163&retired=false\", \
+        or format and language of the birds, e.g. \"format=csv&language=de\"",
+        "QUERY",
+    );
+    opts.optopt(
+        "",
+        "import-birds",
+        "Import a species list into the birds of the mandant and exit",
+        "MANDANT_UUID",
+    );
+    opts.optopt(
+        "i",
+        "input",
+        "Species list imported, the bundled cavity nesters if missing",
+        "INPUT_FILE",
+    );
 
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
@@ -21,21 +81,68 @@ pub fn extract_argv() -> String {
             process::exit(2)
         }
     };
-    match matches.opt_str("c") {
+    let config_file = match matches.opt_str("c") {
         Some(m) => m,
         None => {
             print_usage(&program, &opts);
             process::exit(3)
         }
+    };
+    let labels = match (matches.opt_str("labels"), matches.opt_str("o")) {
+        (Some(mandant_uuid), Some(output_file)) => Some(LabelsArgs {
+            mandant_uuid,
+            output_file,
+            query: matches.opt_str("q").unwrap_or_default(),
+        }),
+        (None, None) => None,
+        _ => {
+            print_usage(&program, &opts);
+            process::exit(2)
+        }
+    };
+    let import_birds = match matches.opt_str("import-birds") {
+        Some(_) if labels.is_some() => {
+            print_usage(&program, &opts);
+            process::exit(2)
+        }
+        Some(mandant_uuid) => Some(ImportBirdsArgs {
+            mandant_uuid,
+            input_file: matches.opt_str("i"),
+            query: matches.opt_str("q").unwrap_or_default(),
+        }),
+        None => None,
+    };
+    Args {
+        config_file,
+        labels,
+        import_birds,
     }
 }
 
+pub const STORAGE_MONGODB: &str = "mongodb";
+pub const STORAGE_MEMORY: &str = "memory";
+pub const STORAGE_POSTGRES: &str = "postgres";
+pub const STORAGE_SQLITE: &str = "sqlite";
+const SQLITE_DEFAULT_FILE: &str = "nestboxd.sqlite";
+pub const SESSION_ABSOLUTE_TIMEOUT_DEFAULT: i64 = 604800;
+pub const SESSION_IDLE_TIMEOUT_DEFAULT: i64 = 86400;
+
+#[derive(Clone, Default)]
 pub struct Config {
+    pub storage_backend: String,
     pub mongodb_uri: String,
     pub mongodb_database: String,
+    pub postgres_uri: String,
+    pub sqlite_file: String,
     pub httpserver_ip: String,
     pub httpserver_port: String,
     pub image_directory: String,
+    pub memory_dump_directory: Option<String>,
+    pub password_pepper: String,
+    pub session_absolute_timeout: i64,
+    pub session_idle_timeout: i64,
+    pub session_cookie: bool,
+    pub qr_base_url: String,
 }
 
 pub fn parse_yaml(config_file: String) -> Config {
@@ -45,21 +152,84 @@ pub fn parse_yaml(config_file: String) -> Config {
     };
     let config_yaml = YamlLoader::load_from_str(&config_str).unwrap();
     let config_doc = &config_yaml[0];
+    // MongoDB stays the default so existing config files keep working.
+    let storage_backend = config_doc["storage"]["backend"]
+        .as_str()
+        .unwrap_or(STORAGE_MONGODB)
+        .to_string();
+    let (mongodb_uri, mongodb_database) = if storage_backend == STORAGE_MONGODB {
+        (
+            config_doc["mongodb"]["uri"].as_str().unwrap().to_string(),
+            config_doc["mongodb"]["database"]
+                .as_str()
+                .unwrap()
+                .to_string(),
+        )
+    } else {
+        (String::new(), String::new())
+    };
+    let postgres_uri = if storage_backend == STORAGE_POSTGRES {
+        config_doc["postgres"]["uri"].as_str().unwrap().to_string()
+    } else {
+        String::new()
+    };
+    let image_directory = config_doc["images"]["directory"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    // Without an explicit file the database is placed next to the image
+    // directory, so one data directory holds everything.
+    let sqlite_file = match config_doc["sqlite"]["file"].as_str() {
+        Some(f) => f.to_string(),
+        None => sqlite_file_next_to(&image_directory),
+    };
+    let httpserver_ip = config_doc["httpserver"]["ip"].as_str().unwrap().to_string();
+    let httpserver_port = config_doc["httpserver"]["port"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    // The labels outlive any test setup, so production wants the public
+    // address here. Without it the codes point to nestboxd itself.
+    let qr_base_url = match config_doc["qr"]["base_url"].as_str() {
+        Some(u) => u.to_string(),
+        None => format!("http://{}:{}", httpserver_ip, httpserver_port),
+    };
     Config {
-        mongodb_uri: config_doc["mongodb"]["uri"].as_str().unwrap().to_string(),
-        mongodb_database: config_doc["mongodb"]["database"]
+        storage_backend,
+        mongodb_uri,
+        mongodb_database,
+        postgres_uri,
+        sqlite_file,
+        httpserver_ip,
+        httpserver_port,
+        image_directory,
+        memory_dump_directory: config_doc["memory"]["dump_directory"]
             .as_str()
-            .unwrap()
-            .to_string(),
-        httpserver_ip: config_doc["httpserver"]["ip"].as_str().unwrap().to_string(),
-        httpserver_port: config_doc["httpserver"]["port"]
-            .as_str()
-            .unwrap()
-            .to_string(),
-        image_directory: config_doc["images"]["directory"]
+            .map(String::from),
+        // Secret mixed into every Argon2 password hash, kept out of the
+        // database on purpose. Changing it invalidates all passwords.
+        password_pepper: config_doc["security"]["pepper"]
             .as_str()
-            .unwrap()
+            .unwrap_or("")
             .to_string(),
+        // Seconds after login respectively after the last request.
+        session_absolute_timeout: config_doc["session"]["absolute_timeout"]
+            .as_i64()
+            .unwrap_or(SESSION_ABSOLUTE_TIMEOUT_DEFAULT),
+        session_idle_timeout: config_doc["session"]["idle_timeout"]
+            .as_i64()
+            .unwrap_or(SESSION_IDLE_TIMEOUT_DEFAULT),
+        // HttpOnly session cookie for a browser GUI, off by default.
+        session_cookie: config_doc["session"]["cookie"].as_bool().unwrap_or(false),
+        qr_base_url,
+    }
+}
+
+fn sqlite_file_next_to(image_directory: &str) -> String {
+    let image_path = Path::new(image_directory.trim_end_matches('/'));
+    match image_path.parent() {
+        Some(p) => p.join(SQLITE_DEFAULT_FILE).to_string_lossy().to_string(),
+        None => String::from(SQLITE_DEFAULT_FILE),
     }
 }
 
@@ -72,9 +242,27 @@ mod tests {
     fn test_config() {
         let config = parse_yaml(String::from("nestboxd_conf.yaml"));
         assert_eq!(config.httpserver_ip, String::from("127.0.0.1"));
+        assert_eq!(config.storage_backend, String::from(STORAGE_MONGODB));
         assert_eq!(
             config.image_directory,
             String::from("/home/doerig/temp/nestbox_images")
-        )
+        );
+        assert_eq!(
+            config.sqlite_file,
+            String::from("/home/doerig/temp/nestboxd.sqlite")
+        );
+        assert_eq!(config.password_pepper, String::from(""));
+        assert_eq!(config.session_absolute_timeout, 604800);
+        assert_eq!(config.session_idle_timeout, 86400);
+        assert!(!config.session_cookie);
+        assert_eq!(config.qr_base_url, String::from("http://127.0.0.1:8080"));
+    }
+
+    #[test]
+    fn test_sqlite_file_next_to() {
+        assert_eq!(
+            sqlite_file_next_to("/var/lib/nestbox/images/"),
+            String::from("/var/lib/nestbox/nestboxd.sqlite")
+        );
     }
 }
@@ -1,69 +1,180 @@
 use actix_web::web::Data;
 use actix_web::{middleware::Logger, App, HttpServer};
-use extract_argv::{extract_argv, parse_yaml};
-use mongodb::{options::ClientOptions, Client, Database};
+use extract_argv::{
+    extract_argv, parse_yaml, Config, STORAGE_MEMORY, STORAGE_MONGODB, STORAGE_POSTGRES,
+    STORAGE_SQLITE,
+};
+use mongodb::{options::ClientOptions, Client};
+use repository::memory::MemoryRepository;
+use repository::mongo::MongoRepository;
+use repository::postgres::PostgresRepository;
+use repository::sqlite::SqliteRepository;
+use repository::Repositories;
 use service::bird::BirdService;
 use service::breed::BreedService;
+use service::export::ExportService;
 use service::geolocation::GeolocationService;
 use service::image::ImageService;
+use service::label::LabelService;
+use service::mandant::MandantService;
 use service::nestbox::NestboxService;
+use service::qr::QrService;
+use service::route::RouteService;
 use service::session::SessionService;
 use service::user::UserService;
+mod cli;
 mod controller;
 mod extract_argv;
+mod repository;
 mod service;
 
 //
 pub struct ServiceContainer {
     image: ImageService,
     nestbox: NestboxService,
+    mandant: MandantService,
     user: UserService,
     session: SessionService,
     breed: BreedService,
     bird: BirdService,
     geolocation: GeolocationService,
+    qr: QrService,
+    label: LabelService,
+    export: ExportService,
+    route: RouteService,
 }
 
 impl ServiceContainer {
-    pub fn new(db: Database, image_directory: String) -> Self {
+    pub fn new(repositories: &Repositories, config: &Config) -> Self {
+        let nestbox = NestboxService::new(repositories.nestbox.clone());
+        let mandant = MandantService::new(repositories.mandant.clone());
+        let qr = QrService::new(&config.qr_base_url);
+        let breed = BreedService::new(repositories.breed.clone());
+        let geolocation = GeolocationService::new(repositories.geolocation.clone());
         ServiceContainer {
-            nestbox: NestboxService::new(&db),
-            user: UserService::new(&db),
-            session: SessionService::new(&db),
-            breed: BreedService::new(&db),
-            bird: BirdService::new(&db),
-            geolocation: GeolocationService::new(&db),
-            image: ImageService::new(image_directory),
+            label: LabelService::new(nestbox.clone(), mandant.clone(), qr.clone()),
+            export: ExportService::new(nestbox.clone(), breed.clone(), geolocation.clone()),
+            route: RouteService::new(nestbox.clone()),
+            nestbox,
+            mandant,
+            user: UserService::new(repositories.user.clone(), &config.password_pepper),
+            session: SessionService::new(
+                repositories.session.clone(),
+                config.session_absolute_timeout,
+                config.session_idle_timeout,
+                config.session_cookie,
+            ),
+            bird: BirdService::new(repositories.bird.clone(), breed.clone()),
+            breed,
+            geolocation,
+            image: ImageService::new(config.image_directory.clone()),
+            qr,
         }
     }
 }
 
+async fn create_repositories(config: &Config) -> Repositories {
+    match config.storage_backend.as_str() {
+        STORAGE_MONGODB => {
+            let client_options = ClientOptions::parse(&config.mongodb_uri).await.unwrap();
+            let client = Client::with_options(client_options).unwrap();
+            let repository = MongoRepository::new(client, &config.mongodb_database);
+            repository.migrate().await.unwrap();
+            Repositories::from_backend(repository)
+        }
+        STORAGE_POSTGRES => Repositories::from_backend(
+            PostgresRepository::connect(&config.postgres_uri)
+                .await
+                .unwrap(),
+        ),
+        STORAGE_SQLITE => Repositories::from_backend(
+            SqliteRepository::connect(&config.sqlite_file)
+                .await
+                .unwrap(),
+        ),
+        STORAGE_MEMORY => match &config.memory_dump_directory {
+            Some(directory) => {
+                Repositories::from_backend(MemoryRepository::from_dump(directory).unwrap())
+            }
+            None => Repositories::from_backend(MemoryRepository::new()),
+        },
+        backend => panic!("Unknown storage backend {}", backend),
+    }
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let config_struct = parse_yaml(extract_argv());
+    let args = extract_argv();
+    let config_struct = parse_yaml(args.config_file);
+    if let Some(labels) = &args.labels {
+        let repositories = create_repositories(&config_struct).await;
+        let services = ServiceContainer::new(&repositories, &config_struct);
+        std::process::exit(cli::labels(&services, labels).await);
+    }
+    if let Some(import_birds) = &args.import_birds {
+        let repositories = create_repositories(&config_struct).await;
+        let services = ServiceContainer::new(&repositories, &config_struct);
+        std::process::exit(cli::import_birds(&services, import_birds).await);
+    }
     let server_http_bind = format!(
         "{}:{}",
         &config_struct.httpserver_ip, &config_struct.httpserver_port
     );
-    let client_options = ClientOptions::parse(&config_struct.mongodb_uri)
-        .await
-        .unwrap();
-    let client = Client::with_options(client_options).unwrap();
-    let db = client.database(&config_struct.mongodb_database);
+    let repositories = create_repositories(&config_struct).await;
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+    // Nestboxes from before the short codes get theirs once. Without one a
+    // nestbox is only not found by code, so the server starts anyway and the
+    // next start tries again.
+    if let Err(e) = NestboxService::new(repositories.nestbox.clone())
+        .assign_missing_codes()
+        .await
+    {
+        eprintln!("Assigning codes to nestboxes without one failed: {}", e);
+    }
     HttpServer::new(move || {
         App::new()
             .app_data(Data::new(ServiceContainer::new(
-                db.clone(),
-                config_struct.image_directory.clone(),
+                &repositories,
+                &config_struct,
             )))
+            .service(controller::nestbox::nestboxes_list_get)
+            .service(controller::nestbox::nestboxes_labels_get)
+            .service(controller::nestbox::nestboxes_near_get)
+            .service(controller::nestbox::nestboxes_export_get)
+            .service(controller::nestbox::nestboxes_route_get)
             .service(controller::nestbox::nestboxes_get)
+            .service(controller::nestbox::nestboxes_post)
+            .service(controller::nestbox::nestboxes_patch)
+            .service(controller::nestbox::nestboxes_retire_post)
+            .service(controller::nestbox::nestboxes_qr_get)
+            .service(controller::nestbox::nestbox_code_get)
             .service(controller::user::login_post)
             .service(controller::breed::breeds_get)
             .service(controller::bird::birds_get)
+            .service(controller::bird::birds_post)
+            .service(controller::bird::birds_import_post)
+            .service(controller::bird::birds_import_default_post)
+            .service(controller::bird::birds_merge_post)
+            .service(controller::bird::birds_put)
+            .service(controller::bird::birds_delete)
             .service(controller::breed::breeds_post)
+            .service(controller::breed::breeds_put)
+            .service(controller::breed::breeds_delete)
+            .service(controller::breed::breeds_versions_get)
+            .service(controller::nestbox::nestboxes_locations_get)
             .service(controller::nestbox::nestboxes_locations_post)
             .service(controller::nestbox::nestboxes_images_post)
+            .service(controller::session::logout_post)
+            .service(controller::session::sessions_get)
+            .service(controller::session::sessions_refresh_post)
+            .service(controller::session::sessions_delete)
+            .service(controller::mandant::mandants_get)
+            .service(controller::mandant::mandants_put)
+            .service(controller::mandant::mandants_post)
+            .service(controller::user::users_get)
+            .service(controller::user::users_post)
+            .service(controller::user::users_put)
+            .service(controller::user::me_password_put)
             .wrap(Logger::default())
     })
     .bind(server_http_bind)?
@@ -73,24 +184,45 @@ async fn main() -> std::io::Result<()> {
 
 #[cfg(test)]
 mod tests {
+    use crate::controller::authentication::SESSION_COOKIE;
     use crate::controller::{
-        req_structs::{BirdReq, GeolocationReq, LoginReq},
+        req_structs::{
+            BirdDataReq, BirdMergeReq, BirdReq, BreedCreateReq, BreedObservationReq,
+            BreedUpdateReq, GeolocationReq, LoginReq, MandantDataReq, NestboxCreateReq,
+            NestboxDataReq, NestboxPatchReq, PasswordChangeReq, UserCreateReq, UserUpdateReq,
+        },
+        role::Role,
         utilities::DocumentResponse,
         validator::is_uuid,
     };
+    use crate::extract_argv::{SESSION_ABSOLUTE_TIMEOUT_DEFAULT, SESSION_IDLE_TIMEOUT_DEFAULT};
+    use crate::service::breed::{Confidence, NestState};
     use crate::service::res_structs::{
-        BirdResponse, BreedResponse, LoginResponse, NestboxResponse,
+        BirdImportResponse, BirdMergeResponse, BirdResponse, BreedResponse, BreedVersionResponse,
+        GeolocationResponse, LoginResponse, MandantResponse, NestboxNearResponse, NestboxResponse,
+        RoutesResponse, SessionResponse, UserResponse,
     };
+    use crate::service::species::{parse_species, SpeciesFormat, CAVITY_NESTERS};
+    use actix_web::http::header::{CONTENT_TYPE, WWW_AUTHENTICATE};
 
     use super::*;
+    use crate::repository::memory::fixtures::{
+        self, MANDANT_1, MANDANT_BIRDLIFE, MANDANT_STRANGER,
+    };
+    use lazy_static::lazy_static;
 
     use actix_http::header::HeaderValue;
     use actix_web::{http::StatusCode, test, App};
+    use mongodb::bson::{Bson, Document};
+    use serde::Serialize;
 
     #[derive(Clone)]
     enum HttpMethod {
         Post,
         Get,
+        Put,
+        Patch,
+        Delete,
     }
 
     #[derive(Clone)]
@@ -100,12 +232,34 @@ mod tests {
         Breeds(HttpMethod),
         Login(HttpMethod),
         Nestboxes(HttpMethod),
+        Sessions(HttpMethod),
+        Mandants(HttpMethod),
+        Users(HttpMethod),
     }
 
+    // Serialized as the bare request body.
+    #[derive(Serialize)]
+    #[serde(untagged)]
     enum RequestData {
         Login(LoginReq),
         Bird(BirdReq),
+        BirdData(BirdDataReq),
+        BirdMerge(BirdMergeReq),
+        Breed(BreedCreateReq),
+        BreedUpdate(BreedUpdateReq),
         Geolocation(GeolocationReq),
+        Mandant(MandantDataReq),
+        UserCreate(UserCreateReq),
+        UserUpdate(UserUpdateReq),
+        PasswordChange(PasswordChangeReq),
+        NestboxCreate(NestboxCreateReq),
+        NestboxPatch(NestboxPatchReq),
+        // Header of a get request rather than a body.
+        #[serde(skip)]
+        AcceptLanguage(String),
+        // Body of a post request as it is.
+        #[serde(skip)]
+        Text(String),
         Empty,
     }
 
@@ -122,6 +276,12 @@ mod tests {
     const USER_MANDANT_1_GEOLOCATION: &str = "fg_180";
     const NESTBOX_MANDANT_1: &str = "45f149a2-b05a-4de8-a358-6e704eb6efca";
     const BIRD_MANDANT_1: &str = "ffbf3bf5-868e-437b-b0e8-cf19ce2a6ad2";
+    const USER_SESSIONS: &str = "fg_11";
+    const USER_SESSIONS_DELETE: &str = "fg_10";
+    const USER_SUPERUSER: &str = "fg_1";
+    const USER_MANDANT_ADMIN: &str = "fg_10";
+    const USER_USERS_COORDINATOR: &str = "fg_180";
+    const USER_NESTBOXES_COORDINATOR: &str = "fg_1001";
 
     #[actix_rt::test]
     async fn test_200_login_post_ok() {
@@ -204,6 +364,88 @@ mod tests {
         )
         .await;
         assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            svr_resp.headers().get(WWW_AUTHENTICATE).unwrap(),
+            "Bearer realm=\"nestboxd\""
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_401_birds_get_invalid_token() {
+        let uri = "/birds?page_limit=100&page_number=1";
+        let svr_resp = build_app(
+            EndPoints::Birds(HttpMethod::Get),
+            uri,
+            NESTBOX_NOT_EXISTING,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            svr_resp.headers().get(WWW_AUTHENTICATE).unwrap(),
+            "Bearer realm=\"nestboxd\", error=\"invalid_token\""
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_200_birds_get_legacy_basic_session() {
+        let login_response = login_ok(USER_BIRDS_TEST).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(ServiceContainer::new(&repositories(), &config())))
+                .service(controller::bird::birds_get),
+        )
+        .await;
+        let svr_resp = test::TestRequest::get()
+            .uri("/birds?page_limit=1&page_number=1")
+            .insert_header((
+                actix_web::http::header::AUTHORIZATION,
+                format!("Basic {}", login_response.session),
+            ))
+            .send_request(&app)
+            .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_200_birds_get_session_cookie() {
+        let cookie_config = Config {
+            session_cookie: true,
+            ..config()
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(ServiceContainer::new(
+                    &repositories(),
+                    &cookie_config,
+                )))
+                .service(controller::user::login_post)
+                .service(controller::bird::birds_get),
+        )
+        .await;
+        let svr_resp = test::TestRequest::post()
+            .uri("/login")
+            .set_json(&LoginReq {
+                username: String::from(USER_BIRDS_TEST),
+                password: String::from(PASSWORD_CORRECT),
+            })
+            .send_request(&app)
+            .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let cookie = svr_resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == SESSION_COOKIE)
+            .unwrap()
+            .into_owned();
+        assert_eq!(cookie.http_only(), Some(true));
+
+        let svr_resp = test::TestRequest::get()
+            .uri("/birds?page_limit=1&page_number=1")
+            .cookie(cookie)
+            .send_request(&app)
+            .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
     }
 
     #[actix_rt::test]
@@ -263,6 +505,7 @@ mod tests {
         assert_eq!(svr_resp.status(), StatusCode::OK);
         let mut paging_response: DocumentResponse<BreedResponse> =
             test::read_body_json(svr_resp).await;
+        assert_eq!(paging_response.documents[0].nestbox_label, "Box 2");
         let total_documents = paging_response.counted_documents;
         let mut count_documents: i64 = 0;
         while !paging_response.documents.is_empty() {
@@ -311,6 +554,7 @@ mod tests {
         assert_eq!(svr_resp.status(), StatusCode::CREATED);
         let resp: BreedResponse = test::read_body_json(svr_resp).await;
         assert!(is_uuid(&resp.uuid));
+        assert_eq!(resp.nestbox_label, "Box 1");
         assert!(
             resp.bird_uuid == BIRD_MANDANT_1,
             "resp.bird_uuid {} expected {}",
@@ -339,122 +583,2304 @@ mod tests {
     }
 
     #[actix_rt::test]
-    async fn test_401_geolocation_post_no_session_unauthorized() {
-        let uri = format!("/nestboxes/{}/geolocations", NESTBOX_MANDANT_1);
-        let geolocation = GeolocationReq {
-            long: 8.005,
-            lat: 48.05,
-        };
+    async fn test_nestboxes_route() {
+        let coordinator = login_ok(USER_NESTBOXES_COORDINATOR).await;
+        let label = format!("Route {}", uuid::Uuid::new_v4());
         let svr_resp = build_app(
-            EndPoints::Geolocations(HttpMethod::Post),
+            EndPoints::Nestboxes(HttpMethod::Post),
+            "/nestboxes",
+            &coordinator.session,
+            RequestData::NestboxCreate(NestboxCreateReq::Bulk(
+                (0..4)
+                    .map(|n| NestboxDataReq {
+                        public: true,
+                        description: String::new(),
+                        box_type: String::new(),
+                        label: format!("{} {}", label, n),
+                    })
+                    .collect(),
+            )),
+        )
+        .await;
+        let created: Document = test::read_body_json(svr_resp).await;
+        let uuids: Vec<String> = created
+            .get_array("uuids")
+            .unwrap()
+            .iter()
+            .map(|u| u.as_str().unwrap().to_string())
+            .collect();
+        // Out of order along a line east of the start, the last one unlocated.
+        for (uuid, long) in uuids.iter().zip([151.75, 151.5, 151.625]) {
+            let svr_resp = build_app(
+                EndPoints::Geolocations(HttpMethod::Post),
+                &format!("/nestboxes/{}/geolocations", uuid),
+                &coordinator.session,
+                RequestData::Geolocation(GeolocationReq { long, lat: -33.5 }),
+            )
+            .await;
+            assert_eq!(svr_resp.status(), StatusCode::CREATED);
+        }
+
+        let uri = format!(
+            "/nestboxes/route?lat=-33.5&long=151.375&label={}",
+            label.replace(' ', "%20")
+        );
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
             &uri,
-            "",
-            RequestData::Geolocation(geolocation),
+            &coordinator.session,
+            RequestData::Empty,
         )
         .await;
-        assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let routes: RoutesResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(routes.without_location, 1);
+        assert_eq!(routes.routes.len(), 1);
+        let stops: Vec<&str> = routes.routes[0]
+            .nestboxes
+            .iter()
+            .map(|n| n.uuid.as_str())
+            .collect();
+        assert_eq!(stops, vec![&uuids[1], &uuids[2], &uuids[0]]);
+        // 0.375 degrees out and back, about 34.8 km each way.
+        assert!(
+            (routes.distance - 69_600.0).abs() < 500.0,
+            "{}",
+            routes.distance
+        );
+
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            &format!("{}&routes=2", uri),
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        let routes: RoutesResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(routes.routes.len(), 2);
+        assert_eq!(
+            routes
+                .routes
+                .iter()
+                .map(|r| r.nestboxes.len())
+                .sum::<usize>(),
+            3
+        );
+
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            &format!("{}&format=gpx", uri),
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        assert_eq!(
+            svr_resp.headers().get(CONTENT_TYPE).unwrap(),
+            "application/gpx+xml"
+        );
+        let body = test::read_body(svr_resp).await;
+        let body = String::from_utf8_lossy(&body);
+        assert!(
+            body.contains("<rtept lat=\"-33.5\" lon=\"151.5\">"),
+            "{}",
+            body
+        );
+
+        for query in ["lat=91&long=0", "lat=0&long=0&routes=0", "lat=0"] {
+            let svr_resp = build_app(
+                EndPoints::Nestboxes(HttpMethod::Get),
+                &format!("/nestboxes/route?{}", query),
+                &coordinator.session,
+                RequestData::Empty,
+            )
+            .await;
+            assert_eq!(svr_resp.status(), StatusCode::BAD_REQUEST);
+        }
     }
 
     #[actix_rt::test]
-    async fn test_401_geolocation_post_authenticated_wrong_mandant() {
-        let uri = format!("/nestboxes/{}/geolocations", NESTBOX_MANDANT_1);
-        let login_response = login_ok(USER_STRANGER_GEOLOCATION_POST).await;
-        let geolocation = GeolocationReq {
-            long: 8.005,
-            lat: 48.05,
-        };
+    async fn test_nestboxes_export() {
+        let coordinator = login_ok(USER_NESTBOXES_COORDINATOR).await;
+        let label = format!("Export {}", uuid::Uuid::new_v4());
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Post),
+            "/nestboxes",
+            &coordinator.session,
+            RequestData::NestboxCreate(NestboxCreateReq::Bulk(vec![NestboxDataReq {
+                public: true,
+                description: String::new(),
+                box_type: String::new(),
+                label: label.clone(),
+            }])),
+        )
+        .await;
+        let created: Document = test::read_body_json(svr_resp).await;
+        let uuid = created.get_array("uuids").unwrap()[0]
+            .as_str()
+            .unwrap()
+            .to_string();
         let svr_resp = build_app(
             EndPoints::Geolocations(HttpMethod::Post),
+            &format!("/nestboxes/{}/geolocations", uuid),
+            &coordinator.session,
+            RequestData::Geolocation(GeolocationReq {
+                long: 8.625,
+                lat: 47.5,
+            }),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::CREATED);
+
+        let uri = format!("/nestboxes/export?label={}", label.replace(' ', "%20"));
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
             &uri,
-            &login_response.session,
-            RequestData::Geolocation(geolocation),
+            &coordinator.session,
+            RequestData::Empty,
         )
         .await;
-        assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
-        //let resp: BreedResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        assert_eq!(
+            svr_resp.headers().get(CONTENT_TYPE).unwrap(),
+            "application/geo+json"
+        );
+        let geojson: Document = test::read_body_json(svr_resp).await;
+        let features = geojson.get_array("features").unwrap();
+        assert_eq!(features.len(), 1);
+        let feature = features[0].as_document().unwrap();
+        assert_eq!(
+            feature.get_document("properties").unwrap().get_str("uuid"),
+            Ok(uuid.as_str())
+        );
+        assert_eq!(
+            feature
+                .get_document("geometry")
+                .unwrap()
+                .get_array("coordinates")
+                .unwrap(),
+            &vec![Bson::Double(8.625), Bson::Double(47.5)]
+        );
+
+        for (format, content_type, needle) in [
+            (
+                "gpx",
+                "application/gpx+xml",
+                "<wpt lat=\"47.5\" lon=\"8.625\">",
+            ),
+            (
+                "kml",
+                "application/vnd.google-earth.kml+xml",
+                "<coordinates>8.625,47.5</coordinates>",
+            ),
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Nestboxes(HttpMethod::Get),
+                &format!("{}&format={}", uri, format),
+                &coordinator.session,
+                RequestData::Empty,
+            )
+            .await;
+            assert_eq!(svr_resp.status(), StatusCode::OK);
+            assert_eq!(svr_resp.headers().get(CONTENT_TYPE).unwrap(), content_type);
+            let body = test::read_body(svr_resp).await;
+            let body = String::from_utf8_lossy(&body);
+            assert!(body.contains(needle), "{}", body);
+            assert!(body.contains(&label));
+        }
+
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            &format!("{}&at=2000-01-01", uri),
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        let geojson: Document = test::read_body_json(svr_resp).await;
+        assert!(geojson.get_array("features").unwrap().is_empty());
+
+        for (query, session, status) in [
+            (
+                "&format=csv",
+                coordinator.session.as_str(),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                "&at=yesterday",
+                coordinator.session.as_str(),
+                StatusCode::BAD_REQUEST,
+            ),
+            ("", "", StatusCode::UNAUTHORIZED),
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Nestboxes(HttpMethod::Get),
+                &format!("{}{}", uri, query),
+                session,
+                RequestData::Empty,
+            )
+            .await;
+            assert_eq!(svr_resp.status(), status, "{}", query);
+        }
     }
 
     #[actix_rt::test]
-    async fn test_401_breeds_authenticated_wrong_mandant() {
-        let uri = format!("/nestboxes/{}/breeds", NESTBOX_MANDANT_1);
-        let login_response = login_ok(USER_STRANGER_BREED_POST).await;
-        let bird_data: BirdReq = BirdReq {
-            bird: String::from("_"),
-            bird_uuid: String::from(BIRD_MANDANT_1),
-        };
+    async fn test_nestboxes_near() {
+        // Far from the locations of the other tests, which share the store.
+        let coordinator = login_ok(USER_NESTBOXES_COORDINATOR).await;
+        let mut uuids: Vec<String> = Vec::new();
+        for (public, long) in [(false, 151.25), (true, 151.28125)] {
+            let svr_resp = build_app(
+                EndPoints::Nestboxes(HttpMethod::Post),
+                "/nestboxes",
+                &coordinator.session,
+                RequestData::NestboxCreate(NestboxCreateReq::Bulk(vec![NestboxDataReq {
+                    public,
+                    description: String::new(),
+                    box_type: String::new(),
+                    label: String::new(),
+                }])),
+            )
+            .await;
+            let created: Document = test::read_body_json(svr_resp).await;
+            let uuid = created.get_array("uuids").unwrap()[0]
+                .as_str()
+                .unwrap()
+                .to_string();
+            let svr_resp = build_app(
+                EndPoints::Geolocations(HttpMethod::Post),
+                &format!("/nestboxes/{}/geolocations", uuid),
+                &coordinator.session,
+                RequestData::Geolocation(GeolocationReq { long, lat: -33.875 }),
+            )
+            .await;
+            assert_eq!(svr_resp.status(), StatusCode::CREATED);
+            uuids.push(uuid);
+        }
         let svr_resp = build_app(
-            EndPoints::Breeds(HttpMethod::Post),
-            &uri,
-            &login_response.session,
-            RequestData::Bird(bird_data),
+            EndPoints::Geolocations(HttpMethod::Post),
+            &format!("/nestboxes/{}/geolocations", uuids[0]),
+            &coordinator.session,
+            RequestData::Geolocation(GeolocationReq {
+                long: 151.25,
+                lat: -95.0,
+            }),
         )
         .await;
-        assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(svr_resp.status(), StatusCode::BAD_REQUEST);
+
+        let uri = "/nestboxes/near?lat=-33.875&long=151.25";
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            &format!("{}&radius=5000", uri),
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let nestboxes: Vec<NestboxNearResponse> = test::read_body_json(svr_resp).await;
+        assert_eq!(
+            nestboxes
+                .iter()
+                .map(|n| n.uuid.as_str())
+                .collect::<Vec<&str>>(),
+            vec![uuids[0].as_str(), uuids[1].as_str()]
+        );
+        assert_eq!(nestboxes[0].distance, 0.0);
+        assert_eq!(nestboxes[1].long, 151.28125);
+        assert_eq!(nestboxes[1].distance, 2888.0);
+
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            &format!("{}&radius=2000", uri),
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        let nestboxes: Vec<NestboxNearResponse> = test::read_body_json(svr_resp).await;
+        assert_eq!(nestboxes.len(), 1);
+
+        // Anonymous users only see the public nestbox, roughly.
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            &format!("{}&radius=5000", uri),
+            "",
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let nestboxes: Vec<NestboxNearResponse> = test::read_body_json(svr_resp).await;
+        assert_eq!(nestboxes.len(), 1);
+        assert_eq!(nestboxes[0].uuid, uuids[1]);
+        assert_eq!(nestboxes[0].long, 151.281);
+        assert_eq!(nestboxes[0].lat, -33.875);
+        assert_eq!(nestboxes[0].distance, 2900.0);
+        // Following the uuid tells no more than the search.
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            &format!("/nestboxes/{}", nestboxes[0].uuid),
+            "",
+            RequestData::Empty,
+        )
+        .await;
+        let nestbox: NestboxResponse = test::read_body_json(svr_resp).await;
+        let geolocation = nestbox.geolocation.unwrap();
+        assert_eq!(
+            (geolocation.long, geolocation.lat),
+            (nestboxes[0].long, nestboxes[0].lat)
+        );
+
+        for query in [
+            "/nestboxes/near?lat=91&long=151.25",
+            "/nestboxes/near?lat=-33.875&long=151.25&radius=100000",
+            "/nestboxes/near?lat=-33.875&long=151.25&radius=0",
+            "/nestboxes/near?long=151.25",
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Nestboxes(HttpMethod::Get),
+                query,
+                "",
+                RequestData::Empty,
+            )
+            .await;
+            assert_eq!(svr_resp.status(), StatusCode::BAD_REQUEST, "{}", query);
+        }
     }
 
     #[actix_rt::test]
-    async fn test_401_breeds_post_not_authorized() {
-        let uri = format!("/nestboxes/{}/breeds", NESTBOX_MANDANT_1);
-        let bird_data: BirdReq = BirdReq {
-            bird: String::from("_"),
-            bird_uuid: String::from(BIRD_MANDANT_1),
-        };
+    async fn test_geolocations_get() {
+        let coordinator = login_ok(USER_NESTBOXES_COORDINATOR).await;
         let svr_resp = build_app(
-            EndPoints::Breeds(HttpMethod::Post),
+            EndPoints::Nestboxes(HttpMethod::Post),
+            "/nestboxes",
+            &coordinator.session,
+            RequestData::NestboxCreate(NestboxCreateReq::Bulk(vec![NestboxDataReq {
+                public: true,
+                description: String::new(),
+                box_type: String::new(),
+                label: String::new(),
+            }])),
+        )
+        .await;
+        let created: Document = test::read_body_json(svr_resp).await;
+        let uri = format!(
+            "/nestboxes/{}",
+            created.get_array("uuids").unwrap()[0].as_str().unwrap()
+        );
+        let uri_geolocations = format!("{}/geolocations", uri);
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
             &uri,
-            "9973e59f-771d-452f-9a1b-8b4a6d5c4f95",
-            RequestData::Bird(bird_data),
+            "",
+            RequestData::Empty,
         )
         .await;
-        assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
-    }
+        let nestbox: NestboxResponse = test::read_body_json(svr_resp).await;
+        assert!(nestbox.geolocation.is_none());
 
-    async fn login_ok(user: &str) -> LoginResponse {
-        let uri = "/login";
-        let user_name = String::from(user);
-        let user_data = LoginReq {
-            username: user_name.clone(),
-            password: String::from(PASSWORD_CORRECT),
-        };
-        let svr_login_resp = build_app(
-            EndPoints::Login(HttpMethod::Post),
-            uri,
-            "",
-            RequestData::Login(user_data),
+        for long in [8.5, 8.253906] {
+            let svr_resp = build_app(
+                EndPoints::Geolocations(HttpMethod::Post),
+                &uri_geolocations,
+                &coordinator.session,
+                RequestData::Geolocation(GeolocationReq { long, lat: 47.5 }),
+            )
+            .await;
+            assert_eq!(svr_resp.status(), StatusCode::CREATED);
+        }
+        let uri_history = format!("{}?page_limit=10&page_number=1", uri_geolocations);
+        let other_mandant = login_ok(USER_MANDANT_1).await;
+        for (session, status) in [
+            ("", StatusCode::UNAUTHORIZED),
+            (other_mandant.session.as_str(), StatusCode::FORBIDDEN),
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Geolocations(HttpMethod::Get),
+                &uri_history,
+                session,
+                RequestData::Empty,
+            )
+            .await;
+            assert_eq!(svr_resp.status(), status);
+        }
+        let svr_resp = build_app(
+            EndPoints::Geolocations(HttpMethod::Get),
+            &uri_history,
+            &coordinator.session,
+            RequestData::Empty,
         )
         .await;
-        let login_response: LoginResponse = test::read_body_json(svr_login_resp).await;
-        login_response
-    }
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let geolocations: DocumentResponse<GeolocationResponse> =
+            test::read_body_json(svr_resp).await;
+        assert_eq!(geolocations.counted_documents, 2);
+        assert_eq!(geolocations.documents[0].long, 8.25390625);
+        assert_eq!(geolocations.documents[0].lat, 47.5);
 
-    async fn build_app(
-        endpoint: EndPoints,
-        uri: &str,
-        sessiontoken: &str,
-        req: RequestData,
-    ) -> actix_web::dev::ServiceResponse {
-        let mut http_method = HttpMethod::Get;
-        let app = match endpoint {
-            EndPoints::Birds(m) => {
-                http_method = m.clone();
-                test::init_service(
-                    App::new()
-                        .app_data(Data::new(ServiceContainer::new(
-                            get_db().await,
-                            String::from(IMAGE_DIRECTORY),
-                        )))
-                        .service(controller::bird::birds_get),
-                )
-                .await
-            }
-            EndPoints::Geolocations(m) => {
-                http_method = m.clone();
-                test::init_service(
+        // Others see public nestboxes located to about 100 m only.
+        for (session, long) in [("", 8.254), (coordinator.session.as_str(), 8.25390625)] {
+            let svr_resp = build_app(
+                EndPoints::Nestboxes(HttpMethod::Get),
+                &uri,
+                session,
+                RequestData::Empty,
+            )
+            .await;
+            let nestbox: NestboxResponse = test::read_body_json(svr_resp).await;
+            let geolocation = nestbox.geolocation.unwrap();
+            assert_eq!(geolocation.uuid, geolocations.documents[0].uuid);
+            assert_eq!(geolocation.long, long);
+        }
+
+        for (query, status) in [
+            ("?at=2999-01-01T00:00:00Z", StatusCode::OK),
+            ("?at=2000-01-01", StatusCode::NOT_FOUND),
+            ("?at=yesterday", StatusCode::BAD_REQUEST),
+            ("", StatusCode::BAD_REQUEST),
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Geolocations(HttpMethod::Get),
+                &format!("{}{}", uri_geolocations, query),
+                &coordinator.session,
+                RequestData::Empty,
+            )
+            .await;
+            assert_eq!(svr_resp.status(), status, "{}", query);
+        }
+        let svr_resp = build_app(
+            EndPoints::Geolocations(HttpMethod::Get),
+            &format!("{}?at=2999-01-01T00:00:00Z", uri_geolocations),
+            "",
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
+        let svr_resp = build_app(
+            EndPoints::Geolocations(HttpMethod::Get),
+            &format!("/nestboxes/{}/geolocations", NESTBOX_NOT_EXISTING),
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_nestbox_get_hides_location_of_private_nestbox() {
+        let coordinator = login_ok(USER_NESTBOXES_COORDINATOR).await;
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Post),
+            "/nestboxes",
+            &coordinator.session,
+            RequestData::NestboxCreate(NestboxCreateReq::Bulk(vec![NestboxDataReq {
+                public: false,
+                description: String::new(),
+                box_type: String::new(),
+                label: String::new(),
+            }])),
+        )
+        .await;
+        let created: Document = test::read_body_json(svr_resp).await;
+        let uri = format!(
+            "/nestboxes/{}",
+            created.get_array("uuids").unwrap()[0].as_str().unwrap()
+        );
+        let svr_resp = build_app(
+            EndPoints::Geolocations(HttpMethod::Post),
+            &format!("{}/geolocations", uri),
+            &coordinator.session,
+            RequestData::Geolocation(GeolocationReq {
+                long: 8.253906,
+                lat: 47.5,
+            }),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::CREATED);
+
+        let other_mandant = login_ok(USER_MANDANT_1).await;
+        for (session, located) in [
+            ("", false),
+            (other_mandant.session.as_str(), false),
+            (coordinator.session.as_str(), true),
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Nestboxes(HttpMethod::Get),
+                &uri,
+                session,
+                RequestData::Empty,
+            )
+            .await;
+            assert_eq!(svr_resp.status(), StatusCode::OK);
+            let nestbox: NestboxResponse = test::read_body_json(svr_resp).await;
+            assert!(!nestbox.public);
+            assert_eq!(nestbox.geolocation.is_some(), located, "{}", session);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_403_geolocation_post_cleaner() {
+        // Cleaners record breeds but must not relocate nestboxes.
+        let uri = format!("/nestboxes/{}/geolocations", NESTBOX_MANDANT_1);
+        let login_response = login_ok(USER_MANDANT_1).await;
+        assert_eq!(login_response.role, "cleaner");
+        let geolocation = GeolocationReq {
+            long: 8.005,
+            lat: 48.05,
+        };
+        let svr_resp = build_app(
+            EndPoints::Geolocations(HttpMethod::Post),
+            &uri,
+            &login_response.session,
+            RequestData::Geolocation(geolocation),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_rt::test]
+    async fn test_birds_post_put_delete() {
+        let coordinator = login_ok(USER_MANDANT_1_GEOLOCATION).await;
+        let cleaner = login_ok(USER_MANDANT_1).await;
+        let stranger = login_ok(USER_STRANGER_BREED_POST).await;
+        let name = format!("Kohlmeise {}", uuid::Uuid::new_v4());
+        let post = |session: &str, bird: &str| {
+            let (session, bird) = (session.to_string(), bird.to_string());
+            async move {
+                build_app(
+                    EndPoints::Birds(HttpMethod::Post),
+                    "/birds",
+                    &session,
+                    RequestData::BirdData(BirdDataReq {
+                        bird,
+                        ..Default::default()
+                    }),
+                )
+                .await
+            }
+        };
+        let svr_resp = post(&coordinator.session, &format!(" {} ", name)).await;
+        assert_eq!(svr_resp.status(), StatusCode::CREATED);
+        let bird: BirdResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(bird.bird, name);
+        for (session, bird, status) in [
+            (
+                &coordinator.session,
+                name.to_uppercase(),
+                StatusCode::CONFLICT,
+            ),
+            (
+                &coordinator.session,
+                String::from(" "),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                &cleaner.session,
+                format!("{} 2", name),
+                StatusCode::FORBIDDEN,
+            ),
+        ] {
+            assert_eq!(post(session, &bird).await.status(), status, "{}", bird);
+        }
+        let svr_resp = post(&stranger.session, &name).await;
+        assert_eq!(svr_resp.status(), StatusCode::CREATED);
+        let stranger_bird: BirdResponse = test::read_body_json(svr_resp).await;
+
+        let uri = format!("/birds/{}", bird.uuid);
+        let renamed = format!("{} major", name);
+        for (session, bird, status) in [
+            (&stranger.session, renamed.clone(), StatusCode::FORBIDDEN),
+            (
+                &coordinator.session,
+                String::from("bird_0"),
+                StatusCode::CONFLICT,
+            ),
+            (&coordinator.session, renamed.clone(), StatusCode::OK),
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Birds(HttpMethod::Put),
+                &uri,
+                session,
+                RequestData::BirdData(BirdDataReq {
+                    bird: bird.clone(),
+                    ..Default::default()
+                }),
+            )
+            .await;
+            assert_eq!(svr_resp.status(), status, "{}", bird);
+        }
+
+        // Breeds name their bird, known names are looked up regardless of
+        // case, new ones added to the birds of the mandant.
+        let breed_uri = format!("/nestboxes/{}/breeds", NESTBOX_MANDANT_1);
+        let post_breed = |bird_uuid: &str, bird: &str| {
+            let (bird_uuid, bird) = (bird_uuid.to_string(), bird.to_string());
+            let session = cleaner.session.clone();
+            let breed_uri = breed_uri.clone();
+            async move {
+                build_app(
+                    EndPoints::Breeds(HttpMethod::Post),
+                    &breed_uri,
+                    &session,
+                    RequestData::Bird(BirdReq { bird_uuid, bird }),
+                )
+                .await
+            }
+        };
+        let svr_resp = post_breed("", &renamed.to_lowercase()).await;
+        assert_eq!(svr_resp.status(), StatusCode::CREATED);
+        let breed: BreedResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(breed.bird_uuid, bird.uuid);
+        assert_eq!(breed.bird, renamed);
+        let new_name = format!("Tannenmeise {}", uuid::Uuid::new_v4());
+        let svr_resp = post_breed("", &new_name).await;
+        assert_eq!(svr_resp.status(), StatusCode::CREATED);
+        let breed: BreedResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(breed.bird, new_name);
+        assert_ne!(breed.bird_uuid, bird.uuid);
+        let svr_resp = post_breed(&stranger_bird.uuid, "").await;
+        assert_eq!(svr_resp.status(), StatusCode::BAD_REQUEST);
+        let error: Document = test::read_body_json(svr_resp).await;
+        assert_eq!(error.get_str("error_message"), Ok("BIRD_OF_OTHER_MANDANT"));
+        assert_eq!(post_breed("", "").await.status(), StatusCode::BAD_REQUEST);
+
+        let svr_resp = build_app(
+            EndPoints::Birds(HttpMethod::Delete),
+            &uri,
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::CONFLICT);
+        let svr_resp = build_app(
+            EndPoints::Birds(HttpMethod::Delete),
+            &format!("/birds/{}", stranger_bird.uuid),
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::FORBIDDEN);
+        // The version kept of a deleted breed still refers to its bird.
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Delete),
+            &format!("/breeds/{}", breed.uuid),
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::NO_CONTENT);
+        let svr_resp = build_app(
+            EndPoints::Birds(HttpMethod::Delete),
+            &format!("/birds/{}", breed.bird_uuid),
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::CONFLICT);
+        for status in [StatusCode::NO_CONTENT, StatusCode::NOT_FOUND] {
+            let svr_resp = build_app(
+                EndPoints::Birds(HttpMethod::Delete),
+                &format!("/birds/{}", stranger_bird.uuid),
+                &stranger.session,
+                RequestData::Empty,
+            )
+            .await;
+            assert_eq!(svr_resp.status(), status);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_birds_taxonomy() {
+        let coordinator = login_ok(USER_STRANGER_GEOLOCATION_POST).await;
+        let id = uuid::Uuid::new_v4();
+        let bird_data = |bird: String, taxon_key: String, language: &str| {
+            RequestData::BirdData(BirdDataReq {
+                bird,
+                scientific_name: String::from("Parus major"),
+                taxon_key,
+                names: [(language, "Kohlmeise"), ("en", "Great tit")]
+                    .iter()
+                    .map(|(l, n)| (l.to_string(), n.to_string()))
+                    .collect(),
+                bird_website: String::from("https://en.wikipedia.org/wiki/Parus_major"),
+            })
+        };
+        let svr_resp = build_app(
+            EndPoints::Birds(HttpMethod::Post),
+            "/birds",
+            &coordinator.session,
+            bird_data(format!("Great tit {}", id), format!("gbif:{}", id), "de-CH"),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::CREATED);
+        let bird: BirdResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(bird.scientific_name, "Parus major");
+        assert_eq!(
+            bird.bird_website,
+            "https://en.wikipedia.org/wiki/Parus_major"
+        );
+        assert_eq!(
+            bird.names.get("de-ch").map(String::as_str),
+            Some("Kohlmeise")
+        );
+
+        for (bird_name, taxon_key, language, status) in [
+            (
+                format!("Parus {}", id),
+                format!("gbif:{}", id),
+                "de",
+                StatusCode::CONFLICT,
+            ),
+            (
+                format!("Parus {}", id),
+                String::new(),
+                "de_CH",
+                StatusCode::BAD_REQUEST,
+            ),
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Birds(HttpMethod::Post),
+                "/birds",
+                &coordinator.session,
+                bird_data(bird_name, taxon_key, language),
+            )
+            .await;
+            assert_eq!(svr_resp.status(), status, "{}", language);
+        }
+        // The bird keeps its own key.
+        let svr_resp = build_app(
+            EndPoints::Birds(HttpMethod::Put),
+            &format!("/birds/{}", bird.uuid),
+            &coordinator.session,
+            bird_data(bird.bird.clone(), bird.taxon_key.clone(), "de"),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+
+        for (accept_language, name) in [
+            ("de-CH, en;q=0.5", "Kohlmeise"),
+            ("fr,en-GB;q=0.8,de;q=0.7", "Great tit"),
+            ("fr", bird.bird.as_str()),
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Birds(HttpMethod::Get),
+                "/birds?page_limit=100&page_number=1",
+                &coordinator.session,
+                RequestData::AcceptLanguage(accept_language.to_string()),
+            )
+            .await;
+            let birds: DocumentResponse<BirdResponse> = test::read_body_json(svr_resp).await;
+            let found = birds
+                .documents
+                .iter()
+                .find(|b| b.uuid == bird.uuid)
+                .unwrap();
+            assert_eq!(found.bird, name, "{}", accept_language);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_birds_import() {
+        let coordinator = login_ok(USER_MANDANT_1_GEOLOCATION).await;
+        let cleaner = login_ok(USER_MANDANT_1).await;
+        let import = |uri: &str, session: &str, list: &str| {
+            let (uri, session, list) = (uri.to_string(), session.to_string(), list.to_string());
+            async move {
+                build_app(
+                    EndPoints::Birds(HttpMethod::Post),
+                    &uri,
+                    &session,
+                    RequestData::Text(list),
+                )
+                .await
+            }
+        };
+        let svr_resp = import("/birds/import/default", &cleaner.session, "").await;
+        assert_eq!(svr_resp.status(), StatusCode::FORBIDDEN);
+        let species = parse_species(SpeciesFormat::Csv, CAVITY_NESTERS).unwrap();
+        for (created, unchanged) in [(species.len(), 0), (0, species.len())] {
+            let svr_resp = import(
+                "/birds/import/default?language=de",
+                &coordinator.session,
+                "",
+            )
+            .await;
+            assert_eq!(svr_resp.status(), StatusCode::OK);
+            let result: BirdImportResponse = test::read_body_json(svr_resp).await;
+            assert_eq!(result.created, created);
+            assert_eq!(result.unchanged, unchanged);
+            assert!(result.rejected.is_empty());
+        }
+
+        // Known species are completed, a name of another species and an
+        // empty record rejected.
+        let name = format!("Star {}", uuid::Uuid::new_v4());
+        let list = format!(
+            "bird,scientific_name,taxon_key,link,fr\n\
+            Kohlmeise,Parus major,gbif:9705453,,Mésange\n\
+            {},,,https://www.vogelwarte.ch,\n\
+            ,,,,\n\
+            Blaumeise,Parus caeruleus,,,\n",
+            name
+        );
+        let svr_resp = import("/birds/import?format=csv", &coordinator.session, &list).await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let result: BirdImportResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(
+            (result.created, result.updated, result.unchanged),
+            (1, 1, 0)
+        );
+        assert_eq!(result.rejected, vec![3, 4]);
+        let svr_resp = import(
+            "/birds/import",
+            &coordinator.session,
+            &format!(r#"[{{"bird": "{}", "names": {{"en": "Starling"}}}}]"#, name),
+        )
+        .await;
+        let result: BirdImportResponse = test::read_body_json(svr_resp).await;
+        assert_eq!((result.created, result.updated), (0, 1));
+
+        // Quotes are part of the name, importing it again changes nothing.
+        let quoted = format!(r#"Meise "Wald" {}"#, uuid::Uuid::new_v4());
+        let list = serde_json::json!([{
+            "bird": &quoted,
+            "bird_website": r#"https://www.vogelwarte.ch/?q="Meise""#,
+        }])
+        .to_string();
+        for (created, unchanged) in [(1, 0), (0, 1)] {
+            let svr_resp = import("/birds/import", &coordinator.session, &list).await;
+            let result: BirdImportResponse = test::read_body_json(svr_resp).await;
+            assert_eq!((result.created, result.unchanged), (created, unchanged));
+        }
+        let svr_resp = build_app(
+            EndPoints::Birds(HttpMethod::Post),
+            "/birds",
+            &coordinator.session,
+            RequestData::BirdData(BirdDataReq {
+                bird: quoted,
+                ..Default::default()
+            }),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::CONFLICT);
+
+        for (uri, list) in [
+            ("/birds/import?format=csv", "bird,wingspan\nStar,40\n"),
+            ("/birds/import", "bird,scientific_name\n"),
+            ("/birds/import/default?language=de_CH", ""),
+        ] {
+            let svr_resp = import(uri, &coordinator.session, list).await;
+            assert_eq!(svr_resp.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_birds_merge() {
+        let coordinator = login_ok(USER_MANDANT_1_GEOLOCATION).await;
+        let cleaner = login_ok(USER_MANDANT_1).await;
+        let stranger = login_ok(USER_STRANGER_BREED_POST).await;
+        let (mut birds, mut names) = (Vec::new(), Vec::new());
+        for (session, name) in [
+            (&coordinator.session, "Kohlmeise "),
+            (&coordinator.session, "Kohlmeise"),
+            (&stranger.session, "Kohlmeise"),
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Birds(HttpMethod::Post),
+                "/birds",
+                session,
+                RequestData::BirdData(BirdDataReq {
+                    bird: format!("{}{}", name, uuid::Uuid::new_v4()),
+                    ..Default::default()
+                }),
+            )
+            .await;
+            let bird: BirdResponse = test::read_body_json(svr_resp).await;
+            birds.push(bird.uuid);
+            names.push(bird.bird);
+        }
+        let mut breeds = Vec::new();
+        for _ in 0..2 {
+            let svr_resp = build_app(
+                EndPoints::Breeds(HttpMethod::Post),
+                &format!("/nestboxes/{}/breeds", NESTBOX_MANDANT_1),
+                &cleaner.session,
+                RequestData::Bird(BirdReq {
+                    bird_uuid: birds[0].clone(),
+                    bird: String::new(),
+                }),
+            )
+            .await;
+            let breed: BreedResponse = test::read_body_json(svr_resp).await;
+            breeds.push(breed.uuid);
+        }
+        // A correction keeps a version of the breed with the duplicate.
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Put),
+            &format!("/breeds/{}", breeds[1]),
+            &coordinator.session,
+            RequestData::BreedUpdate(BreedUpdateReq {
+                nestbox_uuid: String::from(NESTBOX_MANDANT_1),
+                bird_uuid: birds[0].clone(),
+                observation: BreedObservationReq::default(),
+            }),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+
+        let uri = format!("/birds/{}/merge", birds[0]);
+        let merge = |session: &str, into: &str| {
+            let (session, into, uri) = (session.to_string(), into.to_string(), uri.clone());
+            async move {
+                build_app(
+                    EndPoints::Birds(HttpMethod::Post),
+                    &uri,
+                    &session,
+                    RequestData::BirdMerge(BirdMergeReq { bird_uuid: into }),
+                )
+                .await
+            }
+        };
+        for (session, into, status) in [
+            (&cleaner.session, &birds[1], StatusCode::FORBIDDEN),
+            (&coordinator.session, &birds[2], StatusCode::BAD_REQUEST),
+            (&coordinator.session, &birds[0], StatusCode::BAD_REQUEST),
+            (
+                &coordinator.session,
+                &String::from("invalid"),
+                StatusCode::BAD_REQUEST,
+            ),
+        ] {
+            assert_eq!(merge(session, into).await.status(), status, "{}", into);
+        }
+        let svr_resp = merge(&coordinator.session, &birds[1]).await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let merged: BirdMergeResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(merged.bird.uuid, birds[1]);
+        assert_eq!(merged.breeds, 2);
+        assert_eq!(
+            merge(&coordinator.session, &birds[1]).await.status(),
+            StatusCode::NOT_FOUND
+        );
+
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Get),
+            &format!("/breeds/{}/versions", breeds[1]),
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        let versions: Vec<BreedVersionResponse> = test::read_body_json(svr_resp).await;
+        // The versions tell which bird was merged.
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[1].change, "merge");
+        for version in &versions {
+            assert_eq!((&version.bird_uuid, &version.bird), (&birds[0], &names[0]));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_breeds_put_delete() {
+        let cleaner = login_ok(USER_MANDANT_1).await;
+        let coordinator = login_ok(USER_MANDANT_1_GEOLOCATION).await;
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Post),
+            &format!("/nestboxes/{}/breeds", NESTBOX_MANDANT_1),
+            &cleaner.session,
+            RequestData::Bird(BirdReq {
+                bird: String::from("_"),
+                bird_uuid: String::from(BIRD_MANDANT_1),
+            }),
+        )
+        .await;
+        let breed: BreedResponse = test::read_body_json(svr_resp).await;
+        let svr_resp = build_app(
+            EndPoints::Birds(HttpMethod::Get),
+            "/birds?page_limit=2&page_number=1",
+            &cleaner.session,
+            RequestData::Empty,
+        )
+        .await;
+        let birds: DocumentResponse<BirdResponse> = test::read_body_json(svr_resp).await;
+        let bird = birds
+            .documents
+            .iter()
+            .find(|b| b.uuid != BIRD_MANDANT_1)
+            .unwrap();
+
+        let uri = format!("/breeds/{}", breed.uuid);
+        let update = |nestbox_uuid: &str| {
+            RequestData::BreedUpdate(BreedUpdateReq {
+                nestbox_uuid: nestbox_uuid.to_string(),
+                bird_uuid: bird.uuid.clone(),
+                observation: BreedObservationReq::default(),
+            })
+        };
+        let stranger = login_ok(USER_STRANGER_BREED_POST).await;
+        for (session, nestbox_uuid, status) in [
+            ("", NESTBOX_MANDANT_1, StatusCode::UNAUTHORIZED),
+            (
+                stranger.session.as_str(),
+                NESTBOX_MANDANT_1,
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                &cleaner.session,
+                fixtures::NESTBOX_STRANGER,
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                &cleaner.session,
+                NESTBOX_NOT_EXISTING,
+                StatusCode::BAD_REQUEST,
+            ),
+            (&cleaner.session, "no uuid", StatusCode::BAD_REQUEST),
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Breeds(HttpMethod::Put),
+                &uri,
+                session,
+                update(nestbox_uuid),
+            )
+            .await;
+            assert_eq!(svr_resp.status(), status, "{}", nestbox_uuid);
+        }
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Put),
+            &uri,
+            &cleaner.session,
+            update(NESTBOX_MANDANT_1),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let updated: BreedResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(updated.bird_uuid, bird.uuid);
+        assert_eq!(updated.bird, bird.bird);
+        assert_eq!(updated.nestbox_label, "Box 1");
+
+        // Breeds reported by others are left to the coordinators.
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Get),
+            &format!(
+                "/nestboxes/{}/breeds?page_limit=1&page_number=1",
+                NESTBOX_MANDANT_1
+            ),
+            &cleaner.session,
+            RequestData::Empty,
+        )
+        .await;
+        let others: DocumentResponse<BreedResponse> = test::read_body_json(svr_resp).await;
+        assert_ne!(others.documents[0].user_uuid, breed.user_uuid);
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Delete),
+            &format!("/breeds/{}", others.documents[0].uuid),
+            &cleaner.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::FORBIDDEN);
+
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Delete),
+            &uri,
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::NO_CONTENT);
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Delete),
+            &uri,
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::NOT_FOUND);
+
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Get),
+            &format!("{}/versions", uri),
+            &cleaner.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let versions: Vec<BreedVersionResponse> = test::read_body_json(svr_resp).await;
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].change, "update");
+        assert_eq!(versions[0].bird_uuid, BIRD_MANDANT_1);
+        assert_eq!(versions[0].changed_by, breed.user_uuid);
+        assert_eq!(versions[1].change, "delete");
+        assert_eq!(versions[1].bird_uuid, bird.uuid);
+        assert_ne!(versions[1].changed_by, breed.user_uuid);
+
+        for (session, status) in [
+            (&stranger.session, StatusCode::FORBIDDEN),
+            (&cleaner.session, StatusCode::OK),
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Breeds(HttpMethod::Get),
+                &format!("/breeds/{}/versions", others.documents[0].uuid),
+                session,
+                RequestData::Empty,
+            )
+            .await;
+            assert_eq!(svr_resp.status(), status);
+        }
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Get),
+            &format!("/breeds/{}/versions", NESTBOX_NOT_EXISTING),
+            &cleaner.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_breeds_observations() {
+        let cleaner = login_ok(USER_MANDANT_1).await;
+        let uri = format!("/nestboxes/{}/breeds", NESTBOX_MANDANT_1);
+        let observed = || BreedObservationReq {
+            discovery_date: Some(String::from("2019-05-20")),
+            clutch_size: Some(8),
+            hatched: Some(7),
+            fledged: Some(6),
+            nest_state: Some(NestState::Fledged),
+            confidence: Some(Confidence::Probable),
+            notes: String::from("Copied from the paper records of 2019."),
+        };
+        let breed = |observation: BreedObservationReq| {
+            RequestData::Breed(BreedCreateReq {
+                bird: BirdReq {
+                    bird: String::new(),
+                    bird_uuid: String::from(BIRD_MANDANT_1),
+                },
+                observation,
+            })
+        };
+        let invalid = [
+            BreedObservationReq {
+                fledged: Some(8),
+                ..observed()
+            },
+            BreedObservationReq {
+                clutch_size: Some(6),
+                fledged: None,
+                ..observed()
+            },
+            BreedObservationReq {
+                clutch_size: Some(31),
+                ..BreedObservationReq::default()
+            },
+            BreedObservationReq {
+                discovery_date: Some(String::from("2099-05-20")),
+                ..observed()
+            },
+            BreedObservationReq {
+                discovery_date: Some(String::from("20.05.2019")),
+                ..observed()
+            },
+            BreedObservationReq {
+                notes: "x".repeat(2001),
+                ..observed()
+            },
+        ];
+        for observation in invalid {
+            let svr_resp = build_app(
+                EndPoints::Breeds(HttpMethod::Post),
+                &uri,
+                &cleaner.session,
+                breed(observation),
+            )
+            .await;
+            assert_eq!(svr_resp.status(), StatusCode::BAD_REQUEST);
+        }
+
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Post),
+            &uri,
+            &cleaner.session,
+            breed(observed()),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::CREATED);
+        let posted: BreedResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(posted.discovery_date, "2019-05-20 00:00:00.000 UTC");
+        assert_eq!(
+            (posted.clutch_size, posted.hatched, posted.fledged),
+            (Some(8), Some(7), Some(6))
+        );
+        assert_eq!(posted.nest_state, "fledged");
+        assert_eq!(posted.confidence, "probable");
+        assert_eq!(posted.notes, "Copied from the paper records of 2019.");
+
+        // A correction replaces the observations but keeps the date.
+        let uri = format!("/breeds/{}", posted.uuid);
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Put),
+            &uri,
+            &cleaner.session,
+            RequestData::BreedUpdate(BreedUpdateReq {
+                nestbox_uuid: String::from(NESTBOX_MANDANT_1),
+                bird_uuid: String::from(BIRD_MANDANT_1),
+                observation: BreedObservationReq {
+                    clutch_size: Some(9),
+                    confidence: Some(Confidence::Certain),
+                    ..BreedObservationReq::default()
+                },
+            }),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let updated: BreedResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(updated.discovery_date, posted.discovery_date);
+        assert_eq!(
+            (updated.clutch_size, updated.hatched, updated.fledged),
+            (Some(9), None, None)
+        );
+        assert_eq!(updated.nest_state, "");
+        assert_eq!(updated.confidence, "certain");
+        assert_eq!(updated.notes, "");
+
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Delete),
+            &uri,
+            &cleaner.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::NO_CONTENT);
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Get),
+            &format!("{}/versions", uri),
+            &cleaner.session,
+            RequestData::Empty,
+        )
+        .await;
+        let versions: Vec<BreedVersionResponse> = test::read_body_json(svr_resp).await;
+        assert_eq!(versions[0].hatched, Some(7));
+        assert_eq!(versions[0].notes, posted.notes);
+        assert_eq!(versions[1].clutch_size, Some(9));
+    }
+
+    #[actix_rt::test]
+    async fn test_403_breeds_post_viewer() {
+        let uri = format!("/nestboxes/{}/breeds", NESTBOX_EXISTING);
+        let login_response = login_ok(USER_BIRDS_TEST).await;
+        assert_eq!(login_response.role, "viewer");
+        let bird_data: BirdReq = BirdReq {
+            bird: String::from("_"),
+            bird_uuid: String::from(BIRD_MANDANT_1),
+        };
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Post),
+            &uri,
+            &login_response.session,
+            RequestData::Bird(bird_data),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_rt::test]
+    async fn test_401_geolocation_post_no_session_unauthorized() {
+        let uri = format!("/nestboxes/{}/geolocations", NESTBOX_MANDANT_1);
+        let geolocation = GeolocationReq {
+            long: 8.005,
+            lat: 48.05,
+        };
+        let svr_resp = build_app(
+            EndPoints::Geolocations(HttpMethod::Post),
+            &uri,
+            "",
+            RequestData::Geolocation(geolocation),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_401_geolocation_post_authenticated_wrong_mandant() {
+        let uri = format!("/nestboxes/{}/geolocations", NESTBOX_MANDANT_1);
+        let login_response = login_ok(USER_STRANGER_GEOLOCATION_POST).await;
+        let geolocation = GeolocationReq {
+            long: 8.005,
+            lat: 48.05,
+        };
+        let svr_resp = build_app(
+            EndPoints::Geolocations(HttpMethod::Post),
+            &uri,
+            &login_response.session,
+            RequestData::Geolocation(geolocation),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
+        //let resp: BreedResponse = test::read_body_json(svr_resp).await;
+    }
+
+    #[actix_rt::test]
+    async fn test_401_breeds_authenticated_wrong_mandant() {
+        let uri = format!("/nestboxes/{}/breeds", NESTBOX_MANDANT_1);
+        let login_response = login_ok(USER_STRANGER_BREED_POST).await;
+        let bird_data: BirdReq = BirdReq {
+            bird: String::from("_"),
+            bird_uuid: String::from(BIRD_MANDANT_1),
+        };
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Post),
+            &uri,
+            &login_response.session,
+            RequestData::Bird(bird_data),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_401_breeds_post_not_authorized() {
+        let uri = format!("/nestboxes/{}/breeds", NESTBOX_MANDANT_1);
+        let bird_data: BirdReq = BirdReq {
+            bird: String::from("_"),
+            bird_uuid: String::from(BIRD_MANDANT_1),
+        };
+        let svr_resp = build_app(
+            EndPoints::Breeds(HttpMethod::Post),
+            &uri,
+            "9973e59f-771d-452f-9a1b-8b4a6d5c4f95",
+            RequestData::Bird(bird_data),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_sessions_lifecycle() {
+        let phone = login_ok(USER_SESSIONS).await;
+        let tablet = login_ok(USER_SESSIONS).await;
+        // Logging in on the tablet keeps the phone logged in.
+        let svr_resp = build_app(
+            EndPoints::Sessions(HttpMethod::Get),
+            "/sessions",
+            &phone.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let sessions: Vec<SessionResponse> = test::read_body_json(svr_resp).await;
+        assert_eq!(sessions.iter().filter(|s| s.current).count(), 1);
+        let tablet_session = sessions.iter().find(|s| !s.current).unwrap();
+
+        let svr_resp = build_app(
+            EndPoints::Sessions(HttpMethod::Delete),
+            &format!("/sessions/{}", tablet_session.session_id),
+            &phone.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::NO_CONTENT);
+        let svr_resp = build_app(
+            EndPoints::Sessions(HttpMethod::Get),
+            "/sessions",
+            &tablet.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
+
+        let svr_resp = build_app(
+            EndPoints::Sessions(HttpMethod::Post),
+            "/sessions/refresh",
+            &phone.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let refreshed: LoginResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(refreshed.username, USER_SESSIONS);
+        assert_ne!(refreshed.session, phone.session);
+
+        let svr_resp = build_app(
+            EndPoints::Sessions(HttpMethod::Post),
+            "/logout",
+            &refreshed.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::NO_CONTENT);
+        let svr_resp = build_app(
+            EndPoints::Sessions(HttpMethod::Get),
+            "/sessions",
+            &refreshed.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_401_logout_without_session() {
+        let svr_resp = build_app(
+            EndPoints::Sessions(HttpMethod::Post),
+            "/logout",
+            "",
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_404_sessions_delete_unknown() {
+        let login_response = login_ok(USER_SESSIONS_DELETE).await;
+        let svr_resp = build_app(
+            EndPoints::Sessions(HttpMethod::Delete),
+            &format!("/sessions/{}", NESTBOX_NOT_EXISTING),
+            &login_response.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_200_mandants_get_own() {
+        let login_response = login_ok(USER_BIRDS_TEST).await;
+        let svr_resp = build_app(
+            EndPoints::Mandants(HttpMethod::Get),
+            &format!("/mandants/{}", MANDANT_BIRDLIFE),
+            &login_response.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let mandant: MandantResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(mandant.uuid, MANDANT_BIRDLIFE);
+        assert_eq!(mandant.name, "BirdLife");
+    }
+
+    #[actix_rt::test]
+    async fn test_403_mandants_get_other_mandant() {
+        let login_response = login_ok(USER_BIRDS_TEST).await;
+        let svr_resp = build_app(
+            EndPoints::Mandants(HttpMethod::Get),
+            &format!("/mandants/{}", MANDANT_STRANGER),
+            &login_response.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_rt::test]
+    async fn test_mandants_put() {
+        let mandant_data = MandantDataReq {
+            name: String::from("BirdLife"),
+            phone: String::from("+41 44 457 70 20"),
+            ..Default::default()
+        };
+        let uri = format!("/mandants/{}", MANDANT_BIRDLIFE);
+        // Coordinators look after the nestboxes, not after the association.
+        let login_response = login_ok(USER).await;
+        let svr_resp = build_app(
+            EndPoints::Mandants(HttpMethod::Put),
+            &uri,
+            &login_response.session,
+            RequestData::Mandant(mandant_data.clone()),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::FORBIDDEN);
+
+        let login_response = login_ok(USER_MANDANT_ADMIN).await;
+        let svr_resp = build_app(
+            EndPoints::Mandants(HttpMethod::Put),
+            &uri,
+            &login_response.session,
+            RequestData::Mandant(MandantDataReq {
+                name: String::from(" "),
+                ..Default::default()
+            }),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::BAD_REQUEST);
+        let svr_resp = build_app(
+            EndPoints::Mandants(HttpMethod::Put),
+            &uri,
+            &login_response.session,
+            RequestData::Mandant(mandant_data),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let mandant: MandantResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(mandant.name, "BirdLife");
+        assert_eq!(mandant.phone, "+41 44 457 70 20");
+    }
+
+    #[actix_rt::test]
+    async fn test_mandants_post() {
+        let mandant_data = MandantDataReq {
+            name: String::from("Vogelschutz Zürich"),
+            website: String::from("https://vogelschutz-zuerich.ch"),
+            email: String::from("info@vogelschutz-zuerich.ch"),
+            ..Default::default()
+        };
+        let login_response = login_ok(USER_MANDANT_ADMIN).await;
+        let svr_resp = build_app(
+            EndPoints::Mandants(HttpMethod::Post),
+            "/mandants",
+            &login_response.session,
+            RequestData::Mandant(mandant_data.clone()),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::FORBIDDEN);
+
+        let login_response = login_ok(USER_SUPERUSER).await;
+        assert_eq!(login_response.role, "superuser");
+        let svr_resp = build_app(
+            EndPoints::Mandants(HttpMethod::Post),
+            "/mandants",
+            &login_response.session,
+            RequestData::Mandant(mandant_data.clone()),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::CREATED);
+        let mandant: MandantResponse = test::read_body_json(svr_resp).await;
+        assert!(is_uuid(&mandant.uuid));
+        // The superuser administers every mandant, not only its own.
+        let svr_resp = build_app(
+            EndPoints::Mandants(HttpMethod::Put),
+            &format!("/mandants/{}", mandant.uuid),
+            &login_response.session,
+            RequestData::Mandant(MandantDataReq {
+                logo: String::from("https://vogelschutz-zuerich.ch/logo.png"),
+                ..mandant_data
+            }),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let mandant: MandantResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(mandant.name, "Vogelschutz Zürich");
+        assert_eq!(mandant.logo, "https://vogelschutz-zuerich.ch/logo.png");
+    }
+
+    fn new_user(username: &str, role: Role) -> UserCreateReq {
+        UserCreateReq {
+            username: String::from(username),
+            password: String::from(PASSWORD_CORRECT),
+            lastname: String::from("Meise"),
+            firstname: String::from("Kohl"),
+            email: format!("{}@birdwatch.ch", username),
+            role,
+            mandant_uuid: String::new(),
+        }
+    }
+
+    async fn login_status(user: &str, password: &str) -> StatusCode {
+        build_app(
+            EndPoints::Login(HttpMethod::Post),
+            "/login",
+            "",
+            RequestData::Login(LoginReq {
+                username: String::from(user),
+                password: String::from(password),
+            }),
+        )
+        .await
+        .status()
+    }
+
+    #[actix_rt::test]
+    async fn test_users_onboarding_and_locking() {
+        let coordinator = login_ok(USER_USERS_COORDINATOR).await;
+        let svr_resp = build_app(
+            EndPoints::Users(HttpMethod::Post),
+            "/users",
+            &coordinator.session,
+            RequestData::UserCreate(new_user("fg_cleaner_new", Role::Cleaner)),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::CREATED);
+        let cleaner: UserResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(cleaner.mandant_uuid, MANDANT_1);
+        assert_eq!(cleaner.role, "cleaner");
+        assert!(!cleaner.locked);
+
+        let svr_resp = build_app(
+            EndPoints::Users(HttpMethod::Post),
+            "/users",
+            &coordinator.session,
+            RequestData::UserCreate(new_user("fg_cleaner_new", Role::Viewer)),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::CONFLICT);
+        // Coordinators cannot appoint further coordinators ...
+        let svr_resp = build_app(
+            EndPoints::Users(HttpMethod::Post),
+            "/users",
+            &coordinator.session,
+            RequestData::UserCreate(new_user("fg_coordinator_new", Role::Coordinator)),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::FORBIDDEN);
+        // ... nor onboard users of other mandants.
+        let svr_resp = build_app(
+            EndPoints::Users(HttpMethod::Post),
+            "/users",
+            &coordinator.session,
+            RequestData::UserCreate(UserCreateReq {
+                mandant_uuid: String::from(MANDANT_STRANGER),
+                ..new_user("fg_stranger_new", Role::Viewer)
+            }),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::FORBIDDEN);
+
+        let svr_resp = build_app(
+            EndPoints::Users(HttpMethod::Get),
+            "/users?page_limit=10&page_number=1",
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let users: DocumentResponse<UserResponse> = test::read_body_json(svr_resp).await;
+        assert!(users.documents.iter().any(|u| u.uuid == cleaner.uuid));
+        assert!(users.documents.iter().all(|u| u.mandant_uuid == MANDANT_1));
+
+        let cleaner_session = login_ok("fg_cleaner_new").await;
+        assert_eq!(cleaner_session.role, "cleaner");
+        let svr_resp = build_app(
+            EndPoints::Users(HttpMethod::Get),
+            "/users?page_limit=10&page_number=1",
+            &cleaner_session.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::FORBIDDEN);
+
+        let mut user_update = UserUpdateReq {
+            lastname: cleaner.lastname.clone(),
+            firstname: cleaner.firstname.clone(),
+            email: cleaner.email.clone(),
+            role: Role::Cleaner,
+            locked: true,
+        };
+        let svr_resp = build_app(
+            EndPoints::Users(HttpMethod::Put),
+            &format!("/users/{}", cleaner.uuid),
+            &coordinator.session,
+            RequestData::UserUpdate(user_update.clone()),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let locked: UserResponse = test::read_body_json(svr_resp).await;
+        assert!(locked.locked);
+        // Locking ends the running sessions and prevents new ones.
+        let svr_resp = build_app(
+            EndPoints::Users(HttpMethod::Get),
+            "/users?page_limit=10&page_number=1",
+            &cleaner_session.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            login_status("fg_cleaner_new", PASSWORD_CORRECT).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        user_update.locked = false;
+        let svr_resp = build_app(
+            EndPoints::Users(HttpMethod::Put),
+            &format!("/users/{}", cleaner.uuid),
+            &coordinator.session,
+            RequestData::UserUpdate(user_update),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        assert_eq!(
+            login_status("fg_cleaner_new", PASSWORD_CORRECT).await,
+            StatusCode::OK
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_403_users_put_mandant_admin_by_coordinator() {
+        let coordinator = login_ok(USER).await;
+        let svr_resp = build_app(
+            EndPoints::Users(HttpMethod::Get),
+            "/users?page_limit=100&page_number=1",
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        let users: DocumentResponse<UserResponse> = test::read_body_json(svr_resp).await;
+        let admin = users
+            .documents
+            .iter()
+            .find(|u| u.username == USER_MANDANT_ADMIN)
+            .unwrap();
+        let svr_resp = build_app(
+            EndPoints::Users(HttpMethod::Put),
+            &format!("/users/{}", admin.uuid),
+            &coordinator.session,
+            RequestData::UserUpdate(UserUpdateReq {
+                lastname: admin.lastname.clone(),
+                firstname: admin.firstname.clone(),
+                email: admin.email.clone(),
+                role: Role::Viewer,
+                locked: true,
+            }),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_rt::test]
+    async fn test_me_password_put() {
+        let admin = login_ok(USER_MANDANT_ADMIN).await;
+        let svr_resp = build_app(
+            EndPoints::Users(HttpMethod::Post),
+            "/users",
+            &admin.session,
+            RequestData::UserCreate(new_user("fg_password", Role::Viewer)),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::CREATED);
+        let viewer = login_ok("fg_password").await;
+        let tablet = login_ok("fg_password").await;
+
+        let svr_resp = build_app(
+            EndPoints::Users(HttpMethod::Put),
+            "/me/password",
+            &viewer.session,
+            RequestData::PasswordChange(PasswordChangeReq {
+                password: String::from(PASSWORD_CORRECT),
+                new_password: String::from("short"),
+            }),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::BAD_REQUEST);
+        let svr_resp = build_app(
+            EndPoints::Users(HttpMethod::Put),
+            "/me/password",
+            &viewer.session,
+            RequestData::PasswordChange(PasswordChangeReq {
+                password: String::from(PASSWORD_WRONG),
+                new_password: String::from("anothersecretbird"),
+            }),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::FORBIDDEN);
+        let svr_resp = build_app(
+            EndPoints::Users(HttpMethod::Put),
+            "/me/password",
+            &viewer.session,
+            RequestData::PasswordChange(PasswordChangeReq {
+                password: String::from(PASSWORD_CORRECT),
+                new_password: String::from("anothersecretbird"),
+            }),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::NO_CONTENT);
+        // Only the session that changed the password is left.
+        for (session, status) in [
+            (&tablet.session, StatusCode::UNAUTHORIZED),
+            (&viewer.session, StatusCode::OK),
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Sessions(HttpMethod::Get),
+                "/sessions",
+                session,
+                RequestData::Empty,
+            )
+            .await;
+            assert_eq!(svr_resp.status(), status);
+            if status == StatusCode::OK {
+                let sessions: Vec<SessionResponse> = test::read_body_json(svr_resp).await;
+                assert_eq!(sessions.len(), 1);
+                assert!(sessions[0].current);
+            }
+        }
+        assert_eq!(
+            login_status("fg_password", PASSWORD_CORRECT).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            login_status("fg_password", "anothersecretbird").await,
+            StatusCode::OK
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_nestboxes_create_patch_retire() {
+        let coordinator = login_ok(USER_NESTBOXES_COORDINATOR).await;
+        let nestbox_data = NestboxDataReq {
+            public: true,
+            description: String::from("Eiche am Waldrand"),
+            box_type: String::from("Meisenkasten 32mm"),
+            label: String::new(),
+        };
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Post),
+            "/nestboxes",
+            &coordinator.session,
+            RequestData::NestboxCreate(NestboxCreateReq::Bulk(vec![nestbox_data.clone(); 3])),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::CREATED);
+        let created: Document = test::read_body_json(svr_resp).await;
+        assert_eq!(created.get_array("uuids").unwrap().len(), 3);
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Post),
+            "/nestboxes",
+            &coordinator.session,
+            RequestData::NestboxCreate(NestboxCreateReq::Bulk(Vec::new())),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::BAD_REQUEST);
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Post),
+            "/nestboxes",
+            &coordinator.session,
+            RequestData::NestboxCreate(NestboxCreateReq::Single(nestbox_data)),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::CREATED);
+        let created: Document = test::read_body_json(svr_resp).await;
+        let uuids = created.get_array("uuids").unwrap();
+        assert_eq!(uuids.len(), 1);
+        let uri = format!("/nestboxes/{}", uuids[0].as_str().unwrap());
+
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            &uri,
+            "",
+            RequestData::Empty,
+        )
+        .await;
+        let nestbox: NestboxResponse = test::read_body_json(svr_resp).await;
+        assert!(nestbox.public);
+        assert_eq!(nestbox.description, "Eiche am Waldrand");
+        assert_eq!(nestbox.from_date, nestbox.created_at);
+        assert_eq!(nestbox.until_date, "");
+
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Patch),
+            &uri,
+            &coordinator.session,
+            RequestData::NestboxPatch(NestboxPatchReq::default()),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::BAD_REQUEST);
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Patch),
+            &uri,
+            &coordinator.session,
+            RequestData::NestboxPatch(NestboxPatchReq {
+                public: Some(false),
+                description: Some(String::from("Buche beim \"Hochsitz\"")),
+                ..Default::default()
+            }),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let nestbox: NestboxResponse = test::read_body_json(svr_resp).await;
+        assert!(!nestbox.public);
+        assert_eq!(nestbox.description, "Buche beim \"Hochsitz\"");
+        assert_eq!(nestbox.box_type, "Meisenkasten 32mm");
+
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Post),
+            &format!("{}/retire", uri),
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let retired: NestboxResponse = test::read_body_json(svr_resp).await;
+        assert_ne!(retired.until_date, "");
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Post),
+            &format!("{}/retire", uri),
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        let retired_again: NestboxResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(retired_again.until_date, retired.until_date);
+        // Retired nestboxes stay readable.
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            &uri,
+            "",
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_nestboxes_patch_other_mandant_and_post_cleaner() {
+        let coordinator = login_ok(USER_NESTBOXES_COORDINATOR).await;
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Patch),
+            &format!("/nestboxes/{}", NESTBOX_EXISTING),
+            &coordinator.session,
+            RequestData::NestboxPatch(NestboxPatchReq {
+                public: Some(false),
+                ..Default::default()
+            }),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
+
+        let cleaner = login_ok(USER_MANDANT_1).await;
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Post),
+            "/nestboxes",
+            &cleaner.session,
+            RequestData::NestboxCreate(NestboxCreateReq::Bulk(Vec::new())),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_rt::test]
+    async fn test_nestboxes_list_get() {
+        let coordinator = login_ok(USER_MANDANT_1_GEOLOCATION).await;
+        let year = chrono::Utc::now().format("%Y").to_string();
+        for (query, counted) in [
+            ("", 1),
+            ("&public=true", 1),
+            ("&public=false", 0),
+            ("&retired=true", 0),
+            (&format!("&last_breed_year={}", year), 1),
+            ("&last_breed_year=2000", 0),
+            (&format!("&bird_uuid={}", BIRD_MANDANT_1), 1),
+            ("&not_inspected=true", 0),
+            ("&not_inspected=false", 1),
+        ] {
+            let uri = format!("/nestboxes?page_limit=10&page_number=1{}", query);
+            let svr_resp = build_app(
+                EndPoints::Nestboxes(HttpMethod::Get),
+                &uri,
+                &coordinator.session,
+                RequestData::Empty,
+            )
+            .await;
+            assert_eq!(svr_resp.status(), StatusCode::OK, "{}", uri);
+            let nestboxes: DocumentResponse<NestboxResponse> = test::read_body_json(svr_resp).await;
+            assert_eq!(nestboxes.counted_documents, counted, "{}", uri);
+            if counted > 0 {
+                assert_eq!(nestboxes.documents[0].uuid, NESTBOX_MANDANT_1);
+                assert_eq!(nestboxes.documents[0].mandant_name, "BirdLife 100");
+            }
+        }
+
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            "/nestboxes?page_limit=10&page_number=1&bird_uuid=sparrow",
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::BAD_REQUEST);
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            "/nestboxes?page_limit=10&page_number=1",
+            "",
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_nestboxes_qr_get() {
+        let uri = format!("/nestboxes/{}/qr", NESTBOX_EXISTING);
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            &uri,
+            "",
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        assert_eq!(
+            svr_resp.headers().get("content-type").unwrap(),
+            HeaderValue::from_static("image/png")
+        );
+        let png = test::read_body(svr_resp).await;
+        assert_eq!(&png[..4], b"\x89PNG");
+
+        let svr_resp = test::call_service(
+            &test::init_service(
+                App::new()
+                    .app_data(Data::new(ServiceContainer::new(&repositories(), &config())))
+                    .service(controller::nestbox::nestboxes_qr_get),
+            )
+            .await,
+            test::TestRequest::get()
+                .uri(&uri)
+                .insert_header(("Accept", "image/svg+xml"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(
+            svr_resp.headers().get("content-type").unwrap(),
+            HeaderValue::from_static("image/svg+xml")
+        );
+
+        for (query, status) in [
+            ("?format=svg&size=512&ec=H", StatusCode::OK),
+            ("?size=10000", StatusCode::BAD_REQUEST),
+            ("?ec=X", StatusCode::BAD_REQUEST),
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Nestboxes(HttpMethod::Get),
+                &format!("{}{}", uri, query),
+                "",
+                RequestData::Empty,
+            )
+            .await;
+            assert_eq!(svr_resp.status(), status, "{}", query);
+        }
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            &format!("/nestboxes/{}/qr", NESTBOX_NOT_EXISTING),
+            "",
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_nestboxes_labelled() {
+        let coordinator = login_ok(USER_NESTBOXES_COORDINATOR).await;
+        let labelled = NestboxDataReq {
+            public: true,
+            description: String::new(),
+            box_type: String::new(),
+            label: String::from("Wald Nord 17"),
+        };
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Post),
+            "/nestboxes",
+            &coordinator.session,
+            RequestData::NestboxCreate(NestboxCreateReq::Single(labelled.clone())),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::CREATED);
+        let created: Document = test::read_body_json(svr_resp).await;
+        let uri = format!(
+            "/nestboxes/{}",
+            created.get_array("uuids").unwrap()[0].as_str().unwrap()
+        );
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Post),
+            "/nestboxes",
+            &coordinator.session,
+            RequestData::NestboxCreate(NestboxCreateReq::Single(labelled)),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::CONFLICT);
+
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            "/nestboxes?page_limit=10&page_number=1&label=wald%20nord",
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        let nestboxes: DocumentResponse<NestboxResponse> = test::read_body_json(svr_resp).await;
+        assert_eq!(nestboxes.counted_documents, 1);
+        assert_eq!(nestboxes.documents[0].label, "Wald Nord 17");
+
+        for (label, status) in [
+            ("Box 1", StatusCode::CONFLICT),
+            ("Wald Nord 18", StatusCode::OK),
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Nestboxes(HttpMethod::Patch),
+                &uri,
+                &coordinator.session,
+                RequestData::NestboxPatch(NestboxPatchReq {
+                    label: Some(String::from(label)),
+                    ..Default::default()
+                }),
+            )
+            .await;
+            assert_eq!(svr_resp.status(), status, "{}", label);
+        }
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            &uri,
+            "",
+            RequestData::Empty,
+        )
+        .await;
+        let nestbox: NestboxResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(nestbox.label, "Wald Nord 18");
+    }
+
+    #[actix_rt::test]
+    async fn test_nestboxes_by_code() {
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            &format!("/nestboxes/{}", NESTBOX_MANDANT_1),
+            "",
+            RequestData::Empty,
+        )
+        .await;
+        let nestbox: NestboxResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(nestbox.code.len(), 12);
+        let code = service::nestbox::format_nestbox_code(&nestbox.code);
+
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            &format!("/nestboxes/{}", code),
+            "",
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        let by_code: NestboxResponse = test::read_body_json(svr_resp).await;
+        assert_eq!(by_code.uuid, NESTBOX_MANDANT_1);
+
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            &format!("/n/{}", code.to_lowercase()),
+            "",
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::FOUND);
+        assert_eq!(
+            svr_resp.headers().get("location").unwrap(),
+            HeaderValue::from_str(&format!("/nestboxes/{}", NESTBOX_MANDANT_1)).unwrap()
+        );
+
+        let coordinator = login_ok(USER_MANDANT_1_GEOLOCATION).await;
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Patch),
+            &format!("/nestboxes/{}", code),
+            &coordinator.session,
+            RequestData::NestboxPatch(NestboxPatchReq {
+                public: Some(true),
+                ..Default::default()
+            }),
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+
+        for (uri, status) in [
+            ("/n/0000-0000-0000", StatusCode::NOT_FOUND),
+            ("/nestboxes/0000-0000-0000", StatusCode::NOT_FOUND),
+            ("/n/no-code", StatusCode::BAD_REQUEST),
+            ("/nestboxes/no-code/qr", StatusCode::BAD_REQUEST),
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Nestboxes(HttpMethod::Get),
+                uri,
+                "",
+                RequestData::Empty,
+            )
+            .await;
+            assert_eq!(svr_resp.status(), status, "{}", uri);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_nestboxes_labels_get() {
+        let coordinator = login_ok(USER_MANDANT_1_GEOLOCATION).await;
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            "/nestboxes/labels?layout=5160&ec=H&skip=29&retired=false",
+            &coordinator.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::OK);
+        assert_eq!(
+            svr_resp.headers().get("content-type").unwrap(),
+            HeaderValue::from_static("application/pdf")
+        );
+        let pdf = test::read_body(svr_resp).await;
+        assert!(pdf.starts_with(b"%PDF-"));
+
+        for (query, status) in [
+            ("?layout=l7165&skip=8", StatusCode::BAD_REQUEST),
+            ("?layout=a4", StatusCode::BAD_REQUEST),
+            ("?retired=true", StatusCode::NOT_FOUND),
+        ] {
+            let svr_resp = build_app(
+                EndPoints::Nestboxes(HttpMethod::Get),
+                &format!("/nestboxes/labels{}", query),
+                &coordinator.session,
+                RequestData::Empty,
+            )
+            .await;
+            assert_eq!(svr_resp.status(), status, "{}", query);
+        }
+
+        let cleaner = login_ok(USER_MANDANT_1).await;
+        let svr_resp = build_app(
+            EndPoints::Nestboxes(HttpMethod::Get),
+            "/nestboxes/labels",
+            &cleaner.session,
+            RequestData::Empty,
+        )
+        .await;
+        assert_eq!(svr_resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    async fn login_ok(user: &str) -> LoginResponse {
+        let uri = "/login";
+        let user_name = String::from(user);
+        let user_data = LoginReq {
+            username: user_name.clone(),
+            password: String::from(PASSWORD_CORRECT),
+        };
+        let svr_login_resp = build_app(
+            EndPoints::Login(HttpMethod::Post),
+            uri,
+            "",
+            RequestData::Login(user_data),
+        )
+        .await;
+        let login_response: LoginResponse = test::read_body_json(svr_login_resp).await;
+        login_response
+    }
+
+    async fn build_app(
+        endpoint: EndPoints,
+        uri: &str,
+        sessiontoken: &str,
+        req: RequestData,
+    ) -> actix_web::dev::ServiceResponse {
+        let http_method: HttpMethod;
+        let app = match endpoint {
+            EndPoints::Birds(m) => {
+                http_method = m.clone();
+                test::init_service(
+                    App::new()
+                        .app_data(Data::new(ServiceContainer::new(&repositories(), &config())))
+                        .service(controller::bird::birds_get)
+                        .service(controller::bird::birds_post)
+                        .service(controller::bird::birds_import_post)
+                        .service(controller::bird::birds_import_default_post)
+                        .service(controller::bird::birds_merge_post)
+                        .service(controller::bird::birds_put)
+                        .service(controller::bird::birds_delete),
+                )
+                .await
+            }
+            EndPoints::Geolocations(m) => {
+                http_method = m.clone();
+                test::init_service(
                     App::new()
-                        .app_data(Data::new(ServiceContainer::new(
-                            get_db().await,
-                            String::from(IMAGE_DIRECTORY),
-                        )))
+                        .app_data(Data::new(ServiceContainer::new(&repositories(), &config())))
+                        .service(controller::nestbox::nestboxes_locations_get)
                         .service(controller::nestbox::nestboxes_locations_post),
                 )
                 .await
@@ -464,23 +2890,28 @@ mod tests {
                     http_method = m.clone();
                     test::init_service(
                         App::new()
-                            .app_data(Data::new(ServiceContainer::new(
-                                get_db().await,
-                                String::from(IMAGE_DIRECTORY),
-                            )))
+                            .app_data(Data::new(ServiceContainer::new(&repositories(), &config())))
                             .service(controller::breed::breeds_post),
                     )
                     .await
                 }
-                HttpMethod::Get => {
+                HttpMethod::Put | HttpMethod::Delete => {
+                    http_method = m.clone();
+                    test::init_service(
+                        App::new()
+                            .app_data(Data::new(ServiceContainer::new(&repositories(), &config())))
+                            .service(controller::breed::breeds_put)
+                            .service(controller::breed::breeds_delete),
+                    )
+                    .await
+                }
+                _ => {
                     http_method = m.clone();
                     test::init_service(
                         App::new()
-                            .app_data(Data::new(ServiceContainer::new(
-                                get_db().await,
-                                String::from(IMAGE_DIRECTORY),
-                            )))
-                            .service(controller::breed::breeds_get),
+                            .app_data(Data::new(ServiceContainer::new(&repositories(), &config())))
+                            .service(controller::breed::breeds_get)
+                            .service(controller::breed::breeds_versions_get),
                     )
                     .await
                 }
@@ -490,29 +2921,68 @@ mod tests {
                 http_method = m.clone();
                 test::init_service(
                     App::new()
-                        .app_data(Data::new(ServiceContainer::new(
-                            get_db().await,
-                            String::from(IMAGE_DIRECTORY),
-                        )))
+                        .app_data(Data::new(ServiceContainer::new(&repositories(), &config())))
                         .service(controller::user::login_post),
                 )
                 .await
             }
-            EndPoints::Nestboxes(_) => {
-                // Caution GET only implemented.
+            EndPoints::Nestboxes(m) => {
+                http_method = m.clone();
+                test::init_service(
+                    App::new()
+                        .app_data(Data::new(ServiceContainer::new(&repositories(), &config())))
+                        .service(controller::nestbox::nestboxes_list_get)
+                        .service(controller::nestbox::nestboxes_labels_get)
+                        .service(controller::nestbox::nestboxes_near_get)
+                        .service(controller::nestbox::nestboxes_export_get)
+                        .service(controller::nestbox::nestboxes_route_get)
+                        .service(controller::nestbox::nestboxes_get)
+                        .service(controller::nestbox::nestboxes_post)
+                        .service(controller::nestbox::nestboxes_patch)
+                        .service(controller::nestbox::nestboxes_retire_post)
+                        .service(controller::nestbox::nestboxes_qr_get)
+                        .service(controller::nestbox::nestbox_code_get),
+                )
+                .await
+            }
+            EndPoints::Sessions(m) => {
+                http_method = m.clone();
+                test::init_service(
+                    App::new()
+                        .app_data(Data::new(ServiceContainer::new(&repositories(), &config())))
+                        .service(controller::session::logout_post)
+                        .service(controller::session::sessions_get)
+                        .service(controller::session::sessions_refresh_post)
+                        .service(controller::session::sessions_delete),
+                )
+                .await
+            }
+            EndPoints::Mandants(m) => {
+                http_method = m.clone();
+                test::init_service(
+                    App::new()
+                        .app_data(Data::new(ServiceContainer::new(&repositories(), &config())))
+                        .service(controller::mandant::mandants_get)
+                        .service(controller::mandant::mandants_put)
+                        .service(controller::mandant::mandants_post),
+                )
+                .await
+            }
+            EndPoints::Users(m) => {
+                http_method = m.clone();
                 test::init_service(
                     App::new()
-                        .app_data(Data::new(ServiceContainer::new(
-                            get_db().await,
-                            String::from(IMAGE_DIRECTORY),
-                        )))
-                        .service(controller::nestbox::nestboxes_get),
+                        .app_data(Data::new(ServiceContainer::new(&repositories(), &config())))
+                        .service(controller::user::users_get)
+                        .service(controller::user::users_post)
+                        .service(controller::user::users_put)
+                        .service(controller::user::me_password_put),
                 )
                 .await
             }
         };
 
-        let auth_token = format!("Basic {}", sessiontoken);
+        let auth_token = format!("Bearer {}", sessiontoken);
         match http_method {
             HttpMethod::Post => match req {
                 RequestData::Empty => {
@@ -529,37 +2999,22 @@ mod tests {
                         .send_request(&app)
                         .await
                 }
-                RequestData::Login(req) => {
-                    test::TestRequest::post()
-                        .uri(uri)
-                        .append_header((
-                            actix_web::http::header::CONTENT_TYPE,
-                            HeaderValue::from_static("application/json"),
-                        ))
-                        .append_header((
-                            actix_web::http::header::AUTHORIZATION,
-                            HeaderValue::from_str(&auth_token).unwrap(),
-                        ))
-                        .set_json(&req)
-                        .send_request(&app)
-                        .await
-                }
-                RequestData::Bird(req) => {
+                RequestData::Text(text) => {
                     test::TestRequest::post()
                         .uri(uri)
-                        .append_header((
+                        .insert_header((
                             actix_web::http::header::CONTENT_TYPE,
-                            HeaderValue::from_static("application/json"),
+                            HeaderValue::from_static("text/plain; charset=utf-8"),
                         ))
                         .append_header((
                             actix_web::http::header::AUTHORIZATION,
                             HeaderValue::from_str(&auth_token).unwrap(),
                         ))
-                        .set_json(&req)
+                        .set_payload(text)
                         .send_request(&app)
                         .await
                 }
-                RequestData::Geolocation(req) => {
+                req => {
                     test::TestRequest::post()
                         .uri(uri)
                         .insert_header((
@@ -575,8 +3030,49 @@ mod tests {
                         .await
                 }
             },
+            HttpMethod::Put => {
+                test::TestRequest::put()
+                    .uri(uri)
+                    .insert_header((
+                        actix_web::http::header::CONTENT_TYPE,
+                        HeaderValue::from_static("application/json"),
+                    ))
+                    .append_header((
+                        actix_web::http::header::AUTHORIZATION,
+                        HeaderValue::from_str(&auth_token).unwrap(),
+                    ))
+                    .set_json(&req)
+                    .send_request(&app)
+                    .await
+            }
+            HttpMethod::Patch => {
+                test::TestRequest::patch()
+                    .uri(uri)
+                    .insert_header((
+                        actix_web::http::header::CONTENT_TYPE,
+                        HeaderValue::from_static("application/json"),
+                    ))
+                    .append_header((
+                        actix_web::http::header::AUTHORIZATION,
+                        HeaderValue::from_str(&auth_token).unwrap(),
+                    ))
+                    .set_json(&req)
+                    .send_request(&app)
+                    .await
+            }
             HttpMethod::Get => {
-                test::TestRequest::get()
+                let mut test_req = test::TestRequest::get().uri(uri).insert_header((
+                    actix_web::http::header::AUTHORIZATION,
+                    HeaderValue::from_str(&auth_token).unwrap(),
+                ));
+                if let RequestData::AcceptLanguage(languages) = req {
+                    test_req = test_req
+                        .insert_header((actix_web::http::header::ACCEPT_LANGUAGE, languages));
+                }
+                test_req.send_request(&app).await
+            }
+            HttpMethod::Delete => {
+                test::TestRequest::delete()
                     .uri(uri)
                     .insert_header((
                         actix_web::http::header::AUTHORIZATION,
@@ -588,11 +3084,21 @@ mod tests {
         }
     }
 
-    async fn get_db() -> Database {
-        let client_options_future = ClientOptions::parse("mongodb://localhost:27017");
-        let client_options = client_options_future.await.unwrap();
-        let client = Client::with_options(client_options).unwrap();
+    fn repositories() -> Repositories {
+        // One store for all the apps built by the tests, so a session created
+        // by login_ok is known to the app handling the next request.
+        lazy_static! {
+            static ref REPOSITORIES: Repositories = fixtures::repositories();
+        }
+        REPOSITORIES.clone()
+    }
 
-        client.database("nestbox_testing")
+    fn config() -> Config {
+        Config {
+            image_directory: String::from(IMAGE_DIRECTORY),
+            session_absolute_timeout: SESSION_ABSOLUTE_TIMEOUT_DEFAULT,
+            session_idle_timeout: SESSION_IDLE_TIMEOUT_DEFAULT,
+            ..Default::default()
+        }
     }
 }
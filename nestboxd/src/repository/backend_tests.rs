@@ -0,0 +1,970 @@
+//! Checks every SQL backend has to pass, run by the tests of the backends so
+//! they cannot drift apart. The fixture is created through the repositories.
+use mongodb::bson::{doc, DateTime};
+use uuid::Uuid;
+
+use crate::controller::utilities::PagingQuery;
+use crate::repository::{
+    BirdRepository, BreedRepository, GeolocationRepository, MandantRepository, NestboxFilter,
+    NestboxRepository, SessionRepository, UserRepository,
+};
+use crate::service::nestbox::new_nestbox_code;
+
+/// All the repositories of one backend.
+pub(super) trait Backend:
+    BirdRepository
+    + BreedRepository
+    + GeolocationRepository
+    + MandantRepository
+    + NestboxRepository
+    + SessionRepository
+    + UserRepository
+{
+}
+
+impl<R> Backend for R where
+    R: BirdRepository
+        + BreedRepository
+        + GeolocationRepository
+        + MandantRepository
+        + NestboxRepository
+        + SessionRepository
+        + UserRepository
+{
+}
+
+struct Fixture<R> {
+    repository: R,
+    mandant_uuid: String,
+    user_uuid: String,
+    username: String,
+    nestbox_uuid: String,
+    bird_uuids: Vec<String>,
+}
+
+/// A mandant of its own with a user, a nestbox without code and the birds
+/// bird_2, bird_0 and bird_1.
+async fn fixture<R: Backend>(repository: R) -> Fixture<R> {
+    let mandant_uuid = Uuid::new_v4().to_string();
+    let user_uuid = Uuid::new_v4().to_string();
+    let username = format!("fg_{}", Uuid::new_v4());
+    let nestbox_uuid = Uuid::new_v4().to_string();
+    MandantRepository::insert(
+        &repository,
+        &doc! { "uuid" : &mandant_uuid, "name" : "BirdLife",
+        "website" : "https://www.birdwatcher.ch", "email" : "bird@iseeyou.ch" },
+    )
+    .await
+    .unwrap();
+    UserRepository::insert(
+        &repository,
+        &doc! { "uuid" : &user_uuid, "mandant_uuid" : &mandant_uuid, "username" : &username,
+        "lastname" : "Gucker", "firstname" : "Fritz", "email" : "email@birdwatch.ch",
+        "password_hash" : "0".repeat(64), "salt" : Uuid::new_v4().to_string() },
+    )
+    .await
+    .unwrap();
+    repository
+        .insert_many(&[
+            doc! { "uuid" : &nestbox_uuid, "mandant_uuid" : &mandant_uuid, "public" : true,
+            "created_at" : DateTime::now(), "from_date" : DateTime::now(),
+            "description" : "", "box_type" : "" },
+        ])
+        .await
+        .unwrap();
+    let mut bird_uuids = Vec::new();
+    for name in &["bird_2", "bird_0", "bird_1"] {
+        let bird_uuid = Uuid::new_v4().to_string();
+        BirdRepository::insert(
+            &repository,
+            &doc! { "uuid" : &bird_uuid, "bird" : name, "mandant_uuid" : &mandant_uuid },
+        )
+        .await
+        .unwrap();
+        bird_uuids.push(bird_uuid);
+    }
+    Fixture {
+        repository,
+        mandant_uuid,
+        user_uuid,
+        username,
+        nestbox_uuid,
+        bird_uuids,
+    }
+}
+
+pub(super) async fn nestbox<R: Backend>(repository: R) {
+    let f = fixture(repository).await;
+    for image in &["a.jpg", "b.jpg", "a.jpg"] {
+        f.repository
+            .append_image(&f.nestbox_uuid, image)
+            .await
+            .unwrap();
+    }
+    let nestbox = NestboxRepository::find_by_uuid(&f.repository, &f.nestbox_uuid)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(nestbox.get_array("images").unwrap().len(), 2);
+    let mandant = nestbox.get_array("mandant").unwrap()[0]
+        .as_document()
+        .unwrap();
+    assert_eq!(mandant.get_str("name"), Ok("BirdLife"));
+    assert!(f
+        .repository
+        .find_by_uuid_and_mandant_uuid(&f.nestbox_uuid, &f.mandant_uuid)
+        .await
+        .unwrap()
+        .is_some());
+    assert!(f
+        .repository
+        .find_by_uuid_and_mandant_uuid(&f.nestbox_uuid, &Uuid::new_v4().to_string())
+        .await
+        .unwrap()
+        .is_none());
+}
+
+pub(super) async fn breeds_and_birds<R: Backend>(repository: R) {
+    let f = fixture(repository).await;
+    let now = DateTime::now().timestamp_millis();
+    let breed_uuids: Vec<String> = f
+        .bird_uuids
+        .iter()
+        .map(|_| Uuid::new_v4().to_string())
+        .collect();
+    for (i, bird_uuid) in f.bird_uuids.iter().enumerate() {
+        let mut breed = doc! {
+        "uuid": &breed_uuids[i],
+        "nestbox_uuid": &f.nestbox_uuid,
+        "user_uuid": &f.user_uuid,
+        "discovery_date": DateTime::from_millis(now + i as i64 * 1000),
+        "bird_uuid": bird_uuid};
+        if i == 0 {
+            breed.extend(doc! {"clutch_size": 5, "nest_state": "eggs", "notes": "Cold"});
+        }
+        BreedRepository::insert(&f.repository, &breed)
+            .await
+            .unwrap();
+    }
+    let paging = PagingQuery {
+        page_limit: 2,
+        page_number: 2,
+    };
+    let breeds = BreedRepository::find_by_nestbox_uuid(&f.repository, &f.nestbox_uuid, &paging)
+        .await
+        .unwrap();
+    assert_eq!(breeds.len(), 1);
+    assert_eq!(
+        BreedRepository::count_by_nestbox_uuid(&f.repository, &f.nestbox_uuid)
+            .await
+            .unwrap(),
+        3
+    );
+    let latest = f
+        .repository
+        .find_latest_by_mandant_uuid(&f.mandant_uuid)
+        .await
+        .unwrap();
+    assert_eq!(latest.len(), 1);
+    assert_eq!(
+        latest[0].get_array("bird").unwrap()[0]
+            .as_document()
+            .unwrap()
+            .get_str("uuid"),
+        Ok(f.bird_uuids[2].as_str())
+    );
+    let birds = BirdRepository::find_by_mandant_uuid(&f.repository, &f.mandant_uuid, &paging)
+        .await
+        .unwrap();
+    assert_eq!(birds[0].get_str("bird"), Ok("bird_2"));
+    assert_eq!(
+        BirdRepository::count_by_mandant_uuid(&f.repository, &f.mandant_uuid)
+            .await
+            .unwrap(),
+        3
+    );
+
+    let change = |change: &str, i: i64| {
+        doc! {"uuid": Uuid::new_v4().to_string(), "changed_by": &f.user_uuid,
+        "changed_at": DateTime::from_millis(now + i * 1000), "change": change}
+    };
+    let corrected = doc! {"uuid": &breed_uuids[0], "nestbox_uuid": &f.nestbox_uuid,
+    "bird_uuid": &f.bird_uuids[1], "discovery_date": DateTime::from_millis(now - 86_400_000),
+    "clutch_size": 6, "hatched": 6};
+    assert!(
+        BreedRepository::update(&f.repository, &corrected, &change("update", 1))
+            .await
+            .unwrap()
+    );
+    let breed = BreedRepository::find_by_uuid(&f.repository, &breed_uuids[0])
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        breed.get_array("bird").unwrap()[0]
+            .as_document()
+            .unwrap()
+            .get_str("uuid"),
+        Ok(f.bird_uuids[1].as_str())
+    );
+    assert_eq!(
+        breed
+            .get_datetime("discovery_date")
+            .unwrap()
+            .timestamp_millis(),
+        now - 86_400_000
+    );
+    assert_eq!(
+        (breed.get_i32("clutch_size"), breed.get_i32("hatched")),
+        (Ok(6), Ok(6))
+    );
+    assert!(!breed.contains_key("nest_state") && !breed.contains_key("notes"));
+    assert!(
+        BreedRepository::delete(&f.repository, &breed_uuids[0], &change("delete", 2))
+            .await
+            .unwrap()
+    );
+    assert!(
+        !BreedRepository::delete(&f.repository, &breed_uuids[0], &change("delete", 3))
+            .await
+            .unwrap()
+    );
+    assert!(
+        BreedRepository::find_by_uuid(&f.repository, &breed_uuids[0])
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let versions = f.repository.find_versions(&breed_uuids[0]).await.unwrap();
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[0].get_str("change"), Ok("update"));
+    assert_eq!(
+        versions[0].get_str("breed_uuid"),
+        Ok(breed_uuids[0].as_str())
+    );
+    assert_eq!(
+        versions[0].get_array("bird").unwrap()[0]
+            .as_document()
+            .unwrap()
+            .get_str("uuid"),
+        Ok(f.bird_uuids[0].as_str())
+    );
+    assert_eq!(versions[0].get_i32("clutch_size"), Ok(5));
+    assert_eq!(versions[0].get_str("nest_state"), Ok("eggs"));
+    assert_eq!(versions[1].get_i32("hatched"), Ok(6));
+    assert_eq!(versions[1].get_str("change"), Ok("delete"));
+    assert_eq!(versions[1].get_str("changed_by"), Ok(f.user_uuid.as_str()));
+    assert_eq!(
+        f.repository
+            .count_by_bird_uuid(&f.bird_uuids[1])
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        f.repository
+            .count_versions_by_bird_uuid(&f.bird_uuids[1])
+            .await
+            .unwrap(),
+        1
+    );
+
+    let bird_uuid = Uuid::new_v4().to_string();
+    let bird = doc! {
+    "uuid": &bird_uuid,
+    "bird": "Kohlmeise",
+    "mandant_uuid": &f.mandant_uuid,
+    "scientific_name": "Parus major",
+    "taxon_key": "gbif:9705453",
+    "bird_website": "https://en.wikipedia.org/wiki/Parus_major",
+    "names": {"de": "Kohlmeise", "en": "Great tit"}};
+    BirdRepository::insert(&f.repository, &bird).await.unwrap();
+    assert_eq!(
+        f.repository
+            .find_uuid_by_taxon_key(&f.mandant_uuid, "gbif:9705453")
+            .await
+            .unwrap(),
+        Some(bird_uuid.clone())
+    );
+    let found = BirdRepository::find_by_uuid(&f.repository, &bird_uuid)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.get_str("scientific_name"), Ok("Parus major"));
+    assert_eq!(
+        found.get_str("bird_website"),
+        Ok("https://en.wikipedia.org/wiki/Parus_major")
+    );
+    assert_eq!(
+        found.get_document("names").unwrap(),
+        &doc! {"de": "Kohlmeise", "en": "Great tit"}
+    );
+    assert_eq!(
+        f.repository
+            .find_uuid_by_name(&f.mandant_uuid, "KOHLMEISE")
+            .await
+            .unwrap(),
+        Some(bird_uuid.clone())
+    );
+    assert!(BirdRepository::update(
+        &f.repository,
+        &bird_uuid,
+        &doc! {"bird": "Blaumeise", "names": {"en": "Blue tit"}}
+    )
+    .await
+    .unwrap());
+    let bird = BirdRepository::find_by_uuid(&f.repository, &bird_uuid)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(bird.get_str("bird"), Ok("Blaumeise"));
+    assert!(bird.get_str("taxon_key").is_err());
+    assert_eq!(
+        bird.get_document("names").unwrap(),
+        &doc! {"en": "Blue tit"}
+    );
+    assert_eq!(bird.get_str("mandant_uuid"), Ok(f.mandant_uuid.as_str()));
+    assert!(BirdRepository::delete(&f.repository, &bird_uuid)
+        .await
+        .unwrap());
+    assert!(!BirdRepository::delete(&f.repository, &bird_uuid)
+        .await
+        .unwrap());
+
+    // Breed 1 moves and is kept as a version, the version of the deleted
+    // breed keeps the merged bird and its name.
+    assert_eq!(
+        BirdRepository::merge(
+            &f.repository,
+            &f.bird_uuids[1],
+            &f.bird_uuids[2],
+            &change("merge", 4)
+        )
+        .await
+        .unwrap(),
+        Some(1)
+    );
+    assert_eq!(
+        f.repository
+            .count_by_bird_uuid(&f.bird_uuids[2])
+            .await
+            .unwrap(),
+        2
+    );
+    let merged_bird = doc! {"uuid": &f.bird_uuids[1], "bird": "bird_0"};
+    let versions = f.repository.find_versions(&breed_uuids[1]).await.unwrap();
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0].get_str("change"), Ok("merge"));
+    assert_eq!(
+        versions[0].get_array("bird").unwrap()[0].as_document(),
+        Some(&merged_bird)
+    );
+    let versions = f.repository.find_versions(&breed_uuids[0]).await.unwrap();
+    assert_eq!(
+        versions[1].get_array("bird").unwrap()[0].as_document(),
+        Some(&merged_bird)
+    );
+    assert_eq!(
+        BirdRepository::merge(
+            &f.repository,
+            &f.bird_uuids[1],
+            &f.bird_uuids[2],
+            &change("merge", 5)
+        )
+        .await
+        .unwrap(),
+        None
+    );
+}
+
+pub(super) async fn sessions_and_users<R: Backend>(repository: R) {
+    let f = fixture(repository).await;
+    let user = f
+        .repository
+        .find_by_username(&f.username)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(user.get_str("mandant_uuid"), Ok(f.mandant_uuid.as_str()));
+    let session_key = Uuid::new_v4().to_string();
+    let mut session = user.clone();
+    let session_id = Uuid::new_v4().to_string();
+    session.insert("session_key", &session_key);
+    session.insert("session_id", &session_id);
+    session.insert("session_created_at", DateTime::now());
+    session.insert("user_agent", "phone");
+    SessionRepository::insert(&f.repository, &session)
+        .await
+        .unwrap();
+    let found = f
+        .repository
+        .find_by_session_key(&session_key)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.get_str("uuid"), Ok(f.user_uuid.as_str()));
+    assert_eq!(found.get_str("session_id"), Ok(session_id.as_str()));
+    assert_eq!(found.get_str("user_agent"), Ok("phone"));
+    let last_seen_at = DateTime::from_millis(DateTime::now().timestamp_millis() + 60000);
+    f.repository
+        .update_last_seen(&session_key, &last_seen_at)
+        .await
+        .unwrap();
+    let sessions = f.repository.find_by_user_uuid(&f.user_uuid).await.unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(
+        sessions[0].get_datetime("session_last_seen_at"),
+        Ok(&last_seen_at)
+    );
+    // The session id alone is not enough, it must be one of the user.
+    assert!(!f
+        .repository
+        .delete_by_session_id(&Uuid::new_v4().to_string(), &session_id)
+        .await
+        .unwrap());
+    assert!(f
+        .repository
+        .delete_by_session_id(&f.user_uuid, &session_id)
+        .await
+        .unwrap());
+    SessionRepository::insert(&f.repository, &session)
+        .await
+        .unwrap();
+    f.repository.delete_by_username(&f.username).await.unwrap();
+    assert!(f
+        .repository
+        .find_by_session_key(&session_key)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+pub(super) async fn geolocations<R: Backend>(repository: R) {
+    let f = fixture(repository).await;
+    let geolocation = doc! {
+    "uuid" : Uuid::new_v4().to_string(),
+    "nestbox_uuid" : &f.nestbox_uuid,
+    // A minute ago, so it is not ended within the same millisecond.
+    "from_date" : DateTime::from_millis(DateTime::now().timestamp_millis() - 60000),
+    "until_date" : DateTime::from_millis(DateTime::now().timestamp_millis() + 86400000),
+    "position" : { "type" : "Point", "coordinates" : [ 8.005, 48.05 ] } };
+    GeolocationRepository::insert(&f.repository, &geolocation)
+        .await
+        .unwrap();
+    f.repository
+        .end_current(&f.nestbox_uuid, &DateTime::now())
+        .await
+        .unwrap();
+    let nestbox = NestboxRepository::find_by_uuid(&f.repository, &f.nestbox_uuid)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(nestbox.get_array("geolocation").unwrap().is_empty());
+
+    let ended = DateTime::now();
+    let moved = doc! {
+    "uuid" : Uuid::new_v4().to_string(),
+    "nestbox_uuid" : &f.nestbox_uuid,
+    "from_date" : ended,
+    "until_date" : DateTime::from_millis(ended.timestamp_millis() + 86400000),
+    "position" : { "type" : "Point", "coordinates" : [ 8.5, 47.25 ] } };
+    GeolocationRepository::insert(&f.repository, &moved)
+        .await
+        .unwrap();
+    let paging = PagingQuery {
+        page_limit: 10,
+        page_number: 1,
+    };
+    let history =
+        GeolocationRepository::find_by_nestbox_uuid(&f.repository, &f.nestbox_uuid, &paging)
+            .await
+            .unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].get_str("uuid"), moved.get_str("uuid"));
+    assert_eq!(
+        GeolocationRepository::count_by_nestbox_uuid(&f.repository, &f.nestbox_uuid)
+            .await
+            .unwrap(),
+        2
+    );
+    let first = f
+        .repository
+        .find_at(
+            &f.nestbox_uuid,
+            geolocation.get_datetime("from_date").unwrap(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(first.get_str("uuid"), geolocation.get_str("uuid"));
+    assert!(f
+        .repository
+        .find_at(&f.nestbox_uuid, &DateTime::from_millis(0))
+        .await
+        .unwrap()
+        .is_none());
+    let nestbox = NestboxRepository::find_by_uuid(&f.repository, &f.nestbox_uuid)
+        .await
+        .unwrap()
+        .unwrap();
+    let current = nestbox.get_array("geolocation").unwrap()[0]
+        .as_document()
+        .unwrap();
+    assert_eq!(current.get_str("uuid"), moved.get_str("uuid"));
+    assert_eq!(
+        current
+            .get_document("position")
+            .unwrap()
+            .get_array("coordinates"),
+        moved
+            .get_document("position")
+            .unwrap()
+            .get_array("coordinates")
+    );
+
+    let near = f
+        .repository
+        .find_near(Some(&f.mandant_uuid), 47.25, 8.505, 1000.0, 10)
+        .await
+        .unwrap();
+    assert_eq!(near.len(), 1);
+    assert_eq!(near[0].get_str("uuid"), Ok(f.nestbox_uuid.as_str()));
+    let distance = near[0].get_f64("distance").unwrap();
+    assert!((distance - 378.0).abs() < 1.0, "{}", distance);
+    assert!(f
+        .repository
+        .find_near(Some(&f.mandant_uuid), 48.05, 8.005, 1000.0, 10)
+        .await
+        .unwrap()
+        .is_empty());
+    let public = f
+        .repository
+        .find_near(None, 47.25, 8.5, 10.0, 100)
+        .await
+        .unwrap();
+    assert!(public
+        .iter()
+        .any(|n| n.get_str("uuid") == Ok(f.nestbox_uuid.as_str())));
+    f.repository
+        .retire(&f.nestbox_uuid, &DateTime::now())
+        .await
+        .unwrap();
+    assert!(f
+        .repository
+        .find_near(Some(&f.mandant_uuid), 47.25, 8.5, 1000.0, 10)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+pub(super) async fn mandants<R: Backend>(repository: R) {
+    let f = fixture(repository).await;
+    let mandant = MandantRepository::find_by_uuid(&f.repository, &f.mandant_uuid)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(mandant.get_str("name").unwrap(), "BirdLife");
+    assert_eq!(mandant.get_str("phone").unwrap(), "");
+    assert!(MandantRepository::update(
+        &f.repository,
+        &f.mandant_uuid,
+        &doc! { "name" : "BirdLife Schweiz", "phone" : "+41 44 457 70 20" }
+    )
+    .await
+    .unwrap());
+    let mandant = MandantRepository::find_by_uuid(&f.repository, &f.mandant_uuid)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(mandant.get_str("name").unwrap(), "BirdLife Schweiz");
+    assert_eq!(
+        mandant.get_str("website").unwrap(),
+        "https://www.birdwatcher.ch"
+    );
+    assert_eq!(mandant.get_str("phone").unwrap(), "+41 44 457 70 20");
+    assert!(!MandantRepository::update(
+        &f.repository,
+        &Uuid::new_v4().to_string(),
+        &doc! { "name" : "Nobody" }
+    )
+    .await
+    .unwrap());
+
+    let new_uuid = Uuid::new_v4().to_string();
+    MandantRepository::insert(
+        &f.repository,
+        &doc! { "uuid" : &new_uuid, "name" : "Vogelschutz", "website" : "", "email" : "",
+        "logo" : "", "contact_person" : "", "phone" : "", "address" : "Zürich" },
+    )
+    .await
+    .unwrap();
+    let mandant = MandantRepository::find_by_uuid(&f.repository, &new_uuid)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(mandant.get_str("uuid").unwrap(), new_uuid);
+    assert_eq!(mandant.get_str("address").unwrap(), "Zürich");
+}
+
+pub(super) async fn users<R: Backend>(repository: R) {
+    let f = fixture(repository).await;
+    let uuid = Uuid::new_v4().to_string();
+    UserRepository::insert(
+        &f.repository,
+        &doc! { "uuid" : &uuid, "mandant_uuid" : &f.mandant_uuid,
+        "username" : format!("fg_{}", Uuid::new_v4()), "lastname" : "Meise",
+        "firstname" : "Kohl", "email" : "kohl@birdwatch.ch", "password_hash" : "$argon2id$",
+        "salt" : Uuid::new_v4().to_string(), "role" : "cleaner", "locked" : false },
+    )
+    .await
+    .unwrap();
+    let paging = PagingQuery {
+        page_limit: 10,
+        page_number: 1,
+    };
+    let users = UserRepository::find_by_mandant_uuid(&f.repository, &f.mandant_uuid, &paging)
+        .await
+        .unwrap();
+    assert_eq!(users.len(), 2);
+    assert!(users.iter().all(|u| u.get("password_hash").is_none()));
+    assert_eq!(
+        UserRepository::count_by_mandant_uuid(&f.repository, &f.mandant_uuid)
+            .await
+            .unwrap(),
+        2
+    );
+    assert!(UserRepository::update(
+        &f.repository,
+        &uuid,
+        &doc! { "lastname" : "Blaumeise", "role" : "viewer", "locked" : true }
+    )
+    .await
+    .unwrap());
+    let user = UserRepository::find_by_uuid(&f.repository, &uuid)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(user.get_str("lastname"), Ok("Blaumeise"));
+    assert_eq!(user.get_str("firstname"), Ok("Kohl"));
+    assert_eq!(user.get_str("role"), Ok("viewer"));
+    assert_eq!(user.get_bool("locked"), Ok(true));
+    assert!(!UserRepository::update(
+        &f.repository,
+        &Uuid::new_v4().to_string(),
+        &doc! { "locked" : true }
+    )
+    .await
+    .unwrap());
+}
+
+pub(super) async fn nestbox_lifecycle<R: Backend>(repository: R) {
+    let f = fixture(repository).await;
+    let uuids = [Uuid::new_v4().to_string(), Uuid::new_v4().to_string()];
+    let nestboxes: Vec<_> = uuids
+        .iter()
+        .map(|u| {
+            doc! { "uuid" : u, "mandant_uuid" : &f.mandant_uuid, "public" : true,
+            "created_at" : DateTime::now(), "from_date" : DateTime::now(),
+            "description" : "Eiche", "box_type" : "" }
+        })
+        .collect();
+    f.repository.insert_many(&nestboxes).await.unwrap();
+    assert!(NestboxRepository::update(
+        &f.repository,
+        &uuids[0],
+        &doc! { "public" : false, "box_type" : "Halbhöhle" }
+    )
+    .await
+    .unwrap());
+    let nestbox = NestboxRepository::find_by_uuid(&f.repository, &uuids[0])
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(nestbox.get_bool("public"), Ok(false));
+    assert_eq!(nestbox.get_str("description"), Ok("Eiche"));
+    assert_eq!(nestbox.get_str("box_type"), Ok("Halbhöhle"));
+    assert!(nestbox.get("until_date").is_none());
+
+    let until_date = DateTime::from_millis(DateTime::now().timestamp_millis() / 1000 * 1000);
+    assert!(f.repository.retire(&uuids[0], &until_date).await.unwrap());
+    assert!(f
+        .repository
+        .retire(&uuids[0], &DateTime::now())
+        .await
+        .unwrap());
+    let nestbox = NestboxRepository::find_by_uuid(&f.repository, &uuids[0])
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(nestbox.get_datetime("until_date"), Ok(&until_date));
+    assert!(!f
+        .repository
+        .retire(&Uuid::new_v4().to_string(), &until_date)
+        .await
+        .unwrap());
+}
+
+pub(super) async fn nestbox_codes<R: Backend>(repository: R) {
+    let f = fixture(repository).await;
+    let uuid = Uuid::new_v4().to_string();
+    let code = new_nestbox_code();
+    f.repository
+        .insert_many(&[
+            doc! { "uuid" : &uuid, "code" : &code, "mandant_uuid" : &f.mandant_uuid,
+            "public" : true, "created_at" : DateTime::now(), "from_date" : DateTime::now(),
+            "description" : "", "box_type" : "" },
+        ])
+        .await
+        .unwrap();
+    let nestbox = f.repository.find_by_code(&code).await.unwrap().unwrap();
+    assert_eq!(nestbox.get_str("uuid"), Ok(uuid.as_str()));
+    assert!(f
+        .repository
+        .find_by_code(&new_nestbox_code())
+        .await
+        .unwrap()
+        .is_none());
+
+    let without_code = f.repository.find_uuids_without_code().await.unwrap();
+    assert!(without_code.contains(&f.nestbox_uuid));
+    assert!(!without_code.contains(&uuid));
+    assert!(!f
+        .repository
+        .set_code(&uuid, &new_nestbox_code())
+        .await
+        .unwrap());
+    assert!(f
+        .repository
+        .set_code(&f.nestbox_uuid, &new_nestbox_code())
+        .await
+        .unwrap());
+    assert!(!f
+        .repository
+        .find_uuids_without_code()
+        .await
+        .unwrap()
+        .contains(&f.nestbox_uuid));
+}
+
+pub(super) async fn nestbox_labels<R: Backend>(repository: R) {
+    let f = fixture(repository).await;
+    let uuid = Uuid::new_v4().to_string();
+    f.repository
+        .insert_many(&[
+            doc! { "uuid" : &uuid, "label" : "Wald Nord 17", "mandant_uuid" : &f.mandant_uuid,
+            "public" : true, "created_at" : DateTime::now(), "from_date" : DateTime::now(),
+            "description" : "", "box_type" : "" },
+        ])
+        .await
+        .unwrap();
+    assert_eq!(
+        f.repository
+            .find_uuid_by_label(&f.mandant_uuid, "Wald Nord 17")
+            .await
+            .unwrap(),
+        Some(uuid.clone())
+    );
+    assert_eq!(
+        f.repository
+            .find_uuid_by_label(&f.mandant_uuid, "wald nord 17")
+            .await
+            .unwrap(),
+        Some(uuid.clone())
+    );
+    assert!(f
+        .repository
+        .find_uuid_by_label(&f.mandant_uuid, "Wald Nord")
+        .await
+        .unwrap()
+        .is_none());
+    // The unique index ignores case as well.
+    let taken = f
+        .repository
+        .insert_many(&[
+            doc! { "uuid" : Uuid::new_v4().to_string(), "label" : "WALD NORD 17",
+            "mandant_uuid" : &f.mandant_uuid, "public" : true, "created_at" : DateTime::now(),
+            "from_date" : DateTime::now(), "description" : "", "box_type" : "" },
+        ])
+        .await;
+    assert!(taken.is_err_and(|e| e.is_unique_violation()));
+    let paging = PagingQuery {
+        page_limit: 10,
+        page_number: 1,
+    };
+    let filter = NestboxFilter {
+        label: Some(String::from("wald nord")),
+        ..Default::default()
+    };
+    let nestboxes =
+        NestboxRepository::find_by_mandant_uuid(&f.repository, &f.mandant_uuid, &filter, &paging)
+            .await
+            .unwrap();
+    assert_eq!(nestboxes.len(), 1);
+    assert_eq!(nestboxes[0].get_str("label"), Ok("Wald Nord 17"));
+
+    let breed = doc! {
+    "uuid": Uuid::new_v4().to_string(),
+    "nestbox_uuid": &uuid,
+    "user_uuid": &f.user_uuid,
+    "discovery_date": DateTime::now(),
+    "bird_uuid": &f.bird_uuids[0]};
+    BreedRepository::insert(&f.repository, &breed)
+        .await
+        .unwrap();
+    let breeds = BreedRepository::find_by_nestbox_uuid(&f.repository, &uuid, &paging)
+        .await
+        .unwrap();
+    assert_eq!(breeds[0].get_str("nestbox_label"), Ok("Wald Nord 17"));
+
+    assert!(NestboxRepository::update(
+        &f.repository,
+        &uuid,
+        &doc! { "description" : "Eiche", "label" : null }
+    )
+    .await
+    .unwrap());
+    let nestbox = NestboxRepository::find_by_uuid(&f.repository, &uuid)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(nestbox.get("label").is_none());
+    assert_eq!(nestbox.get_str("description"), Ok("Eiche"));
+    assert_eq!(
+        NestboxRepository::count_by_mandant_uuid(&f.repository, &f.mandant_uuid, &filter)
+            .await
+            .unwrap(),
+        0
+    );
+}
+
+pub(super) async fn nestboxes_filtered<R: Backend>(repository: R) {
+    let f = fixture(repository).await;
+    let retired_uuid = Uuid::new_v4().to_string();
+    f.repository
+        .insert_many(&[
+            doc! { "uuid" : &retired_uuid, "mandant_uuid" : &f.mandant_uuid,
+            "public" : false, "created_at" : DateTime::now(), "from_date" : DateTime::now(),
+            "description" : "", "box_type" : "" },
+        ])
+        .await
+        .unwrap();
+    f.repository
+        .retire(&retired_uuid, &DateTime::now())
+        .await
+        .unwrap();
+    let breed = doc! {
+    "uuid": Uuid::new_v4().to_string(),
+    "nestbox_uuid": &f.nestbox_uuid,
+    "user_uuid": &f.user_uuid,
+    "discovery_date": DateTime::parse_rfc3339_str("2021-05-01T08:00:00Z").unwrap(),
+    "bird_uuid": &f.bird_uuids[0]};
+    BreedRepository::insert(&f.repository, &breed)
+        .await
+        .unwrap();
+
+    let paging = PagingQuery {
+        page_limit: 1,
+        page_number: 2,
+    };
+    let nestboxes = NestboxRepository::find_by_mandant_uuid(
+        &f.repository,
+        &f.mandant_uuid,
+        &NestboxFilter::default(),
+        &paging,
+    )
+    .await
+    .unwrap();
+    assert_eq!(nestboxes.len(), 1);
+    assert!(nestboxes[0].get_array("mandant").is_ok());
+
+    let paging = PagingQuery {
+        page_limit: 10,
+        page_number: 1,
+    };
+    let since = |date: &str| Some(DateTime::parse_rfc3339_str(date).unwrap());
+    for (filter, expected) in [
+        (
+            NestboxFilter::default(),
+            vec![&f.nestbox_uuid, &retired_uuid],
+        ),
+        (
+            NestboxFilter {
+                retired: Some(true),
+                ..Default::default()
+            },
+            vec![&retired_uuid],
+        ),
+        (
+            NestboxFilter {
+                public: Some(true),
+                ..Default::default()
+            },
+            vec![&f.nestbox_uuid],
+        ),
+        (
+            NestboxFilter {
+                last_breed_year: Some(2021),
+                ..Default::default()
+            },
+            vec![&f.nestbox_uuid],
+        ),
+        (
+            NestboxFilter {
+                last_breed_year: Some(2022),
+                ..Default::default()
+            },
+            vec![],
+        ),
+        (
+            NestboxFilter {
+                bird_uuid: Some(f.bird_uuids[0].clone()),
+                ..Default::default()
+            },
+            vec![&f.nestbox_uuid],
+        ),
+        (
+            NestboxFilter {
+                bird_uuid: Some(f.bird_uuids[1].clone()),
+                ..Default::default()
+            },
+            vec![],
+        ),
+        (
+            NestboxFilter {
+                not_inspected_since: since("2021-01-01T00:00:00Z"),
+                ..Default::default()
+            },
+            vec![&retired_uuid],
+        ),
+        (
+            NestboxFilter {
+                retired: Some(false),
+                not_inspected_since: since("2022-01-01T00:00:00Z"),
+                ..Default::default()
+            },
+            vec![&f.nestbox_uuid],
+        ),
+    ] {
+        let mut uuids: Vec<String> = NestboxRepository::find_by_mandant_uuid(
+            &f.repository,
+            &f.mandant_uuid,
+            &filter,
+            &paging,
+        )
+        .await
+        .unwrap()
+        .iter()
+        .map(|n| n.get_str("uuid").unwrap().to_string())
+        .collect();
+        uuids.sort();
+        let mut expected: Vec<String> = expected.into_iter().cloned().collect();
+        expected.sort();
+        assert_eq!(uuids, expected, "{:?}", filter);
+        assert_eq!(
+            NestboxRepository::count_by_mandant_uuid(&f.repository, &f.mandant_uuid, &filter)
+                .await
+                .unwrap(),
+            expected.len() as u64
+        );
+    }
+}
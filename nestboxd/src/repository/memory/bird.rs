@@ -0,0 +1,135 @@
+use async_trait::async_trait;
+use mongodb::bson::Document;
+use uuid::Uuid;
+
+use super::{is_eq, page, MemoryRepository};
+use crate::controller::utilities::PagingQuery;
+use crate::repository::{
+    BirdRepository, RepositoryResult, BIRDS, BIRD_DETAILS, BREEDS, BREED_VERSIONS,
+};
+
+#[async_trait]
+impl BirdRepository for MemoryRepository {
+    async fn find_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>> {
+        let mut birds = self.find(BIRDS, |d| is_eq(d, "mandant_uuid", mandant_uuid));
+        birds.sort_by(|a, b| {
+            a.get_str("bird")
+                .unwrap_or_default()
+                .cmp(b.get_str("bird").unwrap_or_default())
+        });
+        Ok(page(birds, paging)
+            .into_iter()
+            .map(|mut bird| {
+                bird.remove("_id");
+                bird.remove("mandant_uuid");
+                bird
+            })
+            .collect())
+    }
+
+    async fn count_by_mandant_uuid(&self, mandant_uuid: &str) -> RepositoryResult<u64> {
+        Ok(self.count(BIRDS, |d| is_eq(d, "mandant_uuid", mandant_uuid)))
+    }
+
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>> {
+        Ok(self
+            .find_one(BIRDS, |d| is_eq(d, "uuid", uuid))
+            .map(|mut bird| {
+                bird.remove("_id");
+                bird
+            }))
+    }
+
+    async fn find_uuid_by_name(
+        &self,
+        mandant_uuid: &str,
+        name: &str,
+    ) -> RepositoryResult<Option<String>> {
+        let name = name.to_lowercase();
+        Ok(self
+            .find_one(BIRDS, |d| {
+                is_eq(d, "mandant_uuid", mandant_uuid)
+                    && d.get_str("bird").is_ok_and(|b| b.to_lowercase() == name)
+            })
+            .and_then(|b| b.get_str("uuid").ok().map(String::from)))
+    }
+
+    async fn find_uuid_by_taxon_key(
+        &self,
+        mandant_uuid: &str,
+        taxon_key: &str,
+    ) -> RepositoryResult<Option<String>> {
+        Ok(self
+            .find_one(BIRDS, |d| {
+                is_eq(d, "mandant_uuid", mandant_uuid) && is_eq(d, "taxon_key", taxon_key)
+            })
+            .and_then(|b| b.get_str("uuid").ok().map(String::from)))
+    }
+
+    async fn insert(&self, bird: &Document) -> RepositoryResult<()> {
+        self.insert_document(BIRDS, bird.clone());
+        Ok(())
+    }
+
+    async fn update(&self, uuid: &str, bird: &Document) -> RepositoryResult<bool> {
+        let updated = self.update(
+            BIRDS,
+            |d| is_eq(d, "uuid", uuid),
+            |d| {
+                for key in BIRD_DETAILS {
+                    d.remove(key);
+                }
+                d.extend(bird.clone());
+            },
+        );
+        Ok(updated > 0)
+    }
+
+    async fn delete(&self, uuid: &str) -> RepositoryResult<bool> {
+        Ok(self.delete(BIRDS, |d| is_eq(d, "uuid", uuid)) > 0)
+    }
+
+    async fn merge(
+        &self,
+        uuid: &str,
+        into_uuid: &str,
+        change: &Document,
+    ) -> RepositoryResult<Option<u64>> {
+        // One lock over all collections keeps the merge atomic.
+        let mut collections = self.collections.write().unwrap();
+        let birds = collections.entry(BIRDS.to_string()).or_default();
+        let Some(i) = birds.iter().position(|d| is_eq(d, "uuid", uuid)) else {
+            return Ok(None);
+        };
+        let bird = birds.remove(i);
+        let mut versions = Vec::new();
+        for breed in collections
+            .entry(BREEDS.to_string())
+            .or_default()
+            .iter_mut()
+            .filter(|d| is_eq(d, "bird_uuid", uuid))
+        {
+            let mut version = breed.clone();
+            version.remove("_id");
+            version.insert("breed_uuid", breed.get_str("uuid").unwrap_or_default());
+            version.extend(change.clone());
+            version.insert("uuid", Uuid::new_v4().to_string());
+            versions.push(version);
+            breed.insert("bird_uuid", into_uuid);
+        }
+        let moved = versions.len() as u64;
+        let breed_versions = collections.entry(BREED_VERSIONS.to_string()).or_default();
+        breed_versions.extend(versions);
+        for version in breed_versions
+            .iter_mut()
+            .filter(|d| is_eq(d, "bird_uuid", uuid))
+        {
+            version.insert("bird_name", bird.get_str("bird").unwrap_or_default());
+        }
+        Ok(Some(moved))
+    }
+}
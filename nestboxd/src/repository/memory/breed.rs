@@ -0,0 +1,150 @@
+use async_trait::async_trait;
+use mongodb::bson::{doc, Document};
+
+use super::{is_eq, page, MemoryRepository};
+use crate::controller::utilities::PagingQuery;
+use crate::repository::{
+    BreedRepository, RepositoryResult, BIRDS, BREEDS, BREED_VERSIONS, NESTBOXES, OBSERVATIONS,
+};
+
+impl MemoryRepository {
+    /// Joins the bird and the label of the nestbox into the breed like the
+    /// MongoDB lookups.
+    fn with_breed_lookups(&self, mut breed: Document) -> Document {
+        let bird_uuid = breed.get_str("bird_uuid").unwrap_or_default().to_string();
+        let bird: Vec<Document> = self
+            .find(BIRDS, |d| is_eq(d, "uuid", &bird_uuid))
+            .iter()
+            .map(|b| doc! {"uuid": b.get("uuid"), "bird": b.get("bird")})
+            .collect();
+        let nestbox_uuid = breed.get_str("nestbox_uuid").unwrap_or_default();
+        let nestbox_label = self
+            .find_one(NESTBOXES, |d| is_eq(d, "uuid", nestbox_uuid))
+            .and_then(|n| n.get_str("label").ok().map(String::from));
+        breed.remove("_id");
+        breed.remove("mandant_uuid");
+        // The bird of a version may have been merged into another one.
+        let bird = match breed.remove("bird_name") {
+            Some(name) if bird.is_empty() => vec![doc! {"uuid": &bird_uuid, "bird": name}],
+            _ => bird,
+        };
+        breed.remove("bird_uuid");
+        breed.insert("bird", bird);
+        if let Some(label) = nestbox_label {
+            breed.insert("nestbox_label", label);
+        }
+        breed
+    }
+
+    /// Keeps the breed as it is now as a version with the fields of `change`.
+    fn keep_breed_version(&self, uuid: &str, change: &Document) -> bool {
+        let Some(mut version) = self.find_one(BREEDS, |d| is_eq(d, "uuid", uuid)) else {
+            return false;
+        };
+        version.remove("_id");
+        version.insert(
+            "breed_uuid",
+            version.get_str("uuid").unwrap_or_default().to_string(),
+        );
+        version.extend(change.clone());
+        self.insert_document(BREED_VERSIONS, version);
+        true
+    }
+}
+
+#[async_trait]
+impl BreedRepository for MemoryRepository {
+    async fn find_by_nestbox_uuid(
+        &self,
+        nestbox_uuid: &str,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>> {
+        let breeds = page(
+            self.find(BREEDS, |d| is_eq(d, "nestbox_uuid", nestbox_uuid)),
+            paging,
+        );
+        Ok(breeds
+            .into_iter()
+            .map(|b| self.with_breed_lookups(b))
+            .collect())
+    }
+
+    async fn count_by_nestbox_uuid(&self, nestbox_uuid: &str) -> RepositoryResult<u64> {
+        Ok(self.count(BREEDS, |d| is_eq(d, "nestbox_uuid", nestbox_uuid)))
+    }
+
+    async fn count_by_bird_uuid(&self, bird_uuid: &str) -> RepositoryResult<u64> {
+        Ok(self.count(BREEDS, |d| is_eq(d, "bird_uuid", bird_uuid)))
+    }
+
+    async fn count_versions_by_bird_uuid(&self, bird_uuid: &str) -> RepositoryResult<u64> {
+        Ok(self.count(BREED_VERSIONS, |d| is_eq(d, "bird_uuid", bird_uuid)))
+    }
+
+    async fn find_latest_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+    ) -> RepositoryResult<Vec<Document>> {
+        Ok(self
+            .find(NESTBOXES, |d| is_eq(d, "mandant_uuid", mandant_uuid))
+            .iter()
+            .filter_map(|n| {
+                let nestbox_uuid = n.get_str("uuid").unwrap_or_default();
+                self.find(BREEDS, |d| is_eq(d, "nestbox_uuid", nestbox_uuid))
+                    .into_iter()
+                    .max_by_key(|b| b.get_datetime("discovery_date").ok().copied())
+            })
+            .map(|b| self.with_breed_lookups(b))
+            .collect())
+    }
+
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>> {
+        Ok(self
+            .find_one(BREEDS, |d| is_eq(d, "uuid", uuid))
+            .map(|b| self.with_breed_lookups(b)))
+    }
+
+    async fn insert(&self, breed: &Document) -> RepositoryResult<()> {
+        self.insert_document(BREEDS, breed.clone());
+        Ok(())
+    }
+
+    async fn update(&self, breed: &Document, change: &Document) -> RepositoryResult<bool> {
+        let uuid = breed.get_str("uuid").unwrap_or_default();
+        if !self.keep_breed_version(uuid, change) {
+            return Ok(false);
+        }
+        self.update(
+            BREEDS,
+            |d| is_eq(d, "uuid", uuid),
+            |d| {
+                for key in OBSERVATIONS {
+                    d.remove(key);
+                }
+                d.extend(breed.clone());
+            },
+        );
+        Ok(true)
+    }
+
+    async fn delete(&self, uuid: &str, change: &Document) -> RepositoryResult<bool> {
+        if !self.keep_breed_version(uuid, change) {
+            return Ok(false);
+        }
+        self.delete(BREEDS, |d| is_eq(d, "uuid", uuid));
+        Ok(true)
+    }
+
+    async fn find_versions(&self, uuid: &str) -> RepositoryResult<Vec<Document>> {
+        let mut versions = self.find(BREED_VERSIONS, |d| is_eq(d, "breed_uuid", uuid));
+        versions.sort_by_key(|v| v.get_datetime("changed_at").ok().copied());
+        Ok(versions
+            .into_iter()
+            .map(|v| {
+                let mut version = self.with_breed_lookups(v);
+                version.remove("nestbox_label");
+                version
+            })
+            .collect())
+    }
+}
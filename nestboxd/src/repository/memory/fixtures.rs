@@ -0,0 +1,137 @@
+//! Test data for the in-memory backend, shaped like the records
+//! database_bouncycastle generates. Every user has the password `secretbird`,
+//! fg_1 is the superuser, fg_10 the mandant-admin of BirdLife, fg_198 a viewer
+//! and fg_200 a cleaner, all others are coordinators.
+use mongodb::bson::{doc, DateTime};
+use sha3::{Digest, Sha3_256};
+use uuid::Uuid;
+
+use super::MemoryRepository;
+use crate::repository::{Repositories, BIRDS, BREEDS, MANDANTS, NESTBOXES, USERS};
+use crate::service::nestbox::new_nestbox_code;
+
+pub const PASSWORD: &str = "secretbird";
+
+pub const MANDANT_BIRDLIFE: &str = "4ac9971c-91de-455c-a1fd-4b9dfb862cee";
+pub const MANDANT_1: &str = "5bcb187b-996a-4169-8f12-cc315c2b22f7";
+pub const MANDANT_STRANGER: &str = "e7620353-b6f6-47e9-b543-66af20769145";
+
+pub const NESTBOX_BIRDLIFE: &str = "a446545d-f594-4eb5-96b4-c2312554050c";
+pub const NESTBOX_EXISTING: &str = "9ede3c8c-f552-4f74-bb8c-0b574be9895c";
+pub const NESTBOX_MANDANT_1: &str = "45f149a2-b05a-4de8-a358-6e704eb6efca";
+pub const NESTBOX_STRANGER: &str = "74a0d653-f93a-4383-822f-8f55ab853fca";
+
+pub const BIRD_MANDANT_1: &str = "ffbf3bf5-868e-437b-b0e8-cf19ce2a6ad2";
+
+const BIRDS_PER_MANDANT: usize = 150;
+const BREEDS_PER_NESTBOX: usize = 6;
+
+pub fn repositories() -> Repositories {
+    Repositories::from_backend(memory_repository())
+}
+
+pub fn memory_repository() -> MemoryRepository {
+    let repository = MemoryRepository::new();
+    add_mandant(
+        &repository,
+        MANDANT_BIRDLIFE,
+        "BirdLife",
+        &[
+            ("fg_1", "superuser"),
+            ("fg_10", "mandant-admin"),
+            ("fg_11", "coordinator"),
+            ("fg_198", "viewer"),
+            ("fg_199", "coordinator"),
+        ],
+        &[NESTBOX_BIRDLIFE, NESTBOX_EXISTING],
+        None,
+    );
+    add_mandant(
+        &repository,
+        MANDANT_1,
+        "BirdLife 100",
+        &[("fg_180", "coordinator"), ("fg_200", "cleaner")],
+        &[NESTBOX_MANDANT_1],
+        Some(BIRD_MANDANT_1),
+    );
+    add_mandant(
+        &repository,
+        MANDANT_STRANGER,
+        "BirdLife 1000",
+        &[("fg_1001", "coordinator"), ("fg_1002", "coordinator")],
+        &[NESTBOX_STRANGER],
+        None,
+    );
+    repository
+}
+
+fn add_mandant(
+    repository: &MemoryRepository,
+    mandant_uuid: &str,
+    name: &str,
+    users: &[(&str, &str)],
+    nestboxes: &[&str],
+    first_bird: Option<&str>,
+) {
+    repository.insert_document(
+        MANDANTS,
+        doc! {"uuid": mandant_uuid, "name": name,
+        "website": "https://www.birdwatcher.ch", "email": "bird@iseeyou.ch"},
+    );
+    let mut bird_uuids: Vec<String> = Vec::new();
+    for b in 0..BIRDS_PER_MANDANT {
+        let bird_uuid = match (b, first_bird) {
+            (0, Some(u)) => u.to_string(),
+            _ => Uuid::new_v4().to_string(),
+        };
+        repository.insert_document(
+            BIRDS,
+            doc! {"uuid": &bird_uuid, "bird": format!("bird_{}", b), "mandant_uuid": mandant_uuid},
+        );
+        bird_uuids.push(bird_uuid);
+    }
+    let mut user_uuids: Vec<String> = Vec::new();
+    for (username, role) in users {
+        let user_uuid = Uuid::new_v4().to_string();
+        let salt = Uuid::new_v4().to_string();
+        repository.insert_document(
+            USERS,
+            doc! {
+            "mandant_uuid": mandant_uuid,
+            "username": username,
+            "uuid": &user_uuid,
+            "lastname": "Gucker",
+            "firstname":"Fritz",
+            "email": format!("email_{}@birdwatch.ch", username),
+            "password_hash": sha3_password_hash(PASSWORD, &salt),
+            "salt": &salt,
+            "role": role},
+        );
+        user_uuids.push(user_uuid);
+    }
+    for (n, nestbox_uuid) in nestboxes.iter().enumerate() {
+        repository.insert_document(
+            NESTBOXES,
+            doc! {"public": true, "uuid": nestbox_uuid, "code": new_nestbox_code(),
+            "label": format!("Box {}", n + 1), "mandant_uuid": mandant_uuid,
+            "created_at": DateTime::now()},
+        );
+        for b in 0..BREEDS_PER_NESTBOX {
+            repository.insert_document(
+                BREEDS,
+                doc! {
+                "uuid": Uuid::new_v4().to_string(),
+                "nestbox_uuid": nestbox_uuid,
+                "user_uuid": &user_uuids[n % user_uuids.len()],
+                "discovery_date": DateTime::now(),
+                "bird_uuid": &bird_uuids[(n * BREEDS_PER_NESTBOX + b) % bird_uuids.len()]},
+            );
+        }
+    }
+}
+
+fn sha3_password_hash(password: &str, salt: &str) -> String {
+    let mut hasher = Sha3_256::new();
+    hasher.update(format!("{}_{}", password, salt));
+    hex::encode(hasher.finalize())
+}
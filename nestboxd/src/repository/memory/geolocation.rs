@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+use mongodb::bson::{DateTime, Document};
+
+use super::{is_eq, page, MemoryRepository};
+use crate::controller::utilities::PagingQuery;
+use crate::repository::{GeolocationRepository, RepositoryResult, GEOLOCATIONS};
+
+impl MemoryRepository {
+    /// Locations of the nestbox, the latest first.
+    pub(super) fn find_geolocations(&self, nestbox_uuid: &str) -> Vec<Document> {
+        let mut geolocations = self.find(GEOLOCATIONS, |d| is_eq(d, "nestbox_uuid", nestbox_uuid));
+        geolocations.sort_by(|a, b| {
+            b.get_datetime("from_date")
+                .ok()
+                .cmp(&a.get_datetime("from_date").ok())
+        });
+        for geolocation in geolocations.iter_mut() {
+            geolocation.remove("_id");
+        }
+        geolocations
+    }
+}
+
+#[async_trait]
+impl GeolocationRepository for MemoryRepository {
+    async fn end_current(&self, nestbox_uuid: &str, now: &DateTime) -> RepositoryResult<()> {
+        self.update(
+            GEOLOCATIONS,
+            |d| {
+                is_eq(d, "nestbox_uuid", nestbox_uuid)
+                    && matches!(d.get_datetime("until_date"), Ok(until) if until > now)
+            },
+            |d| {
+                d.insert("until_date", *now);
+            },
+        );
+        Ok(())
+    }
+
+    async fn insert(&self, geolocation: &Document) -> RepositoryResult<()> {
+        self.insert_document(GEOLOCATIONS, geolocation.clone());
+        Ok(())
+    }
+
+    async fn find_by_nestbox_uuid(
+        &self,
+        nestbox_uuid: &str,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>> {
+        Ok(page(self.find_geolocations(nestbox_uuid), paging))
+    }
+
+    async fn count_by_nestbox_uuid(&self, nestbox_uuid: &str) -> RepositoryResult<u64> {
+        Ok(self.count(GEOLOCATIONS, |d| is_eq(d, "nestbox_uuid", nestbox_uuid)))
+    }
+
+    async fn find_at(
+        &self,
+        nestbox_uuid: &str,
+        at: &DateTime,
+    ) -> RepositoryResult<Option<Document>> {
+        Ok(self.find_geolocations(nestbox_uuid).into_iter().find(|d| {
+            matches!(d.get_datetime("from_date"), Ok(from) if from <= at)
+                && matches!(d.get_datetime("until_date"), Ok(until) if until > at)
+        }))
+    }
+}
@@ -0,0 +1,30 @@
+use async_trait::async_trait;
+use mongodb::bson::Document;
+
+use super::{is_eq, MemoryRepository};
+use crate::repository::{MandantRepository, RepositoryResult, MANDANTS};
+
+#[async_trait]
+impl MandantRepository for MemoryRepository {
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>> {
+        Ok(self.find_one(MANDANTS, |d| is_eq(d, "uuid", uuid)))
+    }
+
+    async fn insert(&self, mandant: &Document) -> RepositoryResult<()> {
+        self.insert_document(MANDANTS, mandant.clone());
+        Ok(())
+    }
+
+    async fn update(&self, uuid: &str, mandant: &Document) -> RepositoryResult<bool> {
+        let updated = self.update(
+            MANDANTS,
+            |d| is_eq(d, "uuid", uuid),
+            |d| {
+                for (key, value) in mandant {
+                    d.insert(key, value.clone());
+                }
+            },
+        );
+        Ok(updated > 0)
+    }
+}
@@ -0,0 +1,139 @@
+//! In-memory backend. Keeps every collection as a vector of documents, so
+//! nestboxd can run without any database, e.g. for tests or prototyping.
+//! Nothing is persisted, all data is lost on shutdown.
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::RwLock;
+
+use mongodb::bson::Document;
+
+use super::{BIRDS, BREEDS, BREED_VERSIONS, GEOLOCATIONS, MANDANTS, NESTBOXES, SESSIONS, USERS};
+use crate::controller::utilities::PagingQuery;
+
+mod bird;
+mod breed;
+#[cfg(test)]
+pub mod fixtures;
+mod geolocation;
+mod mandant;
+mod nestbox;
+mod session;
+mod user;
+
+#[derive(Default)]
+pub struct MemoryRepository {
+    collections: RwLock<HashMap<String, Vec<Document>>>,
+}
+
+impl MemoryRepository {
+    pub fn new() -> Self {
+        MemoryRepository::default()
+    }
+
+    /// Loads the collections of a mongodump directory (`<collection>.bson`),
+    /// e.g. test_db_dump/nestbox_testing. Missing files are skipped.
+    pub fn from_dump(directory: &str) -> std::io::Result<Self> {
+        let repository = MemoryRepository::new();
+        for collection in &[
+            MANDANTS,
+            USERS,
+            SESSIONS,
+            NESTBOXES,
+            GEOLOCATIONS,
+            BREEDS,
+            BREED_VERSIONS,
+            BIRDS,
+        ] {
+            let path = Path::new(directory).join(format!("{}.bson", collection));
+            if !path.exists() {
+                continue;
+            }
+            let mut reader = BufReader::new(File::open(path)?);
+            while let Ok(doc) = Document::from_reader(&mut reader) {
+                repository.insert_document(collection, doc);
+            }
+        }
+        Ok(repository)
+    }
+
+    pub fn insert_document(&self, collection: &str, doc: Document) {
+        self.collections
+            .write()
+            .unwrap()
+            .entry(collection.to_string())
+            .or_default()
+            .push(doc);
+    }
+
+    fn find<P>(&self, collection: &str, predicate: P) -> Vec<Document>
+    where
+        P: Fn(&Document) -> bool,
+    {
+        match self.collections.read().unwrap().get(collection) {
+            Some(docs) => docs.iter().filter(|d| predicate(d)).cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn find_one<P>(&self, collection: &str, predicate: P) -> Option<Document>
+    where
+        P: Fn(&Document) -> bool,
+    {
+        match self.collections.read().unwrap().get(collection) {
+            Some(docs) => docs.iter().find(|d| predicate(d)).cloned(),
+            None => None,
+        }
+    }
+
+    fn count<P>(&self, collection: &str, predicate: P) -> u64
+    where
+        P: Fn(&Document) -> bool,
+    {
+        match self.collections.read().unwrap().get(collection) {
+            Some(docs) => docs.iter().filter(|d| predicate(d)).count() as u64,
+            None => 0,
+        }
+    }
+
+    fn update<P, U>(&self, collection: &str, predicate: P, mut update: U) -> u64
+    where
+        P: Fn(&Document) -> bool,
+        U: FnMut(&mut Document),
+    {
+        let mut updated = 0;
+        if let Some(docs) = self.collections.write().unwrap().get_mut(collection) {
+            for d in docs.iter_mut().filter(|d| predicate(d)) {
+                update(d);
+                updated += 1;
+            }
+        }
+        updated
+    }
+
+    fn delete<P>(&self, collection: &str, predicate: P) -> u64
+    where
+        P: Fn(&Document) -> bool,
+    {
+        match self.collections.write().unwrap().get_mut(collection) {
+            Some(docs) => {
+                let before = docs.len();
+                docs.retain(|d| !predicate(d));
+                (before - docs.len()) as u64
+            }
+            None => 0,
+        }
+    }
+}
+
+fn is_eq(doc: &Document, key: &str, value: &str) -> bool {
+    doc.get_str(key) == Ok(value)
+}
+
+fn page(docs: Vec<Document>, paging: &PagingQuery) -> Vec<Document> {
+    docs.into_iter()
+        .skip((paging.page_limit * (paging.page_number - 1)) as usize)
+        .take(paging.page_limit as usize)
+        .collect()
+}
@@ -0,0 +1,240 @@
+use async_trait::async_trait;
+use chrono::Datelike;
+use mongodb::bson::{doc, Bson, DateTime, Document};
+
+use super::{is_eq, page, MemoryRepository};
+use crate::controller::utilities::PagingQuery;
+use crate::repository::{
+    nearest, NestboxFilter, NestboxRepository, RepositoryResult, BREEDS, MANDANTS, NESTBOXES,
+};
+
+impl MemoryRepository {
+    /// Joins the mandant and the current location into the nestbox like the
+    /// MongoDB lookups.
+    fn with_lookups(&self, mut nestbox: Document) -> Document {
+        let mandant_uuid = nestbox.get_str("mandant_uuid").unwrap_or_default();
+        let mandant: Vec<Document> = self
+            .find(MANDANTS, |d| is_eq(d, "uuid", mandant_uuid))
+            .iter()
+            .map(|m| {
+                doc! {"uuid": m.get("uuid"), "name": m.get("name"), "website": m.get("website")}
+            })
+            .collect();
+        let now = DateTime::now();
+        let geolocation: Vec<Document> = self
+            .find_geolocations(nestbox.get_str("uuid").unwrap_or_default())
+            .into_iter()
+            .filter(|d| {
+                matches!(d.get_datetime("from_date"), Ok(from) if *from <= now)
+                    && matches!(d.get_datetime("until_date"), Ok(until) if *until > now)
+            })
+            .take(1)
+            .collect();
+        nestbox.remove("_id");
+        nestbox.insert("mandant", mandant);
+        nestbox.insert("geolocation", geolocation);
+        nestbox
+    }
+
+    fn find_filtered(&self, mandant_uuid: &str, filter: &NestboxFilter) -> Vec<Document> {
+        let mut nestboxes = self.find(NESTBOXES, |d| {
+            is_eq(d, "mandant_uuid", mandant_uuid)
+                && filter
+                    .public
+                    .is_none_or(|p| d.get_bool("public").unwrap_or(true) == p)
+                && filter
+                    .retired
+                    .is_none_or(|r| d.get_datetime("until_date").is_ok() == r)
+                && filter.label.as_ref().is_none_or(|l| {
+                    d.get_str("label")
+                        .is_ok_and(|label| label.to_lowercase().contains(&l.to_lowercase()))
+                })
+        });
+        if filter.last_breed_year.is_some()
+            || filter.bird_uuid.is_some()
+            || filter.not_inspected_since.is_some()
+        {
+            nestboxes.retain(|n| {
+                let nestbox_uuid = n.get_str("uuid").unwrap_or_default();
+                let breeds = self.find(BREEDS, |b| is_eq(b, "nestbox_uuid", nestbox_uuid));
+                let last_breed = breeds
+                    .iter()
+                    .filter_map(|b| b.get_datetime("discovery_date").ok())
+                    .max();
+                filter.last_breed_year.is_none_or(|y| {
+                    last_breed.is_some_and(|d| {
+                        chrono::DateTime::<chrono::Utc>::from(d.to_system_time()).year() == y
+                    })
+                }) && filter
+                    .bird_uuid
+                    .as_ref()
+                    .is_none_or(|bird_uuid| breeds.iter().any(|b| is_eq(b, "bird_uuid", bird_uuid)))
+                    && filter
+                        .not_inspected_since
+                        .is_none_or(|since| last_breed.is_none_or(|d| *d < since))
+            });
+        }
+        nestboxes.sort_by(|a, b| {
+            (a.get_datetime("created_at").ok(), a.get_str("uuid").ok())
+                .cmp(&(b.get_datetime("created_at").ok(), b.get_str("uuid").ok()))
+        });
+        nestboxes
+    }
+}
+
+#[async_trait]
+impl NestboxRepository for MemoryRepository {
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>> {
+        Ok(self
+            .find_one(NESTBOXES, |d| is_eq(d, "uuid", uuid))
+            .map(|n| self.with_lookups(n)))
+    }
+
+    async fn find_by_code(&self, code: &str) -> RepositoryResult<Option<Document>> {
+        Ok(self
+            .find_one(NESTBOXES, |d| is_eq(d, "code", code))
+            .map(|n| self.with_lookups(n)))
+    }
+
+    async fn find_uuid_by_label(
+        &self,
+        mandant_uuid: &str,
+        label: &str,
+    ) -> RepositoryResult<Option<String>> {
+        Ok(self
+            .find_one(NESTBOXES, |d| {
+                is_eq(d, "mandant_uuid", mandant_uuid)
+                    && d.get_str("label")
+                        .is_ok_and(|l| l.to_lowercase() == label.to_lowercase())
+            })
+            .and_then(|n| n.get_str("uuid").ok().map(String::from)))
+    }
+
+    async fn find_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        filter: &NestboxFilter,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>> {
+        Ok(page(self.find_filtered(mandant_uuid, filter), paging)
+            .into_iter()
+            .map(|n| self.with_lookups(n))
+            .collect())
+    }
+
+    async fn count_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        filter: &NestboxFilter,
+    ) -> RepositoryResult<u64> {
+        Ok(self.find_filtered(mandant_uuid, filter).len() as u64)
+    }
+
+    async fn find_by_uuid_and_mandant_uuid(
+        &self,
+        uuid: &str,
+        mandant_uuid: &str,
+    ) -> RepositoryResult<Option<Document>> {
+        Ok(self.find_one(NESTBOXES, |d| {
+            is_eq(d, "uuid", uuid) && is_eq(d, "mandant_uuid", mandant_uuid)
+        }))
+    }
+
+    async fn append_image(&self, uuid: &str, image: &str) -> RepositoryResult<()> {
+        self.update(
+            NESTBOXES,
+            |d| is_eq(d, "uuid", uuid),
+            |d| {
+                let mut images = d.get_array("images").cloned().unwrap_or_default();
+                let image = Bson::from(image);
+                if !images.contains(&image) {
+                    images.push(image);
+                }
+                d.insert("images", images);
+            },
+        );
+        Ok(())
+    }
+
+    async fn insert_many(&self, nestboxes: &[Document]) -> RepositoryResult<()> {
+        for nestbox in nestboxes {
+            self.insert_document(NESTBOXES, nestbox.clone());
+        }
+        Ok(())
+    }
+
+    async fn update(&self, uuid: &str, nestbox: &Document) -> RepositoryResult<bool> {
+        let updated = self.update(
+            NESTBOXES,
+            |d| is_eq(d, "uuid", uuid),
+            |d| {
+                for key in &["public", "description", "box_type", "label"] {
+                    match nestbox.get(key) {
+                        Some(Bson::Null) => {
+                            d.remove(*key);
+                        }
+                        Some(value) => {
+                            d.insert(*key, value.clone());
+                        }
+                        None => {}
+                    }
+                }
+            },
+        );
+        Ok(updated > 0)
+    }
+
+    async fn retire(&self, uuid: &str, until_date: &DateTime) -> RepositoryResult<bool> {
+        let updated = self.update(
+            NESTBOXES,
+            |d| is_eq(d, "uuid", uuid),
+            |d| {
+                if d.get_datetime("until_date").is_err() {
+                    d.insert("until_date", *until_date);
+                }
+            },
+        );
+        Ok(updated > 0)
+    }
+
+    async fn find_uuids_without_code(&self) -> RepositoryResult<Vec<String>> {
+        Ok(self
+            .find(NESTBOXES, |d| d.get_str("code").is_err())
+            .iter()
+            .filter_map(|n| n.get_str("uuid").ok().map(String::from))
+            .collect())
+    }
+
+    async fn set_code(&self, uuid: &str, code: &str) -> RepositoryResult<bool> {
+        let updated = self.update(
+            NESTBOXES,
+            |d| is_eq(d, "uuid", uuid) && d.get_str("code").is_err(),
+            |d| {
+                d.insert("code", code);
+            },
+        );
+        Ok(updated > 0)
+    }
+
+    async fn find_near(
+        &self,
+        mandant_uuid: Option<&str>,
+        lat: f64,
+        long: f64,
+        radius: f64,
+        limit: i64,
+    ) -> RepositoryResult<Vec<Document>> {
+        let nestboxes = self
+            .find(NESTBOXES, |d| {
+                d.get_datetime("until_date").is_err()
+                    && match mandant_uuid {
+                        Some(mandant_uuid) => is_eq(d, "mandant_uuid", mandant_uuid),
+                        None => d.get_bool("public").unwrap_or(true),
+                    }
+            })
+            .into_iter()
+            .map(|n| self.with_lookups(n))
+            .collect();
+        Ok(nearest(nestboxes, lat, long, radius, limit))
+    }
+}
@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+use mongodb::bson::{DateTime, Document};
+
+use super::{is_eq, MemoryRepository};
+use crate::repository::{RepositoryResult, SessionRepository, SESSIONS};
+
+#[async_trait]
+impl SessionRepository for MemoryRepository {
+    async fn insert(&self, session: &Document) -> RepositoryResult<()> {
+        self.insert_document(SESSIONS, session.clone());
+        Ok(())
+    }
+
+    async fn find_by_session_key(&self, session_key: &str) -> RepositoryResult<Option<Document>> {
+        Ok(self.find_one(SESSIONS, |d| is_eq(d, "session_key", session_key)))
+    }
+
+    async fn find_by_user_uuid(&self, user_uuid: &str) -> RepositoryResult<Vec<Document>> {
+        Ok(self.find(SESSIONS, |d| is_eq(d, "uuid", user_uuid)))
+    }
+
+    async fn update_last_seen(&self, session_key: &str, now: &DateTime) -> RepositoryResult<()> {
+        self.update(
+            SESSIONS,
+            |d| is_eq(d, "session_key", session_key),
+            |d| {
+                d.insert("session_last_seen_at", *now);
+            },
+        );
+        Ok(())
+    }
+
+    async fn delete_by_session_key(&self, session_key: &str) -> RepositoryResult<()> {
+        self.delete(SESSIONS, |d| is_eq(d, "session_key", session_key));
+        Ok(())
+    }
+
+    async fn delete_by_session_id(
+        &self,
+        user_uuid: &str,
+        session_id: &str,
+    ) -> RepositoryResult<bool> {
+        let deleted = self.delete(SESSIONS, |d| {
+            is_eq(d, "uuid", user_uuid) && is_eq(d, "session_id", session_id)
+        });
+        Ok(deleted > 0)
+    }
+
+    async fn delete_by_username(&self, username: &str) -> RepositoryResult<()> {
+        self.delete(SESSIONS, |d| is_eq(d, "username", username));
+        Ok(())
+    }
+}
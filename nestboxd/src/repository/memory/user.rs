@@ -0,0 +1,66 @@
+use async_trait::async_trait;
+use mongodb::bson::Document;
+
+use super::{is_eq, page, MemoryRepository};
+use crate::controller::utilities::PagingQuery;
+use crate::repository::{RepositoryResult, UserRepository, USERS};
+
+#[async_trait]
+impl UserRepository for MemoryRepository {
+    async fn find_by_username(&self, username: &str) -> RepositoryResult<Option<Document>> {
+        Ok(self.find_one(USERS, |d| is_eq(d, "username", username)))
+    }
+
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>> {
+        Ok(self.find_one(USERS, |d| is_eq(d, "uuid", uuid)))
+    }
+
+    async fn find_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>> {
+        let mut users = self.find(USERS, |d| is_eq(d, "mandant_uuid", mandant_uuid));
+        users.sort_by(|a, b| {
+            a.get_str("username")
+                .unwrap_or_default()
+                .cmp(b.get_str("username").unwrap_or_default())
+        });
+        Ok(page(users, paging))
+    }
+
+    async fn count_by_mandant_uuid(&self, mandant_uuid: &str) -> RepositoryResult<u64> {
+        Ok(self.count(USERS, |d| is_eq(d, "mandant_uuid", mandant_uuid)))
+    }
+
+    async fn insert(&self, user: &Document) -> RepositoryResult<()> {
+        self.insert_document(USERS, user.clone());
+        Ok(())
+    }
+
+    async fn update(&self, uuid: &str, user: &Document) -> RepositoryResult<bool> {
+        let updated = self.update(
+            USERS,
+            |d| is_eq(d, "uuid", uuid),
+            |d| {
+                for key in &["lastname", "firstname", "email", "role", "locked"] {
+                    if let Some(value) = user.get(key) {
+                        d.insert(*key, value.clone());
+                    }
+                }
+            },
+        );
+        Ok(updated > 0)
+    }
+
+    async fn update_password_hash(&self, uuid: &str, password_hash: &str) -> RepositoryResult<()> {
+        self.update(
+            USERS,
+            |d| is_eq(d, "uuid", uuid),
+            |d| {
+                d.insert("password_hash", password_hash);
+            },
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,477 @@
+//! Storage abstraction of nestboxd.
+//!
+//! Every aggregate (nestboxes, mandants, breeds, birds, geolocations, sessions
+//! and users) has its own repository trait. The services only ever talk to
+//! these traits, so the backend behind them - MongoDB or PostgreSQL in
+//! production, SQLite for small single box deployments, an in-memory store for
+//! tests and prototyping - can be chosen at startup.
+//!
+//! Records are passed around as bson documents shaped like the ones in the
+//! MongoDB collections described in the README, so the `MapDocument`
+//! implementations work the same for every backend.
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use mongodb::bson::{DateTime, Document};
+use mongodb::error::{ErrorKind, WriteFailure};
+
+use crate::controller::utilities::PagingQuery;
+
+#[cfg(test)]
+mod backend_tests;
+pub mod memory;
+pub mod mongo;
+pub mod postgres;
+mod sql_helper;
+pub mod sqlite;
+
+pub const NESTBOXES: &str = "nestboxes";
+pub const MANDANTS: &str = "mandants";
+pub const BREEDS: &str = "breeds";
+pub const BREED_VERSIONS: &str = "breed_versions";
+pub const BIRDS: &str = "birds";
+pub const GEOLOCATIONS: &str = "geolocations";
+pub const SESSIONS: &str = "sessions";
+pub const USERS: &str = "users";
+
+/// Optional fields of a bird, see `BirdRepository::update`.
+pub const BIRD_DETAILS: [&str; 4] = ["scientific_name", "taxon_key", "names", "bird_website"];
+
+/// Optional fields of a breed, see `BreedRepository::update`.
+pub const OBSERVATIONS: [&str; 6] = [
+    "clutch_size",
+    "hatched",
+    "fledged",
+    "nest_state",
+    "confidence",
+    "notes",
+];
+
+/// Error code of MongoDB for a write refused by a unique index.
+const DUPLICATE_KEY: i32 = 11000;
+
+#[derive(Debug)]
+pub enum RepositoryError {
+    MongoDb(mongodb::error::Error),
+    Sql(sqlx::Error),
+    Migration(sqlx::migrate::MigrateError),
+}
+
+impl fmt::Display for RepositoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepositoryError::MongoDb(e) => write!(f, "mongodb: {}", e),
+            RepositoryError::Sql(e) => write!(f, "sql: {}", e),
+            RepositoryError::Migration(e) => write!(f, "migration: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RepositoryError {}
+
+impl RepositoryError {
+    /// Whether a unique index refused the write, e.g. a label a concurrent
+    /// request took first.
+    pub fn is_unique_violation(&self) -> bool {
+        match self {
+            RepositoryError::MongoDb(e) => match e.kind.as_ref() {
+                ErrorKind::Write(WriteFailure::WriteError(w)) => w.code == DUPLICATE_KEY,
+                ErrorKind::BulkWrite(b) => b
+                    .write_errors
+                    .iter()
+                    .flatten()
+                    .any(|w| w.code == DUPLICATE_KEY),
+                _ => false,
+            },
+            RepositoryError::Sql(e) => e
+                .as_database_error()
+                .is_some_and(|d| d.is_unique_violation()),
+            RepositoryError::Migration(_) => false,
+        }
+    }
+}
+
+impl From<mongodb::error::Error> for RepositoryError {
+    fn from(e: mongodb::error::Error) -> Self {
+        RepositoryError::MongoDb(e)
+    }
+}
+
+impl From<sqlx::Error> for RepositoryError {
+    fn from(e: sqlx::Error) -> Self {
+        RepositoryError::Sql(e)
+    }
+}
+
+impl From<sqlx::migrate::MigrateError> for RepositoryError {
+    fn from(e: sqlx::migrate::MigrateError) -> Self {
+        RepositoryError::Migration(e)
+    }
+}
+
+pub type RepositoryResult<T> = Result<T, RepositoryError>;
+
+/// Earth radius MongoDB uses for spherical distances, in meters.
+const EARTH_RADIUS: f64 = 6_378_100.0;
+
+/// Great circle distance between two points in meters.
+pub(crate) fn distance(lat_a: f64, long_a: f64, lat_b: f64, long_b: f64) -> f64 {
+    let (lat_a, lat_b) = (lat_a.to_radians(), lat_b.to_radians());
+    let d_lat = lat_b - lat_a;
+    let d_long = (long_b - long_a).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_long / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().min(1.0).asin()
+}
+
+/// Keeps the nestboxes whose joined location is within `radius` meters of
+/// the point, adds their `distance` and sorts them the nearest first. For
+/// the backends without geospatial queries.
+pub(crate) fn nearest(
+    nestboxes: Vec<Document>,
+    lat: f64,
+    long: f64,
+    radius: f64,
+    limit: i64,
+) -> Vec<Document> {
+    let mut nestboxes: Vec<(f64, Document)> = nestboxes
+        .into_iter()
+        .filter_map(|mut n| {
+            let coordinates = n
+                .get_array("geolocation")
+                .ok()?
+                .first()?
+                .as_document()?
+                .get_document("position")
+                .ok()?
+                .get_array("coordinates")
+                .ok()?
+                .clone();
+            let d = distance(
+                lat,
+                long,
+                coordinates.get(1)?.as_f64()?,
+                coordinates.first()?.as_f64()?,
+            );
+            n.insert("distance", d);
+            Some((d, n))
+        })
+        .filter(|(d, _)| *d <= radius)
+        .collect();
+    nestboxes.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+    nestboxes
+        .into_iter()
+        .take(limit.max(0) as usize)
+        .map(|(_, n)| n)
+        .collect()
+}
+
+/// Criteria for listing the nestboxes of a mandant, None matches every
+/// nestbox.
+#[derive(Default, Debug, Clone)]
+pub struct NestboxFilter {
+    pub public: Option<bool>,
+    pub retired: Option<bool>,
+    /// Year of the latest breed in the nestbox.
+    pub last_breed_year: Option<i32>,
+    /// Nestboxes with at least one breed of this bird.
+    pub bird_uuid: Option<String>,
+    /// Nestboxes without any breed recorded since.
+    pub not_inspected_since: Option<DateTime>,
+    /// Part of the label, case is ignored.
+    pub label: Option<String>,
+}
+
+#[async_trait]
+pub trait NestboxRepository: Send + Sync {
+    /// Nestbox with its mandant joined into the array `mandant`
+    /// (`uuid`, `name` and `website`) and its current location into the
+    /// array `geolocation`, which is empty if the location is unknown.
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>>;
+
+    /// Nestbox by its short `code`, shaped like `find_by_uuid`.
+    async fn find_by_code(&self, code: &str) -> RepositoryResult<Option<Document>>;
+
+    /// Page of the nestboxes of a mandant matching the filter, oldest first,
+    /// shaped like `find_by_uuid`.
+    async fn find_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        filter: &NestboxFilter,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>>;
+
+    async fn count_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        filter: &NestboxFilter,
+    ) -> RepositoryResult<u64>;
+
+    /// Nestbox only if it belongs to the given mandant.
+    async fn find_by_uuid_and_mandant_uuid(
+        &self,
+        uuid: &str,
+        mandant_uuid: &str,
+    ) -> RepositoryResult<Option<Document>>;
+
+    /// Adds the image to the set of images of the nestbox.
+    async fn append_image(&self, uuid: &str, image: &str) -> RepositoryResult<()>;
+
+    /// Adds new nestboxes, all of them or none.
+    async fn insert_many(&self, nestboxes: &[Document]) -> RepositoryResult<()>;
+
+    /// Uuid of the nestbox of the mandant with this label, case is ignored
+    /// like by the unique index on labels.
+    async fn find_uuid_by_label(
+        &self,
+        mandant_uuid: &str,
+        label: &str,
+    ) -> RepositoryResult<Option<String>>;
+
+    /// Overwrites `public`, `description`, `box_type` and `label` of the
+    /// nestbox if present in `nestbox`, a null label removes the label.
+    /// Returns whether the nestbox exists.
+    async fn update(&self, uuid: &str, nestbox: &Document) -> RepositoryResult<bool>;
+
+    /// Sets the `until_date` of the nestbox unless it is retired already,
+    /// returns whether the nestbox exists.
+    async fn retire(&self, uuid: &str, until_date: &DateTime) -> RepositoryResult<bool>;
+
+    /// Uuids of the nestboxes created before there were short codes.
+    async fn find_uuids_without_code(&self) -> RepositoryResult<Vec<String>>;
+
+    /// Sets the short code of the nestbox unless it has one already, returns
+    /// whether it was set.
+    async fn set_code(&self, uuid: &str, code: &str) -> RepositoryResult<bool>;
+
+    /// Active nestboxes whose current location is within `radius` meters of
+    /// the point, the nearest first, shaped like `find_by_uuid` with the
+    /// `distance` in meters added. Without mandant the public nestboxes of
+    /// all mandants.
+    async fn find_near(
+        &self,
+        mandant_uuid: Option<&str>,
+        lat: f64,
+        long: f64,
+        radius: f64,
+        limit: i64,
+    ) -> RepositoryResult<Vec<Document>>;
+}
+
+#[async_trait]
+pub trait MandantRepository: Send + Sync {
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>>;
+
+    async fn insert(&self, mandant: &Document) -> RepositoryResult<()>;
+
+    /// Overwrites the attributes of the mandant present in `mandant`,
+    /// returns whether the mandant exists.
+    async fn update(&self, uuid: &str, mandant: &Document) -> RepositoryResult<bool>;
+}
+
+#[async_trait]
+pub trait BreedRepository: Send + Sync {
+    /// Page of breeds of a nestbox, each with its bird joined into the
+    /// array `bird` (`uuid` and `bird`) and the label of the nestbox as
+    /// `nestbox_label` if it has one.
+    async fn find_by_nestbox_uuid(
+        &self,
+        nestbox_uuid: &str,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>>;
+
+    async fn count_by_nestbox_uuid(&self, nestbox_uuid: &str) -> RepositoryResult<u64>;
+
+    async fn count_by_bird_uuid(&self, bird_uuid: &str) -> RepositoryResult<u64>;
+
+    /// Number of breed versions, see `find_versions`, with the bird.
+    async fn count_versions_by_bird_uuid(&self, bird_uuid: &str) -> RepositoryResult<u64>;
+
+    /// Latest breed of every nestbox of the mandant, shaped like
+    /// `find_by_nestbox_uuid`. Nestboxes without breeds are left out.
+    async fn find_latest_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+    ) -> RepositoryResult<Vec<Document>>;
+
+    /// Breed shaped like in `find_by_nestbox_uuid`.
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>>;
+
+    async fn insert(&self, breed: &Document) -> RepositoryResult<()>;
+
+    /// Sets `nestbox_uuid`, `bird_uuid` and `discovery_date` if given of the
+    /// breed `uuid` and replaces its `OBSERVATIONS`, the counts are Int32.
+    /// Its previous version is kept with the fields of `change` (`uuid`,
+    /// `changed_by`, `changed_at` and `change`). False if there is no such
+    /// breed.
+    async fn update(&self, breed: &Document, change: &Document) -> RepositoryResult<bool>;
+
+    /// Deletes the breed, keeping its last version like `update`.
+    async fn delete(&self, uuid: &str, change: &Document) -> RepositoryResult<bool>;
+
+    /// Versions kept of the breed, the oldest first. Each one has the fields
+    /// of the breed then, `breed_uuid` and the fields of the change.
+    async fn find_versions(&self, uuid: &str) -> RepositoryResult<Vec<Document>>;
+}
+
+#[async_trait]
+pub trait BirdRepository: Send + Sync {
+    /// Page of the birds of a mandant sorted by name. Besides `uuid` and
+    /// `bird` a bird may have a `scientific_name`, a `taxon_key`, a
+    /// `bird_website` and its `names` as a document of names by language.
+    async fn find_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>>;
+
+    async fn count_by_mandant_uuid(&self, mandant_uuid: &str) -> RepositoryResult<u64>;
+
+    /// Bird with its `mandant_uuid`, like in `find_by_mandant_uuid`.
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>>;
+
+    /// Uuid of the bird of the mandant with this name, case is ignored.
+    async fn find_uuid_by_name(
+        &self,
+        mandant_uuid: &str,
+        name: &str,
+    ) -> RepositoryResult<Option<String>>;
+
+    async fn find_uuid_by_taxon_key(
+        &self,
+        mandant_uuid: &str,
+        taxon_key: &str,
+    ) -> RepositoryResult<Option<String>>;
+
+    async fn insert(&self, bird: &Document) -> RepositoryResult<()>;
+
+    /// Replaces name, scientific name, taxonomic key, website and names of the
+    /// bird by those of `bird`, false if there is no such bird.
+    async fn update(&self, uuid: &str, bird: &Document) -> RepositoryResult<bool>;
+
+    async fn delete(&self, uuid: &str) -> RepositoryResult<bool>;
+
+    /// Moves the breeds of the bird to the bird `into_uuid` and deletes it,
+    /// all or nothing. Each breed moved is kept as a version before, with the
+    /// fields of `change` and an uuid of its own. The versions still refer
+    /// to the deleted bird and keep its name as `bird_name`. The number of
+    /// breeds moved, None if there is no such bird.
+    async fn merge(
+        &self,
+        uuid: &str,
+        into_uuid: &str,
+        change: &Document,
+    ) -> RepositoryResult<Option<u64>>;
+}
+
+#[async_trait]
+pub trait GeolocationRepository: Send + Sync {
+    /// Ends all locations of the nestbox still valid at `now`.
+    async fn end_current(&self, nestbox_uuid: &str, now: &DateTime) -> RepositoryResult<()>;
+
+    async fn insert(&self, geolocation: &Document) -> RepositoryResult<()>;
+
+    /// Page of the locations of the nestbox, the latest first.
+    async fn find_by_nestbox_uuid(
+        &self,
+        nestbox_uuid: &str,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>>;
+
+    async fn count_by_nestbox_uuid(&self, nestbox_uuid: &str) -> RepositoryResult<u64>;
+
+    /// Location of the nestbox valid at `at`, from `from_date` up to but
+    /// not including `until_date`.
+    async fn find_at(
+        &self,
+        nestbox_uuid: &str,
+        at: &DateTime,
+    ) -> RepositoryResult<Option<Document>>;
+}
+
+#[async_trait]
+pub trait SessionRepository: Send + Sync {
+    async fn insert(&self, session: &Document) -> RepositoryResult<()>;
+
+    /// Session regardless of its age, the timeouts are up to the caller.
+    async fn find_by_session_key(&self, session_key: &str) -> RepositoryResult<Option<Document>>;
+
+    /// All sessions of a user, one per device logged in.
+    async fn find_by_user_uuid(&self, user_uuid: &str) -> RepositoryResult<Vec<Document>>;
+
+    async fn update_last_seen(&self, session_key: &str, now: &DateTime) -> RepositoryResult<()>;
+
+    async fn delete_by_session_key(&self, session_key: &str) -> RepositoryResult<()>;
+
+    /// Deletes the session with the (public) `session_id` only if it belongs
+    /// to the user, returns whether there was one.
+    async fn delete_by_session_id(
+        &self,
+        user_uuid: &str,
+        session_id: &str,
+    ) -> RepositoryResult<bool>;
+
+    async fn delete_by_username(&self, username: &str) -> RepositoryResult<()>;
+}
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn find_by_username(&self, username: &str) -> RepositoryResult<Option<Document>>;
+
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>>;
+
+    /// Page of the users of a mandant sorted by username.
+    async fn find_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>>;
+
+    async fn count_by_mandant_uuid(&self, mandant_uuid: &str) -> RepositoryResult<u64>;
+
+    async fn insert(&self, user: &Document) -> RepositoryResult<()>;
+
+    /// Overwrites `lastname`, `firstname`, `email`, `role` and `locked` of the
+    /// user, returns whether the user exists.
+    async fn update(&self, uuid: &str, user: &Document) -> RepositoryResult<bool>;
+
+    async fn update_password_hash(&self, uuid: &str, password_hash: &str) -> RepositoryResult<()>;
+}
+
+/// One trait object per aggregate, all served by the same backend.
+#[derive(Clone)]
+pub struct Repositories {
+    pub nestbox: Arc<dyn NestboxRepository>,
+    pub mandant: Arc<dyn MandantRepository>,
+    pub breed: Arc<dyn BreedRepository>,
+    pub bird: Arc<dyn BirdRepository>,
+    pub geolocation: Arc<dyn GeolocationRepository>,
+    pub session: Arc<dyn SessionRepository>,
+    pub user: Arc<dyn UserRepository>,
+}
+
+impl Repositories {
+    /// Wires a backend implementing every repository trait.
+    pub fn from_backend<B>(backend: B) -> Self
+    where
+        B: NestboxRepository
+            + MandantRepository
+            + BreedRepository
+            + BirdRepository
+            + GeolocationRepository
+            + SessionRepository
+            + UserRepository
+            + 'static,
+    {
+        let backend = Arc::new(backend);
+        Repositories {
+            nestbox: backend.clone(),
+            mandant: backend.clone(),
+            breed: backend.clone(),
+            bird: backend.clone(),
+            geolocation: backend.clone(),
+            session: backend.clone(),
+            user: backend,
+        }
+    }
+}
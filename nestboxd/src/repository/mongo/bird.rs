@@ -0,0 +1,179 @@
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use mongodb::bson::{doc, Bson, Document};
+use mongodb::options::FindOneOptions;
+use uuid::Uuid;
+
+use super::{read_cursor, MongoRepository};
+use crate::controller::utilities::PagingQuery;
+use crate::repository::{
+    BirdRepository, RepositoryResult, BIRDS, BIRD_DETAILS, BREEDS, BREED_VERSIONS,
+};
+
+#[async_trait]
+impl BirdRepository for MongoRepository {
+    async fn find_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>> {
+        let cursor = self
+            .collection(BIRDS)
+            .aggregate(
+                vec![
+                    doc! {"$match": {"mandant_uuid": {"$eq": mandant_uuid}}},
+                    doc! { "$sort" : { "bird" : 1} },
+                    doc! {"$skip": (paging.page_limit * (paging.page_number -1))},
+                    doc! {"$limit": paging.page_limit},
+                    doc! {"$project": {"_id": 0, "mandant_uuid": 0}},
+                ],
+                None,
+            )
+            .await?;
+        read_cursor(cursor).await
+    }
+
+    async fn count_by_mandant_uuid(&self, mandant_uuid: &str) -> RepositoryResult<u64> {
+        Ok(self
+            .collection(BIRDS)
+            .count_documents(doc! {"mandant_uuid": mandant_uuid}, None)
+            .await?)
+    }
+
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>> {
+        let options = FindOneOptions::builder()
+            .projection(doc! {"_id": 0})
+            .build();
+        Ok(self
+            .collection(BIRDS)
+            .find_one(doc! {"uuid": uuid}, options)
+            .await?)
+    }
+
+    async fn find_uuid_by_name(
+        &self,
+        mandant_uuid: &str,
+        name: &str,
+    ) -> RepositoryResult<Option<String>> {
+        let name = format!("^{}$", regex::escape(name));
+        Ok(self
+            .collection(BIRDS)
+            .find_one(
+                doc! {"mandant_uuid": mandant_uuid, "bird": {"$regex": name, "$options": "i"}},
+                None,
+            )
+            .await?
+            .and_then(|b| b.get_str("uuid").ok().map(String::from)))
+    }
+
+    async fn find_uuid_by_taxon_key(
+        &self,
+        mandant_uuid: &str,
+        taxon_key: &str,
+    ) -> RepositoryResult<Option<String>> {
+        Ok(self
+            .collection(BIRDS)
+            .find_one(
+                doc! {"mandant_uuid": mandant_uuid, "taxon_key": taxon_key},
+                None,
+            )
+            .await?
+            .and_then(|b| b.get_str("uuid").ok().map(String::from)))
+    }
+
+    async fn insert(&self, bird: &Document) -> RepositoryResult<()> {
+        self.collection(BIRDS).insert_one(bird, None).await?;
+        Ok(())
+    }
+
+    async fn update(&self, uuid: &str, bird: &Document) -> RepositoryResult<bool> {
+        // A classic update stores the values as they are, in a pipeline
+        // names starting with $ would be taken for field paths.
+        let unset: Document = BIRD_DETAILS
+            .iter()
+            .filter(|key| !bird.contains_key(key))
+            .map(|key| (key.to_string(), Bson::String(String::new())))
+            .collect();
+        let mut update = doc! {"$set": bird};
+        if !unset.is_empty() {
+            update.insert("$unset", unset);
+        }
+        let result = self
+            .collection(BIRDS)
+            .update_one(doc! {"uuid": uuid}, update, None)
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn delete(&self, uuid: &str) -> RepositoryResult<bool> {
+        let result = self
+            .collection(BIRDS)
+            .delete_one(doc! {"uuid": uuid}, None)
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    // Transactions need a replica set, a single node one will do. Dropping
+    // the session on an error aborts the transaction.
+    async fn merge(
+        &self,
+        uuid: &str,
+        into_uuid: &str,
+        change: &Document,
+    ) -> RepositoryResult<Option<u64>> {
+        let mut session = self.client.start_session(None).await?;
+        session.start_transaction(None).await?;
+        let Some(bird) = self
+            .collection(BIRDS)
+            .find_one_with_session(doc! {"uuid": uuid}, None, &mut session)
+            .await?
+        else {
+            session.abort_transaction().await?;
+            return Ok(None);
+        };
+        let breeds: Vec<Document> = self
+            .collection(BREEDS)
+            .find_with_session(doc! {"bird_uuid": uuid}, None, &mut session)
+            .await?
+            .stream(&mut session)
+            .try_collect()
+            .await?;
+        let versions: Vec<Document> = breeds
+            .into_iter()
+            .map(|mut version| {
+                version.remove("_id");
+                let breed_uuid = version.get_str("uuid").unwrap_or_default().to_string();
+                version.insert("breed_uuid", breed_uuid);
+                version.extend(change.clone());
+                version.insert("uuid", Uuid::new_v4().to_string());
+                version
+            })
+            .collect();
+        if !versions.is_empty() {
+            self.collection(BREED_VERSIONS)
+                .insert_many_with_session(&versions, None, &mut session)
+                .await?;
+        }
+        self.collection(BREED_VERSIONS)
+            .update_many_with_session(
+                doc! {"bird_uuid": uuid},
+                doc! {"$set": {"bird_name": bird.get_str("bird").unwrap_or_default()}},
+                None,
+                &mut session,
+            )
+            .await?;
+        self.collection(BREEDS)
+            .update_many_with_session(
+                doc! {"bird_uuid": uuid},
+                doc! {"$set": {"bird_uuid": into_uuid}},
+                None,
+                &mut session,
+            )
+            .await?;
+        self.collection(BIRDS)
+            .delete_one_with_session(doc! {"uuid": uuid}, None, &mut session)
+            .await?;
+        session.commit_transaction().await?;
+        Ok(Some(versions.len() as u64))
+    }
+}
@@ -0,0 +1,211 @@
+use async_trait::async_trait;
+use mongodb::bson::{doc, Bson, Document};
+
+use super::{read_cursor, MongoRepository};
+use crate::controller::utilities::PagingQuery;
+use crate::repository::{
+    BreedRepository, RepositoryResult, BREEDS, BREED_VERSIONS, NESTBOXES, OBSERVATIONS,
+};
+
+/// Joins the bird and the label of the nestbox into the breeds.
+fn breed_lookups() -> Vec<Document> {
+    vec![
+        doc! {"$lookup": {
+        "from": "birds",
+        "let": {
+          "breeds_bird_uuid": "$bird_uuid" },
+        "pipeline":[
+          {
+            "$match": {
+              "$expr": {
+                "$eq": [
+                  "$$breeds_bird_uuid", "$uuid"
+                ]
+              }
+            }
+          },
+          {
+            "$project": {
+               "_id":0, "uuid": 1, "bird": 1
+            }
+          }
+        ], "as": "bird"}},
+        doc! {"$lookup": {
+        "from": NESTBOXES,
+        "localField": "nestbox_uuid",
+        "foreignField": "uuid",
+        "as": "nestbox"}},
+        doc! {"$addFields": {"nestbox_label": {"$arrayElemAt": ["$nestbox.label", 0]}}},
+        doc! {"$project": {"_id": 0, "mandant_uuid": 0, "bird_uuid": 0, "nestbox": 0}},
+    ]
+}
+
+impl MongoRepository {
+    /// Keeps the breed as it was before as a version with the fields of
+    /// `change`.
+    async fn keep_breed_version(
+        &self,
+        breed: Option<Document>,
+        change: &Document,
+    ) -> RepositoryResult<bool> {
+        let Some(mut version) = breed else {
+            return Ok(false);
+        };
+        version.remove("_id");
+        version.insert(
+            "breed_uuid",
+            version.get_str("uuid").unwrap_or_default().to_string(),
+        );
+        version.extend(change.clone());
+        self.collection(BREED_VERSIONS)
+            .insert_one(version, None)
+            .await?;
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl BreedRepository for MongoRepository {
+    async fn find_by_nestbox_uuid(
+        &self,
+        nestbox_uuid: &str,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>> {
+        let cursor = self
+            .collection(BREEDS)
+            .aggregate(
+                vec![
+                    doc! {"$match": {"nestbox_uuid": {"$eq": nestbox_uuid}}},
+                    doc! {"$skip": (paging.page_limit * (paging.page_number -1))},
+                    doc! {"$limit": paging.page_limit},
+                ]
+                .into_iter()
+                .chain(breed_lookups())
+                .collect::<Vec<Document>>(),
+                None,
+            )
+            .await?;
+        read_cursor(cursor).await
+    }
+
+    async fn count_by_nestbox_uuid(&self, nestbox_uuid: &str) -> RepositoryResult<u64> {
+        Ok(self
+            .collection(BREEDS)
+            .count_documents(doc! {"nestbox_uuid": nestbox_uuid}, None)
+            .await?)
+    }
+
+    async fn count_by_bird_uuid(&self, bird_uuid: &str) -> RepositoryResult<u64> {
+        Ok(self
+            .collection(BREEDS)
+            .count_documents(doc! {"bird_uuid": bird_uuid}, None)
+            .await?)
+    }
+
+    async fn count_versions_by_bird_uuid(&self, bird_uuid: &str) -> RepositoryResult<u64> {
+        Ok(self
+            .collection(BREED_VERSIONS)
+            .count_documents(doc! {"bird_uuid": bird_uuid}, None)
+            .await?)
+    }
+
+    async fn find_latest_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+    ) -> RepositoryResult<Vec<Document>> {
+        let cursor = self
+            .collection(NESTBOXES)
+            .aggregate(
+                vec![
+                    doc! {"$match": {"mandant_uuid": mandant_uuid}},
+                    doc! {"$lookup": {
+                    "from": BREEDS,
+                    "let": {"breeds_nestbox_uuid": "$uuid"},
+                    "pipeline": [
+                        {"$match": {"$expr": {"$eq": ["$$breeds_nestbox_uuid", "$nestbox_uuid"]}}},
+                        {"$sort": {"discovery_date": -1}},
+                        {"$limit": 1}
+                    ], "as": "breed"}},
+                    doc! {"$unwind": "$breed"},
+                    doc! {"$replaceRoot": {"newRoot": "$breed"}},
+                ]
+                .into_iter()
+                .chain(breed_lookups())
+                .collect::<Vec<Document>>(),
+                None,
+            )
+            .await?;
+        read_cursor(cursor).await
+    }
+
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>> {
+        let cursor = self
+            .collection(BREEDS)
+            .aggregate(
+                std::iter::once(doc! {"$match": {"uuid": uuid}})
+                    .chain(breed_lookups())
+                    .collect::<Vec<Document>>(),
+                None,
+            )
+            .await?;
+        Ok(read_cursor(cursor).await?.pop())
+    }
+
+    async fn insert(&self, breed: &Document) -> RepositoryResult<()> {
+        self.collection(BREEDS).insert_one(breed, None).await?;
+        Ok(())
+    }
+
+    // The previous version is returned by the update itself, so concurrent
+    // changes each keep the version they replaced.
+    async fn update(&self, breed: &Document, change: &Document) -> RepositoryResult<bool> {
+        // Observations no longer given are removed.
+        let unset: Document = OBSERVATIONS
+            .iter()
+            .filter(|key| !breed.contains_key(key))
+            .map(|key| (key.to_string(), Bson::String(String::new())))
+            .collect();
+        let mut update = doc! {"$set": breed};
+        if !unset.is_empty() {
+            update.insert("$unset", unset);
+        }
+        let previous = self
+            .collection(BREEDS)
+            .find_one_and_update(
+                doc! {"uuid": breed.get_str("uuid").unwrap_or_default()},
+                update,
+                None,
+            )
+            .await?;
+        self.keep_breed_version(previous, change).await
+    }
+
+    async fn delete(&self, uuid: &str, change: &Document) -> RepositoryResult<bool> {
+        let previous = self
+            .collection(BREEDS)
+            .find_one_and_delete(doc! {"uuid": uuid}, None)
+            .await?;
+        self.keep_breed_version(previous, change).await
+    }
+
+    async fn find_versions(&self, uuid: &str) -> RepositoryResult<Vec<Document>> {
+        let mut pipeline = vec![
+            doc! {"$match": {"breed_uuid": uuid}},
+            doc! {"$sort": {"changed_at": 1}},
+        ];
+        // The bird only, the nestbox label is the one of the breed.
+        pipeline.extend(breed_lookups().into_iter().take(1));
+        // The bird may have been merged into another one, its name is kept.
+        pipeline.push(doc! {"$addFields": {"bird": {"$cond": [
+        {"$and": [{"$eq": [{"$size": "$bird"}, 0]}, {"$gt": ["$bird_name", null]}]},
+        [{"uuid": "$bird_uuid", "bird": "$bird_name"}],
+        "$bird"]}}});
+        pipeline
+            .push(doc! {"$project": {"_id": 0, "mandant_uuid": 0, "bird_uuid": 0, "bird_name": 0}});
+        let cursor = self
+            .collection(BREED_VERSIONS)
+            .aggregate(pipeline, None)
+            .await?;
+        read_cursor(cursor).await
+    }
+}
@@ -0,0 +1,73 @@
+use async_trait::async_trait;
+use mongodb::bson::{doc, DateTime, Document};
+use mongodb::options::{FindOneOptions, FindOptions};
+
+use super::{read_cursor, MongoRepository};
+use crate::controller::utilities::PagingQuery;
+use crate::repository::{GeolocationRepository, RepositoryResult, GEOLOCATIONS};
+
+#[async_trait]
+impl GeolocationRepository for MongoRepository {
+    async fn end_current(&self, nestbox_uuid: &str, now: &DateTime) -> RepositoryResult<()> {
+        //db.geolocations.updateMany({"nestbox_uuid": "eb0c7048-2cda-471d-beb3-7777b7d54858", "until_date": {"$lt": new ISODate("2021-06-12T20:15:31Z")}}, {$set: {"until_date": new ISODate("2021-06-12T20:15:31Z")}})
+        self.collection(GEOLOCATIONS)
+            .update_many(
+                doc! {"nestbox_uuid": nestbox_uuid, "until_date": {"$gt": now}},
+                doc! {"$set": {"until_date": now}},
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn insert(&self, geolocation: &Document) -> RepositoryResult<()> {
+        self.collection(GEOLOCATIONS)
+            .insert_one(geolocation, None)
+            .await?;
+        Ok(())
+    }
+
+    async fn find_by_nestbox_uuid(
+        &self,
+        nestbox_uuid: &str,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>> {
+        let options = FindOptions::builder()
+            .projection(doc! {"_id": 0})
+            .sort(doc! {"from_date": -1})
+            .skip((paging.page_limit * (paging.page_number - 1)) as u64)
+            .limit(paging.page_limit)
+            .build();
+        let cursor = self
+            .collection(GEOLOCATIONS)
+            .find(doc! {"nestbox_uuid": nestbox_uuid}, options)
+            .await?;
+        read_cursor(cursor).await
+    }
+
+    async fn count_by_nestbox_uuid(&self, nestbox_uuid: &str) -> RepositoryResult<u64> {
+        Ok(self
+            .collection(GEOLOCATIONS)
+            .count_documents(doc! {"nestbox_uuid": nestbox_uuid}, None)
+            .await?)
+    }
+
+    async fn find_at(
+        &self,
+        nestbox_uuid: &str,
+        at: &DateTime,
+    ) -> RepositoryResult<Option<Document>> {
+        let options = FindOneOptions::builder()
+            .projection(doc! {"_id": 0})
+            .sort(doc! {"from_date": -1})
+            .build();
+        Ok(self
+            .collection(GEOLOCATIONS)
+            .find_one(
+                doc! {"nestbox_uuid": nestbox_uuid, "from_date": {"$lte": at},
+                "until_date": {"$gt": at}},
+                options,
+            )
+            .await?)
+    }
+}
@@ -0,0 +1,28 @@
+use async_trait::async_trait;
+use mongodb::bson::{doc, Document};
+
+use super::MongoRepository;
+use crate::repository::{MandantRepository, RepositoryResult, MANDANTS};
+
+#[async_trait]
+impl MandantRepository for MongoRepository {
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>> {
+        Ok(self
+            .collection(MANDANTS)
+            .find_one(doc! {"uuid": uuid}, None)
+            .await?)
+    }
+
+    async fn insert(&self, mandant: &Document) -> RepositoryResult<()> {
+        self.collection(MANDANTS).insert_one(mandant, None).await?;
+        Ok(())
+    }
+
+    async fn update(&self, uuid: &str, mandant: &Document) -> RepositoryResult<bool> {
+        let result = self
+            .collection(MANDANTS)
+            .update_one(doc! {"uuid": uuid}, doc! {"$set": mandant}, None)
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+}
@@ -0,0 +1,237 @@
+//! MongoDB backend, the collections and indices are described in the README.
+use futures::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::error::ErrorKind;
+use mongodb::options::{Collation, CollationStrength, IndexOptions};
+use mongodb::{Client, Collection, Cursor, Database, IndexModel};
+
+use super::{RepositoryResult, GEOLOCATIONS, NESTBOXES};
+
+mod bird;
+mod breed;
+mod geolocation;
+mod mandant;
+mod nestbox;
+mod session;
+mod user;
+
+#[derive(Clone)]
+pub struct MongoRepository {
+    // Sessions for transactions are started on the client.
+    client: Client,
+    db: Database,
+}
+
+impl MongoRepository {
+    pub fn new(client: Client, database: &str) -> Self {
+        let db = client.database(database);
+        MongoRepository { client, db }
+    }
+
+    fn collection(&self, name: &str) -> Collection<Document> {
+        self.db.collection(name)
+    }
+
+    /// Brings a database from before up to date on startup, like the
+    /// migrations of the SQL backends. Positions stored as type "point" are
+    /// no valid GeoJSON and would fail the 2dsphere index `find_near` needs.
+    /// The unique index on labels is made to ignore case. All steps are
+    /// idempotent.
+    pub async fn migrate(&self) -> RepositoryResult<()> {
+        let geolocations = self.collection(GEOLOCATIONS);
+        geolocations
+            .update_many(
+                doc! {"position.type": "point"},
+                doc! {"$set": {"position.type": "Point"}},
+                None,
+            )
+            .await?;
+        geolocations
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! {"position": "2dsphere"})
+                    .build(),
+                None,
+            )
+            .await?;
+        // The unique index on labels ignores case since the label search
+        // does, it replaces the exact one.
+        let nestboxes = self.collection(NESTBOXES);
+        if let Err(e) = nestboxes.drop_index("mandant_uuid_1_label_1", None).await {
+            // 26: no nestboxes yet, 27: the index is gone already.
+            if !matches!(e.kind.as_ref(), ErrorKind::Command(c) if c.code == 26 || c.code == 27) {
+                return Err(e.into());
+            }
+        }
+        nestboxes
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! {"mandant_uuid": 1, "label": 1})
+                    .options(
+                        IndexOptions::builder()
+                            .name(String::from("mandant_uuid_label"))
+                            .unique(true)
+                            .collation(label_collation())
+                            .partial_filter_expression(doc! {"label": {"$exists": true}})
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// Labels compare regardless of case, like the label search.
+fn label_collation() -> Collation {
+    Collation::builder()
+        .locale("en")
+        .strength(CollationStrength::Secondary)
+        .build()
+}
+
+async fn read_cursor(cursor: Cursor<Document>) -> RepositoryResult<Vec<Document>> {
+    Ok(cursor.try_collect().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    // These tests need a running MongoDB with test_db_dump restored
+    // (mongorestore nestboxd/test_db_dump), run them with --ignored.
+    use super::*;
+    use crate::controller::utilities::PagingQuery;
+    use crate::repository::{BirdRepository, BreedRepository, NestboxRepository, UserRepository};
+    use mongodb::{options::ClientOptions, Client};
+
+    // Nestbox from mandant_uuid 4ac9971c-91de-455c-a1fd-4b9dfb862cee
+    const NESTBOX_UUID_OK: &str = "a446545d-f594-4eb5-96b4-c2312554050c";
+    const NESTBOX_UUID_NOK: &str = "74a0d653-f93a-4383-822f-8f55ab853fca";
+    const MANDANT_UUID: &str = "4ac9971c-91de-455c-a1fd-4b9dfb862cee";
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_mongo_nestbox_find_by_uuid() {
+        let repository = fetch_repository().await;
+        let nestbox = NestboxRepository::find_by_uuid(&repository, NESTBOX_UUID_OK)
+            .await
+            .unwrap();
+        assert_eq!(nestbox.unwrap().get_str("uuid"), Ok(NESTBOX_UUID_OK));
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_mongo_nestbox_find_by_uuid_and_mandant_uuid() {
+        let repository = fetch_repository().await;
+        let nestbox = repository
+            .find_by_uuid_and_mandant_uuid(NESTBOX_UUID_NOK, MANDANT_UUID)
+            .await
+            .unwrap();
+        assert_eq!(nestbox, None);
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_mongo_breed_count_by_nestbox_uuid() {
+        let repository = fetch_repository().await;
+        let breeds = repository
+            .find_by_nestbox_uuid(
+                NESTBOX_UUID_OK,
+                &PagingQuery {
+                    page_limit: 2,
+                    page_number: 1,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(breeds.len(), 2);
+        let counted = repository
+            .count_by_nestbox_uuid(NESTBOX_UUID_OK)
+            .await
+            .unwrap();
+        assert_eq!(counted, 6);
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_mongo_user_find_by_username() {
+        let repository = fetch_repository().await;
+        let user = repository.find_by_username("fg_10").await.unwrap();
+        assert_eq!(user.unwrap().get_str("username"), Ok("fg_10"));
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_mongo_migrate() {
+        let repository = fetch_repository().await;
+        let uuid = uuid::Uuid::new_v4().to_string();
+        let geolocations = repository.collection(GEOLOCATIONS);
+        // The index refuses the old positions, as in a database from before.
+        let _ = geolocations.drop_index("position_2dsphere", None).await;
+        geolocations
+            .insert_one(
+                doc! {"uuid": &uuid, "position": {"type": "point", "coordinates": [8.5, 47.5]}},
+                None,
+            )
+            .await
+            .unwrap();
+        repository.migrate().await.unwrap();
+        // Repeated on every start.
+        repository.migrate().await.unwrap();
+        let geolocation = geolocations
+            .find_one(doc! {"uuid": &uuid}, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            geolocation
+                .get_document("position")
+                .unwrap()
+                .get_str("type"),
+            Ok("Point")
+        );
+        geolocations
+            .delete_one(doc! {"uuid": &uuid}, None)
+            .await
+            .unwrap();
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_mongo_bird_update_stores_dollar_names() {
+        let repository = fetch_repository().await;
+        let uuid = uuid::Uuid::new_v4().to_string();
+        BirdRepository::insert(
+            &repository,
+            &doc! {"uuid": &uuid, "mandant_uuid": MANDANT_UUID, "bird": "Kohlmeise",
+            "taxon_key": format!("test:{}", uuid)},
+        )
+        .await
+        .unwrap();
+        let bird = doc! {"uuid": &uuid, "mandant_uuid": MANDANT_UUID, "bird": "$uuid",
+        "scientific_name": "$$ROOT", "names": {"de": "$mandant_uuid"}};
+        assert!(BirdRepository::update(&repository, &uuid, &bird)
+            .await
+            .unwrap());
+        let stored = BirdRepository::find_by_uuid(&repository, &uuid)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.get_str("bird"), Ok("$uuid"));
+        assert_eq!(stored.get_str("scientific_name"), Ok("$$ROOT"));
+        assert_eq!(
+            stored.get_document("names").unwrap().get_str("de"),
+            Ok("$mandant_uuid")
+        );
+        assert!(!stored.contains_key("taxon_key"));
+        BirdRepository::delete(&repository, &uuid).await.unwrap();
+    }
+
+    async fn fetch_repository() -> MongoRepository {
+        let client_options_future = ClientOptions::parse("mongodb://localhost:27017");
+        let client_options = client_options_future.await.unwrap();
+        let client = Client::with_options(client_options).unwrap();
+
+        MongoRepository::new(client, "nestbox_testing")
+    }
+}
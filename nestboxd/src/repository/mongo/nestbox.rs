@@ -0,0 +1,365 @@
+use async_trait::async_trait;
+use mongodb::bson::{doc, Bson, DateTime, Document};
+use mongodb::options::{FindOneOptions, FindOptions};
+
+use super::{label_collation, read_cursor, MongoRepository};
+use crate::controller::utilities::PagingQuery;
+use crate::repository::{
+    NestboxFilter, NestboxRepository, RepositoryResult, BREEDS, GEOLOCATIONS, NESTBOXES,
+};
+
+fn mandant_lookup() -> Document {
+    doc! {"$lookup": {
+    "from": "mandants",
+     "let": {
+       "nestboxes_mandant_uuid": "$mandant_uuid"},
+     "pipeline": [
+                    {
+                      "$match": {
+                        "$expr": {
+                          "$eq": [
+                            "$$nestboxes_mandant_uuid", "$uuid"
+                          ]
+                        }
+                      }
+                    },
+                    {
+                      "$project": {
+                         "_id":0, "uuid": 1, "name": 1, "website": 1
+                      }
+                    }
+                  ], "as": "mandant"}}
+}
+
+/// Joins the location of the nestbox valid now, if there is one.
+fn geolocation_lookup() -> Document {
+    let now = DateTime::now();
+    doc! {"$lookup": {
+    "from": GEOLOCATIONS,
+    "let": {"geolocations_nestbox_uuid": "$uuid"},
+    "pipeline": [
+        {"$match": {"$expr": {"$and": [
+            {"$eq": ["$$geolocations_nestbox_uuid", "$nestbox_uuid"]},
+            {"$lte": ["$from_date", now]},
+            {"$gt": ["$until_date", now]}]}}},
+        {"$sort": {"from_date": -1}},
+        {"$limit": 1},
+        {"$project": {"_id": 0}}
+    ], "as": "geolocation"}}
+}
+
+/// Stages selecting the nestboxes of the mandant matching the filter. The
+/// breeds are only looked up if a filter needs them.
+fn filter_stages(mandant_uuid: &str, filter: &NestboxFilter) -> Vec<Document> {
+    let mut nestbox_match = doc! {"mandant_uuid": mandant_uuid};
+    if let Some(public) = filter.public {
+        nestbox_match.insert("public", public);
+    }
+    if let Some(retired) = filter.retired {
+        nestbox_match.insert("until_date", doc! {"$exists": retired});
+    }
+    if let Some(label) = &filter.label {
+        nestbox_match.insert(
+            "label",
+            doc! {"$regex": regex::escape(label), "$options": "i"},
+        );
+    }
+    let mut stages = vec![doc! {"$match": nestbox_match}];
+    if filter.last_breed_year.is_none()
+        && filter.bird_uuid.is_none()
+        && filter.not_inspected_since.is_none()
+    {
+        return stages;
+    }
+    stages.push(doc! {"$lookup": {
+    "from": BREEDS,
+    "localField": "uuid",
+    "foreignField": "nestbox_uuid",
+    "as": "breeds"}});
+    stages.push(doc! {"$addFields": {"last_breed": {"$max": "$breeds.discovery_date"}}});
+    let mut breed_match = Document::new();
+    if let Some(year) = filter.last_breed_year {
+        breed_match.insert("$expr", doc! {"$eq": [{"$year": "$last_breed"}, year]});
+    }
+    if let Some(bird_uuid) = &filter.bird_uuid {
+        breed_match.insert("breeds.bird_uuid", bird_uuid);
+    }
+    if let Some(since) = filter.not_inspected_since {
+        breed_match.insert(
+            "$or",
+            vec![
+                doc! {"last_breed": null},
+                doc! {"last_breed": {"$lt": since}},
+            ],
+        );
+    }
+    stages.push(doc! {"$match": breed_match});
+    stages.push(doc! {"$project": {"breeds": 0, "last_breed": 0}});
+    stages
+}
+
+#[async_trait]
+impl NestboxRepository for MongoRepository {
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>> {
+        /*
+                Example aggreation request
+                {"$match": {"uuid": {"$eq": "4ea60d3e-4a81-4bcc-b96a-c508fe73a48a"}}},
+        {"$skip": 0},
+        {"$limit": 1},
+        {
+          $lookup: {
+            "from": "mandants",
+            "let": {
+              "nestboxes_mandant_uuid": "$mandant_uuid"},
+            "pipeline": [
+                              {
+                                "$match": {
+                                  "$expr": {
+                                    "$eq": [
+                                      "$$nestboxes_mandant_uuid", "$uuid"
+                                    ]
+                                  }
+                                }
+                              },
+                              {
+                                "$project": {
+                                   "_id":0, "uuid": 1, "name": 1, "website": 1
+                                }
+                              }
+                            ], "as": "mandant"}}, {"$project": {"_id": 0}}
+                */
+        let cursor = self
+            .collection(NESTBOXES)
+            .aggregate(
+                vec![
+                    doc! {"$match": {"uuid": {"$eq": uuid}}},
+                    doc! {"$skip": 0},
+                    doc! {"$limit": 1},
+                    mandant_lookup(),
+                    geolocation_lookup(),
+                    doc! {"$project": {"_id": 0}},
+                ],
+                None,
+            )
+            .await?;
+        Ok(read_cursor(cursor).await?.into_iter().next())
+    }
+
+    async fn find_by_code(&self, code: &str) -> RepositoryResult<Option<Document>> {
+        let cursor = self
+            .collection(NESTBOXES)
+            .aggregate(
+                vec![
+                    doc! {"$match": {"code": {"$eq": code}}},
+                    doc! {"$limit": 1},
+                    mandant_lookup(),
+                    geolocation_lookup(),
+                    doc! {"$project": {"_id": 0}},
+                ],
+                None,
+            )
+            .await?;
+        Ok(read_cursor(cursor).await?.into_iter().next())
+    }
+
+    async fn find_uuid_by_label(
+        &self,
+        mandant_uuid: &str,
+        label: &str,
+    ) -> RepositoryResult<Option<String>> {
+        Ok(self
+            .collection(NESTBOXES)
+            .find_one(
+                doc! {"mandant_uuid": mandant_uuid, "label": label},
+                FindOneOptions::builder()
+                    .collation(label_collation())
+                    .build(),
+            )
+            .await?
+            .and_then(|n| n.get_str("uuid").ok().map(String::from)))
+    }
+
+    async fn find_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        filter: &NestboxFilter,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>> {
+        let mut pipeline = filter_stages(mandant_uuid, filter);
+        pipeline.extend(vec![
+            doc! {"$sort": {"created_at": 1, "uuid": 1}},
+            doc! {"$skip": (paging.page_limit * (paging.page_number -1))},
+            doc! {"$limit": paging.page_limit},
+            mandant_lookup(),
+            geolocation_lookup(),
+            doc! {"$project": {"_id": 0}},
+        ]);
+        let cursor = self.collection(NESTBOXES).aggregate(pipeline, None).await?;
+        read_cursor(cursor).await
+    }
+
+    async fn count_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        filter: &NestboxFilter,
+    ) -> RepositoryResult<u64> {
+        let mut pipeline = filter_stages(mandant_uuid, filter);
+        pipeline.push(doc! {"$count": "counted"});
+        let cursor = self.collection(NESTBOXES).aggregate(pipeline, None).await?;
+        Ok(read_cursor(cursor)
+            .await?
+            .first()
+            .and_then(|d| d.get("counted"))
+            .and_then(|c| c.as_i32().map(i64::from).or_else(|| c.as_i64()))
+            .unwrap_or(0) as u64)
+    }
+
+    async fn find_by_uuid_and_mandant_uuid(
+        &self,
+        uuid: &str,
+        mandant_uuid: &str,
+    ) -> RepositoryResult<Option<Document>> {
+        Ok(self
+            .collection(NESTBOXES)
+            .find_one(doc! {"uuid": uuid, "mandant_uuid": mandant_uuid}, None)
+            .await?)
+    }
+
+    async fn append_image(&self, uuid: &str, image: &str) -> RepositoryResult<()> {
+        self.collection(NESTBOXES)
+            .update_one(
+                doc! {"uuid": uuid},
+                doc! {"$addToSet": doc!{"images":image}},
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn insert_many(&self, nestboxes: &[Document]) -> RepositoryResult<()> {
+        self.collection(NESTBOXES)
+            .insert_many(nestboxes, None)
+            .await?;
+        Ok(())
+    }
+
+    async fn update(&self, uuid: &str, nestbox: &Document) -> RepositoryResult<bool> {
+        let mut attributes = Document::new();
+        let mut removed = Document::new();
+        for key in &["public", "description", "box_type", "label"] {
+            match nestbox.get(key) {
+                Some(Bson::Null) => {
+                    removed.insert(*key, "");
+                }
+                Some(value) => {
+                    attributes.insert(*key, value.clone());
+                }
+                None => {}
+            }
+        }
+        // MongoDB refuses empty operators, clearing the label sets nothing.
+        let mut update = Document::new();
+        if !attributes.is_empty() {
+            update.insert("$set", attributes);
+        }
+        if !removed.is_empty() {
+            update.insert("$unset", removed);
+        }
+        let result = self
+            .collection(NESTBOXES)
+            .update_one(doc! {"uuid": uuid}, update, None)
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn retire(&self, uuid: &str, until_date: &DateTime) -> RepositoryResult<bool> {
+        // Update pipeline, so a nestbox retired before keeps its date.
+        let result = self
+            .collection(NESTBOXES)
+            .update_one(
+                doc! {"uuid": uuid},
+                vec![doc! {"$set": {"until_date": {"$ifNull": ["$until_date", until_date]}}}],
+                None,
+            )
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn find_uuids_without_code(&self) -> RepositoryResult<Vec<String>> {
+        let options = FindOptions::builder()
+            .projection(doc! {"_id": 0, "uuid": 1})
+            .build();
+        let cursor = self
+            .collection(NESTBOXES)
+            .find(doc! {"code": {"$exists": false}}, options)
+            .await?;
+        Ok(read_cursor(cursor)
+            .await?
+            .iter()
+            .filter_map(|n| n.get_str("uuid").ok().map(String::from))
+            .collect())
+    }
+
+    async fn set_code(&self, uuid: &str, code: &str) -> RepositoryResult<bool> {
+        let result = self
+            .collection(NESTBOXES)
+            .update_one(
+                doc! {"uuid": uuid, "code": {"$exists": false}},
+                doc! {"$set": {"code": code}},
+                None,
+            )
+            .await?;
+        Ok(result.modified_count > 0)
+    }
+
+    async fn find_near(
+        &self,
+        mandant_uuid: Option<&str>,
+        lat: f64,
+        long: f64,
+        radius: f64,
+        limit: i64,
+    ) -> RepositoryResult<Vec<Document>> {
+        // $geoNear needs the 2dsphere index on geolocations.position and
+        // must be the first stage, so the search starts at the locations.
+        let now = DateTime::now();
+        let mut nestbox_match = doc! {"nestbox.until_date": {"$exists": false}};
+        match mandant_uuid {
+            Some(mandant_uuid) => nestbox_match.insert("nestbox.mandant_uuid", mandant_uuid),
+            None => nestbox_match.insert("nestbox.public", true),
+        };
+        let cursor = self
+            .collection(GEOLOCATIONS)
+            .aggregate(
+                vec![
+                    doc! {"$geoNear": {
+                    "near": {"type": "Point", "coordinates": [long, lat]},
+                    "distanceField": "distance",
+                    "maxDistance": radius,
+                    "spherical": true,
+                    "query": {"from_date": {"$lte": now}, "until_date": {"$gt": now}}}},
+                    doc! {"$lookup": {
+                    "from": NESTBOXES,
+                    "localField": "nestbox_uuid",
+                    "foreignField": "uuid",
+                    "as": "nestbox"}},
+                    doc! {"$unwind": "$nestbox"},
+                    doc! {"$match": nestbox_match},
+                    doc! {"$limit": limit},
+                    doc! {"$replaceRoot": {"newRoot": {"$mergeObjects": ["$nestbox", {
+                    "distance": "$distance",
+                    "geolocation": [{
+                        "uuid": "$uuid",
+                        "nestbox_uuid": "$nestbox_uuid",
+                        "from_date": "$from_date",
+                        "until_date": "$until_date",
+                        "position": "$position"}]}]}}},
+                    mandant_lookup(),
+                    doc! {"$project": {"_id": 0}},
+                ],
+                None,
+            )
+            .await?;
+        read_cursor(cursor).await
+    }
+}
@@ -0,0 +1,65 @@
+use async_trait::async_trait;
+use mongodb::bson::{doc, DateTime, Document};
+
+use super::{read_cursor, MongoRepository};
+use crate::repository::{RepositoryResult, SessionRepository, SESSIONS};
+
+#[async_trait]
+impl SessionRepository for MongoRepository {
+    async fn insert(&self, session: &Document) -> RepositoryResult<()> {
+        self.collection(SESSIONS).insert_one(session, None).await?;
+        Ok(())
+    }
+
+    async fn find_by_session_key(&self, session_key: &str) -> RepositoryResult<Option<Document>> {
+        Ok(self
+            .collection(SESSIONS)
+            .find_one(doc! {"session_key": session_key}, None)
+            .await?)
+    }
+
+    async fn find_by_user_uuid(&self, user_uuid: &str) -> RepositoryResult<Vec<Document>> {
+        let cursor = self
+            .collection(SESSIONS)
+            .find(doc! {"uuid": user_uuid}, None)
+            .await?;
+        read_cursor(cursor).await
+    }
+
+    async fn update_last_seen(&self, session_key: &str, now: &DateTime) -> RepositoryResult<()> {
+        self.collection(SESSIONS)
+            .update_one(
+                doc! {"session_key": session_key},
+                doc! {"$set": {"session_last_seen_at": now}},
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_by_session_key(&self, session_key: &str) -> RepositoryResult<()> {
+        self.collection(SESSIONS)
+            .delete_one(doc! {"session_key": session_key}, None)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_by_session_id(
+        &self,
+        user_uuid: &str,
+        session_id: &str,
+    ) -> RepositoryResult<bool> {
+        let result = self
+            .collection(SESSIONS)
+            .delete_one(doc! {"uuid": user_uuid, "session_id": session_id}, None)
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    async fn delete_by_username(&self, username: &str) -> RepositoryResult<()> {
+        self.collection(SESSIONS)
+            .delete_many(doc! {"username": username}, None)
+            .await?;
+        Ok(())
+    }
+}
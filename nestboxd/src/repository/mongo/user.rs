@@ -0,0 +1,81 @@
+use async_trait::async_trait;
+use mongodb::bson::{doc, Document};
+
+use super::{read_cursor, MongoRepository};
+use crate::controller::utilities::PagingQuery;
+use crate::repository::{RepositoryResult, UserRepository, USERS};
+
+#[async_trait]
+impl UserRepository for MongoRepository {
+    async fn find_by_username(&self, username: &str) -> RepositoryResult<Option<Document>> {
+        Ok(self
+            .collection(USERS)
+            .find_one(doc! {"username": username}, None)
+            .await?)
+    }
+
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>> {
+        Ok(self
+            .collection(USERS)
+            .find_one(doc! {"uuid": uuid}, None)
+            .await?)
+    }
+
+    async fn find_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>> {
+        let cursor = self
+            .collection(USERS)
+            .aggregate(
+                vec![
+                    doc! {"$match": {"mandant_uuid": {"$eq": mandant_uuid}}},
+                    doc! { "$sort" : { "username" : 1} },
+                    doc! {"$skip": (paging.page_limit * (paging.page_number -1))},
+                    doc! {"$limit": paging.page_limit},
+                    doc! {"$project": {"_id": 0, "password_hash": 0, "salt": 0}},
+                ],
+                None,
+            )
+            .await?;
+        read_cursor(cursor).await
+    }
+
+    async fn count_by_mandant_uuid(&self, mandant_uuid: &str) -> RepositoryResult<u64> {
+        Ok(self
+            .collection(USERS)
+            .count_documents(doc! {"mandant_uuid": mandant_uuid}, None)
+            .await?)
+    }
+
+    async fn insert(&self, user: &Document) -> RepositoryResult<()> {
+        self.collection(USERS).insert_one(user, None).await?;
+        Ok(())
+    }
+
+    async fn update(&self, uuid: &str, user: &Document) -> RepositoryResult<bool> {
+        let mut attributes = Document::new();
+        for key in &["lastname", "firstname", "email", "role", "locked"] {
+            if let Some(value) = user.get(key) {
+                attributes.insert(*key, value.clone());
+            }
+        }
+        let result = self
+            .collection(USERS)
+            .update_one(doc! {"uuid": uuid}, doc! {"$set": attributes}, None)
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn update_password_hash(&self, uuid: &str, password_hash: &str) -> RepositoryResult<()> {
+        self.collection(USERS)
+            .update_one(
+                doc! {"uuid": uuid},
+                doc! {"$set": {"password_hash": password_hash}},
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+}
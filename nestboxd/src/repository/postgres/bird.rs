@@ -0,0 +1,223 @@
+use async_trait::async_trait;
+use mongodb::bson::{doc, Bson, Document};
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+use uuid::Uuid;
+
+use super::breed::keep_breed_version;
+use super::PostgresRepository;
+use crate::controller::utilities::PagingQuery;
+use crate::repository::{BirdRepository, RepositoryResult};
+
+const SELECT_BIRDS: &str = "SELECT b.id::text AS uuid, b.name, b.mandants_id::text AS mandant_uuid,
+        b.scientific_name, b.taxon_key, b.website,
+        ARRAY(SELECT l.language::text FROM birds_names l WHERE l.birds_id = b.id
+            ORDER BY l.language) AS languages,
+        ARRAY(SELECT l.name::text FROM birds_names l WHERE l.birds_id = b.id
+            ORDER BY l.language) AS names
+    FROM birds b";
+
+fn bird_doc(r: &PgRow) -> RepositoryResult<Document> {
+    let languages: Vec<String> = r.try_get("languages")?;
+    let names: Vec<String> = r.try_get("names")?;
+    let mut bird = doc! {
+        "uuid": r.try_get::<String, _>("uuid")?,
+        "bird": r.try_get::<String, _>("name")?,
+        "mandant_uuid": r.try_get::<String, _>("mandant_uuid")?,
+        "names": languages.into_iter().zip(names.into_iter().map(Bson::String)).collect::<Document>(),
+    };
+    if let Some(scientific_name) = r.try_get::<Option<String>, _>("scientific_name")? {
+        bird.insert("scientific_name", scientific_name);
+    }
+    if let Some(taxon_key) = r.try_get::<Option<String>, _>("taxon_key")? {
+        bird.insert("taxon_key", taxon_key);
+    }
+    if let Some(website) = r.try_get::<Option<String>, _>("website")? {
+        bird.insert("bird_website", website);
+    }
+    Ok(bird)
+}
+
+/// Replaces the names per language of the bird by those in `bird`.
+async fn replace_bird_names(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    uuid: &str,
+    bird: &Document,
+) -> RepositoryResult<()> {
+    sqlx::query("DELETE FROM birds_names WHERE birds_id = $1::uuid")
+        .bind(uuid)
+        .execute(&mut **tx)
+        .await?;
+    if let Ok(names) = bird.get_document("names") {
+        for (language, name) in names {
+            sqlx::query(
+                "INSERT INTO birds_names (birds_id, language, name) VALUES ($1::uuid, $2, $3)",
+            )
+            .bind(uuid)
+            .bind(language)
+            .bind(name.as_str().unwrap_or_default())
+            .execute(&mut **tx)
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl BirdRepository for PostgresRepository {
+    async fn find_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>> {
+        let rows = sqlx::query(&format!(
+            "{} WHERE b.mandants_id = $1::uuid ORDER BY b.name LIMIT $2 OFFSET $3",
+            SELECT_BIRDS
+        ))
+        .bind(mandant_uuid)
+        .bind(paging.page_limit)
+        .bind(paging.page_limit * (paging.page_number - 1))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(bird_doc).collect()
+    }
+
+    async fn count_by_mandant_uuid(&self, mandant_uuid: &str) -> RepositoryResult<u64> {
+        let counted: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM birds WHERE mandants_id = $1::uuid")
+                .bind(mandant_uuid)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(counted as u64)
+    }
+
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>> {
+        let row = sqlx::query(&format!("{} WHERE b.id = $1::uuid", SELECT_BIRDS))
+            .bind(uuid)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(bird_doc).transpose()
+    }
+
+    async fn find_uuid_by_name(
+        &self,
+        mandant_uuid: &str,
+        name: &str,
+    ) -> RepositoryResult<Option<String>> {
+        Ok(sqlx::query_scalar(
+            "SELECT id::text FROM birds WHERE mandants_id = $1::uuid AND lower(name) = lower($2)",
+        )
+        .bind(mandant_uuid)
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn find_uuid_by_taxon_key(
+        &self,
+        mandant_uuid: &str,
+        taxon_key: &str,
+    ) -> RepositoryResult<Option<String>> {
+        Ok(sqlx::query_scalar(
+            "SELECT id::text FROM birds WHERE mandants_id = $1::uuid AND taxon_key = $2",
+        )
+        .bind(mandant_uuid)
+        .bind(taxon_key)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn insert(&self, bird: &Document) -> RepositoryResult<()> {
+        let uuid = bird.get_str("uuid").unwrap_or_default();
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO birds (id, mandants_id, name, scientific_name, taxon_key, website)
+            VALUES ($1::uuid, $2::uuid, $3, $4, $5, $6)",
+        )
+        .bind(uuid)
+        .bind(bird.get_str("mandant_uuid").unwrap_or_default())
+        .bind(bird.get_str("bird").unwrap_or_default())
+        .bind(bird.get_str("scientific_name").ok())
+        .bind(bird.get_str("taxon_key").ok())
+        .bind(bird.get_str("bird_website").ok())
+        .execute(&mut *tx)
+        .await?;
+        replace_bird_names(&mut tx, uuid, bird).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn update(&self, uuid: &str, bird: &Document) -> RepositoryResult<bool> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            "UPDATE birds SET name = $1, scientific_name = $2, taxon_key = $3, website = $4
+            WHERE id = $5::uuid",
+        )
+        .bind(bird.get_str("bird").unwrap_or_default())
+        .bind(bird.get_str("scientific_name").ok())
+        .bind(bird.get_str("taxon_key").ok())
+        .bind(bird.get_str("bird_website").ok())
+        .bind(uuid)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        replace_bird_names(&mut tx, uuid, bird).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn delete(&self, uuid: &str) -> RepositoryResult<bool> {
+        let result = sqlx::query("DELETE FROM birds WHERE id = $1::uuid")
+            .bind(uuid)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn merge(
+        &self,
+        uuid: &str,
+        into_uuid: &str,
+        change: &Document,
+    ) -> RepositoryResult<Option<u64>> {
+        let mut tx = self.pool.begin().await?;
+        let Some(name) = sqlx::query_scalar::<_, String>(
+            "SELECT name FROM birds WHERE id = $1::uuid FOR UPDATE",
+        )
+        .bind(uuid)
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            // Dropping the transaction rolls it back.
+            return Ok(None);
+        };
+        let breeds: Vec<String> =
+            sqlx::query_scalar("SELECT id::text FROM breeds WHERE birds_id = $1::uuid")
+                .bind(uuid)
+                .fetch_all(&mut *tx)
+                .await?;
+        for breed_uuid in &breeds {
+            let mut change = change.clone();
+            change.insert("uuid", Uuid::new_v4().to_string());
+            keep_breed_version(&mut tx, breed_uuid, &change).await?;
+        }
+        sqlx::query("UPDATE breeds_versions SET bird_name = $1 WHERE birds_id = $2::uuid")
+            .bind(name)
+            .bind(uuid)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE breeds SET birds_id = $1::uuid WHERE birds_id = $2::uuid")
+            .bind(into_uuid)
+            .bind(uuid)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM birds WHERE id = $1::uuid")
+            .bind(uuid)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(Some(breeds.len() as u64))
+    }
+}
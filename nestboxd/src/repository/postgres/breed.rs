@@ -0,0 +1,248 @@
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use mongodb::bson::{doc, Document};
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+
+use super::PostgresRepository;
+use crate::controller::utilities::PagingQuery;
+use crate::repository::sql_helper::{to_bson_date, to_naive_date};
+use crate::repository::{BreedRepository, RepositoryResult};
+
+const SELECT_BREEDS: &str = "SELECT b.id::text AS uuid, b.nestboxes_id::text AS nestbox_uuid,
+        b.users_id::text AS user_uuid, b.created_at,
+        b.clutch_size, b.hatched, b.fledged, b.nest_state, b.confidence, b.notes,
+        bi.id::text AS bird_uuid, bi.name AS bird, n.label AS nestbox_label
+    FROM breeds b LEFT JOIN birds bi ON bi.id = b.birds_id
+        JOIN nestboxes n ON n.id = b.nestboxes_id";
+
+fn breed_doc(r: &PgRow) -> RepositoryResult<Document> {
+    let mut bird: Vec<Document> = Vec::new();
+    // The bird of a version may be gone, unless it was merged into another.
+    if let (Some(bird_uuid), Some(name)) = (
+        r.try_get::<Option<String>, _>("bird_uuid")?,
+        r.try_get::<Option<String>, _>("bird")?,
+    ) {
+        bird.push(doc! {"uuid": bird_uuid, "bird": name});
+    }
+    let mut breed = doc! {
+        "uuid": r.try_get::<String, _>("uuid")?,
+        "nestbox_uuid": r.try_get::<String, _>("nestbox_uuid")?,
+        "user_uuid": r.try_get::<String, _>("user_uuid")?,
+        "discovery_date": to_bson_date(r.try_get::<NaiveDateTime, _>("created_at")?),
+        "bird": bird,
+    };
+    if let Some(label) = r.try_get::<Option<String>, _>("nestbox_label")? {
+        breed.insert("nestbox_label", label);
+    }
+    for key in ["clutch_size", "hatched", "fledged"] {
+        if let Some(count) = r.try_get::<Option<i32>, _>(key)? {
+            breed.insert(key, count);
+        }
+    }
+    for key in ["nest_state", "confidence", "notes"] {
+        if let Some(value) = r.try_get::<Option<String>, _>(key)? {
+            breed.insert(key, value);
+        }
+    }
+    Ok(breed)
+}
+
+const SELECT_BREED_VERSIONS: &str = "SELECT v.id::text AS uuid, v.breeds_id::text AS breed_uuid,
+        v.nestboxes_id::text AS nestbox_uuid, v.users_id::text AS user_uuid, v.created_at,
+        v.clutch_size, v.hatched, v.fledged, v.nest_state, v.confidence, v.notes,
+        v.birds_id::text AS bird_uuid, COALESCE(bi.name, v.bird_name) AS bird, NULL::text AS nestbox_label,
+        v.changed_by::text AS changed_by, v.changed_at, v.change
+    FROM breeds_versions v LEFT JOIN birds bi ON bi.id = v.birds_id";
+
+fn breed_version_doc(r: &PgRow) -> RepositoryResult<Document> {
+    let mut version = breed_doc(r)?;
+    version.insert("breed_uuid", r.try_get::<String, _>("breed_uuid")?);
+    version.insert("changed_by", r.try_get::<String, _>("changed_by")?);
+    version.insert(
+        "changed_at",
+        to_bson_date(r.try_get::<NaiveDateTime, _>("changed_at")?),
+    );
+    version.insert("change", r.try_get::<String, _>("change")?);
+    Ok(version)
+}
+
+/// Keeps the breed as it is now as a version with the fields of `change`,
+/// false if there is no such breed.
+pub(super) async fn keep_breed_version(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    uuid: &str,
+    change: &Document,
+) -> RepositoryResult<bool> {
+    let kept = sqlx::query(
+        "INSERT INTO breeds_versions (id, breeds_id, nestboxes_id, users_id, birds_id,
+            created_at, clutch_size, hatched, fledged, nest_state, confidence, notes,
+            changed_by, changed_at, change)
+        SELECT $1::uuid, id, nestboxes_id, users_id, birds_id, created_at,
+            clutch_size, hatched, fledged, nest_state, confidence, notes, $2::uuid, $3, $4
+        FROM breeds WHERE id = $5::uuid FOR UPDATE",
+    )
+    .bind(change.get_str("uuid").unwrap_or_default())
+    .bind(change.get_str("changed_by").unwrap_or_default())
+    .bind(
+        change
+            .get_datetime("changed_at")
+            .map(to_naive_date)
+            .unwrap_or_else(|_| Utc::now().naive_utc()),
+    )
+    .bind(change.get_str("change").unwrap_or_default())
+    .bind(uuid)
+    .execute(&mut **tx)
+    .await?;
+    Ok(kept.rows_affected() > 0)
+}
+
+#[async_trait]
+impl BreedRepository for PostgresRepository {
+    async fn find_by_nestbox_uuid(
+        &self,
+        nestbox_uuid: &str,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>> {
+        let rows = sqlx::query(&format!(
+            "{} WHERE b.nestboxes_id = $1::uuid
+            ORDER BY b.created_at, b.id LIMIT $2 OFFSET $3",
+            SELECT_BREEDS
+        ))
+        .bind(nestbox_uuid)
+        .bind(paging.page_limit)
+        .bind(paging.page_limit * (paging.page_number - 1))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(breed_doc).collect()
+    }
+
+    async fn count_by_nestbox_uuid(&self, nestbox_uuid: &str) -> RepositoryResult<u64> {
+        let counted: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM breeds WHERE nestboxes_id = $1::uuid")
+                .bind(nestbox_uuid)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(counted as u64)
+    }
+
+    async fn count_by_bird_uuid(&self, bird_uuid: &str) -> RepositoryResult<u64> {
+        let counted: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM breeds WHERE birds_id = $1::uuid")
+                .bind(bird_uuid)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(counted as u64)
+    }
+
+    async fn count_versions_by_bird_uuid(&self, bird_uuid: &str) -> RepositoryResult<u64> {
+        let counted: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM breeds_versions WHERE birds_id = $1::uuid")
+                .bind(bird_uuid)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(counted as u64)
+    }
+
+    async fn find_latest_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+    ) -> RepositoryResult<Vec<Document>> {
+        let rows = sqlx::query(&format!(
+            "{} WHERE n.mandants_id = $1::uuid AND b.id = (SELECT l.id FROM breeds l
+                WHERE l.nestboxes_id = b.nestboxes_id ORDER BY l.created_at DESC, l.id DESC LIMIT 1)",
+            SELECT_BREEDS
+        ))
+        .bind(mandant_uuid)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(breed_doc).collect()
+    }
+
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>> {
+        let row = sqlx::query(&format!("{} WHERE b.id = $1::uuid", SELECT_BREEDS))
+            .bind(uuid)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(breed_doc).transpose()
+    }
+
+    async fn insert(&self, breed: &Document) -> RepositoryResult<()> {
+        sqlx::query(
+            "INSERT INTO breeds (id, nestboxes_id, users_id, birds_id, created_at,
+                clutch_size, hatched, fledged, nest_state, confidence, notes)
+            VALUES ($1::uuid, $2::uuid, $3::uuid, $4::uuid, $5, $6, $7, $8, $9, $10, $11)",
+        )
+        .bind(breed.get_str("uuid").unwrap_or_default())
+        .bind(breed.get_str("nestbox_uuid").unwrap_or_default())
+        .bind(breed.get_str("user_uuid").unwrap_or_default())
+        .bind(breed.get_str("bird_uuid").unwrap_or_default())
+        .bind(
+            breed
+                .get_datetime("discovery_date")
+                .map(to_naive_date)
+                .unwrap_or_else(|_| Utc::now().naive_utc()),
+        )
+        .bind(breed.get_i32("clutch_size").ok())
+        .bind(breed.get_i32("hatched").ok())
+        .bind(breed.get_i32("fledged").ok())
+        .bind(breed.get_str("nest_state").ok())
+        .bind(breed.get_str("confidence").ok())
+        .bind(breed.get_str("notes").ok())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update(&self, breed: &Document, change: &Document) -> RepositoryResult<bool> {
+        let uuid = breed.get_str("uuid").unwrap_or_default();
+        let mut tx = self.pool.begin().await?;
+        if !keep_breed_version(&mut tx, uuid, change).await? {
+            return Ok(false);
+        }
+        sqlx::query(
+            "UPDATE breeds SET nestboxes_id = $1::uuid, birds_id = $2::uuid,
+                created_at = COALESCE($3, created_at), clutch_size = $4, hatched = $5,
+                fledged = $6, nest_state = $7, confidence = $8, notes = $9
+            WHERE id = $10::uuid",
+        )
+        .bind(breed.get_str("nestbox_uuid").unwrap_or_default())
+        .bind(breed.get_str("bird_uuid").unwrap_or_default())
+        .bind(breed.get_datetime("discovery_date").ok().map(to_naive_date))
+        .bind(breed.get_i32("clutch_size").ok())
+        .bind(breed.get_i32("hatched").ok())
+        .bind(breed.get_i32("fledged").ok())
+        .bind(breed.get_str("nest_state").ok())
+        .bind(breed.get_str("confidence").ok())
+        .bind(breed.get_str("notes").ok())
+        .bind(uuid)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn delete(&self, uuid: &str, change: &Document) -> RepositoryResult<bool> {
+        let mut tx = self.pool.begin().await?;
+        if !keep_breed_version(&mut tx, uuid, change).await? {
+            return Ok(false);
+        }
+        sqlx::query("DELETE FROM breeds WHERE id = $1::uuid")
+            .bind(uuid)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn find_versions(&self, uuid: &str) -> RepositoryResult<Vec<Document>> {
+        let rows = sqlx::query(&format!(
+            "{} WHERE v.breeds_id = $1::uuid ORDER BY v.changed_at, v.id",
+            SELECT_BREED_VERSIONS
+        ))
+        .bind(uuid)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(breed_version_doc).collect()
+    }
+}
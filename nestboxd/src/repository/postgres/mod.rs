@@ -0,0 +1,102 @@
+//! PostgreSQL backend. The schema lives in db/postgres and is migrated on
+//! startup, uuids are passed as text and cast by the queries.
+use sqlx::postgres::{PgPool, PgPoolOptions};
+
+use super::RepositoryResult;
+
+mod bird;
+mod breed;
+mod geolocation;
+mod mandant;
+mod nestbox;
+mod session;
+mod user;
+
+#[derive(Clone)]
+pub struct PostgresRepository {
+    pool: PgPool,
+}
+
+impl PostgresRepository {
+    pub async fn connect(uri: &str) -> RepositoryResult<Self> {
+        let pool = PgPoolOptions::new().connect(uri).await?;
+        sqlx::migrate!("db/postgres").run(&pool).await?;
+        Ok(PostgresRepository { pool })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // These tests need a running PostgreSQL, the database is taken from
+    // NESTBOX_TEST_POSTGRES_URI. Run them with --ignored.
+    use super::*;
+    use crate::repository::backend_tests;
+
+    const DEFAULT_URI: &str = "postgres://postgres@localhost:5432/nestbox_testing";
+
+    async fn connect() -> PostgresRepository {
+        let uri =
+            std::env::var("NESTBOX_TEST_POSTGRES_URI").unwrap_or_else(|_| DEFAULT_URI.to_string());
+        PostgresRepository::connect(&uri).await.unwrap()
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_postgres_nestbox() {
+        backend_tests::nestbox(connect().await).await;
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_postgres_breeds_and_birds() {
+        backend_tests::breeds_and_birds(connect().await).await;
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_postgres_sessions_and_users() {
+        backend_tests::sessions_and_users(connect().await).await;
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_postgres_geolocations() {
+        backend_tests::geolocations(connect().await).await;
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_postgres_mandants() {
+        backend_tests::mandants(connect().await).await;
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_postgres_users() {
+        backend_tests::users(connect().await).await;
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_postgres_nestbox_lifecycle() {
+        backend_tests::nestbox_lifecycle(connect().await).await;
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_postgres_nestbox_codes() {
+        backend_tests::nestbox_codes(connect().await).await;
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_postgres_nestbox_labels() {
+        backend_tests::nestbox_labels(connect().await).await;
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_postgres_nestboxes_filtered() {
+        backend_tests::nestboxes_filtered(connect().await).await;
+    }
+}
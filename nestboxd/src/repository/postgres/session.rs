@@ -0,0 +1,131 @@
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use mongodb::bson::{doc, DateTime, Document};
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+
+use super::PostgresRepository;
+use crate::repository::sql_helper::{to_bson_date, to_naive_date};
+use crate::repository::{RepositoryResult, SessionRepository};
+
+const SELECT_SESSIONS: &str = "SELECT s.id::text AS session_key, s.public_id::text AS session_id,
+        s.created_at, s.last_seen_at, s.expires_at, s.user_agent,
+        u.id::text AS uuid, u.mandants_id::text AS mandant_uuid, u.username, u.lastname,
+        u.firstname, u.email, u.role
+    FROM sessions s JOIN users u ON u.id = s.users_id";
+
+fn session_doc(r: &PgRow) -> RepositoryResult<Document> {
+    Ok(doc! {
+        "mandant_uuid": r.try_get::<String, _>("mandant_uuid")?,
+        "username": r.try_get::<String, _>("username")?,
+        "uuid": r.try_get::<String, _>("uuid")?,
+        "lastname": r.try_get::<String, _>("lastname")?,
+        "firstname": r.try_get::<String, _>("firstname")?,
+        "email": r.try_get::<String, _>("email")?,
+        "role": r.try_get::<String, _>("role")?,
+        "session_key": r.try_get::<String, _>("session_key")?,
+        "session_id": r.try_get::<String, _>("session_id")?,
+        "session_created_at": to_bson_date(r.try_get::<NaiveDateTime, _>("created_at")?),
+        "session_last_seen_at": to_bson_date(r.try_get::<NaiveDateTime, _>("last_seen_at")?),
+        "session_expires_at": to_bson_date(r.try_get::<NaiveDateTime, _>("expires_at")?),
+        "user_agent": r.try_get::<String, _>("user_agent")?,
+    })
+}
+
+#[async_trait]
+impl SessionRepository for PostgresRepository {
+    async fn insert(&self, session: &Document) -> RepositoryResult<()> {
+        let created_at = session
+            .get_datetime("session_created_at")
+            .map(to_naive_date)
+            .unwrap_or_else(|_| Utc::now().naive_utc());
+        sqlx::query(
+            "INSERT INTO sessions (id, public_id, users_id, created_at, last_seen_at, expires_at,
+                user_agent)
+            VALUES ($1::uuid, $2::uuid, $3::uuid, $4, $5, $6, $7)",
+        )
+        .bind(session.get_str("session_key").unwrap_or_default())
+        .bind(session.get_str("session_id").unwrap_or_default())
+        .bind(session.get_str("uuid").unwrap_or_default())
+        .bind(created_at)
+        .bind(
+            session
+                .get_datetime("session_last_seen_at")
+                .map(to_naive_date)
+                .unwrap_or(created_at),
+        )
+        .bind(
+            session
+                .get_datetime("session_expires_at")
+                .map(to_naive_date)
+                .unwrap_or(created_at),
+        )
+        .bind(session.get_str("user_agent").unwrap_or_default())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn find_by_session_key(&self, session_key: &str) -> RepositoryResult<Option<Document>> {
+        let row = sqlx::query(&format!("{} WHERE s.id = $1::uuid", SELECT_SESSIONS))
+            .bind(session_key)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(r) => Ok(Some(session_doc(&r)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn find_by_user_uuid(&self, user_uuid: &str) -> RepositoryResult<Vec<Document>> {
+        let rows = sqlx::query(&format!(
+            "{} WHERE s.users_id = $1::uuid ORDER BY s.created_at",
+            SELECT_SESSIONS
+        ))
+        .bind(user_uuid)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(session_doc).collect()
+    }
+
+    async fn update_last_seen(&self, session_key: &str, now: &DateTime) -> RepositoryResult<()> {
+        sqlx::query("UPDATE sessions SET last_seen_at = $2 WHERE id = $1::uuid")
+            .bind(session_key)
+            .bind(to_naive_date(now))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_by_session_key(&self, session_key: &str) -> RepositoryResult<()> {
+        sqlx::query("DELETE FROM sessions WHERE id = $1::uuid")
+            .bind(session_key)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_by_session_id(
+        &self,
+        user_uuid: &str,
+        session_id: &str,
+    ) -> RepositoryResult<bool> {
+        let result =
+            sqlx::query("DELETE FROM sessions WHERE users_id = $1::uuid AND public_id = $2::uuid")
+                .bind(user_uuid)
+                .bind(session_id)
+                .execute(&self.pool)
+                .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_by_username(&self, username: &str) -> RepositoryResult<()> {
+        sqlx::query(
+            "DELETE FROM sessions WHERE users_id IN (SELECT id FROM users WHERE username = $1)",
+        )
+        .bind(username)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use async_trait::async_trait;
+use mongodb::bson::{doc, Document};
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+
+use super::PostgresRepository;
+use crate::controller::utilities::PagingQuery;
+use crate::repository::{RepositoryResult, UserRepository};
+
+const SELECT_USERS: &str = "SELECT id::text AS uuid, mandants_id::text AS mandant_uuid, username,
+        lastname, firstname, email, password_hash, salt::text AS salt, role, locked
+    FROM users";
+
+fn user_doc(r: &PgRow) -> RepositoryResult<Document> {
+    Ok(doc! {
+        "mandant_uuid": r.try_get::<String, _>("mandant_uuid")?,
+        "username": r.try_get::<String, _>("username")?,
+        "uuid": r.try_get::<String, _>("uuid")?,
+        "lastname": r.try_get::<String, _>("lastname")?,
+        "firstname": r.try_get::<String, _>("firstname")?,
+        "email": r.try_get::<String, _>("email")?,
+        "password_hash": r.try_get::<String, _>("password_hash")?,
+        "salt": r.try_get::<String, _>("salt")?,
+        "role": r.try_get::<String, _>("role")?,
+        "locked": r.try_get::<bool, _>("locked")?,
+    })
+}
+
+#[async_trait]
+impl UserRepository for PostgresRepository {
+    async fn find_by_username(&self, username: &str) -> RepositoryResult<Option<Document>> {
+        let row = sqlx::query(&format!("{} WHERE username = $1", SELECT_USERS))
+            .bind(username)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(r) => Ok(Some(user_doc(&r)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>> {
+        let row = sqlx::query(&format!("{} WHERE id = $1::uuid", SELECT_USERS))
+            .bind(uuid)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(r) => Ok(Some(user_doc(&r)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn find_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>> {
+        let rows = sqlx::query(&format!(
+            "{} WHERE mandants_id = $1::uuid ORDER BY username LIMIT $2 OFFSET $3",
+            SELECT_USERS
+        ))
+        .bind(mandant_uuid)
+        .bind(paging.page_limit)
+        .bind(paging.page_limit * (paging.page_number - 1))
+        .fetch_all(&self.pool)
+        .await?;
+        let mut users: Vec<Document> = Vec::new();
+        for r in rows {
+            let mut user = user_doc(&r)?;
+            user.remove("password_hash");
+            user.remove("salt");
+            users.push(user);
+        }
+        Ok(users)
+    }
+
+    async fn count_by_mandant_uuid(&self, mandant_uuid: &str) -> RepositoryResult<u64> {
+        let counted: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE mandants_id = $1::uuid")
+                .bind(mandant_uuid)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(counted as u64)
+    }
+
+    async fn insert(&self, user: &Document) -> RepositoryResult<()> {
+        sqlx::query(
+            "INSERT INTO users (id, mandants_id, username, lastname, firstname, email,
+                password_hash, salt, role, locked)
+            VALUES ($1::uuid, $2::uuid, $3, $4, $5, $6, $7, $8::uuid, $9, $10)",
+        )
+        .bind(user.get_str("uuid").unwrap_or_default())
+        .bind(user.get_str("mandant_uuid").unwrap_or_default())
+        .bind(user.get_str("username").unwrap_or_default())
+        .bind(user.get_str("lastname").unwrap_or_default())
+        .bind(user.get_str("firstname").unwrap_or_default())
+        .bind(user.get_str("email").unwrap_or_default())
+        .bind(user.get_str("password_hash").unwrap_or_default())
+        .bind(user.get_str("salt").unwrap_or_default())
+        .bind(user.get_str("role").unwrap_or("viewer"))
+        .bind(user.get_bool("locked").unwrap_or(false))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update(&self, uuid: &str, user: &Document) -> RepositoryResult<bool> {
+        // Attributes missing in the document keep their value.
+        let result = sqlx::query(
+            "UPDATE users SET lastname = COALESCE($2, lastname),
+                firstname = COALESCE($3, firstname), email = COALESCE($4, email),
+                role = COALESCE($5, role), locked = COALESCE($6, locked)
+            WHERE id = $1::uuid",
+        )
+        .bind(uuid)
+        .bind(user.get_str("lastname").ok())
+        .bind(user.get_str("firstname").ok())
+        .bind(user.get_str("email").ok())
+        .bind(user.get_str("role").ok())
+        .bind(user.get_bool("locked").ok())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn update_password_hash(&self, uuid: &str, password_hash: &str) -> RepositoryResult<()> {
+        sqlx::query("UPDATE users SET password_hash = $2 WHERE id = $1::uuid")
+            .bind(uuid)
+            .bind(password_hash)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use chrono::NaiveDateTime;
+use mongodb::bson::{doc, DateTime, Document};
+
+// The sql backends store timestamps without time zone, always in UTC.
+
+pub(crate) fn to_bson_date(date: NaiveDateTime) -> DateTime {
+    DateTime::from_millis(date.and_utc().timestamp_millis())
+}
+
+pub(crate) fn to_naive_date(date: &DateTime) -> NaiveDateTime {
+    chrono::DateTime::<chrono::Utc>::from(date.to_system_time()).naive_utc()
+}
+
+/// Row of nestboxes_geolocations shaped like a MongoDB geolocation.
+pub(crate) fn to_geolocation_doc(
+    uuid: String,
+    nestbox_uuid: String,
+    lat: f64,
+    lng: f64,
+    from_date: NaiveDateTime,
+    until_date: Option<NaiveDateTime>,
+) -> Document {
+    let mut geolocation = doc! {
+        "uuid": uuid,
+        "nestbox_uuid": nestbox_uuid,
+        "from_date": to_bson_date(from_date),
+        "position": {"type": "Point", "coordinates": [lng, lat]},
+    };
+    if let Some(until_date) = until_date {
+        geolocation.insert("until_date", to_bson_date(until_date));
+    }
+    geolocation
+}
+
+/// Latitudes and longitudes (min_lat, max_lat, min_lng, max_lng) enclosing
+/// the circle, to narrow the candidates before the exact distance is
+/// computed. Spans all longitudes near the poles and the antimeridian.
+pub(crate) fn bounding_box(lat: f64, lng: f64, radius: f64) -> (f64, f64, f64, f64) {
+    // meters per degree of latitude, a bit less to keep the box wide enough
+    const METERS_PER_DEGREE: f64 = 111_000.0;
+    let d_lat = radius / METERS_PER_DEGREE;
+    let (min_lat, max_lat) = (lat - d_lat, lat + d_lat);
+    if min_lat <= -90.0 || max_lat >= 90.0 {
+        return (min_lat.max(-90.0), max_lat.min(90.0), -180.0, 180.0);
+    }
+    let d_lng = d_lat / min_lat.abs().max(max_lat.abs()).to_radians().cos();
+    if lng - d_lng < -180.0 || lng + d_lng > 180.0 {
+        return (min_lat, max_lat, -180.0, 180.0);
+    }
+    (min_lat, max_lat, lng - d_lng, lng + d_lng)
+}
@@ -0,0 +1,116 @@
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use mongodb::bson::{DateTime, Document};
+use sqlx::Row;
+
+use super::SqliteRepository;
+use crate::controller::utilities::PagingQuery;
+use crate::repository::sql_helper::{to_geolocation_doc, to_naive_date};
+use crate::repository::{GeolocationRepository, RepositoryResult};
+
+const SELECT_GEOLOCATIONS: &str = "SELECT id AS uuid, nestboxes_id AS nestbox_uuid,
+        lat, lng, created_at, expired_at
+    FROM nestboxes_geolocations";
+
+fn geolocation_doc(r: &sqlx::sqlite::SqliteRow) -> RepositoryResult<Document> {
+    Ok(to_geolocation_doc(
+        r.try_get("uuid")?,
+        r.try_get("nestbox_uuid")?,
+        r.try_get("lat")?,
+        r.try_get("lng")?,
+        r.try_get::<NaiveDateTime, _>("created_at")?,
+        r.try_get::<Option<NaiveDateTime>, _>("expired_at")?,
+    ))
+}
+
+#[async_trait]
+impl GeolocationRepository for SqliteRepository {
+    async fn end_current(&self, nestbox_uuid: &str, now: &DateTime) -> RepositoryResult<()> {
+        sqlx::query(
+            "UPDATE nestboxes_geolocations SET expired_at = $2
+            WHERE nestboxes_id = $1 AND (expired_at IS NULL OR expired_at > $2)",
+        )
+        .bind(nestbox_uuid)
+        .bind(to_naive_date(now))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn insert(&self, geolocation: &Document) -> RepositoryResult<()> {
+        // "position" : { "type" : "Point", "coordinates" : [ long, lat ] }
+        let coordinates = geolocation
+            .get_document("position")
+            .and_then(|p| p.get_array("coordinates"))
+            .cloned()
+            .unwrap_or_default();
+        let long = coordinates.first().and_then(|c| c.as_f64()).unwrap_or(0.0);
+        let lat = coordinates.get(1).and_then(|c| c.as_f64()).unwrap_or(0.0);
+        sqlx::query(
+            "INSERT INTO nestboxes_geolocations (id, nestboxes_id, lat, lng, created_at, expired_at)
+            VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(geolocation.get_str("uuid").unwrap_or_default())
+        .bind(geolocation.get_str("nestbox_uuid").unwrap_or_default())
+        .bind(lat)
+        .bind(long)
+        .bind(
+            geolocation
+                .get_datetime("from_date")
+                .map(to_naive_date)
+                .unwrap_or_else(|_| Utc::now().naive_utc()),
+        )
+        .bind(geolocation.get_datetime("until_date").ok().map(to_naive_date))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn find_by_nestbox_uuid(
+        &self,
+        nestbox_uuid: &str,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>> {
+        let rows = sqlx::query(&format!(
+            "{} WHERE nestboxes_id = $1 ORDER BY created_at DESC, id LIMIT $2 OFFSET $3",
+            SELECT_GEOLOCATIONS
+        ))
+        .bind(nestbox_uuid)
+        .bind(paging.page_limit)
+        .bind(paging.page_limit * (paging.page_number - 1))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(geolocation_doc).collect()
+    }
+
+    async fn count_by_nestbox_uuid(&self, nestbox_uuid: &str) -> RepositoryResult<u64> {
+        let counted: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM nestboxes_geolocations WHERE nestboxes_id = $1",
+        )
+        .bind(nestbox_uuid)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(counted as u64)
+    }
+
+    async fn find_at(
+        &self,
+        nestbox_uuid: &str,
+        at: &DateTime,
+    ) -> RepositoryResult<Option<Document>> {
+        let row = sqlx::query(&format!(
+            "{} WHERE nestboxes_id = $1 AND created_at <= $2
+                AND (expired_at IS NULL OR expired_at > $2)
+            ORDER BY created_at DESC LIMIT 1",
+            SELECT_GEOLOCATIONS
+        ))
+        .bind(nestbox_uuid)
+        .bind(to_naive_date(at))
+        .fetch_optional(&self.pool)
+        .await?;
+        match row {
+            Some(r) => Ok(Some(geolocation_doc(&r)?)),
+            None => Ok(None),
+        }
+    }
+}
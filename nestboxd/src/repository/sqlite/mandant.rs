@@ -0,0 +1,74 @@
+use async_trait::async_trait;
+use mongodb::bson::{doc, Document};
+use sqlx::Row;
+
+use super::SqliteRepository;
+use crate::repository::{MandantRepository, RepositoryResult};
+
+#[async_trait]
+impl MandantRepository for SqliteRepository {
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>> {
+        let row = sqlx::query(
+            "SELECT id AS uuid, association_name, website, email, logo, contact_person,
+                phone, address
+            FROM mandants WHERE id = $1",
+        )
+        .bind(uuid)
+        .fetch_optional(&self.pool)
+        .await?;
+        match row {
+            Some(r) => Ok(Some(doc! {
+                "uuid": r.try_get::<String, _>("uuid")?,
+                "name": r.try_get::<String, _>("association_name")?,
+                "website": r.try_get::<String, _>("website")?,
+                "email": r.try_get::<String, _>("email")?,
+                "logo": r.try_get::<String, _>("logo")?,
+                "contact_person": r.try_get::<String, _>("contact_person")?,
+                "phone": r.try_get::<String, _>("phone")?,
+                "address": r.try_get::<String, _>("address")?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    async fn insert(&self, mandant: &Document) -> RepositoryResult<()> {
+        sqlx::query(
+            "INSERT INTO mandants (id, association_name, website, email, logo, contact_person,
+                phone, address)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+        .bind(mandant.get_str("uuid").unwrap_or_default())
+        .bind(mandant.get_str("name").unwrap_or_default())
+        .bind(mandant.get_str("website").unwrap_or_default())
+        .bind(mandant.get_str("email").unwrap_or_default())
+        .bind(mandant.get_str("logo").unwrap_or_default())
+        .bind(mandant.get_str("contact_person").unwrap_or_default())
+        .bind(mandant.get_str("phone").unwrap_or_default())
+        .bind(mandant.get_str("address").unwrap_or_default())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update(&self, uuid: &str, mandant: &Document) -> RepositoryResult<bool> {
+        // Attributes missing in the document keep their value.
+        let result = sqlx::query(
+            "UPDATE mandants SET association_name = COALESCE($2, association_name),
+                website = COALESCE($3, website), email = COALESCE($4, email),
+                logo = COALESCE($5, logo), contact_person = COALESCE($6, contact_person),
+                phone = COALESCE($7, phone), address = COALESCE($8, address)
+            WHERE id = $1",
+        )
+        .bind(uuid)
+        .bind(mandant.get_str("name").ok())
+        .bind(mandant.get_str("website").ok())
+        .bind(mandant.get_str("email").ok())
+        .bind(mandant.get_str("logo").ok())
+        .bind(mandant.get_str("contact_person").ok())
+        .bind(mandant.get_str("phone").ok())
+        .bind(mandant.get_str("address").ok())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,105 @@
+//! SQLite backend for small single box deployments, e.g. on a Raspberry Pi.
+//! All data lives in one file, the schema in db/sqlite is created on first
+//! start.
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+
+use super::RepositoryResult;
+
+mod bird;
+mod breed;
+mod geolocation;
+mod mandant;
+mod nestbox;
+mod session;
+mod user;
+
+#[derive(Clone)]
+pub struct SqliteRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteRepository {
+    pub async fn connect(file: &str) -> RepositoryResult<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(file)
+            .create_if_missing(true)
+            .foreign_keys(true);
+        let pool = SqlitePoolOptions::new().connect_with(options).await?;
+        sqlx::migrate!("db/sqlite").run(&pool).await?;
+        Ok(SqliteRepository { pool })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // Each test works on its own database in memory.
+    use super::*;
+    use crate::repository::backend_tests;
+
+    /// A database of its own in memory. It lives as long as its single
+    /// connection, so that one is never closed.
+    async fn in_memory() -> SqliteRepository {
+        let options = SqliteConnectOptions::new()
+            .in_memory(true)
+            .foreign_keys(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await
+            .unwrap();
+        sqlx::migrate!("db/sqlite").run(&pool).await.unwrap();
+        SqliteRepository { pool }
+    }
+
+    #[actix_rt::test]
+    async fn test_sqlite_nestbox() {
+        backend_tests::nestbox(in_memory().await).await;
+    }
+
+    #[actix_rt::test]
+    async fn test_sqlite_breeds_and_birds() {
+        backend_tests::breeds_and_birds(in_memory().await).await;
+    }
+
+    #[actix_rt::test]
+    async fn test_sqlite_sessions_and_users() {
+        backend_tests::sessions_and_users(in_memory().await).await;
+    }
+
+    #[actix_rt::test]
+    async fn test_sqlite_geolocations() {
+        backend_tests::geolocations(in_memory().await).await;
+    }
+
+    #[actix_rt::test]
+    async fn test_sqlite_mandants() {
+        backend_tests::mandants(in_memory().await).await;
+    }
+
+    #[actix_rt::test]
+    async fn test_sqlite_users() {
+        backend_tests::users(in_memory().await).await;
+    }
+
+    #[actix_rt::test]
+    async fn test_sqlite_nestbox_lifecycle() {
+        backend_tests::nestbox_lifecycle(in_memory().await).await;
+    }
+
+    #[actix_rt::test]
+    async fn test_sqlite_nestbox_codes() {
+        backend_tests::nestbox_codes(in_memory().await).await;
+    }
+
+    #[actix_rt::test]
+    async fn test_sqlite_nestbox_labels() {
+        backend_tests::nestbox_labels(in_memory().await).await;
+    }
+
+    #[actix_rt::test]
+    async fn test_sqlite_nestboxes_filtered() {
+        backend_tests::nestboxes_filtered(in_memory().await).await;
+    }
+}
@@ -0,0 +1,328 @@
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use mongodb::bson::{doc, DateTime, Document};
+use sqlx::query::Query;
+use sqlx::sqlite::{SqliteArguments, SqliteRow};
+use sqlx::{Row, Sqlite};
+
+use super::SqliteRepository;
+use crate::controller::utilities::PagingQuery;
+use crate::repository::sql_helper::{
+    bounding_box, to_bson_date, to_geolocation_doc, to_naive_date,
+};
+use crate::repository::{nearest, NestboxFilter, NestboxRepository, RepositoryResult};
+
+// Timestamps are stored as text, "now" needs the milliseconds to compare.
+const SELECT_NESTBOXES: &str =
+    "SELECT n.id AS uuid, n.mandants_id AS mandant_uuid, n.code, n.label, n.public,
+        n.created_at, COALESCE(n.from_date, n.created_at) AS from_date, n.until_date,
+        n.description, n.box_type, m.association_name, m.website,
+        g.id AS geolocation_uuid, g.lat, g.lng,
+        g.created_at AS geolocation_from_date, g.expired_at AS geolocation_until_date
+    FROM nestboxes n JOIN mandants m ON m.id = n.mandants_id
+        LEFT JOIN nestboxes_geolocations g ON g.id = (SELECT c.id
+            FROM nestboxes_geolocations c WHERE c.nestboxes_id = n.id
+                AND c.created_at <= strftime('%Y-%m-%d %H:%M:%f', 'now')
+                AND (c.expired_at IS NULL OR c.expired_at > strftime('%Y-%m-%d %H:%M:%f', 'now'))
+            ORDER BY c.created_at DESC LIMIT 1)";
+
+// Parameters $1 to $7 are bound by bind_filter, a NULL disables the criteria.
+const FILTER_NESTBOXES: &str = "n.mandants_id = $1
+    AND ($2 IS NULL OR n.public = $2)
+    AND ($3 IS NULL OR (n.until_date IS NOT NULL) = $3)
+    AND ($4 IS NULL OR (SELECT CAST(strftime('%Y', MAX(b.created_at)) AS INTEGER)
+        FROM breeds b WHERE b.nestboxes_id = n.id) = $4)
+    AND ($5 IS NULL OR EXISTS(SELECT 1 FROM breeds b
+        WHERE b.nestboxes_id = n.id AND b.birds_id = $5))
+    AND ($6 IS NULL OR NOT EXISTS(SELECT 1 FROM breeds b
+        WHERE b.nestboxes_id = n.id AND b.created_at >= $6))
+    AND ($7 IS NULL OR instr(lower(n.label), lower($7)) > 0)";
+
+fn bind_filter<'q>(
+    query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    mandant_uuid: &'q str,
+    filter: &'q NestboxFilter,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    query
+        .bind(mandant_uuid)
+        .bind(filter.public)
+        .bind(filter.retired)
+        .bind(filter.last_breed_year)
+        .bind(filter.bird_uuid.as_deref())
+        .bind(filter.not_inspected_since.as_ref().map(to_naive_date))
+        .bind(filter.label.as_deref())
+}
+
+impl SqliteRepository {
+    async fn nestbox_doc(&self, r: &SqliteRow) -> RepositoryResult<Document> {
+        let uuid: String = r.try_get("uuid")?;
+        let images: Vec<String> = sqlx::query_scalar(
+            "SELECT file_name FROM nestboxes_images WHERE nestboxes_id = $1 ORDER BY created_at",
+        )
+        .bind(&uuid)
+        .fetch_all(&self.pool)
+        .await?;
+        let mandant_uuid: String = r.try_get("mandant_uuid")?;
+        let mut nestbox = doc! {
+            "public": r.try_get::<bool, _>("public")?,
+            "uuid": &uuid,
+            "mandant_uuid": &mandant_uuid,
+            "created_at": to_bson_date(r.try_get::<NaiveDateTime, _>("created_at")?),
+            "from_date": to_bson_date(r.try_get::<NaiveDateTime, _>("from_date")?),
+            "description": r.try_get::<String, _>("description")?,
+            "box_type": r.try_get::<String, _>("box_type")?,
+            "images": images,
+            "mandant": [{
+                "uuid": &mandant_uuid,
+                "name": r.try_get::<String, _>("association_name")?,
+                "website": r.try_get::<String, _>("website")?}]
+        };
+        if let Some(until_date) = r.try_get::<Option<NaiveDateTime>, _>("until_date")? {
+            nestbox.insert("until_date", to_bson_date(until_date));
+        }
+        if let Some(code) = r.try_get::<Option<String>, _>("code")? {
+            nestbox.insert("code", code);
+        }
+        if let Some(label) = r.try_get::<Option<String>, _>("label")? {
+            nestbox.insert("label", label);
+        }
+        let mut geolocation: Vec<Document> = Vec::new();
+        if let Some(geolocation_uuid) = r.try_get::<Option<String>, _>("geolocation_uuid")? {
+            geolocation.push(to_geolocation_doc(
+                geolocation_uuid,
+                uuid.clone(),
+                r.try_get("lat")?,
+                r.try_get("lng")?,
+                r.try_get::<NaiveDateTime, _>("geolocation_from_date")?,
+                r.try_get::<Option<NaiveDateTime>, _>("geolocation_until_date")?,
+            ));
+        }
+        nestbox.insert("geolocation", geolocation);
+        Ok(nestbox)
+    }
+}
+
+#[async_trait]
+impl NestboxRepository for SqliteRepository {
+    async fn find_by_uuid(&self, uuid: &str) -> RepositoryResult<Option<Document>> {
+        let row = sqlx::query(&format!("{} WHERE n.id = $1", SELECT_NESTBOXES))
+            .bind(uuid)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(r) => Ok(Some(self.nestbox_doc(&r).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn find_by_code(&self, code: &str) -> RepositoryResult<Option<Document>> {
+        let row = sqlx::query(&format!("{} WHERE n.code = $1", SELECT_NESTBOXES))
+            .bind(code)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(r) => Ok(Some(self.nestbox_doc(&r).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn find_uuid_by_label(
+        &self,
+        mandant_uuid: &str,
+        label: &str,
+    ) -> RepositoryResult<Option<String>> {
+        Ok(sqlx::query_scalar(
+            "SELECT id FROM nestboxes WHERE mandants_id = $1 AND lower(label) = lower($2)",
+        )
+        .bind(mandant_uuid)
+        .bind(label)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn find_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        filter: &NestboxFilter,
+        paging: &PagingQuery,
+    ) -> RepositoryResult<Vec<Document>> {
+        let rows = bind_filter(
+            sqlx::query(&format!(
+                "{} WHERE {} ORDER BY n.created_at, n.id LIMIT $8 OFFSET $9",
+                SELECT_NESTBOXES, FILTER_NESTBOXES
+            )),
+            mandant_uuid,
+            filter,
+        )
+        .bind(paging.page_limit)
+        .bind(paging.page_limit * (paging.page_number - 1))
+        .fetch_all(&self.pool)
+        .await?;
+        let mut nestboxes = Vec::with_capacity(rows.len());
+        for r in rows.iter() {
+            nestboxes.push(self.nestbox_doc(r).await?);
+        }
+        Ok(nestboxes)
+    }
+
+    async fn count_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        filter: &NestboxFilter,
+    ) -> RepositoryResult<u64> {
+        let row = bind_filter(
+            sqlx::query(&format!(
+                "SELECT COUNT(*) AS counted FROM nestboxes n WHERE {}",
+                FILTER_NESTBOXES
+            )),
+            mandant_uuid,
+            filter,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.try_get::<i64, _>("counted")? as u64)
+    }
+
+    async fn find_by_uuid_and_mandant_uuid(
+        &self,
+        uuid: &str,
+        mandant_uuid: &str,
+    ) -> RepositoryResult<Option<Document>> {
+        let row = sqlx::query(
+            "SELECT id AS uuid, mandants_id AS mandant_uuid, public, created_at
+            FROM nestboxes WHERE id = $1 AND mandants_id = $2",
+        )
+        .bind(uuid)
+        .bind(mandant_uuid)
+        .fetch_optional(&self.pool)
+        .await?;
+        match row {
+            Some(r) => Ok(Some(doc! {
+                "public": r.try_get::<bool, _>("public")?,
+                "uuid": r.try_get::<String, _>("uuid")?,
+                "mandant_uuid": r.try_get::<String, _>("mandant_uuid")?,
+                "created_at": to_bson_date(r.try_get::<NaiveDateTime, _>("created_at")?),
+            })),
+            None => Ok(None),
+        }
+    }
+
+    async fn append_image(&self, uuid: &str, image: &str) -> RepositoryResult<()> {
+        sqlx::query(
+            "INSERT INTO nestboxes_images (nestboxes_id, file_name) VALUES ($1, $2)
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(uuid)
+        .bind(image)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+    async fn insert_many(&self, nestboxes: &[Document]) -> RepositoryResult<()> {
+        let mut tx = self.pool.begin().await?;
+        for nestbox in nestboxes {
+            let created_at = nestbox
+                .get_datetime("created_at")
+                .map(to_naive_date)
+                .unwrap_or_else(|_| Utc::now().naive_utc());
+            sqlx::query(
+                "INSERT INTO nestboxes (id, mandants_id, public, created_at, from_date,
+                    description, box_type, code, label)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+            )
+            .bind(nestbox.get_str("uuid").unwrap_or_default())
+            .bind(nestbox.get_str("mandant_uuid").unwrap_or_default())
+            .bind(nestbox.get_bool("public").unwrap_or(true))
+            .bind(created_at)
+            .bind(
+                nestbox
+                    .get_datetime("from_date")
+                    .map(to_naive_date)
+                    .unwrap_or(created_at),
+            )
+            .bind(nestbox.get_str("description").unwrap_or_default())
+            .bind(nestbox.get_str("box_type").unwrap_or_default())
+            .bind(nestbox.get_str("code").ok())
+            .bind(nestbox.get_str("label").ok())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn update(&self, uuid: &str, nestbox: &Document) -> RepositoryResult<bool> {
+        // Attributes missing in the document keep their value.
+        let result = sqlx::query(
+            "UPDATE nestboxes SET public = COALESCE($2, public),
+                description = COALESCE($3, description), box_type = COALESCE($4, box_type),
+                label = CASE WHEN $5 THEN $6 ELSE label END
+            WHERE id = $1",
+        )
+        .bind(uuid)
+        .bind(nestbox.get_bool("public").ok())
+        .bind(nestbox.get_str("description").ok())
+        .bind(nestbox.get_str("box_type").ok())
+        .bind(nestbox.contains_key("label"))
+        .bind(nestbox.get_str("label").ok())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn retire(&self, uuid: &str, until_date: &DateTime) -> RepositoryResult<bool> {
+        let result =
+            sqlx::query("UPDATE nestboxes SET until_date = COALESCE(until_date, $2) WHERE id = $1")
+                .bind(uuid)
+                .bind(to_naive_date(until_date))
+                .execute(&self.pool)
+                .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find_uuids_without_code(&self) -> RepositoryResult<Vec<String>> {
+        Ok(
+            sqlx::query_scalar("SELECT id FROM nestboxes WHERE code IS NULL")
+                .fetch_all(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn set_code(&self, uuid: &str, code: &str) -> RepositoryResult<bool> {
+        let result = sqlx::query("UPDATE nestboxes SET code = $2 WHERE id = $1 AND code IS NULL")
+            .bind(uuid)
+            .bind(code)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find_near(
+        &self,
+        mandant_uuid: Option<&str>,
+        lat: f64,
+        long: f64,
+        radius: f64,
+        limit: i64,
+    ) -> RepositoryResult<Vec<Document>> {
+        let (min_lat, max_lat, min_lng, max_lng) = bounding_box(lat, long, radius);
+        let rows = sqlx::query(&format!(
+            "{} WHERE n.until_date IS NULL
+                AND (n.mandants_id = $1 OR ($1 IS NULL AND n.public))
+                AND g.lat BETWEEN $2 AND $3 AND g.lng BETWEEN $4 AND $5",
+            SELECT_NESTBOXES
+        ))
+        .bind(mandant_uuid)
+        .bind(min_lat)
+        .bind(max_lat)
+        .bind(min_lng)
+        .bind(max_lng)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut nestboxes = Vec::new();
+        for r in rows.iter() {
+            nestboxes.push(self.nestbox_doc(r).await?);
+        }
+        Ok(nearest(nestboxes, lat, long, radius, limit))
+    }
+}
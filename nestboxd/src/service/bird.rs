@@ -1,66 +1,432 @@
-use mongodb::bson::{doc, Document};
+use std::collections::BTreeMap;
+use std::sync::Arc;
 
-use super::{
-    res_structs::{BirdResponse, MapDocument},
-    service_helper as sa,
-};
+use mongodb::bson::{doc, Bson, Document};
+use uuid::Uuid;
+
+use super::breed::{breed_change, BreedService};
+use super::res_structs::{BirdImportResponse, BirdMergeResponse, BirdResponse, MapDocument};
+use crate::controller::req_structs::{BirdDataReq, BirdReq};
 use crate::controller::utilities::{PagingQuery, SessionObject};
-use mongodb::{error::Error, Collection, Database};
+use crate::controller::validator::Validator;
+use crate::repository::{BirdRepository, RepositoryError};
+
+pub enum BirdSaveResult {
+    Ok(BirdResponse),
+    NotFound,
+    NameTaken,
+    Error,
+}
+
+pub enum BirdMergeResult {
+    Ok(BirdMergeResponse),
+    NotFound,
+    OtherMandant,
+    Error,
+}
+
+pub enum BirdDeleteResult {
+    Ok,
+    NotFound,
+    InUse,
+    Error,
+}
+
+/// Name of the bird in the first of the languages there is one for. A
+/// language matches the exact tag, then its primary language and then any
+/// region of it, so "en-gb" finds "en-gb", then "en" and then "en-us".
+pub fn localized_name<'a>(
+    names: &'a BTreeMap<String, String>,
+    languages: &[String],
+) -> Option<&'a String> {
+    languages.iter().find_map(|language| {
+        let primary = language.split('-').next().unwrap_or_default();
+        names
+            .get(language)
+            .or_else(|| names.get(primary))
+            .or_else(|| {
+                names
+                    .iter()
+                    .find(|(l, _)| l.split('-').next() == Some(primary))
+                    .map(|(_, n)| n)
+            })
+    })
+}
+
+/// Bird document of the request, optional fields only if given.
+fn bird_doc(bird_req: &BirdDataReq) -> Document {
+    let mut bird = doc! {"bird": bird_req.bird.trim()};
+    let scientific_name = bird_req.scientific_name.trim();
+    if !scientific_name.is_empty() {
+        bird.insert("scientific_name", scientific_name);
+    }
+    let taxon_key = bird_req.taxon_key.trim();
+    if !taxon_key.is_empty() {
+        bird.insert("taxon_key", taxon_key);
+    }
+    let bird_website = bird_req.bird_website.trim();
+    if !bird_website.is_empty() {
+        bird.insert("bird_website", bird_website);
+    }
+    bird.insert(
+        "names",
+        bird_req
+            .names
+            .iter()
+            .map(|(l, n)| (l.to_lowercase(), Bson::String(n.trim().to_string())))
+            .collect::<Document>(),
+    );
+    bird
+}
+
+/// Species of a list trimmed, with lower case languages and named in the
+/// first of the languages if the list has no name of the catalog.
+fn normalized_species(species: &BirdDataReq, languages: &[String]) -> BirdDataReq {
+    let names: BTreeMap<String, String> = species
+        .names
+        .iter()
+        .map(|(l, n)| (l.to_lowercase(), n.trim().to_string()))
+        .collect();
+    let mut bird = species.bird.trim().to_string();
+    if bird.is_empty() {
+        bird = localized_name(&names, languages)
+            .cloned()
+            .unwrap_or_else(|| species.scientific_name.trim().to_string());
+    }
+    BirdDataReq {
+        bird,
+        scientific_name: species.scientific_name.trim().to_string(),
+        taxon_key: species.taxon_key.trim().to_string(),
+        names,
+        bird_website: species.bird_website.trim().to_string(),
+    }
+}
 
-const BIRDS: &str = "birds";
+/// Index of the bird the species is, by taxonomic key, scientific name or
+/// else name regardless of case.
+fn same_species(birds: &[BirdResponse], species: &BirdDataReq) -> Option<usize> {
+    let same = |a: &str, b: &str| !a.is_empty() && a.to_lowercase() == b.to_lowercase();
+    birds
+        .iter()
+        .position(|b| !b.taxon_key.is_empty() && b.taxon_key == species.taxon_key)
+        .or_else(|| {
+            birds
+                .iter()
+                .position(|b| same(&b.scientific_name, &species.scientific_name))
+        })
+        .or_else(|| birds.iter().position(|b| same(&b.bird, &species.bird)))
+}
+
+/// The bird completed by the species, it keeps its name and the names of
+/// languages the species has none for.
+fn merged_species(bird: &BirdResponse, species: &BirdDataReq) -> BirdDataReq {
+    let or = |new: &str, old: &str| {
+        if new.is_empty() {
+            old.to_string()
+        } else {
+            new.to_string()
+        }
+    };
+    let mut names = bird.names.clone();
+    names.extend(species.names.clone());
+    BirdDataReq {
+        bird: bird.bird.clone(),
+        scientific_name: or(&species.scientific_name, &bird.scientific_name),
+        taxon_key: or(&species.taxon_key, &bird.taxon_key),
+        names,
+        bird_website: or(&species.bird_website, &bird.bird_website),
+    }
+}
 
 #[derive(Clone)]
 pub struct BirdService {
-    collection: Collection<Document>,
+    repository: Arc<dyn BirdRepository>,
+    breed: BreedService,
 }
 
 impl BirdService {
-    pub fn new(db: &Database) -> Self {
-        BirdService {
-            collection: db.collection(BIRDS),
-        }
+    pub fn new(repository: Arc<dyn BirdRepository>, breed: BreedService) -> Self {
+        BirdService { repository, breed }
     }
 
+    /// Birds of the mandant named in the first of the languages they have a
+    /// name for.
     pub async fn get_by_mandant_uuid(
         &self,
         session_obj: &SessionObject,
         paging: &PagingQuery,
+        languages: &[String],
     ) -> (Vec<BirdResponse>, i64) {
-        let res = self
-            .collection
-            .aggregate(
-                vec![
-                    doc! {"$match": {"mandant_uuid": {"$eq": session_obj.get_mandant_uuid()}}},
-                    doc! { "$sort" : { "bird" : 1} },
-                    doc! {"$skip": (paging.page_limit * (paging.page_number -1))},
-                    doc! {"$limit": paging.page_limit},
-                    doc! {"$project": {"_id": 0, "mandant_uuid": 0}},
-                ],
-                None,
-            )
-            .await;
-        let counted_documents_res = self.get_by_mandant_uuid_count(session_obj).await;
-
-        let documents = sa::read_mongodb_cursor(res).await;
-        let counted_documents = match counted_documents_res {
-            Ok(i) => i,
-            Err(_e) => 0,
-        };
+        let documents = self
+            .repository
+            .find_by_mandant_uuid(session_obj.get_mandant_uuid(), paging)
+            .await
+            .unwrap_or_default();
+        let counted_documents = self
+            .repository
+            .count_by_mandant_uuid(session_obj.get_mandant_uuid())
+            .await
+            .unwrap_or(0);
 
         let mut bird_documents: Vec<BirdResponse> = Vec::new();
         for bird in documents {
-            bird_documents.push(BirdResponse::map_doc(&bird));
+            let mut bird = BirdResponse::map_doc(&bird);
+            if let Some(name) = localized_name(&bird.names, languages) {
+                bird.bird = name.clone();
+            }
+            bird_documents.push(bird);
         }
 
         (bird_documents, counted_documents as i64)
     }
 
-    pub async fn get_by_mandant_uuid_count(
+    /// Bird and the mandant it belongs to.
+    pub async fn get_by_uuid(
+        &self,
+        uuid: &str,
+    ) -> Result<Option<(BirdResponse, String)>, RepositoryError> {
+        Ok(self.repository.find_by_uuid(uuid).await?.map(|b| {
+            let mandant_uuid = b.get_str("mandant_uuid").unwrap_or_default().to_string();
+            (BirdResponse::map_doc(&b), mandant_uuid)
+        }))
+    }
+
+    /// Bird of a new breed within the mandant of the session. None if the
+    /// uuid is not one of a bird of the mandant. A name is looked up and
+    /// added to the birds of the mandant if it is new.
+    pub async fn resolve(
         &self,
         session_obj: &SessionObject,
-    ) -> Result<u64, Error> {
-        self.collection
-            .count_documents(doc! {"mandant_uuid": session_obj.get_mandant_uuid()}, None)
-            .await
+        bird_req: &BirdReq,
+    ) -> Result<Option<BirdResponse>, RepositoryError> {
+        let mandant_uuid = session_obj.get_mandant_uuid();
+        if !bird_req.bird_uuid.is_empty() {
+            return Ok(self
+                .get_by_uuid(&bird_req.bird_uuid)
+                .await?
+                .filter(|(_, m)| m == mandant_uuid)
+                .map(|(b, _)| b));
+        }
+        let name = bird_req.bird.trim();
+        if let Some(uuid) = self
+            .repository
+            .find_uuid_by_name(mandant_uuid, name)
+            .await?
+        {
+            return Ok(self.get_by_uuid(&uuid).await?.map(|(b, _)| b));
+        }
+        Ok(Some(self.insert(mandant_uuid, doc! {"bird": name}).await?))
+    }
+
+    async fn insert(
+        &self,
+        mandant_uuid: &str,
+        mut bird: Document,
+    ) -> Result<BirdResponse, RepositoryError> {
+        bird.insert("uuid", Uuid::new_v4().to_string());
+        bird.insert("mandant_uuid", mandant_uuid);
+        self.repository.insert(&bird).await?;
+        Ok(BirdResponse::map_doc(&bird))
+    }
+
+    /// Whether another bird of the mandant has the name or taxonomic key of
+    /// the request.
+    async fn is_taken(
+        &self,
+        mandant_uuid: &str,
+        uuid: Option<&str>,
+        bird_req: &BirdDataReq,
+    ) -> Result<bool, RepositoryError> {
+        let other = |u: &String| Some(u.as_str()) != uuid;
+        if let Some(u) = self
+            .repository
+            .find_uuid_by_name(mandant_uuid, bird_req.bird.trim())
+            .await?
+        {
+            if other(&u) {
+                return Ok(true);
+            }
+        }
+        let taxon_key = bird_req.taxon_key.trim();
+        if taxon_key.is_empty() {
+            return Ok(false);
+        }
+        Ok(self
+            .repository
+            .find_uuid_by_taxon_key(mandant_uuid, taxon_key)
+            .await?
+            .is_some_and(|u| other(&u)))
+    }
+
+    /// Adds a bird to the mandant of the session, names are unique within
+    /// the mandant regardless of case and so are taxonomic keys.
+    pub async fn create(
+        &self,
+        session_obj: &SessionObject,
+        bird_req: &BirdDataReq,
+    ) -> BirdSaveResult {
+        let mandant_uuid = session_obj.get_mandant_uuid();
+        match self.is_taken(mandant_uuid, None, bird_req).await {
+            Ok(false) => {}
+            Ok(true) => return BirdSaveResult::NameTaken,
+            Err(_e) => return BirdSaveResult::Error,
+        }
+        match self.insert(mandant_uuid, bird_doc(bird_req)).await {
+            Ok(b) => BirdSaveResult::Ok(b),
+            Err(_e) => BirdSaveResult::Error,
+        }
+    }
+
+    /// Replaces name, scientific name, taxonomic key and names of the bird of
+    /// the mandant.
+    pub async fn update(
+        &self,
+        mandant_uuid: &str,
+        uuid: &str,
+        bird_req: &BirdDataReq,
+    ) -> BirdSaveResult {
+        match self.is_taken(mandant_uuid, Some(uuid), bird_req).await {
+            Ok(false) => {}
+            Ok(true) => return BirdSaveResult::NameTaken,
+            Err(_e) => return BirdSaveResult::Error,
+        }
+        match self.repository.update(uuid, &bird_doc(bird_req)).await {
+            Ok(true) => match self.get_by_uuid(uuid).await {
+                Ok(Some((b, _))) => BirdSaveResult::Ok(b),
+                Ok(None) => BirdSaveResult::NotFound,
+                Err(_e) => BirdSaveResult::Error,
+            },
+            Ok(false) => BirdSaveResult::NotFound,
+            Err(_e) => BirdSaveResult::Error,
+        }
+    }
+
+    /// Adds the species of the list to the birds of the mandant and completes
+    /// the birds they already are. Species naming another one by its
+    /// scientific name are rejected, as are invalid ones.
+    pub async fn import(
+        &self,
+        mandant_uuid: &str,
+        species: &[BirdDataReq],
+        languages: &[String],
+    ) -> Result<BirdImportResponse, RepositoryError> {
+        let counted = self.repository.count_by_mandant_uuid(mandant_uuid).await?;
+        let all = PagingQuery {
+            page_limit: counted.max(1) as i64,
+            page_number: 1,
+        };
+        let mut birds: Vec<BirdResponse> = self
+            .repository
+            .find_by_mandant_uuid(mandant_uuid, &all)
+            .await?
+            .iter()
+            .map(BirdResponse::map_doc)
+            .collect();
+        let mut result = BirdImportResponse::default();
+        for (i, species) in species.iter().enumerate() {
+            let species = normalized_species(species, languages);
+            if !species.is_valid() {
+                result.rejected.push(i + 1);
+                continue;
+            }
+            let Some(position) = same_species(&birds, &species) else {
+                let bird = self.insert(mandant_uuid, bird_doc(&species)).await?;
+                birds.push(bird);
+                result.created += 1;
+                continue;
+            };
+            let bird = &birds[position];
+            if !bird.scientific_name.is_empty()
+                && !species.scientific_name.is_empty()
+                && bird.scientific_name.to_lowercase() != species.scientific_name.to_lowercase()
+            {
+                result.rejected.push(i + 1);
+                continue;
+            }
+            let merged = merged_species(bird, &species);
+            if merged.scientific_name == bird.scientific_name
+                && merged.taxon_key == bird.taxon_key
+                && merged.bird_website == bird.bird_website
+                && merged.names == bird.names
+            {
+                result.unchanged += 1;
+                continue;
+            }
+            let mut merged_bird = bird_doc(&merged);
+            self.repository.update(&bird.uuid, &merged_bird).await?;
+            merged_bird.insert("uuid", &bird.uuid);
+            birds[position] = BirdResponse::map_doc(&merged_bird);
+            result.updated += 1;
+        }
+        Ok(result)
+    }
+
+    /// Merges a duplicate bird of the mandant into the bird `into_uuid` of
+    /// the same mandant. Its breeds then refer to that bird, each kept as a
+    /// version with the change "merge", and it is deleted. The versions from
+    /// before keep the merged bird.
+    pub async fn merge(
+        &self,
+        session_obj: &SessionObject,
+        mandant_uuid: &str,
+        uuid: &str,
+        into_uuid: &str,
+    ) -> BirdMergeResult {
+        let bird = match self.get_by_uuid(into_uuid).await {
+            Ok(Some((b, m))) if m == mandant_uuid && uuid != into_uuid => b,
+            Ok(_) => return BirdMergeResult::OtherMandant,
+            Err(_e) => return BirdMergeResult::Error,
+        };
+        let change = breed_change(session_obj, "merge");
+        match self.repository.merge(uuid, into_uuid, &change).await {
+            Ok(Some(breeds)) => BirdMergeResult::Ok(BirdMergeResponse { bird, breeds }),
+            Ok(None) => BirdMergeResult::NotFound,
+            Err(_e) => BirdMergeResult::Error,
+        }
+    }
+
+    /// Deletes the bird unless breeds or breed versions refer to it, the
+    /// history of a breed must keep telling its bird.
+    pub async fn delete(&self, uuid: &str) -> BirdDeleteResult {
+        match (
+            self.breed.count_by_bird_uuid(uuid).await,
+            self.breed.count_versions_by_bird_uuid(uuid).await,
+        ) {
+            (Ok(0), Ok(0)) => {}
+            (Ok(_), Ok(_)) => return BirdDeleteResult::InUse,
+            _ => return BirdDeleteResult::Error,
+        }
+        match self.repository.delete(uuid).await {
+            Ok(true) => BirdDeleteResult::Ok,
+            Ok(false) => BirdDeleteResult::NotFound,
+            Err(_e) => BirdDeleteResult::Error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_localized_name() {
+        let names: BTreeMap<String, String> = [
+            ("de", "Kohlmeise"),
+            ("de-ch", "Chohlmeisi"),
+            ("en-us", "Great tit"),
+        ]
+        .iter()
+        .map(|(l, n)| (l.to_string(), n.to_string()))
+        .collect();
+        let name = |languages: &[&str]| {
+            let languages: Vec<String> = languages.iter().map(|l| l.to_string()).collect();
+            localized_name(&names, &languages).cloned()
+        };
+        assert_eq!(name(&["de-ch", "en"]).as_deref(), Some("Chohlmeisi"));
+        assert_eq!(name(&["en-gb", "de"]).as_deref(), Some("Great tit"));
+        assert_eq!(name(&["de-at"]).as_deref(), Some("Kohlmeise"));
+        assert_eq!(name(&["fr", "en"]).as_deref(), Some("Great tit"));
+        assert_eq!(name(&["fr"]), None);
+        assert_eq!(name(&[]), None);
     }
 }
@@ -1,25 +1,103 @@
+use std::sync::Arc;
+
 use mongodb::bson::{doc, DateTime, Document};
 //use chrono::Utc;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::res_structs::{BreedResponse, MapDocument};
-use super::service_helper as sa;
-use crate::controller::{req_structs::BirdReq, utilities::PagingQuery};
+use super::res_structs::{BreedResponse, BreedVersionResponse, MapDocument};
+use crate::controller::req_structs::{BreedObservationReq, BreedUpdateReq};
+use crate::controller::utilities::PagingQuery;
 use crate::controller::{req_structs::NestboxReq, utilities::SessionObject};
-use mongodb::{error::Error, Collection, Database};
+use crate::repository::{BreedRepository, RepositoryError};
 
-const BREEDS: &str = "breeds";
+/// State of the nest when the breed was discovered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NestState {
+    Building,
+    Eggs,
+    Nestlings,
+    Fledged,
+    Abandoned,
+    Predated,
+}
+
+impl NestState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NestState::Building => "building",
+            NestState::Eggs => "eggs",
+            NestState::Nestlings => "nestlings",
+            NestState::Fledged => "fledged",
+            NestState::Abandoned => "abandoned",
+            NestState::Predated => "predated",
+        }
+    }
+}
+
+/// How sure the cleaner is about the bird, often only the nest is left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    Certain,
+    Probable,
+    Possible,
+}
+
+impl Confidence {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Confidence::Certain => "certain",
+            Confidence::Probable => "probable",
+            Confidence::Possible => "possible",
+        }
+    }
+}
+
+/// The observed fields of `observation`, the discovery date is left to the
+/// caller.
+fn observation_doc(observation: &BreedObservationReq) -> Document {
+    let mut breed = Document::new();
+    for (key, count) in [
+        ("clutch_size", observation.clutch_size),
+        ("hatched", observation.hatched),
+        ("fledged", observation.fledged),
+    ] {
+        if let Some(count) = count {
+            breed.insert(key, count as i32);
+        }
+    }
+    if let Some(nest_state) = observation.nest_state {
+        breed.insert("nest_state", nest_state.as_str());
+    }
+    if let Some(confidence) = observation.confidence {
+        breed.insert("confidence", confidence.as_str());
+    }
+    let notes = observation.notes.trim();
+    if !notes.is_empty() {
+        breed.insert("notes", notes);
+    }
+    breed
+}
+
+/// Who changed a breed when, kept with its previous version.
+pub(crate) fn breed_change(session_obj: &SessionObject, change: &str) -> Document {
+    doc! {
+    "uuid": Uuid::new_v4().to_string(),
+    "changed_by": session_obj.get_user_uuid(),
+    "changed_at": DateTime::now(),
+    "change": change}
+}
 
 #[derive(Clone)]
 pub struct BreedService {
-    collection: Collection<Document>,
+    repository: Arc<dyn BreedRepository>,
 }
 
 impl BreedService {
-    pub fn new(db: &Database) -> Self {
-        BreedService {
-            collection: db.collection(BREEDS),
-        }
+    pub fn new(repository: Arc<dyn BreedRepository>) -> Self {
+        BreedService { repository }
     }
 
     pub async fn get_by_nestbox_uuid(
@@ -28,97 +106,147 @@ impl BreedService {
         req: &NestboxReq,
         paging: &PagingQuery,
     ) -> (Vec<BreedResponse>, i64) {
-        let mut projection =
-            doc! {"$project": {"_id": 0, "mandant_uuid": 0, "user_uuid": 0, "bird_uuid": 0}};
-        if session_obj.is_valid_session() {
-            projection = doc! {"$project": {"_id": 0, "mandant_uuid": 0, "bird_uuid": 0}};
-        }
-        let res = self
-            .collection
-            .aggregate(
-                vec![
-                    doc! {"$match": {"nestbox_uuid": {"$eq": &req.uuid}}},
-                    doc! {"$skip": (paging.page_limit * (paging.page_number -1))},
-                    doc! {"$limit": paging.page_limit},
-                    doc! {"$lookup": {
-                    "from": "birds",
-                    "let": {
-                      "breeds_bird_uuid": "$bird_uuid" },
-                    "pipeline":[
-                      {
-                        "$match": {
-                          "$expr": {
-                            "$eq": [
-                              "$$breeds_bird_uuid", "$uuid"
-                            ]
-                          }
-                        }
-                      },
-                      {
-                        "$project": {
-                           "_id":0, "uuid": 1, "bird": 1
-                        }
-                      }
-                    ], "as": "bird"}},
-                    projection,
-                ],
-                None,
-            )
-            .await;
-        let counted_documents_res = self.get_by_nestbox_count(&req.uuid).await;
-
-        let documents = sa::read_mongodb_cursor(res).await;
+        let documents = self
+            .repository
+            .find_by_nestbox_uuid(&req.uuid, paging)
+            .await
+            .unwrap_or_default();
+        let counted_documents = self
+            .repository
+            .count_by_nestbox_uuid(&req.uuid)
+            .await
+            .unwrap_or(0);
 
         let mut breed_responses: Vec<BreedResponse> = Vec::new();
         for d in documents {
-            breed_responses.push(BreedResponse::map_doc(&d));
+            let mut breed = BreedResponse::map_doc(&d);
+            // Who discovered a breed is only revealed to authenticated users.
+            if !session_obj.is_valid_session() {
+                breed.user_uuid = String::new();
+            }
+            breed_responses.push(breed);
         }
 
-        let counted_documents = match counted_documents_res {
-            Ok(i) => i,
-            Err(_e) => 0,
-        };
-
         (breed_responses, counted_documents as i64)
     }
 
-    pub async fn get_by_nestbox_count(&self, nestbox_uuid: &str) -> Result<u64, Error> {
-        self.collection
-            .count_documents(doc! {"nestbox_uuid": nestbox_uuid}, None)
-            .await
+    pub async fn count_by_bird_uuid(&self, bird_uuid: &str) -> Result<u64, RepositoryError> {
+        self.repository.count_by_bird_uuid(bird_uuid).await
+    }
+
+    pub async fn count_versions_by_bird_uuid(
+        &self,
+        bird_uuid: &str,
+    ) -> Result<u64, RepositoryError> {
+        self.repository.count_versions_by_bird_uuid(bird_uuid).await
+    }
+
+    /// Latest breed of every nestbox of the mandant which had one.
+    pub async fn get_latest_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+    ) -> Result<Vec<BreedResponse>, RepositoryError> {
+        Ok(self
+            .repository
+            .find_latest_by_mandant_uuid(mandant_uuid)
+            .await?
+            .iter()
+            .map(BreedResponse::map_doc)
+            .collect())
     }
 
     pub async fn post_breed(
         &self,
         session_obj: &SessionObject,
         nestbox_req: &NestboxReq,
-        bird: &BirdReq,
-    ) -> std::result::Result<BreedResponse, Error> {
-        let breed = doc! {
+        bird_uuid: &str,
+        observation: &BreedObservationReq,
+    ) -> std::result::Result<BreedResponse, RepositoryError> {
+        // Paper records are backfilled with the date they were taken.
+        let mut breed = doc! {
         "uuid": Uuid::new_v4().to_string(),
         "nestbox_uuid": &nestbox_req.uuid,
         "user_uuid": session_obj.get_user_uuid(),
-        "discovery_date": DateTime::now(),
-        "bird_uuid": &bird.bird_uuid};
-        match self.collection.insert_one(&breed, None).await {
-            Ok(_o) => Ok(BreedResponse::map_doc(&breed)),
-            Err(e) => Err(e),
+        "discovery_date": observation.discovery_date().unwrap_or_else(DateTime::now),
+        "bird_uuid": bird_uuid};
+        breed.extend(observation_doc(observation));
+        self.repository.insert(&breed).await?;
+        Ok(BreedResponse::map_doc(&breed))
+    }
+
+    pub async fn get_by_uuid(&self, uuid: &str) -> Result<Option<BreedResponse>, RepositoryError> {
+        Ok(self
+            .repository
+            .find_by_uuid(uuid)
+            .await?
+            .as_ref()
+            .map(BreedResponse::map_doc))
+    }
+
+    /// Moves the breed to another nestbox or bird and replaces its
+    /// observations, the discovery date only if one is given. None if there
+    /// is no such breed. The breed as it was before is kept as a version.
+    pub async fn put_breed(
+        &self,
+        session_obj: &SessionObject,
+        uuid: &str,
+        breed_req: &BreedUpdateReq,
+    ) -> Result<Option<BreedResponse>, RepositoryError> {
+        let mut breed = doc! {
+        "uuid": uuid,
+        "nestbox_uuid": &breed_req.nestbox_uuid,
+        "bird_uuid": &breed_req.bird_uuid};
+        if let Some(discovery_date) = breed_req.observation.discovery_date() {
+            breed.insert("discovery_date", discovery_date);
         }
+        breed.extend(observation_doc(&breed_req.observation));
+        if !self
+            .repository
+            .update(&breed, &breed_change(session_obj, "update"))
+            .await?
+        {
+            return Ok(None);
+        }
+        self.get_by_uuid(uuid).await
+    }
+
+    /// Deletes the breed, which is kept as a version. False if there is no
+    /// such breed.
+    pub async fn delete_breed(
+        &self,
+        session_obj: &SessionObject,
+        uuid: &str,
+    ) -> Result<bool, RepositoryError> {
+        self.repository
+            .delete(uuid, &breed_change(session_obj, "delete"))
+            .await
+    }
+
+    pub async fn get_versions(
+        &self,
+        uuid: &str,
+    ) -> Result<Vec<BreedVersionResponse>, RepositoryError> {
+        Ok(self
+            .repository
+            .find_versions(uuid)
+            .await?
+            .iter()
+            .map(BreedVersionResponse::map_doc)
+            .collect())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use mongodb::{options::ClientOptions, Client};
+    use crate::repository::memory::fixtures;
 
     // Nestbox from mandant_uuid 4ac9971c-91de-455c-a1fd-4b9dfb862cee
     const NESTBOX_UUID_OK: &str = "a446545d-f594-4eb5-96b4-c2312554050c";
 
     #[actix_rt::test]
     async fn test_service_breed_get_by_nestbox_uuid() {
-        let db = fetch_db().await;
-        let breeds_service = BreedService::new(&db);
+        let breeds_service = BreedService::new(fixtures::repositories().breed);
         // Creating a mock session object
         let session_doc = doc! { "mandant_uuid" : "4ac9971c-91de-455c-a1fd-4b9dfb862cee",
         "username" : "fg_11", "uuid" : "15eaa6ca-4797-442b-b6c9-f1e7a1f3416d",
@@ -143,11 +271,25 @@ mod tests {
         assert_eq!(counted_documents, 6_i64);
     }
 
-    async fn fetch_db() -> Database {
-        let client_options_future = ClientOptions::parse("mongodb://localhost:27017");
-        let client_options = client_options_future.await.unwrap();
-        let client = Client::with_options(client_options).unwrap();
-
-        client.database("nestbox_testing")
+    #[actix_rt::test]
+    async fn test_service_breed_get_by_nestbox_uuid_hides_user_uuid() {
+        let breeds_service = BreedService::new(fixtures::repositories().breed);
+        let session = SessionObject::new(Ok(None));
+        let nestbox_req = NestboxReq {
+            uuid: String::from(NESTBOX_UUID_OK),
+        };
+        let (documents, _counted_documents) = breeds_service
+            .get_by_nestbox_uuid(
+                &session,
+                &nestbox_req,
+                &PagingQuery {
+                    page_limit: 10,
+                    page_number: 1,
+                },
+            )
+            .await;
+        assert_eq!(documents.len(), 6);
+        assert!(documents.iter().all(|b| b.user_uuid.is_empty()));
+        assert!(documents.iter().all(|b| !b.bird.is_empty()));
     }
 }
@@ -0,0 +1,378 @@
+//! Locations of the nestboxes of a mandant for desktop GIS (GeoJSON), GPS
+//! handhelds (GPX) and Google Earth (KML).
+use std::collections::HashMap;
+
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+use super::breed::BreedService;
+use super::geolocation::GeolocationService;
+use super::label::nestbox_number;
+use super::nestbox::NestboxService;
+use super::res_structs::NestboxResponse;
+use crate::controller::req_structs::NestboxesQuery;
+use crate::repository::RepositoryError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Geojson,
+    Gpx,
+    Kml,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Geojson => "application/geo+json",
+            ExportFormat::Gpx => "application/gpx+xml",
+            ExportFormat::Kml => "application/vnd.google-earth.kml+xml",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Geojson => "geojson",
+            ExportFormat::Gpx => "gpx",
+            ExportFormat::Kml => "kml",
+        }
+    }
+}
+
+/// Nestbox on the map with what was found in it last.
+struct Placemark {
+    /// Label or else the short code, as on the tag of the nestbox.
+    name: String,
+    nestbox: NestboxResponse,
+    long: f64,
+    lat: f64,
+    /// Bird of the latest breed.
+    last_breed: Option<String>,
+    /// Date of the latest breed.
+    last_inspection: Option<String>,
+}
+
+#[derive(Serialize)]
+struct FeatureCollection<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    features: Vec<Feature<'a>>,
+}
+
+#[derive(Serialize)]
+struct Feature<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    geometry: Point,
+    properties: FeatureProperties<'a>,
+}
+
+#[derive(Serialize)]
+struct Point {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    coordinates: [f64; 2],
+}
+
+#[derive(Serialize)]
+struct FeatureProperties<'a> {
+    uuid: &'a str,
+    code: &'a str,
+    name: &'a str,
+    label: &'a str,
+    last_breed: Option<&'a str>,
+    last_inspection: Option<&'a str>,
+}
+
+fn render_geojson(placemarks: &[Placemark]) -> String {
+    let collection = FeatureCollection {
+        kind: "FeatureCollection",
+        features: placemarks
+            .iter()
+            .map(|p| Feature {
+                kind: "Feature",
+                geometry: Point {
+                    kind: "Point",
+                    coordinates: [p.long, p.lat],
+                },
+                properties: FeatureProperties {
+                    uuid: &p.nestbox.uuid,
+                    code: &p.nestbox.code,
+                    name: &p.name,
+                    label: &p.nestbox.label,
+                    last_breed: p.last_breed.as_deref(),
+                    last_inspection: p.last_inspection.as_deref(),
+                },
+            })
+            .collect(),
+    };
+    serde_json::to_string(&collection).unwrap_or_default()
+}
+
+fn render_gpx(placemarks: &[Placemark]) -> String {
+    let mut gpx = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <gpx version=\"1.1\" creator=\"nestboxd\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+    );
+    for p in placemarks {
+        gpx.push_str(&format!(
+            "  <wpt lat=\"{}\" lon=\"{}\">\n    <name>{}</name>\n",
+            p.lat,
+            p.long,
+            xml_escape(&p.name)
+        ));
+        // Handhelds show the description only, so it carries the breed.
+        let desc: Vec<String> = [
+            p.last_breed.as_ref().map(|b| format!("Last breed: {}", b)),
+            p.last_inspection
+                .as_ref()
+                .map(|i| format!("Last inspection: {}", i)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !desc.is_empty() {
+            gpx.push_str(&format!(
+                "    <desc>{}</desc>\n",
+                xml_escape(&desc.join(", "))
+            ));
+        }
+        gpx.push_str("  </wpt>\n");
+    }
+    gpx.push_str("</gpx>\n");
+    gpx
+}
+
+fn render_kml(placemarks: &[Placemark]) -> String {
+    let mut kml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n",
+    );
+    for p in placemarks {
+        kml.push_str(&format!(
+            "  <Placemark>\n    <name>{}</name>\n    <ExtendedData>\n",
+            xml_escape(&p.name)
+        ));
+        for (name, value) in [
+            ("uuid", Some(p.nestbox.uuid.as_str())),
+            ("code", Some(p.nestbox.code.as_str())),
+            ("label", Some(p.nestbox.label.as_str())),
+            ("last_breed", p.last_breed.as_deref()),
+            ("last_inspection", p.last_inspection.as_deref()),
+        ] {
+            if let Some(value) = value.filter(|v| !v.is_empty()) {
+                kml.push_str(&format!(
+                    "      <Data name=\"{}\"><value>{}</value></Data>\n",
+                    name,
+                    xml_escape(value)
+                ));
+            }
+        }
+        kml.push_str(&format!(
+            "    </ExtendedData>\n    <Point><coordinates>{},{}</coordinates></Point>\n  </Placemark>\n",
+            p.long, p.lat
+        ));
+    }
+    kml.push_str("</Document>\n</kml>\n");
+    kml
+}
+
+pub(super) fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[derive(Clone)]
+pub struct ExportService {
+    nestbox: NestboxService,
+    breed: BreedService,
+    geolocation: GeolocationService,
+}
+
+impl ExportService {
+    pub fn new(
+        nestbox: NestboxService,
+        breed: BreedService,
+        geolocation: GeolocationService,
+    ) -> Self {
+        ExportService {
+            nestbox,
+            breed,
+            geolocation,
+        }
+    }
+
+    /// Nestboxes of the mandant matching the query at their current location
+    /// or the one valid at `at`, those without location are left out.
+    pub async fn export(
+        &self,
+        mandant_uuid: &str,
+        query: &NestboxesQuery,
+        at: Option<DateTime>,
+        format: ExportFormat,
+    ) -> Result<String, RepositoryError> {
+        let nestboxes = self
+            .nestbox
+            .get_all_by_mandant_uuid_filtered(mandant_uuid, query)
+            .await?;
+        let mut last_breeds: HashMap<String, _> = self
+            .breed
+            .get_latest_by_mandant_uuid(mandant_uuid)
+            .await?
+            .into_iter()
+            .map(|b| (b.nestbox_uuid.clone(), b))
+            .collect();
+        let mut placemarks = Vec::new();
+        for nestbox in nestboxes {
+            let location = match at {
+                Some(at) => self.geolocation.get_at(&nestbox.uuid, &at).await?,
+                None => nestbox.geolocation.clone(),
+            };
+            let location = match location {
+                Some(l) => l,
+                None => continue,
+            };
+            let last_breed = last_breeds.remove(&nestbox.uuid);
+            placemarks.push(Placemark {
+                name: nestbox_number(&nestbox),
+                long: location.long,
+                lat: location.lat,
+                last_breed: last_breed
+                    .as_ref()
+                    .map(|b| b.bird.clone())
+                    .filter(|b| !b.is_empty()),
+                last_inspection: last_breed.map(|b| b.discovery_date),
+                nestbox,
+            });
+        }
+        Ok(match format {
+            ExportFormat::Geojson => render_geojson(&placemarks),
+            ExportFormat::Gpx => render_gpx(&placemarks),
+            ExportFormat::Kml => render_kml(&placemarks),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::memory::fixtures::{self, MANDANT_1, NESTBOX_MANDANT_1};
+    use crate::service::service_helper::InsertResult;
+
+    fn placemarks() -> Vec<Placemark> {
+        vec![
+            Placemark {
+                name: String::from("Wald <Nord> & 17"),
+                nestbox: NestboxResponse {
+                    uuid: String::from("1bec20fc-5416-4941-b7e4-e15aa26a5c7a"),
+                    code: String::from("7k2mq9xd4hrv"),
+                    label: String::from("Wald <Nord> & 17"),
+                    ..Default::default()
+                },
+                long: 8.5,
+                lat: 47.25,
+                last_breed: Some(String::from("Kohlmeise")),
+                last_inspection: Some(String::from("2021-06-01 18:36:38.418 UTC")),
+            },
+            Placemark {
+                name: String::from("M3X8-Q2RK-9TPD"),
+                nestbox: NestboxResponse {
+                    uuid: String::from("a446545d-f594-4eb5-96b4-c2312554050c"),
+                    code: String::from("m3x8q2rk9tpd"),
+                    ..Default::default()
+                },
+                long: 8.625,
+                lat: 47.5,
+                last_breed: None,
+                last_inspection: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_render_geojson() {
+        let geojson: serde_json::Value =
+            serde_json::from_str(&render_geojson(&placemarks())).unwrap();
+        assert_eq!(geojson["type"], "FeatureCollection");
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), 2);
+        assert_eq!(features[0]["geometry"]["type"], "Point");
+        assert_eq!(
+            features[0]["geometry"]["coordinates"],
+            serde_json::json!([8.5, 47.25])
+        );
+        assert_eq!(features[0]["properties"]["last_breed"], "Kohlmeise");
+        assert_eq!(features[1]["properties"]["name"], "M3X8-Q2RK-9TPD");
+        assert!(features[1]["properties"]["last_inspection"].is_null());
+    }
+
+    #[test]
+    fn test_render_gpx() {
+        let gpx = render_gpx(&placemarks());
+        assert!(gpx.contains("xmlns=\"http://www.topografix.com/GPX/1/1\""));
+        assert!(gpx.contains("<wpt lat=\"47.25\" lon=\"8.5\">"));
+        assert!(gpx.contains("<name>Wald &lt;Nord&gt; &amp; 17</name>"));
+        assert!(gpx.contains(
+            "<desc>Last breed: Kohlmeise, Last inspection: 2021-06-01 18:36:38.418 UTC</desc>"
+        ));
+        assert_eq!(gpx.matches("<wpt ").count(), 2);
+        assert_eq!(gpx.matches("<desc>").count(), 1);
+    }
+
+    #[test]
+    fn test_render_kml() {
+        let kml = render_kml(&placemarks());
+        assert!(kml.contains("xmlns=\"http://www.opengis.net/kml/2.2\""));
+        assert!(kml.contains("<coordinates>8.625,47.5</coordinates>"));
+        assert!(kml.contains("<Data name=\"last_breed\"><value>Kohlmeise</value></Data>"));
+        assert_eq!(kml.matches("<Placemark>").count(), 2);
+        assert_eq!(kml.matches("name=\"label\"").count(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_service_export() {
+        let repositories = fixtures::repositories();
+        let geolocation = GeolocationService::new(repositories.geolocation.clone());
+        let export_service = ExportService::new(
+            NestboxService::new(repositories.nestbox.clone()),
+            BreedService::new(repositories.breed.clone()),
+            geolocation.clone(),
+        );
+        let query = NestboxesQuery::default();
+        let export = |at: Option<DateTime>| {
+            let export_service = export_service.clone();
+            let query = &query;
+            async move {
+                let geojson = export_service
+                    .export(MANDANT_1, query, at, ExportFormat::Geojson)
+                    .await
+                    .unwrap();
+                serde_json::from_str::<serde_json::Value>(&geojson).unwrap()["features"]
+                    .as_array()
+                    .unwrap()
+                    .clone()
+            }
+        };
+        // Without location there is nothing to put on the map.
+        assert!(export(None).await.is_empty());
+        assert!(matches!(
+            geolocation
+                .post_geolocation(NESTBOX_MANDANT_1, 8.5, 47.25)
+                .await,
+            InsertResult::Ok(_)
+        ));
+        let features = export(None).await;
+        assert_eq!(features.len(), 1);
+        assert_eq!(features[0]["properties"]["uuid"], NESTBOX_MANDANT_1);
+        assert_eq!(features[0]["properties"]["name"], "Box 1");
+        assert!(features[0]["properties"]["last_breed"]
+            .as_str()
+            .unwrap()
+            .starts_with("bird_"));
+        assert!(features[0]["properties"]["last_inspection"].is_string());
+        assert!(export(Some(DateTime::from_millis(0))).await.is_empty());
+    }
+}
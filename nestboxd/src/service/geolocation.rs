@@ -1,52 +1,136 @@
+use std::sync::Arc;
+
+use mongodb::bson::doc;
 use mongodb::bson::DateTime;
-use mongodb::bson::{doc, Document};
 //use chrono::{Duration, Utc};
 use std::time::{Duration, SystemTime};
 use uuid::Uuid;
 
+use super::res_structs::{GeolocationResponse, MapDocument};
 use super::service_helper::InsertResult;
-
-use mongodb::{Collection, Database};
-
-const GEOLOCATIONS: &str = "geolocations";
+use crate::controller::utilities::PagingQuery;
+use crate::repository::{GeolocationRepository, RepositoryError};
 
 #[derive(Clone)]
 pub struct GeolocationService {
-    collection: Collection<Document>,
+    repository: Arc<dyn GeolocationRepository>,
 }
 
 impl GeolocationService {
-    pub fn new(db: &Database) -> Self {
-        GeolocationService {
-            collection: db.collection(GEOLOCATIONS),
-        }
+    pub fn new(repository: Arc<dyn GeolocationRepository>) -> Self {
+        GeolocationService { repository }
     }
 
     pub async fn post_geolocation(&self, nestbox_uuid: &str, long: f32, lat: f32) -> InsertResult {
         // End current location before entering a new one.
         let now = DateTime::now();
-        match self
-            .collection
-            .update_many(
-                doc! {"nestbox_uuid": nestbox_uuid, "until_date": {"$gt": &now}},
-                doc! {"$set": {"until_date": &now}},
-                None,
-            )
+        if self
+            .repository
+            .end_current(nestbox_uuid, &now)
             .await
+            .is_err()
         {
-            Ok(_) => {}
-            Err(_) => return InsertResult::TerminationError,
-        };
+            return InsertResult::TerminationError;
+        }
         let geolocation = doc! {
         "uuid" : Uuid::new_v4().to_string(),
         "nestbox_uuid" : nestbox_uuid,
         "from_date" : &now,
         "until_date" : DateTime::from( SystemTime::now() + Duration::new(31536000000, 0)),
-        "position" : { "type" : "point", "coordinates" : [ &long, &lat ] } };
-        match self.collection.insert_one(&geolocation, None).await {
+        "position" : { "type" : "Point", "coordinates" : [ &long, &lat ] } };
+        match self.repository.insert(&geolocation).await {
             Ok(_d) => InsertResult::Ok(geolocation),
             Err(_) => InsertResult::InsertError,
         }
-        //db.geolocations.updateMany({"nestbox_uuid": "eb0c7048-2cda-471d-beb3-7777b7d54858", "until_date": {"$lt": new ISODate("2021-06-12T20:15:31Z")}}, {$set: {"until_date": new ISODate("2021-06-12T20:15:31Z")}})
+    }
+
+    /// Page of the locations the nestbox ever had, the latest first.
+    pub async fn get_by_nestbox_uuid(
+        &self,
+        nestbox_uuid: &str,
+        paging: &PagingQuery,
+    ) -> Result<(Vec<GeolocationResponse>, i64), RepositoryError> {
+        let geolocations = self
+            .repository
+            .find_by_nestbox_uuid(nestbox_uuid, paging)
+            .await?;
+        let counted_documents = self.repository.count_by_nestbox_uuid(nestbox_uuid).await?;
+        Ok((
+            geolocations
+                .iter()
+                .map(GeolocationResponse::map_doc)
+                .collect(),
+            counted_documents as i64,
+        ))
+    }
+
+    /// Where the nestbox hung at `at`, None if it was nowhere known.
+    pub async fn get_at(
+        &self,
+        nestbox_uuid: &str,
+        at: &DateTime,
+    ) -> Result<Option<GeolocationResponse>, RepositoryError> {
+        Ok(self
+            .repository
+            .find_at(nestbox_uuid, at)
+            .await?
+            .map(|g| GeolocationResponse::map_doc(&g)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::memory::fixtures::{self, NESTBOX_STRANGER};
+    use crate::service::nestbox::NestboxService;
+
+    #[actix_rt::test]
+    async fn test_service_geolocation_history() {
+        let repositories = fixtures::repositories();
+        let geolocation_service = GeolocationService::new(repositories.geolocation.clone());
+        let before = DateTime::now();
+        std::thread::sleep(Duration::from_millis(5));
+        for long in [8.5, 8.625] {
+            assert!(matches!(
+                geolocation_service
+                    .post_geolocation(NESTBOX_STRANGER, long, 47.3)
+                    .await,
+                InsertResult::Ok(_)
+            ));
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let paging = PagingQuery {
+            page_limit: 10,
+            page_number: 1,
+        };
+        let (geolocations, counted) = geolocation_service
+            .get_by_nestbox_uuid(NESTBOX_STRANGER, &paging)
+            .await
+            .unwrap();
+        assert_eq!(counted, 2);
+        assert_eq!(geolocations[0].long, 8.625);
+        assert_eq!(geolocations[1].until_date, geolocations[0].from_date);
+
+        let at = |g: &GeolocationResponse| {
+            DateTime::parse_rfc3339_str(g.from_date.replace(" UTC", "Z").replace(' ', "T")).unwrap()
+        };
+        let first = geolocation_service
+            .get_at(NESTBOX_STRANGER, &at(&geolocations[1]))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.long, 8.5);
+        assert!(geolocation_service
+            .get_at(NESTBOX_STRANGER, &before)
+            .await
+            .unwrap()
+            .is_none());
+
+        let nestbox = NestboxService::new(repositories.nestbox)
+            .get_by_uuid(NESTBOX_STRANGER)
+            .await
+            .pop()
+            .unwrap();
+        assert_eq!(nestbox.geolocation.unwrap().uuid, geolocations[0].uuid);
     }
 }
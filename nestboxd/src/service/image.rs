@@ -45,12 +45,8 @@ impl ImageService {
             }
             let kind = infer::get_from_path(&filepath_check_type);
             let sha3_checksum = sha256_str(&filepath_check_type).await;
-            if sha3_checksum.is_some() && kind.is_ok() {
-                let file_name = format!(
-                    "{}.{}",
-                    sha3_checksum.unwrap(),
-                    kind.unwrap().unwrap().extension()
-                );
+            if let (Some(sha3_checksum), Ok(Some(kind))) = (sha3_checksum, kind) {
+                let file_name = format!("{}.{}", sha3_checksum, kind.extension());
                 let checksummed_path = format!("{}/{}", &self.image_directory, &file_name);
                 let fm = std::fs::rename(&filepath_check_type, &checksummed_path);
                 if fm.is_ok() {
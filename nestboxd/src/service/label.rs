@@ -0,0 +1,434 @@
+//! Printable label sheets. Every label holds the QR code of a nestbox, the
+//! name of the mandant and the number of the box, laid out for the common
+//! self-adhesive sheets so a whole inventory prints in one go.
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str};
+use qrcode::{Color, QrCode};
+use serde::Deserialize;
+
+use super::mandant::MandantService;
+use super::nestbox::{format_nestbox_code, NestboxService};
+use super::qr::{QrEcLevel, QrService};
+use super::res_structs::NestboxResponse;
+use crate::controller::req_structs::NestboxesQuery;
+use crate::repository::RepositoryError;
+
+const PT_PER_MM: f32 = 72.0 / 25.4;
+const FONT_REGULAR: Name = Name(b"F1");
+const FONT_BOLD: Name = Name(b"F2");
+// Rough average glyph width of Helvetica relative to the font size, good
+// enough to keep the text within the label.
+const GLYPH_WIDTH: f32 = 0.56;
+
+/// Label sheets nestboxd knows, named after their Avery article number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum LabelLayout {
+    /// A4, 3 x 7 labels of 63.5 x 38.1 mm
+    #[serde(rename = "l7160")]
+    L7160,
+    /// A4, 2 x 7 labels of 99.1 x 38.1 mm
+    #[serde(rename = "l7163")]
+    L7163,
+    /// A4, 2 x 4 labels of 99.1 x 67.7 mm
+    #[serde(rename = "l7165")]
+    L7165,
+    /// US Letter, 3 x 10 labels of 2 5/8 x 1 inch
+    #[serde(rename = "5160")]
+    Letter5160,
+    /// US Letter, 2 x 5 labels of 4 x 2 inch
+    #[serde(rename = "5163")]
+    Letter5163,
+}
+
+/// Measures of a label sheet in mm, the margins are the ones of the top left
+/// label.
+struct SheetGeometry {
+    page_width: f32,
+    page_height: f32,
+    columns: usize,
+    rows: usize,
+    label_width: f32,
+    label_height: f32,
+    margin_left: f32,
+    margin_top: f32,
+    pitch_x: f32,
+    pitch_y: f32,
+}
+
+impl LabelLayout {
+    fn geometry(&self) -> SheetGeometry {
+        match self {
+            LabelLayout::L7160 => SheetGeometry {
+                page_width: 210.0,
+                page_height: 297.0,
+                columns: 3,
+                rows: 7,
+                label_width: 63.5,
+                label_height: 38.1,
+                margin_left: 7.25,
+                margin_top: 15.15,
+                pitch_x: 66.04,
+                pitch_y: 38.1,
+            },
+            LabelLayout::L7163 => SheetGeometry {
+                page_width: 210.0,
+                page_height: 297.0,
+                columns: 2,
+                rows: 7,
+                label_width: 99.1,
+                label_height: 38.1,
+                margin_left: 4.65,
+                margin_top: 15.15,
+                pitch_x: 101.6,
+                pitch_y: 38.1,
+            },
+            LabelLayout::L7165 => SheetGeometry {
+                page_width: 210.0,
+                page_height: 297.0,
+                columns: 2,
+                rows: 4,
+                label_width: 99.1,
+                label_height: 67.7,
+                margin_left: 4.65,
+                margin_top: 13.1,
+                pitch_x: 101.6,
+                pitch_y: 67.7,
+            },
+            LabelLayout::Letter5160 => SheetGeometry {
+                page_width: 215.9,
+                page_height: 279.4,
+                columns: 3,
+                rows: 10,
+                label_width: 66.675,
+                label_height: 25.4,
+                margin_left: 4.7625,
+                margin_top: 12.7,
+                pitch_x: 69.85,
+                pitch_y: 25.4,
+            },
+            LabelLayout::Letter5163 => SheetGeometry {
+                page_width: 215.9,
+                page_height: 279.4,
+                columns: 2,
+                rows: 5,
+                label_width: 101.6,
+                label_height: 50.8,
+                margin_left: 3.96875,
+                margin_top: 12.7,
+                pitch_x: 104.775,
+                pitch_y: 50.8,
+            },
+        }
+    }
+
+    pub fn labels_per_sheet(&self) -> usize {
+        let g = self.geometry();
+        g.columns * g.rows
+    }
+}
+
+pub struct Label {
+    pub code: QrCode,
+    pub mandant_name: String,
+    pub number: String,
+}
+
+/// Human readable number of a nestbox printed below the mandant name, its
+/// label or else its short code.
+pub fn nestbox_number(nestbox: &NestboxResponse) -> String {
+    if !nestbox.label.is_empty() {
+        return nestbox.label.clone();
+    }
+    if !nestbox.code.is_empty() {
+        return format_nestbox_code(&nestbox.code);
+    }
+    nestbox
+        .uuid
+        .chars()
+        .take(8)
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// PDF with one label per entry, `skip` positions of the first sheet are
+/// left blank so partly used sheets can be used up.
+pub fn render_label_sheets(labels: &[Label], layout: LabelLayout, skip: usize) -> Vec<u8> {
+    let g = layout.geometry();
+    let per_sheet = layout.labels_per_sheet();
+    let skip = skip % per_sheet;
+    let pages = (labels.len() + skip).div_ceil(per_sheet).max(1);
+
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let font_regular_id = Ref::new(3);
+    let font_bold_id = Ref::new(4);
+    let page_ids: Vec<Ref> = (0..pages).map(|p| Ref::new(5 + 2 * p as i32)).collect();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id)
+        .kids(page_ids.iter().copied())
+        .count(pages as i32);
+    pdf.type1_font(font_regular_id)
+        .base_font(Name(b"Helvetica"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.type1_font(font_bold_id)
+        .base_font(Name(b"Helvetica-Bold"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+
+    for (p, page_id) in page_ids.iter().enumerate() {
+        let content_id = Ref::new(page_id.get() + 1);
+        let mut page = pdf.page(*page_id);
+        page.media_box(Rect::new(
+            0.0,
+            0.0,
+            g.page_width * PT_PER_MM,
+            g.page_height * PT_PER_MM,
+        ));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        page.resources()
+            .fonts()
+            .pair(FONT_REGULAR, font_regular_id)
+            .pair(FONT_BOLD, font_bold_id);
+        page.finish();
+
+        let mut content = Content::new();
+        for position in 0..per_sheet {
+            let index = (p * per_sheet + position).checked_sub(skip);
+            if let Some(label) = index.and_then(|i| labels.get(i)) {
+                let column = position % g.columns;
+                let row = position / g.columns;
+                let x = g.margin_left + column as f32 * g.pitch_x;
+                let y = g.page_height - g.margin_top - row as f32 * g.pitch_y - g.label_height;
+                draw_label(&mut content, label, &g, x * PT_PER_MM, y * PT_PER_MM);
+            }
+        }
+        pdf.stream(content_id, &content.finish());
+    }
+    pdf.finish()
+}
+
+/// Draws the label with its lower left corner at x, y (pt).
+fn draw_label(content: &mut Content, label: &Label, g: &SheetGeometry, x: f32, y: f32) {
+    let width = g.label_width * PT_PER_MM;
+    let height = g.label_height * PT_PER_MM;
+    let padding = (height * 0.08).min(3.0 * PT_PER_MM);
+
+    // The padding keeps the quiet zone around the code.
+    let side = height - 2.0 * padding;
+    let modules = label.code.width();
+    let module = side / modules as f32;
+    content.set_fill_gray(0.0);
+    for (i, color) in label.code.to_colors().iter().enumerate() {
+        if *color == Color::Dark {
+            let column = (i % modules) as f32;
+            let row = (i / modules) as f32;
+            content.rect(
+                x + padding + column * module,
+                y + height - padding - (row + 1.0) * module,
+                module,
+                module,
+            );
+        }
+    }
+    content.fill_nonzero();
+
+    let text_x = x + side + 2.0 * padding;
+    let text_width = width - side - 3.0 * padding;
+    let number_size = (height * 0.3).min(20.0);
+    let name_size = (number_size * 0.5).max(6.0);
+    show_text(
+        content,
+        FONT_REGULAR,
+        name_size,
+        text_x,
+        y + height - padding - name_size,
+        &fit(&label.mandant_name, name_size, text_width),
+    );
+    show_text(
+        content,
+        FONT_BOLD,
+        number_size,
+        text_x,
+        y + padding + height * 0.15,
+        &fit(&label.number, number_size, text_width),
+    );
+}
+
+fn show_text(content: &mut Content, font: Name, size: f32, x: f32, y: f32, text: &str) {
+    content.begin_text();
+    content.set_font(font, size);
+    content.next_line(x, y);
+    content.show(Str(&win_ansi(text)));
+    content.end_text();
+}
+
+/// Cuts the text to the width available.
+fn fit(text: &str, size: f32, width: f32) -> String {
+    let max_chars = (width / (size * GLYPH_WIDTH)).floor() as usize;
+    text.chars().take(max_chars).collect()
+}
+
+/// The base fonts only know WinAnsi, which matches Latin-1 for the letters
+/// in names like "Vogelschutz Zürich". Anything else is replaced.
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x20..=0x7e | 0xa0..=0xff => c as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct LabelService {
+    nestbox: NestboxService,
+    mandant: MandantService,
+    qr: QrService,
+}
+
+impl LabelService {
+    pub fn new(nestbox: NestboxService, mandant: MandantService, qr: QrService) -> Self {
+        LabelService {
+            nestbox,
+            mandant,
+            qr,
+        }
+    }
+
+    /// Label sheets for the nestboxes of the mandant matching the query, None
+    /// if there is no such mandant or no nestbox to print.
+    pub async fn label_sheets(
+        &self,
+        mandant_uuid: &str,
+        query: &NestboxesQuery,
+        layout: LabelLayout,
+        ec: QrEcLevel,
+        skip: usize,
+    ) -> Result<Option<Vec<u8>>, RepositoryError> {
+        let mandant_name = match self.mandant.get_by_uuid(mandant_uuid).await? {
+            Some(m) => m.name,
+            None => return Ok(None),
+        };
+        let nestboxes = self
+            .nestbox
+            .get_all_by_mandant_uuid_filtered(mandant_uuid, query)
+            .await?;
+        if nestboxes.is_empty() {
+            return Ok(None);
+        }
+        let labels: Vec<Label> = nestboxes
+            .iter()
+            .filter_map(|n| {
+                Some(Label {
+                    code: self.qr.code(n, ec)?,
+                    mandant_name: mandant_name.clone(),
+                    number: nestbox_number(n),
+                })
+            })
+            .collect();
+        Ok(Some(render_label_sheets(&labels, layout, skip)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::memory::fixtures::{self, MANDANT_1, MANDANT_BIRDLIFE};
+
+    fn labels(count: usize) -> Vec<Label> {
+        let qr_service = QrService::new("https://nestbox.birdwatcher.ch");
+        (0..count)
+            .map(|n| Label {
+                code: qr_service
+                    .code(
+                        &NestboxResponse {
+                            uuid: uuid::Uuid::new_v4().to_string(),
+                            code: crate::service::nestbox::new_nestbox_code(),
+                            ..Default::default()
+                        },
+                        QrEcLevel::Q,
+                    )
+                    .unwrap(),
+                mandant_name: String::from("Vogelschutz Zürich"),
+                number: format!("{}", n),
+            })
+            .collect()
+    }
+
+    fn page_count(pdf: &[u8]) -> usize {
+        String::from_utf8_lossy(pdf)
+            .matches("/Type /Page\n")
+            .count()
+    }
+
+    #[test]
+    fn test_render_label_sheets() {
+        let pdf = render_label_sheets(&labels(22), LabelLayout::L7160, 0);
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert_eq!(page_count(&pdf), 2);
+        assert_eq!(
+            page_count(&render_label_sheets(&labels(20), LabelLayout::L7160, 1)),
+            1
+        );
+        assert_eq!(
+            page_count(&render_label_sheets(&labels(20), LabelLayout::L7160, 2)),
+            2
+        );
+        assert_eq!(
+            page_count(&render_label_sheets(
+                &labels(30),
+                LabelLayout::Letter5160,
+                0
+            )),
+            1
+        );
+        assert_eq!(win_ansi("Zürich €"), b"Z\xfcrich ?");
+        assert_eq!(fit("BirdLife Schweiz", 10.0, 50.0), "BirdLife");
+        let mut nestbox = NestboxResponse {
+            uuid: String::from("a446545d-f594-4eb5-96b4-c2312554050c"),
+            ..Default::default()
+        };
+        assert_eq!(nestbox_number(&nestbox), "A446545D");
+        nestbox.code = String::from("7k2mq9xd4hrv");
+        assert_eq!(nestbox_number(&nestbox), "7K2M-Q9XD-4HRV");
+        nestbox.label = String::from("Wald Nord 17");
+        assert_eq!(nestbox_number(&nestbox), "Wald Nord 17");
+    }
+
+    #[actix_rt::test]
+    async fn test_service_label_sheets() {
+        let repositories = fixtures::repositories();
+        let label_service = LabelService::new(
+            NestboxService::new(repositories.nestbox.clone()),
+            MandantService::new(repositories.mandant.clone()),
+            QrService::new("https://nestbox.birdwatcher.ch"),
+        );
+        let pdf = label_service
+            .label_sheets(
+                MANDANT_BIRDLIFE,
+                &NestboxesQuery::default(),
+                LabelLayout::L7163,
+                QrEcLevel::M,
+                0,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(page_count(&pdf), 1);
+        assert!(label_service
+            .label_sheets(
+                MANDANT_1,
+                &NestboxesQuery {
+                    retired: Some(true),
+                    ..Default::default()
+                },
+                LabelLayout::L7163,
+                QrEcLevel::M,
+                0,
+            )
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use mongodb::bson::{doc, Document};
+use uuid::Uuid;
+
+use super::res_structs::{MandantResponse, MapDocument};
+use crate::controller::req_structs::MandantDataReq;
+use crate::repository::{MandantRepository, RepositoryError};
+
+#[derive(Clone)]
+pub struct MandantService {
+    repository: Arc<dyn MandantRepository>,
+}
+
+impl MandantService {
+    pub fn new(repository: Arc<dyn MandantRepository>) -> Self {
+        MandantService { repository }
+    }
+
+    pub async fn get_by_uuid(
+        &self,
+        uuid: &str,
+    ) -> Result<Option<MandantResponse>, RepositoryError> {
+        Ok(self
+            .repository
+            .find_by_uuid(uuid)
+            .await?
+            .map(|m| MandantResponse::map_doc(&m)))
+    }
+
+    /// Onboards a new association.
+    pub async fn create(
+        &self,
+        mandant_req: &MandantDataReq,
+    ) -> Result<MandantResponse, RepositoryError> {
+        let mut mandant = mandant_doc(mandant_req);
+        mandant.insert("uuid", Uuid::new_v4().to_string());
+        self.repository.insert(&mandant).await?;
+        Ok(MandantResponse::map_doc(&mandant))
+    }
+
+    /// Replaces the attributes of the mandant, None if there is no such
+    /// mandant.
+    pub async fn update(
+        &self,
+        uuid: &str,
+        mandant_req: &MandantDataReq,
+    ) -> Result<Option<MandantResponse>, RepositoryError> {
+        if !self
+            .repository
+            .update(uuid, &mandant_doc(mandant_req))
+            .await?
+        {
+            return Ok(None);
+        }
+        self.get_by_uuid(uuid).await
+    }
+}
+
+fn mandant_doc(mandant_req: &MandantDataReq) -> Document {
+    doc! {
+        "name": mandant_req.name.trim(),
+        "website": &mandant_req.website,
+        "email": &mandant_req.email,
+        "logo": &mandant_req.logo,
+        "contact_person": &mandant_req.contact_person,
+        "phone": &mandant_req.phone,
+        "address": &mandant_req.address,
+    }
+}
@@ -1,9 +1,16 @@
 pub mod bird;
 pub mod breed;
+pub mod export;
 pub mod geolocation;
 pub mod image;
+pub mod label;
+pub mod mandant;
 pub mod nestbox;
+pub mod password;
+pub mod qr;
 pub mod res_structs;
+pub mod route;
 pub mod service_helper;
 pub mod session;
+pub mod species;
 pub mod user;
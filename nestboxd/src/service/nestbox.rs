@@ -1,132 +1,402 @@
-use super::res_structs::{MapDocument, NestboxResponse};
-use super::service_helper as sa;
-use crate::controller::{req_structs::NestboxReq, utilities::SessionObject};
-use mongodb::bson::{doc, Document};
-use mongodb::{error::Error, Collection, Database};
+use std::sync::Arc;
+
+use super::res_structs::{MapDocument, NestboxNearResponse, NestboxResponse};
+use crate::controller::{
+    req_structs::{NearQuery, NestboxDataReq, NestboxPatchReq, NestboxReq, NestboxesQuery},
+    utilities::{PagingQuery, SessionObject},
+};
+use crate::repository::{NestboxFilter, NestboxRepository, RepositoryError};
+use chrono::{Datelike, TimeZone, Utc};
+use data_encoding::{Encoding, Specification};
+use lazy_static::lazy_static;
+use mongodb::bson::{doc, Bson, DateTime, Document};
+use rand::RngCore;
+use uuid::Uuid;
+
+const NESTBOX_CODE_LEN: usize = 12;
+// Base32 without the letters easily taken for digits (i, l, o) and u, as
+// proposed by Douglas Crockford.
+const NESTBOX_CODE_SYMBOLS: &str = "0123456789abcdefghjkmnpqrstvwxyz";
+const NEAR_LIMIT: i64 = 100;
+const NESTBOXES_PAGE_LIMIT: i64 = 100;
+// Anonymous users see the nestboxes near them to about 100 m, so nobody
+// is guided right to a brood.
+const NEAR_ANONYMOUS_DEGREES: f64 = 0.001;
+const NEAR_ANONYMOUS_METERS: f64 = 100.0;
+
+lazy_static! {
+    static ref NESTBOX_CODE_ENCODING: Encoding = {
+        let mut spec = Specification::new();
+        spec.symbols.push_str(NESTBOX_CODE_SYMBOLS);
+        spec.encoding().unwrap()
+    };
+}
+
+/// New short code of a nestbox. The 12 characters carry 60 random bits, so
+/// like the uuids the codes can not be guessed.
+pub fn new_nestbox_code() -> String {
+    let mut bytes = [0u8; 8];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    NESTBOX_CODE_ENCODING
+        .encode(&bytes)
+        .chars()
+        .take(NESTBOX_CODE_LEN)
+        .collect()
+}
+
+/// Code as stored, None if it is no code at all. Codes are typed from
+/// weathered tags, so case, dashes and blanks are ignored and i, l and o
+/// are read as 1 and 0.
+pub fn normalize_nestbox_code(code: &str) -> Option<String> {
+    let normalized: String = code
+        .chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .map(|c| match c.to_ascii_lowercase() {
+            'i' | 'l' => '1',
+            'o' => '0',
+            c => c,
+        })
+        .collect();
+    if normalized.len() == NESTBOX_CODE_LEN
+        && normalized.chars().all(|c| NESTBOX_CODE_SYMBOLS.contains(c))
+    {
+        Some(normalized)
+    } else {
+        None
+    }
+}
+
+/// Code as printed on the labels, e.g. 7KQ2-M9XD-4TPA.
+pub fn format_nestbox_code(code: &str) -> String {
+    code.to_uppercase()
+        .as_bytes()
+        .chunks(4)
+        .map(|c| String::from_utf8_lossy(c).to_string())
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
+fn round_to(value: f64, step: f64) -> f64 {
+    // Rounded to the step and back to decimals, 47.377 not 47.377000000000002.
+    let decimals = (-step.log10()).max(0.0) as i32;
+    let factor = 10f64.powi(decimals);
+    ((value / step).round() * step * factor).round() / factor
+}
+
+/// Hides the location from users outside the mandant of the nestbox: public
+/// nestboxes are located like in `get_near` for anonymous users, the others
+/// not at all.
+pub fn conceal_location(nestbox: &mut NestboxResponse) {
+    if !nestbox.public {
+        nestbox.geolocation = None;
+    }
+    if let Some(geolocation) = nestbox.geolocation.as_mut() {
+        geolocation.lat = round_to(geolocation.lat, NEAR_ANONYMOUS_DEGREES);
+        geolocation.long = round_to(geolocation.long, NEAR_ANONYMOUS_DEGREES);
+    }
+}
+
+pub enum NestboxCreateResult {
+    Ok(Vec<String>),
+    LabelTaken,
+    Error,
+}
+
+pub enum NestboxUpdateResult {
+    Ok(Box<NestboxResponse>),
+    NotFound,
+    LabelTaken,
+    Error,
+}
 
 #[derive(Clone)]
 pub struct NestboxService {
-    collection: Collection<Document>,
+    repository: Arc<dyn NestboxRepository>,
 }
-const NESTBOX: &str = "nestboxes";
 
 impl NestboxService {
-    pub fn new(db: &Database) -> NestboxService {
-        NestboxService {
-            collection: db.collection(NESTBOX),
-        }
+    pub fn new(repository: Arc<dyn NestboxRepository>) -> NestboxService {
+        NestboxService { repository }
     }
 
     pub async fn get_by_uuid(&self, uuid: &str) -> Vec<NestboxResponse> {
-        /*
-                Example aggreation request
-                {"$match": {"uuid": {"$eq": "4ea60d3e-4a81-4bcc-b96a-c508fe73a48a"}}},
-        {"$skip": 0},
-        {"$limit": 1},
-        {
-          $lookup: {
-            "from": "mandants",
-            "let": {
-              "nestboxes_mandant_uuid": "$mandant_uuid"},
-            "pipeline": [
-                              {
-                                "$match": {
-                                  "$expr": {
-                                    "$eq": [
-                                      "$$nestboxes_mandant_uuid", "$uuid"
-                                    ]
-                                  }
-                                }
-                              },
-                              {
-                                "$project": {
-                                   "_id":0, "uuid": 1, "name": 1, "website": 1
-                                }
-                              }
-                            ], "as": "mandant"}}, {"$project": {"_id": 0}}
-                */
-        let res = self
-            .collection
-            .aggregate(
-                vec![
-                    doc! {"$match": {"uuid": {"$eq": uuid}}},
-                    doc! {"$skip": 0},
-                    doc! {"$limit": 1},
-                    doc! {"$lookup": {
-                    "from": "mandants",
-                     "let": {
-                       "nestboxes_mandant_uuid": "$mandant_uuid"},
-                     "pipeline": [
-                                    {
-                                      "$match": {
-                                        "$expr": {
-                                          "$eq": [
-                                            "$$nestboxes_mandant_uuid", "$uuid"
-                                          ]
-                                        }
-                                      }
-                                    },
-                                    {
-                                      "$project": {
-                                         "_id":0, "uuid": 1, "name": 1, "website": 1
-                                      }
-                                    }
-                                  ], "as": "mandant"}},
-                    doc! {"$project": {"_id": 0}},
-                ],
-                None,
-            )
-            .await;
         let mut nestboxes: Vec<NestboxResponse> = Vec::new();
-        for d in sa::read_mongodb_cursor(res).await {
+        if let Ok(Some(d)) = self.repository.find_by_uuid(uuid).await {
             nestboxes.push(NestboxResponse::map_doc(&d))
         }
         nestboxes
     }
 
+    /// Nestbox by its short code in any notation.
+    pub async fn get_by_code(
+        &self,
+        code: &str,
+    ) -> Result<Option<NestboxResponse>, RepositoryError> {
+        let code = match normalize_nestbox_code(code) {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+        Ok(self
+            .repository
+            .find_by_code(&code)
+            .await?
+            .map(|n| NestboxResponse::map_doc(&n)))
+    }
+
+    /// Nestboxes of the mandant of the session around the point, the nearest
+    /// first. Anonymous users get the public nestboxes of all mandants at
+    /// reduced precision.
+    pub async fn get_near(
+        &self,
+        session: &SessionObject,
+        query: &NearQuery,
+    ) -> Result<Vec<NestboxNearResponse>, RepositoryError> {
+        let anonymous = !session.is_valid_session();
+        let mandant_uuid = (!anonymous).then(|| session.get_mandant_uuid());
+        let nestboxes = self
+            .repository
+            .find_near(
+                mandant_uuid,
+                query.lat,
+                query.long,
+                query.radius(),
+                NEAR_LIMIT,
+            )
+            .await?;
+        Ok(nestboxes
+            .iter()
+            .map(|n| {
+                let mut nestbox = NestboxNearResponse::map_doc(n);
+                if anonymous {
+                    nestbox.lat = round_to(nestbox.lat, NEAR_ANONYMOUS_DEGREES);
+                    nestbox.long = round_to(nestbox.long, NEAR_ANONYMOUS_DEGREES);
+                    nestbox.distance = round_to(nestbox.distance, NEAR_ANONYMOUS_METERS);
+                } else {
+                    nestbox.distance = nestbox.distance.round();
+                }
+                nestbox
+            })
+            .collect())
+    }
+
+    /// Gives the nestboxes from before the short codes one, returns how many.
+    pub async fn assign_missing_codes(&self) -> Result<usize, RepositoryError> {
+        let mut assigned = 0;
+        for uuid in self.repository.find_uuids_without_code().await? {
+            if self.repository.set_code(&uuid, &new_nestbox_code()).await? {
+                assigned += 1;
+            }
+        }
+        Ok(assigned)
+    }
+
     pub async fn get_by_mandant_uuid(
         &self,
         session: &SessionObject,
         nestbox_req: &NestboxReq,
-    ) -> Result<Option<Document>, Error> {
-        let res = self
-            .collection
-            .find_one(
-                doc! {"uuid": &nestbox_req.uuid,
-                "mandant_uuid": session.get_mandant_uuid()},
-                None,
-            )
-            .await;
-        res
+    ) -> Result<Option<Document>, RepositoryError> {
+        self.repository
+            .find_by_uuid_and_mandant_uuid(&nestbox_req.uuid, session.get_mandant_uuid())
+            .await
+    }
+
+    /// Page of the nestboxes of the mandant matching the query. "Not
+    /// inspected" means no breed recorded in the current season, which starts
+    /// on the 1st of January.
+    pub async fn get_by_mandant_uuid_filtered(
+        &self,
+        mandant_uuid: &str,
+        query: &NestboxesQuery,
+        paging: &PagingQuery,
+    ) -> Result<(Vec<NestboxResponse>, i64), RepositoryError> {
+        let season_start = Utc
+            .with_ymd_and_hms(Utc::now().year(), 1, 1, 0, 0, 0)
+            .single()
+            .map(|d| DateTime::from_millis(d.timestamp_millis()));
+        let filter = NestboxFilter {
+            public: query.public,
+            retired: query.retired,
+            last_breed_year: query.last_breed_year,
+            bird_uuid: query.bird_uuid.clone(),
+            not_inspected_since: match query.not_inspected {
+                Some(true) => season_start,
+                _ => None,
+            },
+            label: query.label.as_ref().map(|l| l.trim().to_string()),
+        };
+        let nestboxes = self
+            .repository
+            .find_by_mandant_uuid(mandant_uuid, &filter, paging)
+            .await?;
+        let counted_documents = self
+            .repository
+            .count_by_mandant_uuid(mandant_uuid, &filter)
+            .await?;
+        Ok((
+            nestboxes.iter().map(NestboxResponse::map_doc).collect(),
+            counted_documents as i64,
+        ))
+    }
+
+    /// All nestboxes of the mandant matching the query, read page by page.
+    pub async fn get_all_by_mandant_uuid_filtered(
+        &self,
+        mandant_uuid: &str,
+        query: &NestboxesQuery,
+    ) -> Result<Vec<NestboxResponse>, RepositoryError> {
+        let mut nestboxes = Vec::new();
+        let mut paging = PagingQuery {
+            page_limit: NESTBOXES_PAGE_LIMIT,
+            page_number: 1,
+        };
+        loop {
+            let (page, counted_documents) = self
+                .get_by_mandant_uuid_filtered(mandant_uuid, query, &paging)
+                .await?;
+            let last_page =
+                page.is_empty() || nestboxes.len() + page.len() >= counted_documents as usize;
+            nestboxes.extend(page);
+            if last_page {
+                return Ok(nestboxes);
+            }
+            paging.page_number += 1;
+        }
+    }
+
+    /// Whether another nestbox of the mandant than `uuid` has the label,
+    /// regardless of case.
+    async fn label_is_taken(
+        &self,
+        mandant_uuid: &str,
+        label: &str,
+        uuid: &str,
+    ) -> Result<bool, RepositoryError> {
+        Ok(self
+            .repository
+            .find_uuid_by_label(mandant_uuid, label)
+            .await?
+            .is_some_and(|u| u != uuid))
+    }
+
+    /// Adds the nestboxes to the mandant of the session, returns their uuids.
+    /// Labels must be unique within the mandant, the request included.
+    pub async fn create(
+        &self,
+        session: &SessionObject,
+        nestbox_reqs: &[NestboxDataReq],
+    ) -> NestboxCreateResult {
+        let mandant_uuid = session.get_mandant_uuid();
+        let mut labels: Vec<String> = Vec::new();
+        for label in nestbox_reqs.iter().map(|n| n.label.trim()) {
+            if label.is_empty() {
+                continue;
+            }
+            // Labels are unique regardless of case.
+            if labels.contains(&label.to_lowercase()) {
+                return NestboxCreateResult::LabelTaken;
+            }
+            match self.label_is_taken(mandant_uuid, label, "").await {
+                Ok(false) => labels.push(label.to_lowercase()),
+                Ok(true) => return NestboxCreateResult::LabelTaken,
+                Err(_e) => return NestboxCreateResult::Error,
+            }
+        }
+        let now = DateTime::now();
+        let nestboxes: Vec<Document> = nestbox_reqs
+            .iter()
+            .map(|n| {
+                let mut nestbox = doc! {
+                    "uuid": Uuid::new_v4().to_string(),
+                    "code": new_nestbox_code(),
+                    "mandant_uuid": mandant_uuid,
+                    "public": n.public,
+                    "created_at": now,
+                    "from_date": now,
+                    "description": n.description.trim(),
+                    "box_type": n.box_type.trim(),
+                };
+                if !n.label.trim().is_empty() {
+                    nestbox.insert("label", n.label.trim());
+                }
+                nestbox
+            })
+            .collect();
+        match self.repository.insert_many(&nestboxes).await {
+            Ok(()) => NestboxCreateResult::Ok(
+                nestboxes
+                    .iter()
+                    .map(|n| n.get_str("uuid").unwrap_or_default().to_string())
+                    .collect(),
+            ),
+            // A concurrent request took the label after the check above.
+            Err(e) if e.is_unique_violation() => NestboxCreateResult::LabelTaken,
+            Err(_e) => NestboxCreateResult::Error,
+        }
+    }
+
+    /// Changes the attributes present in the request, an empty label removes
+    /// the label.
+    pub async fn update(&self, uuid: &str, nestbox_req: &NestboxPatchReq) -> NestboxUpdateResult {
+        let mut nestbox = Document::new();
+        if let Some(label) = nestbox_req.label.as_ref().map(|l| l.trim()) {
+            if label.is_empty() {
+                nestbox.insert("label", Bson::Null);
+            } else {
+                let mandant_uuid = match self.get_by_uuid(uuid).await.pop() {
+                    Some(n) => n.mandant_uuid,
+                    None => return NestboxUpdateResult::NotFound,
+                };
+                match self.label_is_taken(&mandant_uuid, label, uuid).await {
+                    Ok(false) => {}
+                    Ok(true) => return NestboxUpdateResult::LabelTaken,
+                    Err(_e) => return NestboxUpdateResult::Error,
+                }
+                nestbox.insert("label", label);
+            }
+        }
+        if let Some(public) = nestbox_req.public {
+            nestbox.insert("public", public);
+        }
+        if let Some(description) = &nestbox_req.description {
+            nestbox.insert("description", description.trim());
+        }
+        if let Some(box_type) = &nestbox_req.box_type {
+            nestbox.insert("box_type", box_type.trim());
+        }
+        match self.repository.update(uuid, &nestbox).await {
+            Ok(true) => match self.get_by_uuid(uuid).await.pop() {
+                Some(n) => NestboxUpdateResult::Ok(Box::new(n)),
+                None => NestboxUpdateResult::NotFound,
+            },
+            Ok(false) => NestboxUpdateResult::NotFound,
+            Err(e) if e.is_unique_violation() => NestboxUpdateResult::LabelTaken,
+            Err(_e) => NestboxUpdateResult::Error,
+        }
+    }
+
+    /// Takes the nestbox out of service as of now. It is kept with its
+    /// breeds and locations, retiring it again keeps the first date.
+    pub async fn retire(&self, uuid: &str) -> Result<Option<NestboxResponse>, RepositoryError> {
+        if !self.repository.retire(uuid, &DateTime::now()).await? {
+            return Ok(None);
+        }
+        Ok(self.get_by_uuid(uuid).await.pop())
     }
 
     pub async fn append_image_by_uuid(&self, uuid: &str, images: &[String]) -> bool {
-        //let update = doc!
-        let mut update_res = true;
         for image in images {
-            let result = self
-                .collection
-                .update_one(
-                    doc! {"uuid": uuid},
-                    doc! {"$addToSet": doc!{"images":image}},
-                    None,
-                )
-                .await;
-            match result {
-                Ok(_r) => update_res = true,
-                _error => {
-                    update_res = false;
-                    break;
-                }
+            if self.repository.append_image(uuid, image).await.is_err() {
+                return false;
             }
         }
-        update_res
+        true
     }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
-    use mongodb::{options::ClientOptions, Client};
+    use crate::repository::memory::fixtures;
+    use mongodb::bson::doc;
 
     // Nestbox from mandant_uuid 4ac9971c-91de-455c-a1fd-4b9dfb862cee
     const NESTBOX_UUID_OK: &str = "a446545d-f594-4eb5-96b4-c2312554050c";
@@ -134,16 +404,15 @@ mod tests {
 
     #[actix_rt::test]
     async fn test_service_nestbox_get_by_uuid_ok() {
-        let db = fetch_db().await;
-        let nestbox_service = NestboxService::new(&db);
+        let nestbox_service = NestboxService::new(fixtures::repositories().nestbox);
 
         let nestbox = nestbox_service.get_by_uuid(NESTBOX_UUID_OK).await;
-        assert_eq!(nestbox.get(0).unwrap().uuid, String::from(NESTBOX_UUID_OK));
+        assert_eq!(nestbox[0].uuid, String::from(NESTBOX_UUID_OK));
+        assert_eq!(nestbox[0].mandant_name, String::from("BirdLife"));
     }
     #[actix_rt::test]
     async fn test_service_nestbox_get_by_mandant_uuid_ok() {
-        let db = fetch_db().await;
-        let nestbox_service = NestboxService::new(&db);
+        let nestbox_service = NestboxService::new(fixtures::repositories().nestbox);
         // Creating a mock session object
         let session_doc = doc! { "mandant_uuid" : "4ac9971c-91de-455c-a1fd-4b9dfb862cee",
         "username" : "fg_11", "uuid" : "15eaa6ca-4797-442b-b6c9-f1e7a1f3416d",
@@ -172,8 +441,7 @@ mod tests {
 
     #[actix_rt::test]
     async fn test_service_nestbox_get_by_mandant_uuid_nok() {
-        let db = fetch_db().await;
-        let nestbox_service = NestboxService::new(&db);
+        let nestbox_service = NestboxService::new(fixtures::repositories().nestbox);
         // Creating a mock session object
         let session_doc = doc! { "mandant_uuid" : "4ac9971c-91de-455c-a1fd-4b9dfb862cee", "username" : "fg_11", "uuid" : "15eaa6ca-4797-442b-b6c9-f1e7a1f3416d", "lastname" : "Gucker", "firstname" : "Fritz", "email" : "email_11@birdwatch.ch", "session_key" : "0e16a457-d957-431a-ba9e-ff3a961ed60e" };
 
@@ -188,11 +456,168 @@ mod tests {
         assert_eq!(nestbox, None);
     }
 
-    async fn fetch_db() -> Database {
-        let client_options_future = ClientOptions::parse("mongodb://localhost:27017");
-        let client_options = client_options_future.await.unwrap();
-        let client = Client::with_options(client_options).unwrap();
+    #[actix_rt::test]
+    async fn test_service_nestbox_append_image() {
+        let nestbox_service = NestboxService::new(fixtures::repositories().nestbox);
+        let images = vec![String::from("a.jpg"), String::from("a.jpg")];
+        assert!(
+            nestbox_service
+                .append_image_by_uuid(NESTBOX_UUID_OK, &images)
+                .await
+        );
+        let nestbox = nestbox_service.get_by_uuid(NESTBOX_UUID_OK).await;
+        assert_eq!(nestbox[0].images, vec![String::from("a.jpg")]);
+    }
+
+    #[test]
+    fn test_nestbox_code() {
+        let code = new_nestbox_code();
+        assert_eq!(code.len(), 12);
+        assert_eq!(normalize_nestbox_code(&code), Some(code.clone()));
+        assert_ne!(new_nestbox_code(), code);
+        assert_eq!(
+            normalize_nestbox_code("7KQ2-M9XD-4TPA"),
+            Some(String::from("7kq2m9xd4tpa"))
+        );
+        assert_eq!(
+            normalize_nestbox_code("ILO2 m9xd 4tpa"),
+            Some(String::from("1102m9xd4tpa"))
+        );
+        assert_eq!(normalize_nestbox_code("7kq2m9xd4tpu"), None);
+        assert_eq!(normalize_nestbox_code("7kq2m9xd4tp"), None);
+        assert_eq!(normalize_nestbox_code(NESTBOX_UUID_OK), None);
+        assert_eq!(format_nestbox_code("7kq2m9xd4tpa"), "7KQ2-M9XD-4TPA");
+    }
+
+    #[actix_rt::test]
+    async fn test_service_nestbox_codes() {
+        let repository = fixtures::repositories().nestbox;
+        let nestbox_service = NestboxService::new(repository.clone());
+        // Nestbox from before the codes.
+        let legacy_uuid = Uuid::new_v4().to_string();
+        repository
+            .insert_many(&[doc! {"uuid": &legacy_uuid, "mandant_uuid": fixtures::MANDANT_1}])
+            .await
+            .unwrap();
+        assert_eq!(nestbox_service.assign_missing_codes().await.unwrap(), 1);
+        assert_eq!(nestbox_service.assign_missing_codes().await.unwrap(), 0);
+        let legacy = nestbox_service
+            .get_by_uuid(&legacy_uuid)
+            .await
+            .pop()
+            .unwrap();
+        assert!(normalize_nestbox_code(&legacy.code).is_some());
 
-        client.database("nestbox_testing")
+        let nestbox = nestbox_service
+            .get_by_uuid(NESTBOX_UUID_OK)
+            .await
+            .pop()
+            .unwrap();
+        let by_code = nestbox_service
+            .get_by_code(&format_nestbox_code(&nestbox.code))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(by_code.uuid, NESTBOX_UUID_OK);
+        assert_eq!(by_code.mandant_name, "BirdLife");
+        assert!(nestbox_service
+            .get_by_code(&new_nestbox_code())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_service_nestbox_labels() {
+        let nestbox_service = NestboxService::new(fixtures::repositories().nestbox);
+        let session = SessionObject::new(Ok(Some(
+            doc! { "mandant_uuid" : fixtures::MANDANT_STRANGER, "username" : "fg_1001" },
+        )));
+        let labelled = |label: &str| NestboxDataReq {
+            public: true,
+            description: String::new(),
+            box_type: String::new(),
+            label: String::from(label),
+        };
+        assert!(matches!(
+            nestbox_service
+                .create(
+                    &session,
+                    &[labelled("Wald Nord 17"), labelled(" Wald Nord 17 ")]
+                )
+                .await,
+            NestboxCreateResult::LabelTaken
+        ));
+        // Other mandants have a "Box 1" too.
+        let uuids = match nestbox_service
+            .create(&session, &[labelled(" Wald Nord 17 "), labelled("Box 2")])
+            .await
+        {
+            NestboxCreateResult::Ok(uuids) => uuids,
+            _ => panic!("nestboxes not created"),
+        };
+        let nestbox = nestbox_service.get_by_uuid(&uuids[0]).await.pop().unwrap();
+        assert_eq!(nestbox.label, "Wald Nord 17");
+        assert!(matches!(
+            nestbox_service.create(&session, &[labelled("Box 1")]).await,
+            NestboxCreateResult::LabelTaken
+        ));
+        // Labels differing in case only are the same label.
+        for labels in [
+            vec![labelled("wald nord 17")],
+            vec![labelled("Box 3"), labelled("BOX 3")],
+        ] {
+            assert!(matches!(
+                nestbox_service.create(&session, &labels).await,
+                NestboxCreateResult::LabelTaken
+            ));
+        }
+
+        let relabel = |label: &str| NestboxPatchReq {
+            label: Some(String::from(label)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            nestbox_service.update(&uuids[0], &relabel("Box 2")).await,
+            NestboxUpdateResult::LabelTaken
+        ));
+        assert!(matches!(
+            nestbox_service.update(&uuids[0], &relabel("box 2")).await,
+            NestboxUpdateResult::LabelTaken
+        ));
+        assert!(matches!(
+            nestbox_service
+                .update(&uuids[0], &relabel("Wald Nord 17"))
+                .await,
+            NestboxUpdateResult::Ok(_)
+        ));
+        match nestbox_service.update(&uuids[1], &relabel("")).await {
+            NestboxUpdateResult::Ok(n) => assert_eq!(n.label, ""),
+            _ => panic!("label not removed"),
+        }
+        assert!(matches!(
+            nestbox_service
+                .update(&Uuid::new_v4().to_string(), &relabel("Box 3"))
+                .await,
+            NestboxUpdateResult::NotFound
+        ));
+
+        let paging = PagingQuery {
+            page_limit: 10,
+            page_number: 1,
+        };
+        let (nestboxes, counted) = nestbox_service
+            .get_by_mandant_uuid_filtered(
+                fixtures::MANDANT_STRANGER,
+                &NestboxesQuery {
+                    label: Some(String::from("wald nord")),
+                    ..Default::default()
+                },
+                &paging,
+            )
+            .await
+            .unwrap();
+        assert_eq!(counted, 1);
+        assert_eq!(nestboxes[0].uuid, uuids[0]);
     }
 }
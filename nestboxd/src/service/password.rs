@@ -0,0 +1,109 @@
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, SaltString};
+use argon2::{Algorithm, Argon2, Params, PasswordVerifier, Version};
+use sha3::{Digest, Sha3_256};
+
+// Passwords are stored as Argon2id PHC strings, e.g.
+// $argon2id$v=19$m=19456,t=2,p=1$<salt>$<hash>
+// The pepper is not part of the string, it is the Argon2 secret and only
+// known to the server (nestboxd_conf.yaml).
+const PHC_PREFIX: &str = "$argon2";
+
+fn argon2(pepper: &str) -> Option<Argon2<'_>> {
+    if pepper.is_empty() {
+        return Some(Argon2::default());
+    }
+    Argon2::new_with_secret(
+        pepper.as_bytes(),
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .ok()
+}
+
+pub fn hash_password(password: &str, pepper: &str) -> Option<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = argon2(pepper)?
+        .hash_password(password.as_bytes(), &salt)
+        .ok()?;
+    Some(hash.to_string())
+}
+
+pub fn is_legacy_hash(password_hash: &str) -> bool {
+    !password_hash.starts_with(PHC_PREFIX)
+}
+
+pub fn verify_password(password: &str, password_hash: &str, pepper: &str) -> bool {
+    let parsed_hash = match PasswordHash::new(password_hash) {
+        Ok(h) => h,
+        Err(_) => return false,
+    };
+    match argon2(pepper) {
+        Some(a) => a.verify_password(password.as_bytes(), &parsed_hash).is_ok(),
+        None => false,
+    }
+}
+
+pub fn verify_legacy_password(
+    password: &str,
+    password_hash: &str,
+    salt: &str,
+    pepper: &str,
+) -> bool {
+    // Hashes before Argon2 were hex encoded SHA3-256 of "{password}_{salt}",
+    // database_bouncycastle appended the host secret as "_{secret}".
+    if sha3_hex(&format!("{}_{}", password, salt)) == password_hash {
+        return true;
+    }
+    !pepper.is_empty() && sha3_hex(&format!("{}_{}_{}", password, salt, pepper)) == password_hash
+}
+
+fn sha3_hex(input: &str) -> String {
+    let mut hasher = Sha3_256::new();
+    hasher.update(input);
+    hex::encode(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEPPER: &str = "my_Super_H05T_5Cr3t";
+
+    #[test]
+    fn test_hash_and_verify_password() {
+        let password_hash = hash_password("secretbird", PEPPER).unwrap();
+        assert!(password_hash.starts_with("$argon2id$"));
+        assert!(!is_legacy_hash(&password_hash));
+        assert!(verify_password("secretbird", &password_hash, PEPPER));
+        assert!(!verify_password("wrongbird", &password_hash, PEPPER));
+        // Without the pepper the hash is worthless.
+        assert!(!verify_password("secretbird", &password_hash, ""));
+    }
+
+    #[test]
+    fn test_verify_legacy_password() {
+        let salt = "9496be03-8e94-48c9-ad08-0e6fa8b37c20";
+        let nestboxd_hash = sha3_hex(&format!("secretbird_{}", salt));
+        assert!(is_legacy_hash(&nestboxd_hash));
+        assert!(verify_legacy_password(
+            "secretbird",
+            &nestboxd_hash,
+            salt,
+            ""
+        ));
+        assert!(!verify_legacy_password(
+            "wrongbird",
+            &nestboxd_hash,
+            salt,
+            ""
+        ));
+        let bouncycastle_hash = sha3_hex(&format!("secretbird_{}_{}", salt, PEPPER));
+        assert!(verify_legacy_password(
+            "secretbird",
+            &bouncycastle_hash,
+            salt,
+            PEPPER
+        ));
+    }
+}
@@ -0,0 +1,141 @@
+//! QR codes for the nestbox labels. Every code holds the public URL of its
+//! nestbox, so anybody scanning the tag ends up at `GET /n/{code}`.
+use image::{DynamicImage, ImageOutputFormat, Luma};
+use qrcode::render::svg;
+use qrcode::{EcLevel, QrCode};
+use serde::Deserialize;
+
+use super::res_structs::NestboxResponse;
+
+pub const QR_SIZE_DEFAULT: u32 = 256;
+pub const QR_SIZE_MIN: u32 = 64;
+pub const QR_SIZE_MAX: u32 = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QrFormat {
+    Png,
+    Svg,
+}
+
+impl QrFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            QrFormat::Png => "image/png",
+            QrFormat::Svg => "image/svg+xml",
+        }
+    }
+}
+
+/// Share of the code which may be damaged and still be read, from about 7%
+/// (L) to 30% (H). Weathered tags in the woods want one of the higher ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum QrEcLevel {
+    L,
+    M,
+    Q,
+    H,
+}
+
+impl From<QrEcLevel> for EcLevel {
+    fn from(ec: QrEcLevel) -> Self {
+        match ec {
+            QrEcLevel::L => EcLevel::L,
+            QrEcLevel::M => EcLevel::M,
+            QrEcLevel::Q => EcLevel::Q,
+            QrEcLevel::H => EcLevel::H,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct QrService {
+    base_url: String,
+}
+
+impl QrService {
+    pub fn new(base_url: &str) -> Self {
+        QrService {
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// URL the QR code of the nestbox points to. The short code makes for a
+    /// coarser QR code, which is easier to scan on a weathered tag.
+    pub fn nestbox_url(&self, nestbox: &NestboxResponse) -> String {
+        if nestbox.code.is_empty() {
+            format!("{}/nestboxes/{}", self.base_url, nestbox.uuid)
+        } else {
+            format!("{}/n/{}", self.base_url, nestbox.code)
+        }
+    }
+
+    pub fn code(&self, nestbox: &NestboxResponse, ec: QrEcLevel) -> Option<QrCode> {
+        QrCode::with_error_correction_level(self.nestbox_url(nestbox), EcLevel::from(ec)).ok()
+    }
+
+    /// QR code of the nestbox at least `size` pixels wide, None if it can
+    /// not be rendered.
+    pub fn render(
+        &self,
+        nestbox: &NestboxResponse,
+        format: QrFormat,
+        size: u32,
+        ec: QrEcLevel,
+    ) -> Option<Vec<u8>> {
+        let code = self.code(nestbox, ec)?;
+        match format {
+            QrFormat::Png => {
+                let image = code.render::<Luma<u8>>().min_dimensions(size, size).build();
+                let mut png = Vec::new();
+                DynamicImage::ImageLuma8(image)
+                    .write_to(&mut png, ImageOutputFormat::Png)
+                    .ok()?;
+                Some(png)
+            }
+            QrFormat::Svg => Some(
+                code.render::<svg::Color>()
+                    .min_dimensions(size, size)
+                    .build()
+                    .into_bytes(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NESTBOX_UUID: &str = "a446545d-f594-4eb5-96b4-c2312554050c";
+
+    #[test]
+    fn test_qr_render() {
+        let qr_service = QrService::new("https://nestbox.birdwatcher.ch/");
+        let mut nestbox = NestboxResponse {
+            uuid: String::from(NESTBOX_UUID),
+            ..Default::default()
+        };
+        assert_eq!(
+            qr_service.nestbox_url(&nestbox),
+            format!("https://nestbox.birdwatcher.ch/nestboxes/{}", NESTBOX_UUID)
+        );
+        nestbox.code = String::from("7k2mq9xd4hrv");
+        assert_eq!(
+            qr_service.nestbox_url(&nestbox),
+            "https://nestbox.birdwatcher.ch/n/7k2mq9xd4hrv"
+        );
+        let png = qr_service
+            .render(&nestbox, QrFormat::Png, 128, QrEcLevel::M)
+            .unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let png_h = qr_service
+            .render(&nestbox, QrFormat::Png, 128, QrEcLevel::H)
+            .unwrap();
+        assert_ne!(png, png_h);
+        let svg = qr_service
+            .render(&nestbox, QrFormat::Svg, 128, QrEcLevel::M)
+            .unwrap();
+        assert!(String::from_utf8(svg).unwrap().contains("<svg"));
+    }
+}
@@ -0,0 +1,289 @@
+//! Visiting routes for inspecting and cleaning nestboxes. The tour is built
+//! by nearest neighbour and improved by 2-opt, which gets within a few
+//! percent of the optimum for the few hundred nestboxes of a mandant. Several
+//! volunteers share the tour by cutting it into pieces of about equal
+//! length, each of them starting and ending at the start point.
+use serde::Deserialize;
+
+use super::export::xml_escape;
+use super::label::nestbox_number;
+use super::nestbox::NestboxService;
+use super::res_structs::{RouteResponse, RouteStopResponse, RoutesResponse};
+use crate::controller::req_structs::NestboxesQuery;
+use crate::repository::{distance, RepositoryError};
+
+// Enough for 2-opt to converge, it rarely needs more than a dozen passes.
+const TWO_OPT_PASSES_MAX: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RouteFormat {
+    Json,
+    Gpx,
+}
+
+/// Distances between the start (index 0) and the stops (1 to n).
+struct Distances {
+    matrix: Vec<Vec<f64>>,
+}
+
+impl Distances {
+    fn new(points: &[(f64, f64)]) -> Self {
+        let matrix = points
+            .iter()
+            .map(|a| {
+                points
+                    .iter()
+                    .map(|b| distance(a.0, a.1, b.0, b.1))
+                    .collect()
+            })
+            .collect();
+        Distances { matrix }
+    }
+
+    fn get(&self, a: usize, b: usize) -> f64 {
+        self.matrix[a][b]
+    }
+
+    /// Length of the tour from the start along the stops and back.
+    fn tour_length(&self, tour: &[usize]) -> f64 {
+        let mut previous = 0;
+        let mut length = 0.0;
+        for &stop in tour {
+            length += self.get(previous, stop);
+            previous = stop;
+        }
+        length + self.get(previous, 0)
+    }
+}
+
+/// Tour always moving on to the nearest stop not visited yet.
+fn nearest_neighbour(distances: &Distances, stops: &[usize]) -> Vec<usize> {
+    let mut open = stops.to_vec();
+    let mut tour = Vec::with_capacity(open.len());
+    let mut current = 0;
+    while !open.is_empty() {
+        let (i, _) = open
+            .iter()
+            .enumerate()
+            .fold((0, f64::MAX), |best, (i, &s)| {
+                let d = distances.get(current, s);
+                if d < best.1 {
+                    (i, d)
+                } else {
+                    best
+                }
+            });
+        current = open.swap_remove(i);
+        tour.push(current);
+    }
+    tour
+}
+
+/// Reverses parts of the tour as long as this makes it shorter, which
+/// removes its crossings.
+fn two_opt(distances: &Distances, tour: &mut [usize]) {
+    // The tour with the start in front and at the end.
+    let at = |tour: &[usize], i: usize| -> usize {
+        if i == 0 || i > tour.len() {
+            0
+        } else {
+            tour[i - 1]
+        }
+    };
+    for _ in 0..TWO_OPT_PASSES_MAX {
+        let mut improved = false;
+        for i in 1..tour.len() {
+            for j in i + 1..=tour.len() {
+                let (a, b) = (at(tour, i - 1), at(tour, i));
+                let (c, d) = (at(tour, j), at(tour, j + 1));
+                let delta = distances.get(a, c) + distances.get(b, d)
+                    - distances.get(a, b)
+                    - distances.get(c, d);
+                if delta < -1e-6 {
+                    tour[i - 1..j].reverse();
+                    improved = true;
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+}
+
+/// Cuts the tour into `count` consecutive pieces of about equal length
+/// along the tour.
+fn split(distances: &Distances, tour: &[usize], count: usize) -> Vec<Vec<usize>> {
+    let count = count.clamp(1, tour.len().max(1));
+    let mut along = Vec::with_capacity(tour.len());
+    let mut length = 0.0;
+    let mut previous = tour.first().copied().unwrap_or(0);
+    for &stop in tour {
+        length += distances.get(previous, stop);
+        along.push(length);
+        previous = stop;
+    }
+    let mut pieces: Vec<Vec<usize>> = vec![Vec::new(); count];
+    for (i, &stop) in tour.iter().enumerate() {
+        // Equal shares of the path, yet at least one stop per piece.
+        let by_length = if length > 0.0 {
+            ((along[i] / length) * count as f64) as usize
+        } else {
+            i * count / tour.len()
+        };
+        let piece = by_length
+            .min(count - 1)
+            .min(i)
+            .max(count.saturating_sub(tour.len() - i));
+        pieces[piece].push(stop);
+    }
+    pieces.retain(|p| !p.is_empty());
+    pieces
+}
+
+/// Routes as GPX, one `rte` per volunteer.
+pub fn render_gpx(routes: &RoutesResponse) -> String {
+    let mut gpx = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <gpx version=\"1.1\" creator=\"nestboxd\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+    );
+    let start = format!(
+        "    <rtept lat=\"{}\" lon=\"{}\"><name>Start</name></rtept>\n",
+        routes.lat, routes.long
+    );
+    for (n, route) in routes.routes.iter().enumerate() {
+        gpx.push_str(&format!("  <rte>\n    <name>Route {}</name>\n", n + 1));
+        gpx.push_str(&start);
+        for stop in &route.nestboxes {
+            gpx.push_str(&format!(
+                "    <rtept lat=\"{}\" lon=\"{}\"><name>{}</name></rtept>\n",
+                stop.lat,
+                stop.long,
+                xml_escape(&stop.name)
+            ));
+        }
+        gpx.push_str(&start);
+        gpx.push_str("  </rte>\n");
+    }
+    gpx.push_str("</gpx>\n");
+    gpx
+}
+
+#[derive(Clone)]
+pub struct RouteService {
+    nestbox: NestboxService,
+}
+
+impl RouteService {
+    pub fn new(nestbox: NestboxService) -> Self {
+        RouteService { nestbox }
+    }
+
+    /// Routes from the point along the current locations of the nestboxes of
+    /// the mandant matching the query, back to the point.
+    pub async fn plan(
+        &self,
+        mandant_uuid: &str,
+        query: &NestboxesQuery,
+        lat: f64,
+        long: f64,
+        count: usize,
+    ) -> Result<RoutesResponse, RepositoryError> {
+        let nestboxes = self
+            .nestbox
+            .get_all_by_mandant_uuid_filtered(mandant_uuid, query)
+            .await?;
+        let without_location = nestboxes.iter().filter(|n| n.geolocation.is_none()).count();
+        let stops: Vec<RouteStopResponse> = nestboxes
+            .iter()
+            .filter_map(|n| {
+                let location = n.geolocation.as_ref()?;
+                Some(RouteStopResponse {
+                    uuid: n.uuid.clone(),
+                    code: n.code.clone(),
+                    name: nestbox_number(n),
+                    lat: location.lat,
+                    long: location.long,
+                    distance: 0.0,
+                })
+            })
+            .collect();
+        let points: Vec<(f64, f64)> = std::iter::once((lat, long))
+            .chain(stops.iter().map(|s| (s.lat, s.long)))
+            .collect();
+        let distances = Distances::new(&points);
+        let mut tour = nearest_neighbour(&distances, &(1..points.len()).collect::<Vec<usize>>());
+        two_opt(&distances, &mut tour);
+        let routes: Vec<RouteResponse> = split(&distances, &tour, count)
+            .into_iter()
+            .map(|mut piece| {
+                two_opt(&distances, &mut piece);
+                let mut previous = 0;
+                let nestboxes = piece
+                    .iter()
+                    .map(|&s| {
+                        let mut stop = stops[s - 1].clone();
+                        stop.distance = distances.get(previous, s).round();
+                        previous = s;
+                        stop
+                    })
+                    .collect();
+                RouteResponse {
+                    distance: distances.tour_length(&piece).round(),
+                    nestboxes,
+                }
+            })
+            .collect();
+        Ok(RoutesResponse {
+            lat,
+            long,
+            distance: routes.iter().map(|r| r.distance).sum(),
+            without_location,
+            routes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Stops on a line north of the start, 0.01 degrees are about 1.1 km.
+    fn distances() -> Distances {
+        let mut points = vec![(47.0, 8.0)];
+        for lat in [47.04, 47.01, 47.05, 47.02, 47.03, 47.06] {
+            points.push((lat, 8.0));
+        }
+        Distances::new(&points)
+    }
+
+    #[test]
+    fn test_tour() {
+        let distances = distances();
+        let mut tour = nearest_neighbour(&distances, &[1, 2, 3, 4, 5, 6]);
+        assert_eq!(tour, vec![2, 4, 5, 1, 3, 6]);
+        // Out and back along the line.
+        let shortest = 2.0 * distances.get(0, 6);
+        assert!((distances.tour_length(&tour) - shortest).abs() < 1.0);
+
+        tour = vec![6, 2, 1, 4, 3, 5];
+        two_opt(&distances, &mut tour);
+        assert!((distances.tour_length(&tour) - shortest).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_split() {
+        let distances = distances();
+        let tour = vec![2, 4, 5, 1, 3, 6];
+        assert_eq!(split(&distances, &tour, 1), vec![tour.clone()]);
+        assert_eq!(
+            split(&distances, &tour, 2),
+            vec![vec![2, 4, 5], vec![1, 3, 6]]
+        );
+        let pieces = split(&distances, &tour, 10);
+        assert_eq!(pieces.len(), 6);
+        assert!(pieces.iter().all(|p| p.len() == 1));
+        assert!(split(&distances, &[], 3).is_empty());
+    }
+}
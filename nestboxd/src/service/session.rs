@@ -1,62 +1,380 @@
+use std::sync::Arc;
+
 use crate::controller::utilities::SessionObject;
+use crate::repository::{RepositoryError, SessionRepository};
+use crate::service::res_structs::{MapDocument, SessionResponse};
 
-use mongodb::bson::{doc, DateTime, Document};
-//use chrono::Utc;
-use mongodb::{Collection, Database};
+use mongodb::bson::{DateTime, Document};
 use uuid::Uuid;
 
-const SESSIONS: &str = "sessions";
+// Writing the last activity on every request would double the writes, a
+// minute is precise enough for an idle timeout.
+const LAST_SEEN_RESOLUTION_MILLIS: i64 = 60_000;
+const USER_AGENT_MAX_LEN: usize = 256;
 
 #[derive(Clone)]
 pub struct SessionService {
-    collection: Collection<Document>,
+    repository: Arc<dyn SessionRepository>,
+    absolute_timeout_millis: i64,
+    idle_timeout_millis: i64,
+    cookie_enabled: bool,
 }
 
 impl SessionService {
-    pub fn new(db: &Database) -> SessionService {
+    /// Sessions end `absolute_timeout` seconds after login or
+    /// `idle_timeout` seconds after the last request, whatever comes first.
+    /// With `cookie_enabled` the session key is also handed out and accepted
+    /// as HttpOnly cookie.
+    pub fn new(
+        repository: Arc<dyn SessionRepository>,
+        absolute_timeout: i64,
+        idle_timeout: i64,
+        cookie_enabled: bool,
+    ) -> SessionService {
         SessionService {
-            collection: db.collection(SESSIONS),
+            repository,
+            absolute_timeout_millis: absolute_timeout * 1000,
+            idle_timeout_millis: idle_timeout * 1000,
+            cookie_enabled,
         }
     }
 
-    pub async fn create_session(&self, user_obj: Document) -> String {
-        let session_id = Uuid::new_v4().to_string();
-        //let mut doc = Document::new();
-        //doc.
+    pub fn cookie_enabled(&self) -> bool {
+        self.cookie_enabled
+    }
+
+    /// Seconds a new session lasts at most.
+    pub fn absolute_timeout(&self) -> i64 {
+        self.absolute_timeout_millis / 1000
+    }
+
+    /// Starts a new session for the user, sessions on other devices stay
+    /// untouched.
+    pub async fn create_session(&self, user_obj: Document, user_agent: &str) -> String {
+        let session_key = Uuid::new_v4().to_string();
+        let now = DateTime::now();
         let mut session_obj = user_obj;
-        session_obj.remove("_id");
-        session_obj.insert("session_key", &session_id);
-        session_obj.insert("session_created_at", DateTime::now());
-        self.remove_session(&session_obj).await;
-        let _session = self.collection.insert_one(session_obj, None).await;
-        session_id
-    }
-
-    async fn remove_session(&self, session_obj: &Document) {
-        let bson = session_obj.get("username");
-        let username = match bson {
-            Some(b) => b.as_str().unwrap(),
-            None => "n.a.",
+        for key in &["_id", "password_hash", "salt"] {
+            session_obj.remove(key);
+        }
+        session_obj.insert("session_key", &session_key);
+        session_obj.insert("session_id", Uuid::new_v4().to_string());
+        session_obj.insert("session_created_at", now);
+        session_obj.insert("session_last_seen_at", now);
+        session_obj.insert(
+            "session_expires_at",
+            DateTime::from_millis(now.timestamp_millis() + self.absolute_timeout_millis),
+        );
+        session_obj.insert(
+            "user_agent",
+            user_agent
+                .chars()
+                .take(USER_AGENT_MAX_LEN)
+                .collect::<String>(),
+        );
+        let _session = self.repository.insert(&session_obj).await;
+        session_key
+    }
+
+    /// Logout, ends the session of the given token only.
+    pub async fn remove_session(&self, session_token: &str) {
+        let _removed_session = self.repository.delete_by_session_key(session_token).await;
+    }
+
+    /// Ends all sessions of a user, e.g. after locking the account.
+    pub async fn remove_user_sessions(&self, username: &str) -> Result<(), RepositoryError> {
+        self.repository.delete_by_username(username).await
+    }
+
+    /// Ends all sessions of the user but the one of `session`, e.g. after
+    /// the password was changed on this device.
+    pub async fn remove_other_sessions(
+        &self,
+        session: &SessionObject,
+    ) -> Result<(), RepositoryError> {
+        let sessions = self
+            .repository
+            .find_by_user_uuid(session.get_user_uuid())
+            .await?;
+        for session_id in sessions
+            .iter()
+            .filter_map(|s| s.get_str("session_id").ok())
+            .filter(|session_id| *session_id != session.get_session_id())
+        {
+            self.repository
+                .delete_by_session_id(session.get_user_uuid(), session_id)
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn validate_session(&self, session_token: &str) -> SessionObject {
+        SessionObject::new(self.find_valid_session(session_token).await)
+    }
+
+    /// Replaces a still valid session by a new one, so an active user is not
+    /// logged out by the absolute timeout. Returns the new session key.
+    pub async fn refresh_session(&self, session_token: &str, user_agent: &str) -> Option<String> {
+        let session_obj = self.find_valid_session(session_token).await.ok()??;
+        let session_key = self.create_session(session_obj, user_agent).await;
+        self.remove_session(session_token).await;
+        Some(session_key)
+    }
+
+    /// Active sessions of the user, the one of `session` marked as current.
+    pub async fn get_sessions(
+        &self,
+        session: &SessionObject,
+    ) -> Result<Vec<SessionResponse>, RepositoryError> {
+        let now = DateTime::now();
+        let sessions = self
+            .repository
+            .find_by_user_uuid(session.get_user_uuid())
+            .await?;
+        Ok(sessions
+            .iter()
+            .filter(|s| !self.is_expired(s, &now))
+            .map(|s| {
+                let mut session_response = SessionResponse::map_doc(s);
+                session_response.current = session_response.session_id == session.get_session_id();
+                session_response
+            })
+            .collect())
+    }
+
+    /// Ends a session of the user by its id, e.g. of a lost phone.
+    pub async fn remove_session_by_id(
+        &self,
+        session: &SessionObject,
+        session_id: &str,
+    ) -> Result<bool, RepositoryError> {
+        self.repository
+            .delete_by_session_id(session.get_user_uuid(), session_id)
+            .await
+    }
+
+    async fn find_valid_session(
+        &self,
+        session_token: &str,
+    ) -> Result<Option<Document>, RepositoryError> {
+        let session_obj = match self.repository.find_by_session_key(session_token).await? {
+            Some(s) => s,
+            None => return Ok(None),
         };
-        let _removed_session = self
-            .collection
-            .delete_many(doc! {"username": username}, None)
-            .await;
+        let now = DateTime::now();
+        if self.is_expired(&session_obj, &now) {
+            self.remove_session(session_token).await;
+            return Ok(None);
+        }
+        if now.timestamp_millis() - last_seen_millis(&session_obj) > LAST_SEEN_RESOLUTION_MILLIS {
+            let _touched = self.repository.update_last_seen(session_token, &now).await;
+        }
+        Ok(Some(session_obj))
+    }
+
+    fn is_expired(&self, session_obj: &Document, now: &DateTime) -> bool {
+        let now = now.timestamp_millis();
+        let created_at = match session_obj.get_datetime("session_created_at") {
+            Ok(d) => d.timestamp_millis(),
+            Err(_) => return true,
+        };
+        // Sessions from before the timeouts were configurable have no
+        // session_expires_at.
+        let expires_at = match session_obj.get_datetime("session_expires_at") {
+            Ok(d) => d.timestamp_millis(),
+            Err(_) => created_at + self.absolute_timeout_millis,
+        };
+        now >= expires_at || now - last_seen_millis(session_obj) > self.idle_timeout_millis
+    }
+}
+
+fn last_seen_millis(session_obj: &Document) -> i64 {
+    session_obj
+        .get_datetime("session_last_seen_at")
+        .or_else(|_| session_obj.get_datetime("session_created_at"))
+        .map(|d| d.timestamp_millis())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::memory::fixtures;
+    use crate::repository::Repositories;
+
+    const ABSOLUTE_TIMEOUT: i64 = 3600;
+    const IDLE_TIMEOUT: i64 = 600;
+
+    async fn user(repositories: &Repositories, username: &str) -> Document {
+        repositories
+            .user
+            .find_by_username(username)
+            .await
+            .unwrap()
+            .unwrap()
     }
 
-    pub async fn remove_session_by_username(&self, username: &str) {
-        let _removed_session = self
-            .collection
-            .delete_many(doc! {"username": username}, None)
+    #[actix_rt::test]
+    async fn test_service_session_multiple_devices() {
+        let repositories = fixtures::repositories();
+        let session_service = SessionService::new(
+            repositories.session.clone(),
+            ABSOLUTE_TIMEOUT,
+            IDLE_TIMEOUT,
+            false,
+        );
+        let phone = session_service
+            .create_session(user(&repositories, "fg_10").await, "phone")
+            .await;
+        let tablet = session_service
+            .create_session(user(&repositories, "fg_10").await, "tablet")
             .await;
+        let phone_session = session_service.validate_session(&phone).await;
+        assert!(phone_session.is_valid_session());
+        assert!(session_service
+            .validate_session(&tablet)
+            .await
+            .is_valid_session());
+
+        let sessions = session_service.get_sessions(&phone_session).await.unwrap();
+        assert_eq!(sessions.len(), 2);
+        let current: Vec<&SessionResponse> = sessions.iter().filter(|s| s.current).collect();
+        assert_eq!(current.len(), 1);
+        assert_eq!(current[0].user_agent, "phone");
+
+        // The phone ends the session of the tablet...
+        let tablet_id = sessions.iter().find(|s| !s.current).unwrap();
+        assert!(session_service
+            .remove_session_by_id(&phone_session, &tablet_id.session_id)
+            .await
+            .unwrap());
+        assert!(!session_service
+            .validate_session(&tablet)
+            .await
+            .is_valid_session());
+        // ... and logs out.
+        session_service.remove_session(&phone).await;
+        assert!(!session_service
+            .validate_session(&phone)
+            .await
+            .is_valid_session());
     }
 
-    pub async fn validate_session(&self, session_token: &str) -> SessionObject {
-        let session_obj = self
-            .collection
-            .find_one(doc! {"session_key": session_token}, None)
+    #[actix_rt::test]
+    async fn test_service_session_remove_by_id_of_other_user() {
+        let repositories = fixtures::repositories();
+        let session_service = SessionService::new(
+            repositories.session.clone(),
+            ABSOLUTE_TIMEOUT,
+            IDLE_TIMEOUT,
+            false,
+        );
+        let own = session_service
+            .create_session(user(&repositories, "fg_10").await, "")
+            .await;
+        let other = session_service
+            .create_session(user(&repositories, "fg_11").await, "")
             .await;
+        let own_session = session_service.validate_session(&own).await;
+        let other_session = session_service.validate_session(&other).await;
+        assert!(!session_service
+            .remove_session_by_id(&own_session, other_session.get_session_id())
+            .await
+            .unwrap());
+        assert!(session_service
+            .validate_session(&other)
+            .await
+            .is_valid_session());
+    }
+
+    #[actix_rt::test]
+    async fn test_service_session_timeouts() {
+        let repositories = fixtures::repositories();
+        let session_service = SessionService::new(
+            repositories.session.clone(),
+            ABSOLUTE_TIMEOUT,
+            IDLE_TIMEOUT,
+            false,
+        );
+        let now = DateTime::now().timestamp_millis();
+        let seconds_ago = |s: i64| DateTime::from_millis(now - s * 1000);
+        let mut idle = user(&repositories, "fg_10").await;
+        idle.insert("session_key", "idle");
+        idle.insert("session_created_at", seconds_ago(IDLE_TIMEOUT + 10));
+        idle.insert("session_last_seen_at", seconds_ago(IDLE_TIMEOUT + 10));
+        repositories.session.insert(&idle).await.unwrap();
+        let mut active = user(&repositories, "fg_10").await;
+        active.insert("session_key", "active");
+        active.insert("session_created_at", seconds_ago(ABSOLUTE_TIMEOUT - 10));
+        active.insert("session_last_seen_at", seconds_ago(IDLE_TIMEOUT - 10));
+        repositories.session.insert(&active).await.unwrap();
+        let mut too_old = user(&repositories, "fg_10").await;
+        too_old.insert("session_key", "too_old");
+        too_old.insert("session_created_at", seconds_ago(ABSOLUTE_TIMEOUT + 10));
+        too_old.insert("session_last_seen_at", seconds_ago(1));
+        repositories.session.insert(&too_old).await.unwrap();
+
+        assert!(!session_service
+            .validate_session("idle")
+            .await
+            .is_valid_session());
+        assert!(!session_service
+            .validate_session("too_old")
+            .await
+            .is_valid_session());
+        // Expired sessions are removed on the way.
+        assert!(repositories
+            .session
+            .find_by_session_key("idle")
+            .await
+            .unwrap()
+            .is_none());
 
-        SessionObject::new(session_obj)
+        assert!(session_service
+            .validate_session("active")
+            .await
+            .is_valid_session());
+        // The request moved the idle timeout.
+        let last_seen_at = repositories
+            .session
+            .find_by_session_key("active")
+            .await
+            .unwrap()
+            .unwrap()
+            .get_datetime("session_last_seen_at")
+            .unwrap()
+            .timestamp_millis();
+        assert!(last_seen_at >= now);
+    }
+
+    #[actix_rt::test]
+    async fn test_service_session_refresh() {
+        let repositories = fixtures::repositories();
+        let session_service = SessionService::new(
+            repositories.session.clone(),
+            ABSOLUTE_TIMEOUT,
+            IDLE_TIMEOUT,
+            false,
+        );
+        let old = session_service
+            .create_session(user(&repositories, "fg_10").await, "")
+            .await;
+        let new = session_service
+            .refresh_session(&old, "phone")
+            .await
+            .unwrap();
+        assert_ne!(old, new);
+        assert!(!session_service
+            .validate_session(&old)
+            .await
+            .is_valid_session());
+        let new_session = session_service.validate_session(&new).await;
+        assert!(new_session.is_valid_session());
+        assert_eq!(new_session.get_mandant_uuid(), fixtures::MANDANT_BIRDLIFE);
+        // Only a valid session can be refreshed.
+        assert!(session_service
+            .refresh_session(&old, "phone")
+            .await
+            .is_none());
     }
 }
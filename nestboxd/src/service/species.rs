@@ -0,0 +1,154 @@
+//! Species lists imported into the bird catalog of a mandant. A list is
+//! either a JSON array of birds as taken by `post /birds`, or CSV with a
+//! header naming the columns `bird`, `scientific_name`, `taxon_key`, `link`
+//! and a column of names per language tag, e.g.
+//!
+//! ```text
+//! scientific_name,link,de,en
+//! Parus major,https://en.wikipedia.org/wiki/Parus_major,Kohlmeise,Great tit
+//! ```
+use serde::Deserialize;
+
+use crate::controller::req_structs::BirdDataReq;
+use crate::controller::validator::is_language_tag;
+
+/// Bundled list of the birds commonly breeding in European nestboxes.
+pub const CAVITY_NESTERS: &str = include_str!("../../data/cavity_nesters.csv");
+
+// A few times the birds of a country, enough for any regional list.
+pub const SPECIES_MAX: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpeciesFormat {
+    Json,
+    Csv,
+}
+
+/// Birds of the list in its order, an error names what is wrong with it.
+pub fn parse_species(format: SpeciesFormat, list: &str) -> Result<Vec<BirdDataReq>, String> {
+    let species = match format {
+        SpeciesFormat::Json => serde_json::from_str(list).map_err(|e| e.to_string())?,
+        SpeciesFormat::Csv => parse_csv(list)?,
+    };
+    if species.len() > SPECIES_MAX {
+        return Err(format!("More than {} species", SPECIES_MAX));
+    }
+    Ok(species)
+}
+
+fn parse_csv(list: &str) -> Result<Vec<BirdDataReq>, String> {
+    let mut lines = list
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .filter(|l| !l.trim().is_empty());
+    let header = lines.next().ok_or("Header missing")?;
+    // Spreadsheets of regions writing decimal commas separate by semicolons.
+    let separator = if header.contains(';') && !header.contains(',') {
+        ';'
+    } else {
+        ','
+    };
+    let columns = split_csv_line(header, separator);
+    if let Some(c) = columns.iter().find(|c| {
+        !matches!(
+            c.as_str(),
+            "bird" | "scientific_name" | "taxon_key" | "link" | "bird_website"
+        ) && !is_language_tag(c)
+    }) {
+        return Err(format!("Unknown column {}", c));
+    }
+    Ok(lines
+        .map(|line| {
+            let mut bird = BirdDataReq::default();
+            for (column, value) in columns.iter().zip(split_csv_line(line, separator)) {
+                match column.as_str() {
+                    "bird" => bird.bird = value,
+                    "scientific_name" => bird.scientific_name = value,
+                    "taxon_key" => bird.taxon_key = value,
+                    "link" | "bird_website" => bird.bird_website = value,
+                    language if !value.trim().is_empty() => {
+                        bird.names.insert(language.to_string(), value);
+                    }
+                    _ => {}
+                }
+            }
+            bird
+        })
+        .collect())
+}
+
+/// Fields of a CSV line, quoted ones may hold the separator and doubled
+/// quotes.
+fn split_csv_line(line: &str, separator: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => {
+                fields.push(field.trim().to_string());
+                field.clear();
+            }
+            c => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::validator::Validator;
+
+    #[test]
+    fn test_parse_csv() {
+        let species = parse_species(
+            SpeciesFormat::Csv,
+            "bird;scientific_name;de-CH;en\n\n\
+            Meise;Parus major;\"Chohl;meisi\";\"Great \"\"tit\"\"\"\n\
+            ;Sitta europaea;;Nuthatch\n",
+        )
+        .unwrap();
+        assert_eq!(species.len(), 2);
+        assert_eq!(species[0].bird, "Meise");
+        assert_eq!(species[0].names.get("de-CH").unwrap(), "Chohl;meisi");
+        assert_eq!(species[0].names.get("en").unwrap(), "Great \"tit\"");
+        assert_eq!(species[1].scientific_name, "Sitta europaea");
+        assert_eq!(species[1].names.len(), 1);
+        assert!(parse_species(SpeciesFormat::Csv, "scientific_name,wingspan\n").is_err());
+        assert!(parse_species(SpeciesFormat::Csv, "").is_err());
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let species = parse_species(
+            SpeciesFormat::Json,
+            r#"[{"scientific_name": "Parus major", "link": "https://en.wikipedia.org/wiki/Parus_major", "names": {"de": "Kohlmeise"}}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            species[0].bird_website,
+            "https://en.wikipedia.org/wiki/Parus_major"
+        );
+        assert!(parse_species(SpeciesFormat::Json, r#"{"bird": "Kohlmeise"}"#).is_err());
+    }
+
+    #[test]
+    fn test_cavity_nesters() {
+        let species = parse_species(SpeciesFormat::Csv, CAVITY_NESTERS).unwrap();
+        assert!(species.len() > 30);
+        for mut bird in species {
+            assert_eq!(bird.names.len(), 4, "{}", bird.scientific_name);
+            bird.bird = bird.scientific_name.clone();
+            assert!(bird.is_valid(), "{}", bird.scientific_name);
+        }
+    }
+}
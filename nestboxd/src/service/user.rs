@@ -1,75 +1,188 @@
+use std::sync::Arc;
+
 use mongodb::bson::{doc, Document};
-use mongodb::{Collection, Database};
+use uuid::Uuid;
+
+use super::password::{hash_password, is_legacy_hash, verify_legacy_password, verify_password};
+use super::res_structs::{MapDocument, UserResponse};
+use crate::controller::req_structs::{UserCreateReq, UserUpdateReq};
+use crate::controller::utilities::PagingQuery;
+use crate::repository::{RepositoryError, UserRepository};
 
-use sha3::{Digest, Sha3_256};
+pub enum UserCreateResult {
+    Ok(UserResponse),
+    UsernameTaken,
+    Error,
+}
 
-const USERS: &str = "users";
+pub enum PasswordChangeResult {
+    Ok,
+    WrongPassword,
+    Error,
+}
 
 #[derive(Clone)]
 pub struct UserService {
-    collection: Collection<Document>,
+    repository: Arc<dyn UserRepository>,
+    password_pepper: String,
 }
 
 impl UserService {
-    pub fn new(db: &Database) -> UserService {
+    pub fn new(repository: Arc<dyn UserRepository>, password_pepper: &str) -> UserService {
         UserService {
-            collection: db.collection(USERS),
+            repository,
+            password_pepper: password_pepper.to_string(),
         }
     }
 
     pub async fn login(&self, username: &str, password: &str) -> Option<Document> {
-        let user_res = self
-            .collection
-            .find_one(doc! {"username": username}, None)
-            .await
-            .ok()?;
-        //block_on(user_res);
-        let userobj = match user_res {
-            Some(u) => u,
-            None => return None,
+        let user_res = self.repository.find_by_username(username).await.ok()?;
+        let userobj = user_res?;
+        // Locked users keep their record (and breeds) but cannot log in.
+        if userobj.get_bool("locked").unwrap_or(false) {
+            return None;
+        }
+        if !self.verify(&userobj, password) {
+            return None;
+        }
+        if is_legacy_hash(userobj.get_str("password_hash").ok()?) {
+            // The password is known right now, so this is the only chance to
+            // migrate a salted SHA3 hash to Argon2id without bothering the
+            // user.
+            if let (Ok(uuid), Some(new_hash)) = (
+                userobj.get_str("uuid"),
+                hash_password(password, &self.password_pepper),
+            ) {
+                let _updated = self.repository.update_password_hash(uuid, &new_hash).await;
+            }
+        }
+        Some(userobj)
+    }
+
+    pub async fn get_by_uuid(&self, uuid: &str) -> Result<Option<UserResponse>, RepositoryError> {
+        Ok(self
+            .repository
+            .find_by_uuid(uuid)
+            .await?
+            .map(|u| UserResponse::map_doc(&u)))
+    }
+
+    pub async fn get_by_mandant_uuid(
+        &self,
+        mandant_uuid: &str,
+        paging: &PagingQuery,
+    ) -> Result<(Vec<UserResponse>, i64), RepositoryError> {
+        let users = self
+            .repository
+            .find_by_mandant_uuid(mandant_uuid, paging)
+            .await?;
+        let counted_documents = self.repository.count_by_mandant_uuid(mandant_uuid).await?;
+        Ok((
+            users.iter().map(UserResponse::map_doc).collect(),
+            counted_documents as i64,
+        ))
+    }
+
+    pub async fn create(&self, mandant_uuid: &str, user_req: &UserCreateReq) -> UserCreateResult {
+        match self.repository.find_by_username(&user_req.username).await {
+            Ok(None) => {}
+            Ok(Some(_u)) => return UserCreateResult::UsernameTaken,
+            Err(_e) => return UserCreateResult::Error,
+        }
+        let password_hash = match hash_password(&user_req.password, &self.password_pepper) {
+            Some(h) => h,
+            None => return UserCreateResult::Error,
         };
+        let user = doc! {
+            "uuid": Uuid::new_v4().to_string(),
+            "mandant_uuid": mandant_uuid,
+            "username": &user_req.username,
+            "lastname": user_req.lastname.trim(),
+            "firstname": user_req.firstname.trim(),
+            "email": &user_req.email,
+            "password_hash": password_hash,
+            // Only used by legacy SHA3 hashes, kept for the NOT NULL column.
+            "salt": Uuid::new_v4().to_string(),
+            "role": user_req.role.as_str(),
+            "locked": false,
+        };
+        match self.repository.insert(&user).await {
+            Ok(()) => UserCreateResult::Ok(UserResponse::map_doc(&user)),
+            Err(_e) => UserCreateResult::Error,
+        }
+    }
 
-        let mut pw_hash_salt: Vec<String> = Vec::new();
+    /// Replaces the attributes of the user, None if there is no such user.
+    pub async fn update(
+        &self,
+        uuid: &str,
+        user_req: &UserUpdateReq,
+    ) -> Result<Option<UserResponse>, RepositoryError> {
+        let user = doc! {
+            "lastname": user_req.lastname.trim(),
+            "firstname": user_req.firstname.trim(),
+            "email": &user_req.email,
+            "role": user_req.role.as_str(),
+            "locked": user_req.locked,
+        };
+        if !self.repository.update(uuid, &user).await? {
+            return Ok(None);
+        }
+        self.get_by_uuid(uuid).await
+    }
 
-        for key in &["password_hash", "salt"] {
-            let bson = userobj.get(key);
-            let string = match bson {
-                Some(b) => b.as_str()?,
-                None => return None,
-            };
-            pw_hash_salt.push(String::from(string));
+    /// Sets a new password after verifying the current one.
+    pub async fn change_password(
+        &self,
+        uuid: &str,
+        password: &str,
+        new_password: &str,
+    ) -> PasswordChangeResult {
+        let userobj = match self.repository.find_by_uuid(uuid).await {
+            Ok(Some(u)) => u,
+            Ok(None) => return PasswordChangeResult::WrongPassword,
+            Err(_e) => return PasswordChangeResult::Error,
+        };
+        if !self.verify(&userobj, password) {
+            return PasswordChangeResult::WrongPassword;
         }
-        if is_password_correct(
-            password,
-            pw_hash_salt.get(0).unwrap(),
-            pw_hash_salt.get(1).unwrap(),
-        ) {
-            return Some(userobj);
+        let new_hash = match hash_password(new_password, &self.password_pepper) {
+            Some(h) => h,
+            None => return PasswordChangeResult::Error,
+        };
+        match self.repository.update_password_hash(uuid, &new_hash).await {
+            Ok(()) => PasswordChangeResult::Ok,
+            Err(_e) => PasswordChangeResult::Error,
         }
-        None
     }
-}
 
-fn is_password_correct(password: &str, password_hash: &str, salt: &str) -> bool {
-    let mut hasher = Sha3_256::new();
-    let password_with_salt = format!("{}_{}", password, salt);
-    hasher.update(password_with_salt);
-    let password_hash_result = hex::encode(hasher.finalize());
-    if password_hash_result == password_hash {
-        return true;
+    fn verify(&self, userobj: &Document, password: &str) -> bool {
+        let password_hash = match userobj.get_str("password_hash") {
+            Ok(h) => h,
+            Err(_) => return false,
+        };
+        if !is_legacy_hash(password_hash) {
+            return verify_password(password, password_hash, &self.password_pepper);
+        }
+        match userobj.get_str("salt") {
+            Ok(salt) => {
+                verify_legacy_password(password, password_hash, salt, &self.password_pepper)
+            }
+            Err(_) => false,
+        }
     }
-    false
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::repository::memory::fixtures;
     use crate::service::session::SessionService;
-    use mongodb::{options::ClientOptions, Client};
 
     #[actix_rt::test]
     async fn test_service_user_login_ok() {
-        let db = fetch_db().await;
-        let user_service = UserService::new(&db);
+        let repositories = fixtures::repositories();
+        let user_service = UserService::new(repositories.user.clone(), "");
         let login_positive = user_service.login("fg_10", "secretbird").await;
         assert_eq!(
             login_positive
@@ -84,13 +197,13 @@ mod tests {
 
     #[actix_rt::test]
     async fn test_service_session_ok() {
-        let db = fetch_db().await;
-        let user_service = UserService::new(&db);
-        let session_service = SessionService::new(&db);
+        let repositories = fixtures::repositories();
+        let user_service = UserService::new(repositories.user.clone(), "");
+        let session_service = SessionService::new(repositories.session.clone(), 3600, 600, false);
 
         let login_positive = user_service.login("fg_11", "secretbird").await;
         let session_object = session_service
-            .create_session(login_positive.unwrap())
+            .create_session(login_positive.unwrap(), "")
             .await;
         assert_ne!(&session_object, &"n.a.");
 
@@ -99,22 +212,77 @@ mod tests {
         assert!(session_obj.is_valid_session());
     }
 
+    #[actix_rt::test]
+    async fn test_service_user_login_rehashes_legacy_password() {
+        let repositories = fixtures::repositories();
+        let user_service = UserService::new(repositories.user.clone(), "");
+        let legacy_hash = repositories
+            .user
+            .find_by_username("fg_10")
+            .await
+            .unwrap()
+            .unwrap()
+            .get_str("password_hash")
+            .unwrap()
+            .to_string();
+        assert!(is_legacy_hash(&legacy_hash));
+
+        assert!(user_service.login("fg_10", "secretbird").await.is_some());
+        let rehashed = repositories
+            .user
+            .find_by_username("fg_10")
+            .await
+            .unwrap()
+            .unwrap()
+            .get_str("password_hash")
+            .unwrap()
+            .to_string();
+        assert!(!is_legacy_hash(&rehashed));
+        // ... and the migrated hash is used from now on.
+        assert!(user_service.login("fg_10", "secretbird").await.is_some());
+        assert!(user_service.login("fg_10", "secret").await.is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_service_user_login_with_pepper() {
+        let repositories = fixtures::repositories();
+        let user_service = UserService::new(repositories.user.clone(), "pepper");
+        assert!(user_service.login("fg_10", "secretbird").await.is_some());
+        // A hash created with a pepper does not verify without it.
+        let user_service_without_pepper = UserService::new(repositories.user.clone(), "");
+        assert!(user_service_without_pepper
+            .login("fg_10", "secretbird")
+            .await
+            .is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_service_user_login_locked() {
+        let repositories = fixtures::repositories();
+        let user_service = UserService::new(repositories.user.clone(), "");
+        let uuid = user_service
+            .login("fg_10", "secretbird")
+            .await
+            .unwrap()
+            .get_str("uuid")
+            .unwrap()
+            .to_string();
+        repositories
+            .user
+            .update(&uuid, &doc! {"locked": true})
+            .await
+            .unwrap();
+        assert!(user_service.login("fg_10", "secretbird").await.is_none());
+    }
+
     #[actix_rt::test]
     async fn test_service_login_nok() {
-        let db = fetch_db().await;
-        let user_service = UserService::new(&db);
-        let session_service = SessionService::new(&db);
+        let repositories = fixtures::repositories();
+        let user_service = UserService::new(repositories.user.clone(), "");
+        let session_service = SessionService::new(repositories.session.clone(), 3600, 600, false);
         let login_false = user_service.login("fg_10", "secret").await;
         assert_eq!(&login_false, &None);
         let session_object = session_service.validate_session("n.a.").await;
         assert!(!session_object.is_valid_session());
     }
-
-    async fn fetch_db() -> Database {
-        let client_options_future = ClientOptions::parse("mongodb://localhost:27017");
-        let client_options = client_options_future.await.unwrap();
-        let client = Client::with_options(client_options).unwrap();
-
-        client.database("nestbox_testing")
-    }
 }